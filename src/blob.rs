@@ -0,0 +1,166 @@
+use crate::error::OrmError;
+use crate::query_builder::quote_identifier;
+use bytes::Bytes;
+use futures_util::SinkExt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio_postgres::types::ToSql;
+use tokio_postgres::Client;
+
+/// Numbers the temporary tables `write_bytea` uploads into, so calls sharing
+/// a connection do not write into each other's.
+static NEXT_UPLOAD: AtomicU64 = AtomicU64::new(0);
+
+/// Copies the `bytea` value of `column` in the row where `key_column = key`
+/// into `writer`, fetching at most `chunk_size` bytes per query. Values up
+/// to `chunk_size` take a single query; larger ones are never held in
+/// memory whole. A NULL value writes nothing. Returns the number of bytes
+/// written.
+pub async fn read_bytea<W: AsyncWrite + Unpin>(client: &Client, table: &str, column: &str, key_column: &str, key: &(dyn ToSql + Sync), chunk_size: usize, writer: &mut W) -> Result<u64, OrmError> {
+    let (table, column, key_column) = (quote_identifier(table), quote_identifier(column), quote_identifier(key_column));
+    let chunk_size = chunk_length(chunk_size)?;
+    let row = client
+        .query_opt(&format!("SELECT octet_length({column}) FROM {table} WHERE {key_column} = $1"), &[key])
+        .await?
+        .ok_or_else(|| OrmError::QueryError(format!("No row in {} where {} matches the key", table, key_column)))?;
+    let length: i32 = match row.get::<_, Option<i32>>(0) {
+        Some(length) => length,
+        None => return Ok(0),
+    };
+
+    let statement = client.prepare(&format!("SELECT substring({column} FROM $2 FOR $3) FROM {table} WHERE {key_column} = $1")).await?;
+    let mut offset = 0;
+    while offset < length {
+        // substring positions start at 1
+        let start = offset + 1;
+        let row = client.query_opt(&statement, &[key, &start, &chunk_size]).await?
+            .ok_or_else(|| OrmError::QueryError(format!("Row in {} was deleted while reading {}", table, column)))?;
+        let chunk: Option<Vec<u8>> = row.get(0);
+        let chunk = chunk.unwrap_or_default();
+        if chunk.is_empty() {
+            break;
+        }
+        writer.write_all(&chunk).await?;
+        offset += chunk.len() as i32;
+    }
+    writer.flush().await?;
+    Ok(offset as u64)
+}
+
+/// Replaces the `bytea` value of `column` in the row where `key_column = key`
+/// with everything `reader` yields, reading at most `chunk_size` bytes at a
+/// time. The chunks are streamed with COPY into a temporary table and the
+/// value is set from there in a single UPDATE, since appending chunk by chunk
+/// would rewrite the whole value on each append. The value is held on the
+/// server, in the temporary table, until it is stored. Returns the number of
+/// bytes stored.
+pub async fn write_bytea<R: AsyncRead + Unpin>(client: &Client, table: &str, column: &str, key_column: &str, key: &(dyn ToSql + Sync), chunk_size: usize, reader: &mut R) -> Result<u64, OrmError> {
+    let (table, column, key_column) = (quote_identifier(table), quote_identifier(column), quote_identifier(key_column));
+    chunk_length(chunk_size)?;
+    let upload = format!("rust_orm_gen_blob_upload_{}", NEXT_UPLOAD.fetch_add(1, Ordering::Relaxed));
+
+    client.batch_execute(&format!("CREATE TEMP TABLE {upload} (data bytea)")).await?;
+    let result = async {
+        let written = copy_hex(client, &upload, chunk_size, reader).await?;
+        let updated = client
+            .execute(&format!("UPDATE {table} SET {column} = (SELECT data FROM {upload}) WHERE {key_column} = $1"), &[key])
+            .await?;
+        if updated == 0 {
+            return Err(OrmError::QueryError(format!("No row in {} where {} matches the key", table, key_column)));
+        }
+        Ok(written)
+    }.await;
+    // Inside a transaction a failed statement makes this fail too; the
+    // table then goes away with the rollback
+    let dropped = client.batch_execute(&format!("DROP TABLE IF EXISTS {upload}")).await;
+    let written = result?;
+    dropped?;
+    Ok(written)
+}
+
+/// Streams everything `reader` yields into the single row of `upload` as a
+/// hex-encoded `bytea` value in COPY text format.
+async fn copy_hex<R: AsyncRead + Unpin>(client: &Client, upload: &str, chunk_size: usize, reader: &mut R) -> Result<u64, OrmError> {
+    const HEX_DIGITS: &[u8; 16] = b"0123456789abcdef";
+
+    let sink = client.copy_in(&format!("COPY {upload} (data) FROM STDIN")).await?;
+    futures_util::pin_mut!(sink);
+    // The backslash of the \x hex prefix is escaped, as COPY requires
+    sink.send(Bytes::from_static(b"\\\\x")).await?;
+    let mut chunk = vec![0; chunk_size];
+    let mut written = 0;
+    loop {
+        let filled = fill_chunk(reader, &mut chunk).await?;
+        if filled == 0 {
+            break;
+        }
+        let hex: Vec<u8> = chunk[..filled].iter().flat_map(|byte| [HEX_DIGITS[usize::from(byte >> 4)], HEX_DIGITS[usize::from(byte & 0xf)]]).collect();
+        sink.send(Bytes::from(hex)).await?;
+        written += filled as u64;
+    }
+    sink.send(Bytes::from_static(b"\n")).await?;
+    sink.finish().await?;
+    Ok(written)
+}
+
+/// Reads until `chunk` is full or `reader` is exhausted.
+async fn fill_chunk<R: AsyncRead + Unpin>(reader: &mut R, chunk: &mut [u8]) -> Result<usize, OrmError> {
+    let mut filled = 0;
+    while filled < chunk.len() {
+        match reader.read(&mut chunk[filled..]).await? {
+            0 => break,
+            read => filled += read,
+        }
+    }
+    Ok(filled)
+}
+
+fn chunk_length(chunk_size: usize) -> Result<i32, OrmError> {
+    match i32::try_from(chunk_size) {
+        Ok(length) if length > 0 => Ok(length),
+        _ => Err(OrmError::QueryError(format!("Chunk size must be between 1 and {} bytes, got {}", i32::MAX, chunk_size))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::PostgresConnectionManager;
+    use dotenv::dotenv;
+    use std::env;
+
+    #[tokio::test]
+    async fn test_bytea_round_trips_in_chunks() {
+        dotenv().ok();
+        let database_url = env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+        let manager = PostgresConnectionManager::new(database_url);
+        let client = manager.connect().await.expect("Failed to connect to database");
+        client.batch_execute(
+            "DROP TABLE IF EXISTS blob_documents;
+             CREATE TABLE blob_documents (id INTEGER PRIMARY KEY, content BYTEA);
+             INSERT INTO blob_documents VALUES (1, NULL);"
+        ).await.unwrap();
+
+        // 5 MiB plus a partial chunk, so the last chunk is short
+        let chunk_size = 1024 * 1024;
+        let blob: Vec<u8> = (0..5 * chunk_size + 12345).map(|i| (i % 251) as u8).collect();
+        let stored = write_bytea(&client, "blob_documents", "content", "id", &1, chunk_size, &mut blob.as_slice()).await;
+        let mut read_back = Vec::new();
+        let read = read_bytea(&client, "blob_documents", "content", "id", &1, chunk_size, &mut read_back).await;
+        let stored_length: i32 = client.query_one("SELECT octet_length(content) FROM blob_documents WHERE id = 1", &[]).await.unwrap().get(0);
+        let missing = read_bytea(&client, "blob_documents", "content", "id", &2, chunk_size, &mut Vec::new()).await;
+        let missing_write = write_bytea(&client, "blob_documents", "content", "id", &2, chunk_size, &mut blob.as_slice()).await;
+        let emptied = write_bytea(&client, "blob_documents", "content", "id", &1, chunk_size, &mut &[][..]).await;
+        let empty_length: i32 = client.query_one("SELECT octet_length(content) FROM blob_documents WHERE id = 1", &[]).await.unwrap().get(0);
+        client.batch_execute("DROP TABLE blob_documents").await.unwrap();
+
+        assert_eq!(stored.expect("Failed to write blob"), blob.len() as u64);
+        assert_eq!(read.expect("Failed to read blob"), blob.len() as u64);
+        assert_eq!(stored_length as usize, blob.len());
+        assert!(read_back == blob, "The blob read back differs from the one written");
+        assert!(matches!(missing, Err(OrmError::QueryError(_))));
+        assert!(matches!(missing_write, Err(OrmError::QueryError(_))));
+        assert_eq!(emptied.expect("Failed to empty blob"), 0);
+        assert_eq!(empty_length, 0);
+    }
+}
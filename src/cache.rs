@@ -27,4 +27,14 @@ where
         let mut store = self.store.write().await;
         store.insert(key, value);
     }
-}
\ No newline at end of file
+}
+
+impl<K, V> Default for Cache<K, V>
+where
+    K: Eq + Hash + Clone,
+    V: Clone,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
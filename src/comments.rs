@@ -0,0 +1,124 @@
+use crate::query_builder::quote_identifier;
+
+/// Documentation for a table and its columns, pushed into the database as
+/// `COMMENT ON` statements so database comments stay in sync with code docs.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TableDocs {
+    pub table: String,
+    pub comment: Option<String>,
+    /// `(column name, comment)` pairs.
+    pub columns: Vec<(String, String)>,
+}
+
+impl TableDocs {
+    pub fn new(table: &str) -> Self {
+        Self { table: table.to_string(), ..Self::default() }
+    }
+
+    pub fn with_comment(mut self, comment: &str) -> Self {
+        self.comment = Some(comment.to_string());
+        self
+    }
+
+    pub fn with_column(mut self, column: &str, comment: &str) -> Self {
+        self.columns.push((column.to_string(), comment.to_string()));
+        self
+    }
+
+    /// Reads the `///` doc comments of a struct definition such as the ones
+    /// written by `generate_struct`. The comment above `pub struct` becomes
+    /// the table comment and the comment above each field its column
+    /// comment, using the `#[serde(rename = "...")]` name when present.
+    pub fn from_source(table: &str, source: &str) -> Self {
+        let mut docs = Self::new(table);
+        let mut pending: Vec<&str> = Vec::new();
+
+        for line in source.lines().map(str::trim) {
+            if let Some(doc) = line.strip_prefix("///") {
+                pending.push(doc.trim());
+                continue;
+            }
+            if line.starts_with("#[") && !line.contains("pub ") {
+                continue;
+            }
+            if !pending.is_empty() {
+                let comment = pending.join(" ");
+                if line.contains("pub struct ") {
+                    docs.comment = Some(comment);
+                } else if let Some(column) = field_column_name(line) {
+                    docs.columns.push((column, comment));
+                }
+            }
+            pending.clear();
+        }
+        docs
+    }
+}
+
+/// Builds the `COMMENT ON TABLE` / `COMMENT ON COLUMN` statements for `docs`.
+pub fn comment_statements(docs: &TableDocs) -> Vec<String> {
+    let table = quote_identifier(&docs.table);
+    let mut statements = Vec::new();
+    if let Some(comment) = &docs.comment {
+        statements.push(format!("COMMENT ON TABLE {} IS {};", table, quote_literal(comment)));
+    }
+    for (column, comment) in &docs.columns {
+        statements.push(format!("COMMENT ON COLUMN {}.{} IS {};", table, quote_identifier(column), quote_literal(comment)));
+    }
+    statements
+}
+
+fn field_column_name(line: &str) -> Option<String> {
+    let field = line.split("pub ").nth(1)?.split(':').next()?.trim();
+    if field.is_empty() || field.contains(' ') {
+        return None;
+    }
+    let renamed = line.split("rename = \"").nth(1).and_then(|rest| rest.split('"').next());
+    Some(renamed.unwrap_or(field).to_string())
+}
+
+fn quote_literal(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "''"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_comment_statements() {
+        let docs = TableDocs::new("customer")
+            .with_comment("People who rent films")
+            .with_column("email", "Contact address, may be NULL")
+            .with_column("zip code", "Customer's postal code");
+
+        assert_eq!(comment_statements(&docs), vec![
+            "COMMENT ON TABLE customer IS 'People who rent films';",
+            "COMMENT ON COLUMN customer.email IS 'Contact address, may be NULL';",
+            "COMMENT ON COLUMN customer.\"zip code\" IS 'Customer''s postal code';",
+        ]);
+    }
+
+    #[test]
+    fn test_table_docs_from_source() {
+        let source = r#"
+/// People who rent films
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Customer {
+    /// Primary key
+    #[serde(rename = "customer_id")] pub customer_id: i32,
+    #[serde(rename = "first_name")] pub first_name: String,
+    /// Postal code,
+    /// without country prefix
+    #[serde(rename = "zip code")] pub zip_code: String,
+}
+"#;
+        let docs = TableDocs::from_source("customer", source);
+
+        assert_eq!(docs.comment.as_deref(), Some("People who rent films"));
+        assert_eq!(docs.columns, vec![
+            ("customer_id".to_string(), "Primary key".to_string()),
+            ("zip code".to_string(), "Postal code, without country prefix".to_string()),
+        ]);
+    }
+}
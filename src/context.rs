@@ -1,57 +1,262 @@
 use crate::error::OrmError;
-use crate::metadata::{get_tables, get_columns};
-use crate::generator::generate_struct;
-use crate::crud::generate_crud_operations;
+use crate::metadata::{get_tables, get_views, get_columns, get_composite_types, get_enum_types, get_foreign_keys, get_generated_columns, get_primary_keys, get_unique_constraints, infer_view_key};
+use crate::generator::{generate_composite_type, generate_enum_type, generate_relationship_methods, generate_struct_from_columns, GeneratorConfig};
+use crate::crud::{generate_crud_operations_with_key, generate_header, generate_upsert_operations, generate_view_operations};
 use std::collections::HashMap;
 use std::fs;
 use std::path::Path;
-use log::{info, error};
-use crate::db::PostgresConnectionManager;
+use log::{info, error, warn};
+use crate::db::{build_pool, get_pooled, set_statement_timeout, Pool, PooledClient, PostgresConnectionManager};
 use chrono::Utc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+use tokio_postgres::types::ToSql;
+use tokio_postgres::{Client, Row};
+
+/// Connections `DbContext::new` allows in its pool, see `with_pool`.
+pub const DEFAULT_POOL_SIZE: usize = 10;
 
 pub struct DbContext {
     pub manager: PostgresConnectionManager,
+    /// Reused connections to the same database as `manager`, see `get`.
+    pub pool: Pool,
+    pub generator_config: GeneratorConfig,
+    pub statement_timeout: Option<Duration>,
+    pub pool_timeout: Option<Duration>,
+    /// Queries run through `query` and `execute` that take longer than this
+    /// are logged at warn level.
+    pub slow_query_threshold: Option<Duration>,
+    /// Pools of read replicas `get_read` spreads connections over, see
+    /// `with_replicas`. Empty unless replicas were added.
+    pub replica_pools: Vec<Pool>,
+    next_replica: AtomicUsize,
 }
 
 impl DbContext {
     pub async fn new(database_url: &str) -> Result<Self, OrmError> {
         let manager = PostgresConnectionManager::new(database_url.to_string());
-        Ok(Self { manager })
+        let pool = build_pool(PostgresConnectionManager::new(database_url.to_string()), DEFAULT_POOL_SIZE)?;
+        Ok(Self { manager, pool, generator_config: GeneratorConfig::default(), statement_timeout: None, pool_timeout: None, slow_query_threshold: None, replica_pools: Vec::new(), next_replica: AtomicUsize::new(0) })
+    }
+
+    pub fn with_generator_config(mut self, config: GeneratorConfig) -> Self {
+        self.generator_config = config;
+        self
+    }
+
+    /// Has the server cancel any statement that runs longer than `timeout`
+    /// on connections opened through `connect`.
+    pub fn with_statement_timeout(mut self, timeout: Duration) -> Self {
+        self.statement_timeout = Some(timeout);
+        self
+    }
+
+    /// Replaces the default pool, e.g. with one from `db::build_pool` with
+    /// another maximum size.
+    pub fn with_pool(mut self, pool: Pool) -> Self {
+        self.pool = pool;
+        self
+    }
+
+    /// Makes `get` fail with a `ConnectionError` instead of waiting longer
+    /// than `timeout` for a pooled connection.
+    pub fn with_pool_timeout(mut self, timeout: Duration) -> Self {
+        self.pool_timeout = Some(timeout);
+        self
+    }
+
+    /// Logs queries run through `query` and `execute` that take longer than
+    /// `threshold`, with their SQL and duration. Faster ones are not logged.
+    pub fn with_slow_query_threshold(mut self, threshold: Duration) -> Self {
+        self.slow_query_threshold = Some(threshold);
+        self
+    }
+
+    /// Adds a pool of `DEFAULT_POOL_SIZE` connections for each replica URL,
+    /// which `get_read` then takes connections from.
+    pub fn with_replicas(mut self, replica_urls: &[&str]) -> Result<Self, OrmError> {
+        for url in replica_urls {
+            self.replica_pools.push(build_pool(PostgresConnectionManager::new(url.to_string()), DEFAULT_POOL_SIZE)?);
+        }
+        Ok(self)
+    }
+
+    /// Takes a connection from the pool, applying the statement timeout if
+    /// one is set. It returns to the pool when dropped.
+    pub async fn get(&self) -> Result<PooledClient, OrmError> {
+        self.get_from(&self.pool).await
+    }
+
+    /// A pooled connection for functions that only read (`get_*`, `list_*`,
+    /// `count_*` and `export_*`, see `db::Access`), taken from the replica
+    /// pools in round-robin order, or from the primary pool without replicas.
+    pub async fn get_read(&self) -> Result<PooledClient, OrmError> {
+        if self.replica_pools.is_empty() {
+            return self.get().await;
+        }
+        let index = self.next_replica.fetch_add(1, Ordering::Relaxed) % self.replica_pools.len();
+        self.get_from(&self.replica_pools[index]).await
+    }
+
+    /// A pooled connection for functions that modify data, always taken from
+    /// the primary pool; the same as `get`.
+    pub async fn get_write(&self) -> Result<PooledClient, OrmError> {
+        self.get().await
+    }
+
+    async fn get_from(&self, pool: &Pool) -> Result<PooledClient, OrmError> {
+        let client = get_pooled(pool, self.pool_timeout).await?;
+        if let Some(timeout) = self.statement_timeout {
+            set_statement_timeout(&client, timeout).await?;
+        }
+        Ok(client)
+    }
+
+    /// Opens a new connection outside the pool, applying the statement
+    /// timeout if one is set.
+    pub async fn connect(&self) -> Result<Client, OrmError> {
+        let client = self.manager.connect().await?;
+        if let Some(timeout) = self.statement_timeout {
+            set_statement_timeout(&client, timeout).await?;
+        }
+        Ok(client)
+    }
+
+    /// Runs `sql` on a pooled connection and returns the rows.
+    pub async fn query(&self, sql: &str, params: &[&(dyn ToSql + Sync)]) -> Result<Vec<Row>, OrmError> {
+        let conn = self.get().await?;
+        let start = Instant::now();
+        let result = conn.query(sql, params).await;
+        self.log_if_slow(sql, start.elapsed());
+        Ok(result?)
+    }
+
+    /// Runs `sql` on a pooled connection and returns the number of rows
+    /// affected.
+    pub async fn execute(&self, sql: &str, params: &[&(dyn ToSql + Sync)]) -> Result<u64, OrmError> {
+        let conn = self.get().await?;
+        let start = Instant::now();
+        let result = conn.execute(sql, params).await;
+        self.log_if_slow(sql, start.elapsed());
+        Ok(result?)
+    }
+
+    fn log_if_slow(&self, sql: &str, elapsed: Duration) {
+        if let Some(threshold) = self.slow_query_threshold {
+            if elapsed > threshold {
+                warn!("Slow query took {:?} (threshold {:?}): {}", elapsed, threshold, sql);
+            }
+        }
+    }
+
+    /// Checks that `sql` parses and refers to existing tables and columns by
+    /// preparing it on the server, without executing it.
+    pub async fn validate_query(&self, sql: &str) -> Result<(), OrmError> {
+        let conn = self.get().await?;
+        match conn.prepare(sql).await {
+            Ok(_) => Ok(()),
+            Err(e) => Err(OrmError::QueryError(match e.as_db_error() {
+                Some(db_error) => format!("Invalid query '{}': {}", sql, db_error.message()),
+                None => format!("Invalid query '{}': {}", sql, e),
+            })),
+        }
     }
 
     pub async fn reverse_engineer(&self, output_dir: &str, author: &str, github_link: &str) -> Result<(), OrmError> {
         info!("Reverse engineering the database schema");
-        let conn = self.manager.connect().await?;
+        let conn = self.get().await?;
         let tables = get_tables(&conn).await?;
         let date = Utc::now().date_naive();
+
+        // Columns of composite and enum types map to the types in
+        // composite_types.rs and enum_types.rs
+        let composite_types = get_composite_types(&conn).await?;
+        let enum_types = get_enum_types(&conn).await?;
+        let mut config = self.generator_config.clone();
+        config.composite_types.extend(composite_types.iter().map(|composite| composite.name.clone()));
+        config.enum_types.extend(enum_types.iter().map(|enum_type| enum_type.name.clone()));
+        if !enum_types.is_empty() {
+            fs::create_dir_all(output_dir)?;
+            let mut enum_defs = generate_header(author, github_link, date);
+            for enum_type in &enum_types {
+                enum_defs.push_str(&generate_enum_type(enum_type));
+                enum_defs.push('\n');
+            }
+            self.write_generated_file(&Path::new(output_dir).join("enum_types.rs"), enum_defs)?;
+        }
+        if !composite_types.is_empty() {
+            fs::create_dir_all(output_dir)?;
+            let mut composite_defs = generate_header(author, github_link, date);
+            for composite in &composite_types {
+                composite_defs.push_str(&generate_composite_type(composite, &config));
+                composite_defs.push('\n');
+            }
+            self.write_generated_file(&Path::new(output_dir).join("composite_types.rs"), composite_defs)?;
+        }
+
         for table in tables {
             info!("Processing table: {}", table);
+            if config.insert_structs {
+                config.generated_columns.insert(table.clone(), get_generated_columns(&conn, &table).await?);
+            }
             match get_columns(&conn, &table).await {
                 Ok(columns) => {
-                    let columns_map: HashMap<String, String> = columns.into_iter().collect();
-                    let struct_def = generate_struct(&table, columns_map.clone(), author, github_link, date);
-                    let crud_ops = generate_crud_operations(&table, columns_map, author, github_link, date);
+                    let mut struct_def = generate_struct_from_columns(&table, &columns, &config, author, github_link, date);
+                    let foreign_keys: Vec<_> = get_foreign_keys(&conn, &table).await?.iter().map(|fk| fk.dependency(&table)).collect();
+                    struct_def.push_str(&generate_relationship_methods(&table, &columns, &foreign_keys));
+                    let columns_map: HashMap<String, String> = columns.into_iter().map(|c| (c.name, c.data_type)).collect();
+                    let unique_keys = get_unique_constraints(&conn, &table).await?;
+                    let primary_key = get_primary_keys(&conn, &table).await?;
+                    let primary_key = match primary_key.as_slice() {
+                        [key] => Some(key.as_str()),
+                        _ => None,
+                    };
+                    let mut crud_ops = generate_crud_operations_with_key(&table, columns_map.clone(), primary_key, &config, author, github_link, date)?;
+                    crud_ops.push_str(&generate_upsert_operations(&table, &columns_map, &unique_keys, &config));
 
                     // Ensure output directory exists
                     fs::create_dir_all(output_dir)?;
 
                     // Write struct definition to file
                     let struct_file_path = Path::new(output_dir).join(format!("{}.rs", table));
-                    fs::write(&struct_file_path, struct_def)
-                        .map_err(|e| OrmError::IoError(e))?;
+                    self.write_generated_file(&struct_file_path, struct_def)?;
 
                     // Write CRUD operations to file
                     let crud_file_path = Path::new(output_dir).join(format!("{}_crud.rs", table));
-                    fs::write(&crud_file_path, crud_ops)
-                        .map_err(|e| OrmError::IoError(e))?;
+                    self.write_generated_file(&crud_file_path, crud_ops)?;
 
                     info!("Completed processing table: {}", table);
                 }
                 Err(e) => error!("Failed to get columns for table {}: {}", table, e),
             }
         }
+        for view in get_views(&conn).await? {
+            info!("Processing view: {}", view);
+            match get_columns(&conn, &view).await {
+                Ok(columns) => {
+                    let struct_def = generate_struct_from_columns(&view, &columns, &config, author, github_link, date);
+                    let key = infer_view_key(&view, &columns).map(|c| c.name.clone());
+                    if key.is_none() {
+                        info!("No key column found for view {}, only generating a list function", view);
+                    }
+                    let columns_map: HashMap<String, String> = columns.into_iter().map(|c| (c.name, c.data_type)).collect();
+                    let view_ops = generate_view_operations(&view, columns_map, key.as_deref(), &config, author, github_link, date);
+
+                    fs::create_dir_all(output_dir)?;
+                    self.write_generated_file(&Path::new(output_dir).join(format!("{}.rs", view)), struct_def)?;
+                    self.write_generated_file(&Path::new(output_dir).join(format!("{}_crud.rs", view)), view_ops)?;
+
+                    info!("Completed processing view: {}", view);
+                }
+                Err(e) => error!("Failed to get columns for view {}: {}", view, e),
+            }
+        }
         Ok(())
     }
+
+    fn write_generated_file(&self, path: &Path, contents: String) -> Result<(), OrmError> {
+        fs::write(path, self.generator_config.finish_file(contents)).map_err(OrmError::IoError)
+    }
 }
 
 #[cfg(test)]
@@ -72,4 +277,161 @@ mod tests {
         }
         assert!(result.is_ok(), "Reverse engineering should succeed");
     }
-}
\ No newline at end of file
+
+    #[tokio::test]
+    async fn test_validate_query() {
+        dotenv().ok();
+        let database_url = env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+        let db_context = DbContext::new(&database_url).await.unwrap();
+
+        assert!(db_context.validate_query("SELECT $1::int + 1").await.is_ok());
+
+        match db_context.validate_query("SELECT * FROM table_that_does_not_exist").await {
+            Err(OrmError::QueryError(message)) => assert!(message.contains("relation \"table_that_does_not_exist\" does not exist"), "{}", message),
+            other => panic!("Expected QueryError, got {:?}", other),
+        }
+        match db_context.validate_query("SELEC 1").await {
+            Err(OrmError::QueryError(message)) => assert!(message.contains("syntax error at or near \"SELEC\""), "{}", message),
+            other => panic!("Expected QueryError, got {:?}", other),
+        }
+    }
+
+    /// Keeps warnings in memory so tests can check what was logged.
+    struct CapturingLogger {
+        warnings: std::sync::Mutex<Vec<String>>,
+    }
+
+    impl log::Log for CapturingLogger {
+        fn enabled(&self, metadata: &log::Metadata) -> bool {
+            metadata.level() <= log::Level::Warn
+        }
+
+        fn log(&self, record: &log::Record) {
+            if self.enabled(record.metadata()) {
+                self.warnings.lock().unwrap().push(record.args().to_string());
+            }
+        }
+
+        fn flush(&self) {}
+    }
+
+    static LOGGER: CapturingLogger = CapturingLogger { warnings: std::sync::Mutex::new(Vec::new()) };
+
+    #[tokio::test]
+    async fn test_slow_queries_are_logged_above_threshold() {
+        dotenv().ok();
+        let database_url = env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+        log::set_logger(&LOGGER).expect("Another logger is already installed");
+        log::set_max_level(log::LevelFilter::Warn);
+        let db_context = DbContext::new(&database_url).await.unwrap().with_slow_query_threshold(Duration::from_millis(100));
+
+        let slow = db_context.query("SELECT pg_sleep(0.3), 'slow_query_marker'", &[]).await;
+        let fast = db_context.execute("SELECT 'fast_query_marker'", &[]).await;
+
+        slow.expect("Slow query failed");
+        fast.expect("Fast query failed");
+        let warnings = LOGGER.warnings.lock().unwrap().clone();
+        assert!(warnings.iter().any(|warning| warning.starts_with("Slow query took ") && warning.ends_with("(threshold 100ms): SELECT pg_sleep(0.3), 'slow_query_marker'")), "{:?}", warnings);
+        assert!(!warnings.iter().any(|warning| warning.contains("fast_query_marker")), "{:?}", warnings);
+    }
+
+    #[tokio::test]
+    async fn test_statement_timeout() {
+        dotenv().ok();
+        let database_url = env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+        let db_context = DbContext::new(&database_url).await.unwrap().with_statement_timeout(Duration::from_millis(100));
+        let client = db_context.connect().await.expect("Failed to connect to database");
+
+        match client.execute("SELECT pg_sleep(5)", &[]).await {
+            Err(e) => assert_eq!(e.code(), Some(&tokio_postgres::error::SqlState::QUERY_CANCELED)),
+            Ok(_) => panic!("Expected the server to cancel the query"),
+        }
+        assert!(client.execute("SELECT pg_sleep(0.01)", &[]).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_gets_reuse_pooled_connections() {
+        dotenv().ok();
+        let database_url = env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+        let pool = build_pool(PostgresConnectionManager::new(database_url.clone()), 2).unwrap();
+        let db_context = DbContext::new(&database_url).await.unwrap()
+            .with_pool(pool)
+            .with_pool_timeout(Duration::from_secs(5))
+            .with_statement_timeout(Duration::from_secs(30));
+
+        let backend_pid = || async {
+            let client = db_context.get().await.expect("Failed to get a pooled connection");
+            let row = client.query_one("SELECT pg_backend_pid(), current_setting('statement_timeout')", &[]).await.unwrap();
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            (row.get::<_, i32>(0), row.get::<_, String>(1))
+        };
+        let results = futures_util::future::join_all((0..8).map(|_| backend_pid())).await;
+
+        let mut pids: Vec<i32> = results.iter().map(|(pid, _)| *pid).collect();
+        pids.sort();
+        pids.dedup();
+        assert!(pids.len() <= 2, "Expected at most 2 connections, got {:?}", pids);
+        assert!(results.iter().all(|(_, timeout)| timeout == "30s"));
+        assert_eq!(db_context.pool.status().size, pids.len());
+        assert_eq!(db_context.pool.status().max_size, 2);
+    }
+
+    #[tokio::test]
+    async fn test_reads_go_to_replica_pools() {
+        dotenv().ok();
+        let database_url = env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+        let db_context = DbContext::new(&database_url).await.unwrap()
+            .with_statement_timeout(Duration::from_secs(30))
+            .with_replicas(&[&database_url, &database_url]).unwrap();
+
+        let first = db_context.get_read().await.expect("Failed to get a replica connection");
+        let second = db_context.get_read().await.expect("Failed to get a replica connection");
+        let timeout: String = first.query_one("SELECT current_setting('statement_timeout')", &[]).await.unwrap().get(0);
+        assert_eq!(timeout, "30s");
+        assert_eq!(db_context.pool.status().size, 0);
+        assert_eq!(db_context.replica_pools[0].status().size, 1);
+        assert_eq!(db_context.replica_pools[1].status().size, 1);
+        drop((first, second));
+
+        let writer = db_context.get_write().await.expect("Failed to get a primary connection");
+        assert_eq!(writer.query_one("SELECT 1", &[]).await.unwrap().get::<_, i32>(0), 1);
+        assert_eq!(db_context.pool.status().size, 1);
+
+        let without_replicas = DbContext::new(&database_url).await.unwrap();
+        let reader = without_replicas.get_read().await.expect("Failed to get a primary connection");
+        assert_eq!(reader.query_one("SELECT 1", &[]).await.unwrap().get::<_, i32>(0), 1);
+        assert_eq!(without_replicas.pool.status().size, 1);
+    }
+
+    #[tokio::test]
+    async fn test_reverse_engineer_post_process() {
+        dotenv().ok();
+        let database_url = env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+        let manager = PostgresConnectionManager::new(database_url.clone());
+        let client = manager.connect().await.expect("Failed to connect to database");
+        client.batch_execute(
+            "CREATE TABLE IF NOT EXISTS post_process_notes (id INTEGER PRIMARY KEY, body TEXT NOT NULL)"
+        ).await.expect("Failed to create test table");
+
+        const LICENSE: &str = "// SPDX-License-Identifier: MIT\n";
+        let config = GeneratorConfig::default().with_post_process(|contents| format!("{}{}", LICENSE, contents));
+        let db_context = DbContext::new(&database_url).await.unwrap().with_generator_config(config);
+        let output_dir = env::temp_dir().join(format!("rust_orm_gen_post_process_{}", std::process::id()));
+        let result = db_context.reverse_engineer(output_dir.to_str().unwrap(), "Tom Blanchard", "https://github.com/tomblanchard312/rust_orm_gen").await;
+
+        let files: Vec<(String, String)> = fs::read_dir(&output_dir)
+            .map(|entries| entries
+                .map(|entry| entry.unwrap().path())
+                .map(|path| (path.display().to_string(), fs::read_to_string(&path).unwrap()))
+                .collect())
+            .unwrap_or_default();
+        fs::remove_dir_all(&output_dir).ok();
+        client.batch_execute("DROP TABLE post_process_notes").await.unwrap();
+
+        assert!(result.is_ok(), "Reverse engineering failed: {:?}", result.err());
+        assert!(files.iter().any(|(path, _)| path.ends_with("post_process_notes.rs")));
+        for (path, contents) in files {
+            assert!(contents.starts_with(LICENSE), "{} is missing the header", path);
+        }
+    }
+}
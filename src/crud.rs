@@ -1,6 +1,10 @@
 use std::collections::HashMap;
 use convert_case::{Case, Casing};
 use chrono::NaiveDate;
+use log::warn;
+use crate::generator::{field_name, is_copy_type, map_column_type, variant_name, CrudStyle, GeneratorConfig};
+use crate::error::OrmError;
+use crate::query_builder::quote_identifier;
 
 pub fn generate_header(author: &str, github_link: &str, date: NaiveDate) -> String {
     format!(
@@ -9,88 +13,424 @@ pub fn generate_header(author: &str, github_link: &str, date: NaiveDate) -> Stri
     )
 }
 
-pub fn generate_crud_operations(table_name: &str, columns: HashMap<String, String>, author: &str, github_link: &str, date: NaiveDate) -> String {
+pub fn generate_crud_operations(table_name: &str, columns: HashMap<String, String>, author: &str, github_link: &str, date: NaiveDate) -> Result<String, OrmError> {
+    generate_crud_operations_with_config(table_name, columns, &GeneratorConfig::default(), author, github_link, date)
+}
+
+pub fn generate_crud_operations_with_config(table_name: &str, columns: HashMap<String, String>, config: &GeneratorConfig, author: &str, github_link: &str, date: NaiveDate) -> Result<String, OrmError> {
+    let primary_key = columns.contains_key("id").then_some("id");
+    generate_crud_operations_with_key(table_name, columns, primary_key, config, author, github_link, date)
+}
+
+/// Generates CRUD operations keyed on `primary_key`, as returned by
+/// `metadata::get_primary_keys`. The key's column name and type shape the
+/// signatures, e.g. `get_customer(client, customer_id: i32)`. Without a
+/// single-column key, the functions that look up one row (get, update,
+/// delete and keyset paging) are skipped. Fails with `OrmError::ParseError`
+/// when two columns map to the same struct field, e.g. `zip code` and
+/// `zip_code`, since the generated code could not tell them apart.
+pub fn generate_crud_operations_with_key(table_name: &str, columns: HashMap<String, String>, primary_key: Option<&str>, config: &GeneratorConfig, author: &str, github_link: &str, date: NaiveDate) -> Result<String, OrmError> {
     let header = generate_header(author, github_link, date);
     let struct_name = table_name.to_case(Case::Pascal);
-    let mut crud_ops = format!("{}use tokio_postgres::Client;\nuse crate::query_builder::QueryBuilder;\n\n", header);
+    let mut crud_ops = String::new();
+
+    // Free functions carry the table name; inherent methods are namespaced by the struct
+    let function_name = |verb: &str| match config.crud_style {
+        CrudStyle::FreeFunctions => format!("{}_{}", verb, table_name),
+        CrudStyle::InherentImpl => verb.to_string(),
+    };
+    let create_fn = function_ident(&function_name("create"));
+    let create_batch_fn = function_ident(&format!("{}_batch", function_name("create")));
+    let get_fn = function_ident(&function_name("get"));
+    let get_by_ids_fn = function_ident(&format!("{}_by_ids", function_name("get")));
+    let get_for_update_fn = function_ident(&format!("{}_for_update", function_name("get")));
+    let refresh_fn = function_ident(&function_name("refresh"));
+    let update_fn = function_ident(&function_name("update"));
+    let update_changes_fn = function_ident(&format!("{}_changes", function_name("update")));
+    let delete_fn = function_ident(&function_name("delete"));
+    let delete_returning_fn = function_ident(&format!("{}_returning", function_name("delete")));
+    let list_fn = function_ident(&function_name("list"));
+    let list_page_fn = function_ident(&format!("{}_page", function_name("list")));
+    let list_ordered_fn = function_ident(&format!("{}_ordered", function_name("list")));
+    let list_sorted_fn = function_ident(&format!("{}_sorted", function_name("list")));
+    let count_fn = function_ident(&function_name("count"));
+    let truncate_fn = function_ident(&function_name("truncate"));
+    let export_fn = function_ident(&format!("{}_ndjson", function_name("export")));
+    let to_db_fn = function_ident(&format!("{}_to_db", table_name));
+    let mut type_defs = String::new();
+    let instrument = |operation: &str, code: String| if config.metrics {
+        instrument_fn(&code, table_name, operation)
+    } else {
+        code
+    };
 
     // Sort the column names to ensure consistent order
     let mut column_names: Vec<String> = columns.keys().cloned().collect();
     column_names.sort();
 
-    // Generate Create function
-    crud_ops.push_str(&format!(
-        "pub async fn create_{table_name}(client: &Client, entity: &{struct_name}) -> Result<{struct_name}, tokio_postgres::Error> {{
+    // Private helper the write functions run entities through first
+    let to_db_def = |to_db_fn: &str, struct_name: &str, names: &[&str]| format!(
+        "/// Applies the registered column transforms before a write.
+fn {to_db_fn}(entity: &{struct_name}) -> {struct_name} {{
+    {struct_name} {{
+        {}
+    }}
+}}\n\n",
+        names.iter().map(|name| {
+            let field = field_name(name);
+            let rust_type = map_column_type(&columns[*name], config);
+            if rust_type == "String" {
+                format!("{field}: crate::transforms::to_db_value(\"{table_name}\", \"{name}\", &entity.{field}),")
+            } else if is_copy_type(&rust_type) {
+                format!("{field}: entity.{field},")
+            } else {
+                format!("{field}: entity.{field}.clone(),")
+            }
+        }).collect::<Vec<_>>().join("\n        ")
+    );
+    let all_columns: Vec<&str> = column_names.iter().map(String::as_str).collect();
+    type_defs.push_str(&to_db_def(&to_db_fn, &struct_name, &all_columns));
+
+    // Inserts take New{Struct} when the database fills in some columns itself
+    let (insert_struct, insert_to_db_fn, insert_columns) = match config.insert_columns(table_name, &all_columns) {
+        Some(insert_columns) => {
+            let insert_struct = format!("New{}", struct_name);
+            let insert_to_db_fn = function_ident(&format!("{}_new_to_db", table_name));
+            if !insert_columns.is_empty() {
+                type_defs.push_str(&to_db_def(&insert_to_db_fn, &insert_struct, &insert_columns));
+            }
+            (insert_struct, insert_to_db_fn, insert_columns)
+        }
+        None => (struct_name.clone(), to_db_fn.clone(), all_columns.clone()),
+    };
+    let insert_layout = InsertLayout::new(&insert_columns, &all_columns);
+    insert_layout.check().map_err(|e| OrmError::ParseError(format!("Cannot generate inserts for table '{}': {}", table_name, e)))?;
+
+    let returning_sql = insert_layout.returning.iter().map(|name| quote_identifier(name)).collect::<Vec<_>>().join(", ");
+    if insert_layout.columns.is_empty() {
+        // The database fills in every column, so there is nothing to bind
+        let default_insert_sql = format!("INSERT INTO {} DEFAULT VALUES RETURNING {}", quote_identifier(table_name), returning_sql);
+        crud_ops.push_str(&instrument("create", format!(
+            "pub async fn {create_fn}(client: &Client, _entity: &{insert_struct}) -> Result<{struct_name}, OrmError> {{
+    let row = client.query_one({default_insert_sql:?}, &[]).await?;
+    
+    {struct_name}::try_from_row(&row)
+}}\n\n"
+        )));
+
+        let batch_insert_sql = format!(
+            "INSERT INTO {} SELECT FROM generate_series(1, $1::bigint) AS input(ordinal) ORDER BY ordinal RETURNING {}",
+            quote_identifier(table_name),
+            returning_sql
+        );
+        crud_ops.push_str(&instrument("create_batch", format!(
+            "/// Inserts one row per entry of `entities` in a single statement and
+/// returns the created rows in `entities` order.
+pub async fn {create_batch_fn}(client: &Client, entities: &[{insert_struct}]) -> Result<Vec<{struct_name}>, OrmError> {{
+    // Every column takes its default, as with DEFAULT VALUES
+    let rows = client.query({batch_insert_sql:?}, &[&(entities.len() as i64)]).await?;
+    rows.iter().map({struct_name}::try_from_row).collect()
+}}\n\n"
+        )));
+    } else {
+        // Generate Create function
+        crud_ops.push_str(&instrument("create", format!(
+            "pub async fn {create_fn}(client: &Client, entity: &{insert_struct}) -> Result<{struct_name}, OrmError> {{
+    let entity = &{insert_to_db_fn}(entity);
     let (query, params) = QueryBuilder::insert::<{struct_name}>()
+        .columns(&[{}])
         .values(&[{}])
         .returning(&[{}])
         .build();
     
     let row = client.query_one(&query, &params[..]).await?;
     
-    Ok({struct_name} {{
-        {}
-    }})
+    {struct_name}::try_from_row(&row)
 }}\n\n",
-        column_names.iter().map(|name| format!("&entity.{}", name.replace(" ", "_"))).collect::<Vec<_>>().join(", "),
-        column_names.iter().map(|name| format!("\"{}\"", name)).collect::<Vec<_>>().join(", "),
-        column_names.iter().map(|name| format!("{}: row.get(\"{}\"),", name.replace(" ", "_"), name)).collect::<Vec<_>>().join("\n        ")
-    ));
+            insert_layout.columns.iter().map(|name| format!("\"{}\"", name)).collect::<Vec<_>>().join(", "),
+            insert_layout.values.iter().map(|field| format!("&entity.{}", field)).collect::<Vec<_>>().join(", "),
+            insert_layout.returning.iter().map(|name| format!("\"{}\"", name)).collect::<Vec<_>>().join(", ")
+        )));
 
-    // Generate Read function
-    crud_ops.push_str(&format!(
-        "pub async fn get_{table_name}(client: &Client, id: i32) -> Result<{struct_name}, tokio_postgres::Error> {{
-    let (query, params) = QueryBuilder::select::<{struct_name}>()
-        .where_clause(\"id = $1\")
-        .bind_param(id)
-        .build();
+        // Generate batch Create function. The rows are selected from a VALUES
+        // list ordered by an ordinal, so they are inserted, and returned, in
+        // `entities` order; its first row is never inserted and only gives each
+        // column its type, so Postgres can infer the types of the parameters.
+        let table = quote_identifier(table_name);
+        let ordinal = format!("column{}", insert_layout.columns.len() + 1);
+        let batch_insert_sql = format!(
+            "INSERT INTO {table} ({}) SELECT {} FROM (VALUES ({}, 0), {{}}) AS input WHERE {ordinal} > 0 ORDER BY {ordinal} RETURNING {returning_sql}",
+            insert_layout.columns.iter().map(|name| quote_identifier(name)).collect::<Vec<_>>().join(", "),
+            (1..=insert_layout.columns.len()).map(|i| format!("column{}", i)).collect::<Vec<_>>().join(", "),
+            insert_layout.columns.iter().map(|name| format!("(NULL::{table}).{}", quote_identifier(name))).collect::<Vec<_>>().join(", ")
+        );
+        crud_ops.push_str(&instrument("create_batch", format!(
+            "/// Inserts `entities` in as few statements as the parameter limit allows
+/// and returns the created rows, with the values the database filled in,
+/// in `entities` order.
+pub async fn {create_batch_fn}(client: &Client, entities: &[{insert_struct}]) -> Result<Vec<{struct_name}>, OrmError> {{
+    // Postgres accepts at most 65535 bind parameters per statement
+    const MAX_PARAMS: usize = 65535;
+    const COLUMNS_PER_ROW: usize = {};
+    let entities: Vec<{insert_struct}> = entities.iter().map({insert_to_db_fn}).collect();
+    let mut created = Vec::with_capacity(entities.len());
     
-    let row = client.query_one(&query, &params[..]).await?;
+    for chunk in entities.chunks(MAX_PARAMS / COLUMNS_PER_ROW) {{
+        let mut rows = Vec::with_capacity(chunk.len());
+        let mut params: Vec<&(dyn tokio_postgres::types::ToSql + Sync)> = Vec::with_capacity(chunk.len() * COLUMNS_PER_ROW);
+        for (row_index, entity) in chunk.iter().enumerate() {{
+            let first = row_index * COLUMNS_PER_ROW;
+            let placeholders = (1..=COLUMNS_PER_ROW).map(|i| format!(\"${{}}\", first + i)).collect::<Vec<_>>().join(\", \");
+            rows.push(format!(\"({{}}, {{}})\", placeholders, row_index + 1));
+            params.extend_from_slice(&[{}]);
+        }}
+        let query = format!({batch_insert_sql:?}, rows.join(\", \"));
+        
+        for row in &client.query(&query, &params[..]).await? {{
+            created.push({struct_name}::try_from_row(row)?);
+        }}
+    }}
     
-    Ok({struct_name} {{
-        {}
-    }})
+    Ok(created)
 }}\n\n",
-        column_names.iter().map(|name| format!("{}: row.get(\"{}\"),", name.replace(" ", "_"), name)).collect::<Vec<_>>().join("\n        ")
-    ));
+            insert_layout.columns.len(),
+            insert_layout.values.iter().map(|field| format!("&entity.{}", field)).collect::<Vec<_>>().join(", ")
+        )));
+    }
 
-    // Generate Update function
-    crud_ops.push_str(&format!(
-        "pub async fn update_{table_name}(client: &Client, entity: &{struct_name}) -> Result<{struct_name}, tokio_postgres::Error> {{
-    let (query, params) = QueryBuilder::update::<{struct_name}>()
+    // Every single-row lookup goes through the primary key
+    let primary_key = primary_key.and_then(|key| columns.get(key).map(|data_type| (key, map_column_type(data_type, config))));
+    if primary_key.is_none() {
+        warn!("Table '{}' has no single-column primary key, skipping the get, update and delete functions", table_name);
+    }
+
+    if let Some((key, key_type)) = &primary_key {
+        let key_field = field_name(key);
+        let key_clone = if is_copy_type(key_type) { "" } else { ".clone()" };
+        // Raw conditions on the key, as Rust string literals
+        let key_equals = format!("{:?}", format!("{} = $1", quote_identifier(key)));
+        let key_in = format!("{:?}", format!("{} = ANY($1)", quote_identifier(key)));
+
+        // Generate Read function
+        crud_ops.push_str(&instrument("get", format!(
+            "pub async fn {get_fn}(client: &Client, {key_field}: {key_type}) -> Result<{struct_name}, OrmError> {{
+    QueryBuilder::select::<{struct_name}>()
+        .where_clause({key_equals})
+        .bind_param({key_field})
+        .fetch_one(client)
+        .await
+}}\n\n"
+        )));
+
+        // Generate locking Read function for read-modify-write in a transaction
+        crud_ops.push_str(&instrument("get_for_update", format!(
+            "/// Reads the row and locks it until `transaction` ends, so other
+/// transactions locking or writing it wait for this one to finish.
+pub async fn {get_for_update_fn}(transaction: &tokio_postgres::Transaction<'_>, {key_field}: {key_type}) -> Result<{struct_name}, OrmError> {{
+    let query_builder = QueryBuilder::select::<{struct_name}>()
+        .where_clause({key_equals})
+        .bind_param({key_field})
+        .for_update();
+    
+    let (query, params) = query_builder.try_build()?;
+    
+    let row = transaction.query_one(&query, &params[..]).await?;
+    
+    {struct_name}::try_from_row(&row)
+}}\n\n"
+        )));
+
+        // Generate Refresh function, a method on the entity in the inherent style
+        let (refresh_params, target) = match config.crud_style {
+            CrudStyle::FreeFunctions => (format!("client: &Client, entity: &mut {struct_name}"), "entity"),
+            CrudStyle::InherentImpl => ("&mut self, client: &Client".to_string(), "self"),
+        };
+        crud_ops.push_str(&instrument("refresh", format!(
+            "pub async fn {refresh_fn}({refresh_params}) -> Result<(), OrmError> {{
+    let query_builder = QueryBuilder::select::<{struct_name}>()
+        .where_clause({key_equals})
+        .bind_param({target}.{key_field}{key_clone});
+    
+    let (query, params) = query_builder.build();
+    
+    // The row may have been deleted since the entity was loaded
+    let row = client.query_opt(&query, &params[..]).await?
+        .ok_or_else(|| OrmError::QueryError(format!(\"No row in {table_name} where {key} = {{:?}}\", {target}.{key_field})))?;
+    *{target} = {struct_name}::try_from_row(&row)?;
+    
+    Ok(())
+}}\n\n"
+        )));
+
+        // Generate batched Read function
+        crud_ops.push_str(&instrument("get_by_ids", format!(
+            "pub async fn {get_by_ids_fn}(client: &Client, ids: &[{key_type}]) -> Result<HashMap<{key_type}, {struct_name}>, OrmError> {{
+    let query_builder = QueryBuilder::select::<{struct_name}>()
+        .where_clause({key_in})
+        .bind_param(ids.to_vec());
+    
+    // Ids without a matching row are simply absent from the map
+    let entities = query_builder.fetch_all(client).await?;
+    
+    Ok(entities.into_iter().map(|entity| (entity.{key_field}{key_clone}, entity)).collect())
+}}\n\n"
+        )));
+
+        // Generate Update function
+        crud_ops.push_str(&instrument("update", format!(
+            "pub async fn {update_fn}(client: &Client, entity: &{struct_name}) -> Result<{struct_name}, OrmError> {{
+    let entity = &{to_db_fn}(entity);
+    let query_builder = QueryBuilder::update::<{struct_name}>()
         .set_values(&[{}])
-        .where_clause(\"id = $1\")
-        .bind_param(entity.id)
-        .build();
+        .where_clause({key_equals})
+        .bind_param(entity.{key_field}{key_clone})
+        .returning(&[{}]);
+    
+    let (query, params) = query_builder.build();
     
     let row = client.query_one(&query, &params[..]).await?;
     
-    Ok({struct_name} {{
-        {}
-    }})
+    {struct_name}::try_from_row(&row)
 }}\n\n",
-        column_names.iter().enumerate().map(|(_i, name)| format!("(\"{}\", &entity.{})", name, name.replace(" ", "_"))).collect::<Vec<_>>().join(", "),
-        column_names.iter().map(|name| format!("{}: row.get(\"{}\"),", name.replace(" ", "_"), name)).collect::<Vec<_>>().join("\n        ")
-    ));
+            column_names.iter().map(|name| format!("(\"{}\", &entity.{})", name, field_name(name))).collect::<Vec<_>>().join(", "),
+            column_names.iter().map(|name| format!("\"{}\"", name)).collect::<Vec<_>>().join(", ")
+        )));
 
-    // Generate Delete function
-    crud_ops.push_str(&format!(
-        "pub async fn delete_{table_name}(client: &Client, id: i32) -> Result<bool, tokio_postgres::Error> {{
-    let (query, params) = QueryBuilder::delete::<{struct_name}>()
-        .where_clause(\"id = $1\")
-        .bind_param(id)
-        .build();
+        // Generate Update function that only writes the columns that differ
+        crud_ops.push_str(&instrument("update_changes", format!(
+            "pub async fn {update_changes_fn}(client: &Client, original: &{struct_name}, modified: &{struct_name}) -> Result<Option<{struct_name}>, OrmError> {{
+    // Compared before the column transforms run, since a transform such as
+    // encryption with a random nonce need not give the same output twice
+    let written = &{to_db_fn}(modified);
+    let mut changes: Vec<(&str, &(dyn tokio_postgres::types::ToSql + Sync))> = Vec::new();
+    {}
+    
+    // Nothing changed, so there is nothing to write
+    if changes.is_empty() {{
+        return Ok(None);
+    }}
+    
+    let query_builder = QueryBuilder::update::<{struct_name}>()
+        .set_values(&changes)
+        .where_clause({key_equals})
+        .bind_param(original.{key_field}{key_clone})
+        .returning(&[{}]);
+    
+    let (query, params) = query_builder.build();
+    
+    let row = client.query_one(&query, &params[..]).await?;
+    
+    {struct_name}::try_from_row(&row).map(Some)
+}}\n\n",
+            column_names.iter().map(|name| {
+                let field = field_name(name);
+                format!("if modified.{field} != original.{field} {{\n        changes.push((\"{name}\", &written.{field}));\n    }}")
+            }).collect::<Vec<_>>().join("\n    "),
+            column_names.iter().map(|name| format!("\"{}\"", name)).collect::<Vec<_>>().join(", ")
+        )));
+
+        // Generate Delete function
+        crud_ops.push_str(&instrument("delete", format!(
+            "pub async fn {delete_fn}(client: &Client, {key_field}: {key_type}) -> Result<bool, tokio_postgres::Error> {{
+    let query_builder = QueryBuilder::delete::<{struct_name}>()
+        .where_clause({key_equals})
+        .bind_param({key_field});
+    
+    let (query, params) = query_builder.build();
     
     let result = client.execute(&query, &params[..]).await?;
     
     Ok(result > 0)
 }}\n\n"
-    ));
+        )));
+
+        // Generate Delete function that hands back the removed row
+        crud_ops.push_str(&instrument("delete_returning", format!(
+            "pub async fn {delete_returning_fn}(client: &Client, {key_field}: {key_type}) -> Result<Option<{struct_name}>, OrmError> {{
+    let query_builder = QueryBuilder::delete::<{struct_name}>()
+        .where_clause({key_equals})
+        .bind_param({key_field})
+        .returning(&[{}]);
+    
+    let (query, params) = query_builder.build();
+    
+    let row = client.query_opt(&query, &params[..]).await?;
+    
+    row.as_ref().map({struct_name}::try_from_row).transpose()
+}}\n\n",
+            column_names.iter().map(|name| format!("\"{}\"", name)).collect::<Vec<_>>().join(", ")
+        )));
+    }
+
+    // Generate List function, ordered by the key so pages are stable
+    let list_order = primary_key.as_ref().map(|(key, _)| format!(".tiebreak_by(\"{key}\")")).unwrap_or_default();
+    crud_ops.push_str(&instrument("list", format!(
+        "pub async fn {list_fn}(client: &Client, limit: Option<i64>, offset: Option<i64>) -> Result<Vec<{struct_name}>, OrmError> {{
+    let mut query_builder = QueryBuilder::select::<{struct_name}>(){list_order};
+    
+    if let Some(limit_val) = limit {{
+        query_builder = query_builder.limit(limit_val as usize);
+    }}
+    
+    if let Some(offset_val) = offset {{
+        query_builder = query_builder.offset(offset_val as usize);
+    }}
+    
+    query_builder.fetch_all(client).await
+}}\n"
+    )));
 
-    // Generate List function
-    crud_ops.push_str(&format!(
-        "pub async fn list_{table_name}(client: &Client, limit: Option<i64>, offset: Option<i64>) -> Result<Vec<{struct_name}>, tokio_postgres::Error> {{
+    if let Some((key, key_type)) = &primary_key {
+        let key_field = field_name(key);
+
+        // Generate List function with a caller-chosen order; the key breaks ties
+        crud_ops.push_str(&instrument("list_ordered", format!(
+            "\npub async fn {list_ordered_fn}(client: &Client, order_by: &[(&str, bool)], limit: Option<i64>, offset: Option<i64>) -> Result<Vec<{struct_name}>, OrmError> {{
     let mut query_builder = QueryBuilder::select::<{struct_name}>();
+    for (column, ascending) in order_by {{
+        query_builder = query_builder.order_by(column, *ascending);
+    }}
+    query_builder = query_builder.tiebreak_by(\"{key}\");
+    
+    if let Some(limit_val) = limit {{
+        query_builder = query_builder.limit(limit_val as usize);
+    }}
+    
+    if let Some(offset_val) = offset {{
+        query_builder = query_builder.offset(offset_val as usize);
+    }}
+    
+    query_builder.fetch_all(client).await
+}}\n"
+        )));
+
+        // Generate a sort enum over the orderable columns, so a sort chosen by an API client maps to a fixed ORDER BY
+        let sortable: Vec<&String> = column_names.iter().filter(|name| is_sortable(&columns[name.as_str()])).collect();
+        type_defs.push_str(&format!(
+            "/// A column and direction for `{list_sorted_fn}`. Only these columns can be
+/// sorted on, so a sort taken from a request cannot inject SQL.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum {struct_name}Sort {{
+    {}
+}}
+
+impl {struct_name}Sort {{
+    /// The column to order by and whether the order is ascending.
+    pub fn order_by(self) -> (&'static str, bool) {{
+        match self {{
+            {}
+        }}
+    }}
+}}\n\n",
+            sortable.iter().map(|name| format!("{}(SortDirection),", variant_name(name))).collect::<Vec<_>>().join("\n    "),
+            sortable.iter().map(|name| format!("{struct_name}Sort::{}(direction) => (\"{}\", direction == SortDirection::Asc),", variant_name(name), name)).collect::<Vec<_>>().join("\n            ")
+        ));
+        crud_ops.push_str(&instrument("list_sorted", format!(
+            "\npub async fn {list_sorted_fn}(client: &Client, sort: {struct_name}Sort, limit: Option<i64>, offset: Option<i64>) -> Result<Vec<{struct_name}>, OrmError> {{
+    let (column, ascending) = sort.order_by();
+    let mut query_builder = QueryBuilder::select::<{struct_name}>()
+        .order_by(column, ascending)
+        .tiebreak_by(\"{key}\");
     
     if let Some(limit_val) = limit {{
         query_builder = query_builder.limit(limit_val as usize);
@@ -100,56 +440,1418 @@ pub fn generate_crud_operations(table_name: &str, columns: HashMap<String, Strin
         query_builder = query_builder.offset(offset_val as usize);
     }}
     
+    query_builder.fetch_all(client).await
+}}\n"
+        )));
+
+        // Generate keyset-paginated List function
+        crud_ops.push_str(&instrument("list_page", format!(
+            "\npub async fn {list_page_fn}(client: &Client, cursor: Option<String>, limit: i64) -> Result<Page<{struct_name}>, OrmError> {{
+    let after: Option<{key_type}> = cursor.as_deref().map(decode_cursor).transpose()?;
+    let query_builder = QueryBuilder::select::<{struct_name}>()
+        .keyset_after(\"{key}\", after)
+        .limit(limit as usize);
+    
+    let items = query_builder.fetch_all(client).await?;
+    
+    // A short page means there is nothing left to fetch
+    let next_cursor = if items.len() as i64 == limit {{
+        items.last().map(|entity| encode_cursor(&entity.{key_field}))
+    }} else {{
+        None
+    }};
+    
+    Ok(Page {{ items, next_cursor }})
+}}\n"
+        )));
+    }
+
+    // Generate NDJSON export, streaming rows instead of collecting them
+    let export_order = primary_key.as_ref().map(|(key, _)| format!("\n        .order_by(\"{key}\", true)")).unwrap_or_default();
+    crud_ops.push_str(&instrument("export", format!(
+        "\npub async fn {export_fn}<W: std::io::Write>(client: &Client, writer: &mut W) -> Result<u64, OrmError> {{
+    let query_builder = QueryBuilder::select::<{struct_name}>(){export_order};
+    
     let (query, params) = query_builder.build();
     
-    let rows = client.query(&query, &params[..]).await?;
+    let rows = client.query_raw(&query, params).await?;
     
-    let entities = rows.into_iter().map(|row| {struct_name} {{
-        {}
-    }}).collect();
+    write_ndjson(rows, writer, {struct_name}::try_from_row).await
+}}\n"
+    )));
+
+    // Generate chunked accessors for binary columns, keyed like the other single-row functions
+    if let (Some(chunk_size), Some((key, key_type))) = (config.blob_chunk_size, &primary_key) {
+        let key_field = field_name(key);
+        for column in column_names.iter().filter(|name| columns[name.as_str()] == "bytea") {
+            let column_field = field_name(column);
+            let (read_fn, write_fn) = match config.crud_style {
+                CrudStyle::FreeFunctions => (format!("read_{}_{}", table_name, column_field), format!("write_{}_{}", table_name, column_field)),
+                CrudStyle::InherentImpl => (format!("read_{}", column_field), format!("write_{}", column_field)),
+            };
+            let (read_fn, write_fn) = (function_ident(&read_fn), function_ident(&write_fn));
+            crud_ops.push_str(&instrument("read_blob", format!(
+                "\npub async fn {read_fn}<W: tokio::io::AsyncWrite + Unpin>(client: &Client, {key_field}: {key_type}, writer: &mut W) -> Result<u64, OrmError> {{
+    crate::blob::read_bytea(client, \"{table_name}\", \"{column}\", \"{key}\", &{key_field}, {chunk_size}, writer).await
+}}\n"
+            )));
+            crud_ops.push_str(&instrument("write_blob", format!(
+                "\npub async fn {write_fn}<R: tokio::io::AsyncRead + Unpin>(client: &Client, {key_field}: {key_type}, reader: &mut R) -> Result<u64, OrmError> {{
+    crate::blob::write_bytea(client, \"{table_name}\", \"{column}\", \"{key}\", &{key_field}, {chunk_size}, reader).await
+}}\n"
+            )));
+        }
+    }
+
+    // Generate distinct-value listings for the configured low-cardinality columns
+    for column in config.distinct_columns.get(table_name).into_iter().flatten() {
+        let Some(data_type) = columns.get(column) else { continue };
+        let distinct_fn = function_ident(&match config.crud_style {
+            CrudStyle::FreeFunctions => format!("distinct_{}_{}", table_name, column),
+            CrudStyle::InherentImpl => format!("distinct_{}", column),
+        });
+        let rust_type = map_column_type(data_type, config);
+        let value = if rust_type == "String" {
+            format!("crate::transforms::from_db(\"{table_name}\", \"{column}\", row.get(0))")
+        } else {
+            "row.get(0)".to_string()
+        };
+        let distinct_sql = format!(
+            "SELECT DISTINCT {column} FROM {} WHERE {column} IS NOT NULL ORDER BY {column}",
+            quote_identifier(table_name),
+            column = quote_identifier(column)
+        );
+        crud_ops.push_str(&instrument("distinct", format!(
+            "\npub async fn {distinct_fn}(client: &Client) -> Result<Vec<{rust_type}>, OrmError> {{
+    let rows = client.query({distinct_sql:?}, &[]).await?;
+    
+    Ok(rows.iter().map(|row| {value}).collect())
+}}\n"
+        )));
+    }
+
+    // Generate Filter struct and Count function
+    type_defs.push_str(&format!(
+        "#[derive(Debug, Default, Clone)]
+pub struct {struct_name}Filter {{
+    {}
+}}\n\n",
+        column_names.iter().map(|name| format!("pub {}: Option<{}>,", field_name(name), map_column_type(&columns[name], config))).collect::<Vec<_>>().join("\n    ")
+    ));
+    // Stored text went through the registered transforms, so the filter
+    // values must too; only deterministic transforms can match
+    crud_ops.push_str(&instrument("count", format!(
+        "\npub async fn {count_fn}(client: &Client, filter: {struct_name}Filter) -> Result<i64, OrmError> {{
+    let query_builder = QueryBuilder::select::<{struct_name}>()
+        .select(&[])
+        .select_expr(\"COUNT(*)\", \"count\"){};
+    
+    let (query, params) = query_builder.try_build()?;
     
-    Ok(entities)
+    let row = client.query_one(&query, &params[..]).await?;
+    
+    Ok(row.get(\"count\"))
 }}\n",
-        column_names.iter().map(|name| format!("{}: row.get(\"{}\"),", name.replace(" ", "_"), name)).collect::<Vec<_>>().join("\n        ")
+        column_names.iter().map(|name| {
+            let field = field_name(name);
+            if map_column_type(&columns[name], config) == "String" {
+                format!("\n        .where_eq_opt(\"{name}\", crate::transforms::to_db_value(\"{table_name}\", \"{name}\", &filter.{field}))")
+            } else {
+                format!("\n        .where_eq_opt(\"{name}\", filter.{field})")
+            }
+        }).collect::<String>()
+    )));
+
+    // Destructive, so only generated on request
+    if config.generate_destructive {
+        let truncate_sql = format!("TRUNCATE TABLE {}", quote_identifier(table_name));
+        crud_ops.push_str(&instrument("truncate", format!(
+            "\npub async fn {truncate_fn}(client: &Client, cascade: bool, restart_identity: bool) -> Result<(), OrmError> {{
+    let mut query = String::from({truncate_sql:?});
+    if restart_identity {{
+        query.push_str(\" RESTART IDENTITY\");
+    }}
+    if cascade {{
+        query.push_str(\" CASCADE\");
+    }}
+    
+    client.batch_execute(&query).await?;
+    
+    Ok(())
+}}\n"
+        )));
+    }
+
+    if config.crud_style == CrudStyle::InherentImpl {
+        crud_ops = wrap_in_impl(&struct_name, &crud_ops);
+    }
+
+    Ok(format!(
+        "{}use std::collections::HashMap;\nuse tokio_postgres::Client;\nuse crate::error::OrmError;\nuse crate::export::write_ndjson;\nuse crate::pagination::{{decode_cursor, encode_cursor, Page}};\nuse crate::query_builder::{{Model, QueryBuilder, SortDirection}};\n\n{}{}",
+        header, type_defs, crud_ops
+    ))
+}
+
+/// The parts of the generated `INSERT ... RETURNING` statements that have to
+/// line up: the columns written, the entity field bound to each (in the same
+/// order), the columns read back, and the fields of the struct they are read
+/// into.
+struct InsertLayout {
+    columns: Vec<String>,
+    values: Vec<String>,
+    returning: Vec<String>,
+    struct_fields: Vec<String>,
+}
+
+impl InsertLayout {
+    fn new(insert_columns: &[&str], all_columns: &[&str]) -> Self {
+        InsertLayout {
+            columns: insert_columns.iter().map(|name| name.to_string()).collect(),
+            values: insert_columns.iter().map(|name| field_name(name)).collect(),
+            returning: all_columns.iter().map(|name| name.to_string()).collect(),
+            struct_fields: all_columns.iter().map(|name| field_name(name)).collect(),
+        }
+    }
+
+    /// Reports the first way the parts disagree, e.g. two columns that map
+    /// to the same field, which would generate code that binds the wrong
+    /// value or does not compile.
+    fn check(&self) -> Result<(), String> {
+        if self.columns.len() != self.values.len() {
+            return Err(format!("{} column(s) but {} value(s)", self.columns.len(), self.values.len()));
+        }
+        for (column, value) in self.columns.iter().zip(&self.values) {
+            if *value != field_name(column) {
+                return Err(format!("column '{}' is bound to field '{}'", column, value));
+            }
+            if !self.returning.contains(column) {
+                return Err(format!("column '{}' is inserted but not returned", column));
+            }
+        }
+        let mut returned_fields: Vec<String> = self.returning.iter().map(|name| field_name(name)).collect();
+        let mut struct_fields = self.struct_fields.clone();
+        returned_fields.sort();
+        struct_fields.sort();
+        if let Some(pair) = struct_fields.windows(2).find(|pair| pair[0] == pair[1]) {
+            return Err(format!("more than one column maps to field '{}'", pair[0]));
+        }
+        if returned_fields != struct_fields {
+            return Err(format!("RETURNING reads fields {:?} into a struct with fields {:?}", returned_fields, struct_fields));
+        }
+        Ok(())
+    }
+}
+
+/// Wraps the body of a generated `async fn` in `telemetry::instrument`, so
+/// each call is counted and timed under `table_name` and `operation`.
+fn instrument_fn(code: &str, table_name: &str, operation: &str) -> String {
+    let (Some(open), Some(close)) = (code.find(" {\n"), code.rfind('}')) else { return code.to_string() };
+    let (signature, body) = code[..close].split_at(open + 3);
+    let body: String = body.lines().map(|line| if line.is_empty() { "\n".to_string() } else { format!("    {}\n", line) }).collect();
+    format!(
+        "{signature}    crate::telemetry::instrument(\"{table_name}\", \"{operation}\", async move {{\n{body}    }}).await\n{}",
+        &code[close..]
+    )
+}
+
+/// Generates one `upsert_{table}_by_{columns}` function per unique key, each
+/// inserting the entity or updating the row that conflicts on that key.
+/// `unique_keys` holds the columns of each unique constraint, as returned by
+/// `metadata::get_unique_constraints`. The functions reuse the `{table}_to_db`
+/// helper emitted by `generate_crud_operations_with_config`, so they belong in
+/// the same file.
+pub fn generate_upsert_operations(table_name: &str, columns: &HashMap<String, String>, unique_keys: &[Vec<String>], config: &GeneratorConfig) -> String {
+    let struct_name = table_name.to_case(Case::Pascal);
+    let mut column_names: Vec<&String> = columns.keys().collect();
+    column_names.sort();
+    let mut upsert_ops = String::new();
+    let to_db_fn = function_ident(&format!("{}_to_db", table_name));
+
+    for key in unique_keys {
+        let key_suffix = key.iter().map(|name| field_name(name)).collect::<Vec<_>>().join("_and_");
+        let upsert_fn = function_ident(&match config.crud_style {
+            CrudStyle::FreeFunctions => format!("upsert_{}_by_{}", table_name, key_suffix),
+            CrudStyle::InherentImpl => format!("upsert_by_{}", key_suffix),
+        });
+
+        // The key columns are what conflicted, so only the others are overwritten.
+        // A key spanning every column still needs a SET for RETURNING to yield the row.
+        let mut assignments: Vec<String> = column_names.iter()
+            .filter(|name| !key.contains(name))
+            .map(|name| format!("{column} = EXCLUDED.{column}", column = quote_identifier(name)))
+            .collect();
+        if assignments.is_empty() {
+            assignments.push(format!("{column} = EXCLUDED.{column}", column = quote_identifier(&key[0])));
+        }
+        let quoted_columns = column_names.iter().map(|name| quote_identifier(name)).collect::<Vec<_>>().join(", ");
+        let upsert_sql = format!(
+            "INSERT INTO {} ({quoted_columns}) VALUES ({}) ON CONFLICT ({}) DO UPDATE SET {} RETURNING {quoted_columns}",
+            quote_identifier(table_name),
+            (1..=column_names.len()).map(|i| format!("${}", i)).collect::<Vec<_>>().join(", "),
+            key.iter().map(|name| quote_identifier(name)).collect::<Vec<_>>().join(", "),
+            assignments.join(", ")
+        );
+
+        upsert_ops.push_str(&format!(
+            "\npub async fn {upsert_fn}(client: &Client, entity: &{struct_name}) -> Result<{struct_name}, OrmError> {{
+    let entity = &{to_db_fn}(entity);
+    let query = {upsert_sql:?};
+    
+    let row = client.query_one(query, &[{}]).await?;
+    
+    {struct_name}::try_from_row(&row)
+}}\n",
+            column_names.iter().map(|name| format!("&entity.{}", field_name(name))).collect::<Vec<_>>().join(", ")
+        ));
+    }
+
+    if config.crud_style == CrudStyle::InherentImpl && !upsert_ops.is_empty() {
+        upsert_ops = format!("\n{}", wrap_in_impl(&struct_name, upsert_ops.trim()));
+    }
+    upsert_ops
+}
+
+/// Generates the read-only operations for a view: `list_{view}` and, when a
+/// key column could be inferred, `get_{view}`. Views are not generally
+/// updatable, so no create, update or delete functions are emitted.
+pub fn generate_view_operations(view_name: &str, columns: HashMap<String, String>, key: Option<&str>, config: &GeneratorConfig, author: &str, github_link: &str, date: NaiveDate) -> String {
+    let header = generate_header(author, github_link, date);
+    let struct_name = view_name.to_case(Case::Pascal);
+    let function_name = |verb: &str| match config.crud_style {
+        CrudStyle::FreeFunctions => format!("{}_{}", verb, view_name),
+        CrudStyle::InherentImpl => verb.to_string(),
+    };
+    let get_fn = function_ident(&function_name("get"));
+    let list_fn = function_ident(&function_name("list"));
+    let mut view_ops = String::new();
+
+    let mut column_names: Vec<String> = columns.keys().cloned().collect();
+    column_names.sort();
+
+    if let Some(key) = key {
+        let key_type = map_column_type(&columns[key], config);
+        let key_equals = format!("{:?}", format!("{} = $1", quote_identifier(key)));
+        view_ops.push_str(&format!(
+            "pub async fn {get_fn}(client: &Client, {key_field}: {key_type}) -> Result<{struct_name}, OrmError> {{
+    QueryBuilder::select::<{struct_name}>()
+        .where_clause({key_equals})
+        .bind_param({key_field})
+        .fetch_one(client)
+        .await
+}}\n\n",
+            key_field = field_name(key),
+        ));
+    }
+
+    view_ops.push_str(&format!(
+        "pub async fn {list_fn}(client: &Client, limit: Option<i64>, offset: Option<i64>) -> Result<Vec<{struct_name}>, OrmError> {{
+    let mut query_builder = QueryBuilder::select::<{struct_name}>();
+    
+    if let Some(limit_val) = limit {{
+        query_builder = query_builder.limit(limit_val as usize);
+    }}
+    
+    if let Some(offset_val) = offset {{
+        query_builder = query_builder.offset(offset_val as usize);
+    }}
+    
+    query_builder.fetch_all(client).await
+}}\n"
     ));
 
-    crud_ops
+    if config.crud_style == CrudStyle::InherentImpl {
+        view_ops = wrap_in_impl(&struct_name, &view_ops);
+    }
+
+    format!("{}use tokio_postgres::Client;\nuse crate::error::OrmError;\nuse crate::query_builder::QueryBuilder;\n\n{}", header, view_ops)
+}
+
+/// Whether a column of `data_type` gets a variant in the generated sort enum.
+/// Types without a btree ordering cannot be ordered by at all; arrays and
+/// `jsonb` are ordered, but not in a way an API client would expect.
+fn is_sortable(data_type: &str) -> bool {
+    !data_type.ends_with("[]") && !matches!(
+        data_type,
+        "json" | "jsonb" | "xml" | "bytea" | "ARRAY" | "point" | "line" | "lseg" | "box" | "path" | "polygon" | "circle"
+    )
+}
+
+/// Longest generated function name, matching Postgres' own 63-byte
+/// identifier limit.
+const MAX_FUNCTION_NAME_LEN: usize = 63;
+
+/// Turns a name derived from table and column names into a valid Rust
+/// identifier. Characters Rust does not accept become `_` and letters are
+/// lowercased, and names over `MAX_FUNCTION_NAME_LEN` are cut short. Either
+/// way the result is suffixed with a hash of the original name, so
+/// `order-items` and `order_items`, or two long names sharing a prefix, still
+/// get distinct functions.
+fn function_ident(name: &str) -> String {
+    let mut ident: String = name.chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '_' { c.to_ascii_lowercase() } else { '_' })
+        .collect();
+    if ident.starts_with(|c: char| c.is_ascii_digit()) {
+        ident.insert(0, '_');
+    }
+    if ident != name || ident.len() > MAX_FUNCTION_NAME_LEN {
+        // FNV-1a, which unlike `DefaultHasher` is stable across Rust releases
+        let hash = name.bytes().fold(0xcbf29ce484222325u64, |hash, byte| (hash ^ u64::from(byte)).wrapping_mul(0x100000001b3));
+        let suffix = format!("_{:08x}", hash as u32);
+        ident.truncate(MAX_FUNCTION_NAME_LEN - suffix.len());
+        ident.push_str(&suffix);
+    }
+    ident
+}
+
+fn wrap_in_impl(struct_name: &str, functions: &str) -> String {
+    let indented: Vec<String> = functions.lines()
+        .map(|line| if line.is_empty() { String::new() } else { format!("    {}", line) })
+        .collect();
+    format!("impl {} {{\n{}\n}}\n", struct_name, indented.join("\n"))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use tokio;
+    use dotenv::dotenv;
+    use std::env;
+    use crate::db::PostgresConnectionManager;
+    use crate::generator::{generate_relationship_methods, generate_struct_from_columns, Derive};
+    use crate::metadata::ColumnInfo;
+    use crate::query_builder::{QueryBuilder, SortDirection};
+    use crate::relationships::TableDependency;
+
+    // Code generated for the tables below, checked in so the DB tests can
+    // call it; `test_checked_in_generated_code_is_current` keeps it in sync
+    // with the generator
+    #[allow(dead_code, unused_imports)]
+    mod customers {
+        include!("crud/generated/customers.rs");
+
+        pub mod crud {
+            use super::*;
+            include!("crud/generated/customers_crud.rs");
+        }
+    }
+
+    // Only used by the transform test, since registered transforms apply to
+    // every test reading the same table
+    #[allow(dead_code, unused_imports)]
+    mod patients {
+        include!("crud/generated/patients.rs");
+
+        pub mod crud {
+            use super::*;
+            include!("crud/generated/patients_crud.rs");
+        }
+    }
+
+    #[allow(dead_code, unused_imports)]
+    mod orders {
+        use super::customers::Customers;
+
+        include!("crud/generated/orders.rs");
+
+        pub mod crud {
+            use super::*;
+            include!("crud/generated/orders_crud.rs");
+        }
+    }
+
+    // Tables the database fills in some, or all, columns of on insert
+    #[allow(dead_code, unused_imports)]
+    mod tickets {
+        include!("crud/generated/tickets.rs");
+
+        pub mod crud {
+            use super::*;
+            include!("crud/generated/tickets_crud.rs");
+        }
+    }
+
+    #[allow(dead_code, unused_imports)]
+    mod visits {
+        include!("crud/generated/visits.rs");
+
+        pub mod crud {
+            use super::*;
+            include!("crud/generated/visits_crud.rs");
+        }
+    }
+
+    // Only used by the filter transform test, like `patients`
+    #[allow(dead_code, unused_imports)]
+    mod members {
+        include!("crud/generated/members.rs");
+
+        pub mod crud {
+            use super::*;
+            include!("crud/generated/members_crud.rs");
+        }
+    }
+
+    #[allow(dead_code, unused_imports)]
+    mod invoices {
+        include!("crud/generated/invoices.rs");
+
+        pub mod crud {
+            use super::*;
+            include!("crud/generated/invoices_crud.rs");
+        }
+    }
+
+    // The CRUD code for `customers` built with_metrics, which only compiles
+    // with the metrics feature
+    const METERED_TABLE: &str = "customers";
+
+    #[cfg(feature = "metrics")]
+    #[allow(dead_code, unused_imports)]
+    mod metered_customers {
+        pub use super::customers::*;
+
+        pub mod crud {
+            use super::*;
+            include!("crud/generated/customers_metered_crud.rs");
+        }
+    }
+
+    const CUSTOMERS_TABLE_SQL: &str = "CREATE TABLE customers (id INTEGER PRIMARY KEY, name TEXT NOT NULL, \"zip code\" TEXT NOT NULL, balance INTEGER NOT NULL)";
+
+    const PATIENTS_TABLE_SQL: &str = "CREATE TABLE patients (id INTEGER PRIMARY KEY, name TEXT NOT NULL, ssn TEXT NOT NULL)";
+
+    const ORDERS_TABLE_SQL: &str = "CREATE TABLE orders (id INTEGER PRIMARY KEY, buyer INTEGER NOT NULL REFERENCES customers (id), seller INTEGER REFERENCES customers (id))";
+
+    const TICKETS_TABLE_SQL: &str = "CREATE TABLE tickets (id SERIAL PRIMARY KEY, title TEXT NOT NULL, priority INTEGER NOT NULL DEFAULT 3)";
+
+    const VISITS_TABLE_SQL: &str = "CREATE TABLE visits (id SERIAL PRIMARY KEY, hits INTEGER NOT NULL DEFAULT 1)";
+
+    const MEMBERS_TABLE_SQL: &str = "CREATE TABLE members (id INTEGER PRIMARY KEY, email TEXT NOT NULL)";
+
+    const INVOICES_TABLE_SQL: &str = "CREATE TABLE invoices (id INTEGER PRIMARY KEY, total NUMERIC(12, 2) NOT NULL)";
+
+    /// The tables code is checked in for, with their columns, foreign keys
+    /// and the columns the database fills in on insert.
+    #[allow(clippy::type_complexity)]
+    fn generated_tables() -> Vec<(&'static str, Vec<ColumnInfo>, Vec<TableDependency>, &'static [&'static str])> {
+        vec![
+            ("customers", vec![
+                ColumnInfo::new("id", "integer"),
+                ColumnInfo::new("name", "text"),
+                ColumnInfo::new("zip code", "text"),
+                ColumnInfo::new("balance", "integer"),
+            ], Vec::new(), &[]),
+            ("patients", vec![
+                ColumnInfo::new("id", "integer"),
+                ColumnInfo::new("name", "text"),
+                ColumnInfo::new("ssn", "text"),
+            ], Vec::new(), &[]),
+            ("orders", vec![
+                ColumnInfo::new("id", "integer"),
+                ColumnInfo::new("buyer", "integer"),
+                ColumnInfo::new("seller", "integer").nullable(),
+            ], vec![
+                TableDependency::new("orders", "buyer", "customers", "id"),
+                TableDependency::new("orders", "seller", "customers", "id"),
+            ], &[]),
+            ("tickets", vec![
+                ColumnInfo::new("id", "integer"),
+                ColumnInfo::new("title", "text"),
+                ColumnInfo::new("priority", "integer"),
+            ], Vec::new(), &["id", "priority"]),
+            ("visits", vec![
+                ColumnInfo::new("id", "integer"),
+                ColumnInfo::new("hits", "integer"),
+            ], Vec::new(), &["id", "hits"]),
+            ("members", vec![
+                ColumnInfo::new("id", "integer"),
+                ColumnInfo::new("email", "text"),
+            ], Vec::new(), &[]),
+            ("invoices", vec![
+                ColumnInfo::new("id", "integer"),
+                ColumnInfo::new("total", "numeric"),
+            ], Vec::new(), &[]),
+        ]
+    }
+
+    /// Generates the struct, with its relationship methods, and the CRUD
+    /// code for `table`, instrumented if `metrics` is set.
+    fn generate_table(table: &str, columns: Vec<ColumnInfo>, foreign_keys: &[TableDependency], generated_columns: &[&str], metrics: bool) -> (String, String) {
+        // The tests compare and clone entities
+        let mut config = GeneratorConfig::default()
+            .with_derives(&[Derive::Clone, Derive::PartialEq])
+            .with_distinct_columns("customers", &["name", "balance"]);
+        if !generated_columns.is_empty() {
+            config = config.with_insert_structs().with_generated_columns(table, generated_columns);
+        }
+        if metrics {
+            config = config.with_metrics();
+        }
+        let fixed_date = NaiveDate::from_ymd_opt(2024, 7, 24).unwrap();
+        let mut struct_def = generate_struct_from_columns(table, &columns, &config, "Tom Blanchard", "https://github.com/tomblanchard312/rust_orm_gen", fixed_date);
+        struct_def.push_str(&generate_relationship_methods(table, &columns, foreign_keys));
+        let column_types = columns.into_iter().map(|column| (column.name, column.data_type)).collect();
+        let crud_ops = generate_crud_operations_with_key(table, column_types, Some("id"), &config, "Tom Blanchard", "https://github.com/tomblanchard312/rust_orm_gen", fixed_date).unwrap();
+        (struct_def, crud_ops)
+    }
+
+    /// Connects with `search_path` set to a fresh `schema` holding the empty
+    /// tables created by `table_sql`, so DB tests running the generated code
+    /// in parallel do not see each other's rows. Drop the schema when done.
+    async fn connect_to_schema(schema: &str, table_sql: &str) -> tokio_postgres::Client {
+        dotenv().ok();
+        let database_url = env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+        let manager = PostgresConnectionManager::new(database_url);
+        let client = manager.connect().await.expect("Failed to connect to database");
+        client.batch_execute(&format!(
+            "DROP SCHEMA IF EXISTS {schema} CASCADE; CREATE SCHEMA {schema}; SET search_path TO {schema}; {table_sql};"
+        )).await.expect("Failed to create test tables");
+        client
+    }
+
+    async fn connect_to_customers(schema: &str) -> tokio_postgres::Client {
+        connect_to_schema(schema, CUSTOMERS_TABLE_SQL).await
+    }
 
     #[test]
-    fn test_generate_crud_operations() {
+    fn test_checked_in_generated_code_is_current() {
+        let directory = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("src/crud/generated");
+        for (table, columns, foreign_keys, generated_columns) in generated_tables() {
+            let mut files = Vec::new();
+            if table == METERED_TABLE {
+                let (_, metered_crud_ops) = generate_table(table, columns.clone(), &foreign_keys, generated_columns, true);
+                files.push((format!("{}_metered_crud.rs", table), metered_crud_ops));
+            }
+            let (struct_def, crud_ops) = generate_table(table, columns, &foreign_keys, generated_columns, false);
+            files.push((format!("{}.rs", table), struct_def));
+            files.push((format!("{}_crud.rs", table), crud_ops));
+            for (file, generated) in files {
+                let path = directory.join(file);
+                if env::var_os("UPDATE_GENERATED").is_some() {
+                    std::fs::write(&path, generated).expect("Failed to write generated code");
+                } else {
+                    let checked_in = std::fs::read_to_string(&path).expect("Failed to read generated code");
+                    assert!(checked_in == generated, "{} is out of date; rerun the tests with UPDATE_GENERATED=1", path.display());
+                }
+            }
+        }
+    }
+
+    #[cfg(feature = "metrics")]
+    #[tokio::test]
+    async fn test_metered_generated_code_returns_the_wrapped_results() {
+        use metered_customers::Customers;
+
+        let client = connect_to_customers("crud_metered_test").await;
+        let customer = Customers { id: 1, name: "Ada".to_string(), zip_code: "10115".to_string(), balance: 100 };
+        let created = metered_customers::crud::create_customers(&client, &customer).await;
+        let found = metered_customers::crud::get_customers(&client, 1).await;
+        let missing = metered_customers::crud::get_customers(&client, 2).await;
+        client.batch_execute("DROP SCHEMA crud_metered_test CASCADE").await.unwrap();
+
+        assert_eq!(created.expect("Create failed"), customer);
+        assert_eq!(found.expect("Get failed"), customer);
+        assert!(missing.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_generated_count_filters_on_quoted_columns() {
+        use customers::crud::{count_customers, CustomersFilter};
+
+        let client = connect_to_customers("crud_count_test").await;
+        client.batch_execute(
+            "INSERT INTO customers VALUES (1, 'Ada', '10115', 100), (2, 'Grace', '10115', 200), (3, 'Barbara', '20095', 100);"
+        ).await.expect("Failed to insert customers");
+
+        let by_zip_code = count_customers(&client, CustomersFilter { zip_code: Some("10115".to_string()), ..Default::default() }).await;
+        let by_zip_code_and_balance = count_customers(&client, CustomersFilter { zip_code: Some("10115".to_string()), balance: Some(100), ..Default::default() }).await;
+        let all = count_customers(&client, CustomersFilter::default()).await;
+        client.batch_execute("DROP SCHEMA crud_count_test CASCADE").await.unwrap();
+
+        assert_eq!(by_zip_code.expect("Count failed"), 2);
+        assert_eq!(by_zip_code_and_balance.expect("Count failed"), 1);
+        assert_eq!(all.expect("Count failed"), 3);
+    }
+
+    /// Prefixes each value with a fresh number, standing in for encryption
+    /// with a random nonce, so the same value never encrypts the same twice.
+    struct Nonced(std::sync::atomic::AtomicUsize);
+
+    impl crate::transforms::ColumnTransform for Nonced {
+        fn to_db(&self, value: &str) -> String {
+            format!("{}:{}", self.0.fetch_add(1, std::sync::atomic::Ordering::Relaxed), value)
+        }
+
+        fn from_db(&self, value: &str) -> String {
+            value.split_once(':').map_or(value, |(_, value)| value).to_string()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_generated_crud_applies_registered_transforms() {
+        use crate::transforms::{register_transform, unregister_transform};
+        use patients::crud::{create_patients, get_patients, update_patients_changes};
+        use patients::Patients;
+
+        let client = connect_to_schema("crud_transform_test", PATIENTS_TABLE_SQL).await;
+        register_transform("patients", "ssn", Nonced(std::sync::atomic::AtomicUsize::new(0)));
+
+        let patient = Patients { id: 1, name: "Ada".to_string(), ssn: "123-45-6789".to_string() };
+        let created = create_patients(&client, &patient).await;
+        let stored: String = client.query_one("SELECT ssn FROM patients WHERE id = 1", &[]).await.unwrap().get(0);
+        let read = get_patients(&client, 1).await;
+        let unchanged = update_patients_changes(&client, &patient, &patient).await;
+        let renamed = Patients { id: 1, name: "Grace".to_string(), ssn: "123-45-6789".to_string() };
+        let updated = update_patients_changes(&client, &patient, &renamed).await;
+        let stored_after_update: String = client.query_one("SELECT ssn FROM patients WHERE id = 1", &[]).await.unwrap().get(0);
+
+        unregister_transform("patients", "ssn");
+        client.batch_execute("DROP SCHEMA crud_transform_test CASCADE").await.unwrap();
+
+        assert_eq!(stored, "0:123-45-6789");
+        assert_eq!(created.expect("Create failed").ssn, "123-45-6789");
+        assert_eq!(read.expect("Get failed").ssn, "123-45-6789");
+        assert!(unchanged.expect("Update failed").is_none());
+        let updated = updated.expect("Update failed").expect("Expected the name change to be written");
+        assert_eq!(updated.name, "Grace");
+        assert_eq!(updated.ssn, "123-45-6789");
+        assert_eq!(stored_after_update, stored);
+    }
+
+    /// Reverses the string, standing in for deterministic encryption.
+    struct Reversed;
+
+    impl crate::transforms::ColumnTransform for Reversed {
+        fn to_db(&self, value: &str) -> String {
+            value.chars().rev().collect()
+        }
+
+        fn from_db(&self, value: &str) -> String {
+            value.chars().rev().collect()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_generated_count_filter_applies_registered_transforms() {
+        use crate::transforms::{register_transform, unregister_transform};
+        use members::crud::{count_members, create_members, MembersFilter};
+        use members::Members;
+
+        let client = connect_to_schema("crud_filter_transform_test", MEMBERS_TABLE_SQL).await;
+        register_transform("members", "email", Reversed);
+
+        let created = create_members(&client, &Members { id: 1, email: "ada@example.com".to_string() }).await;
+        let stored: String = client.query_one("SELECT email FROM members WHERE id = 1", &[]).await.unwrap().get(0);
+        let by_email = count_members(&client, MembersFilter { email: Some("ada@example.com".to_string()), ..Default::default() }).await;
+        let by_stored_email = count_members(&client, MembersFilter { email: Some(stored.clone()), ..Default::default() }).await;
+
+        unregister_transform("members", "email");
+        client.batch_execute("DROP SCHEMA crud_filter_transform_test CASCADE").await.unwrap();
+
+        created.expect("Create failed");
+        assert_eq!(stored, "moc.elpmaxe@ada");
+        assert_eq!(by_email.expect("Count failed"), 1);
+        assert_eq!(by_stored_email.expect("Count failed"), 0);
+    }
+
+    #[tokio::test]
+    async fn test_generated_relationship_methods_load_referenced_rows() {
+        let client = connect_to_schema("crud_relations_test", &format!("{CUSTOMERS_TABLE_SQL}; {ORDERS_TABLE_SQL}")).await;
+        client.batch_execute(
+            "INSERT INTO customers VALUES (1, 'Ada', '10115', 100), (2, 'Grace', '20095', 200);
+             INSERT INTO orders VALUES (1, 1, 2), (2, 1, NULL);"
+        ).await.expect("Failed to insert orders");
+
+        let sold = orders::crud::get_orders(&client, 1).await.expect("Failed to get order").with_relations();
+        let unsold = orders::crud::get_orders(&client, 2).await.expect("Failed to get order").with_relations();
+        let buyer = sold.customers_by_buyer(&client).await;
+        let seller = sold.customers_by_seller(&client).await;
+        let no_seller = unsold.customers_by_seller(&client).await;
+        client.batch_execute("DROP SCHEMA crud_relations_test CASCADE").await.unwrap();
+
+        assert_eq!(buyer.expect("Failed to load buyer").name, "Ada");
+        assert_eq!(seller.expect("Failed to load seller").expect("Expected a seller").name, "Grace");
+        assert!(no_seller.expect("Failed to load seller").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_generated_batch_create_writes_quoted_columns() {
+        use customers::Customers;
+
+        let client = connect_to_customers("crud_batch_test").await;
+        let entities = vec![
+            Customers { id: 3, name: "Barbara".to_string(), zip_code: "80331".to_string(), balance: 300 },
+            Customers { id: 1, name: "Ada".to_string(), zip_code: "10115".to_string(), balance: 100 },
+            Customers { id: 2, name: "Grace".to_string(), zip_code: "20095".to_string(), balance: 200 },
+        ];
+        let created = customers::crud::create_customers_batch(&client, &entities).await;
+        let stored: Vec<String> = client.query("SELECT \"zip code\" FROM customers ORDER BY id", &[]).await.unwrap().iter().map(|row| row.get(0)).collect();
+        client.batch_execute("DROP SCHEMA crud_batch_test CASCADE").await.unwrap();
+
+        assert_eq!(created.expect("Batch create failed"), entities);
+        assert_eq!(stored, vec!["10115", "20095", "80331"]);
+    }
+
+    #[tokio::test]
+    async fn test_generated_batch_create_returns_defaults_in_input_order() {
+        use tickets::{NewTickets, Tickets};
+
+        let client = connect_to_schema("crud_batch_defaults_test", TICKETS_TABLE_SQL).await;
+        let entities: Vec<NewTickets> = ["printer", "login", "backup"].into_iter().map(|title| NewTickets { title: title.to_string() }).collect();
+        let created = tickets::crud::create_tickets_batch(&client, &entities).await;
+        client.batch_execute("DROP SCHEMA crud_batch_defaults_test CASCADE").await.unwrap();
+
+        // The serial ids show the rows were inserted in `entities` order too
+        assert_eq!(created.expect("Batch create failed"), vec![
+            Tickets { id: 1, title: "printer".to_string(), priority: 3 },
+            Tickets { id: 2, title: "login".to_string(), priority: 3 },
+            Tickets { id: 3, title: "backup".to_string(), priority: 3 },
+        ]);
+    }
+
+    #[tokio::test]
+    async fn test_generated_code_round_trips_numeric_columns() {
+        use invoices::Invoices;
+        use rust_decimal::Decimal;
+
+        let client = connect_to_schema("crud_numeric_test", INVOICES_TABLE_SQL).await;
+        let invoice = Invoices { id: 1, total: Decimal::new(1234567, 2) };
+        let created = invoices::crud::create_invoices(&client, &invoice).await;
+        let fetched = invoices::crud::get_invoices(&client, 1).await;
+        client.batch_execute("DROP SCHEMA crud_numeric_test CASCADE").await.unwrap();
+
+        assert_eq!(created.expect("Create failed"), invoice);
+        assert_eq!(fetched.expect("Get failed").total.to_string(), "12345.67");
+    }
+
+    #[tokio::test]
+    async fn test_generated_create_inserts_default_values() {
+        use visits::{NewVisits, Visits};
+
+        let client = connect_to_schema("crud_default_values_test", VISITS_TABLE_SQL).await;
+        let created = visits::crud::create_visits(&client, &NewVisits {}).await;
+        let batch = visits::crud::create_visits_batch(&client, &[NewVisits {}, NewVisits {}]).await;
+        let empty = visits::crud::create_visits_batch(&client, &[]).await;
+        client.batch_execute("DROP SCHEMA crud_default_values_test CASCADE").await.unwrap();
+
+        assert_eq!(created.expect("Create failed"), Visits { id: 1, hits: 1 });
+        assert_eq!(batch.expect("Batch create failed"), vec![Visits { id: 2, hits: 1 }, Visits { id: 3, hits: 1 }]);
+        assert_eq!(empty.expect("Empty batch create failed"), Vec::new());
+    }
+
+    #[test]
+    fn test_crud_columns_match_generated_model() {
+        let mut columns = HashMap::new();
+        columns.insert("id".to_string(), "integer".to_string());
+        columns.insert("name".to_string(), "text".to_string());
+        columns.insert("zip code".to_string(), "text".to_string());
+        columns.insert("created_at".to_string(), "timestamp".to_string());
+
+        let fixed_date = NaiveDate::from_ymd_opt(2024, 7, 24).unwrap();
+        let struct_def = crate::generator::generate_struct("users", columns.clone(), "Tom Blanchard", "https://github.com/tomblanchard312/rust_orm_gen", fixed_date);
+        let crud = generate_crud_operations("users", columns, "Tom Blanchard", "https://github.com/tomblanchard312/rust_orm_gen", fixed_date).unwrap();
+
+        // The query builder validates CRUD column names against Model::columns(), i.e. COLUMNS
+        assert!(struct_def.contains("impl crate::query_builder::Model for Users {"));
+        assert!(struct_def.contains("    fn columns() -> &'static [&'static str] {\n        COLUMNS\n    }"));
+        let model_columns = struct_def.split("pub const COLUMNS: &[&str] = &[").nth(1)
+            .and_then(|rest| rest.split("];").next())
+            .expect("COLUMNS not found");
+        assert_eq!(model_columns, "\"created_at\", \"id\", \"name\", \"zip code\"");
+        let returning: Vec<&str> = crud.split(".returning(&[").skip(1)
+            .map(|rest| rest.split("])").next().unwrap())
+            .collect();
+        assert_eq!(returning.len(), 4);
+        assert!(returning.iter().all(|columns| *columns == model_columns), "{:?}", returning);
+    }
+
+    #[test]
+    fn test_insert_layout_lines_up_columns_values_and_fields() {
         let mut columns = HashMap::new();
         columns.insert("id".to_string(), "integer".to_string());
         columns.insert("name".to_string(), "text".to_string());
         columns.insert("zip code".to_string(), "text".to_string());
+        let config = GeneratorConfig::default().with_insert_structs().with_generated_columns("users", &["id"]);
 
+        let fixed_date = NaiveDate::from_ymd_opt(2024, 7, 24).unwrap();
+        let result = generate_crud_operations_with_config("users", columns, &config, "Tom Blanchard", "https://github.com/tomblanchard312/rust_orm_gen", fixed_date).unwrap();
+
+        assert!(result.contains(".columns(&[\"name\", \"zip code\"])\n        .values(&[&entity.name, &entity.zip_code])\n        .returning(&[\"id\", \"name\", \"zip code\"])"));
+        assert!(result.contains("params.extend_from_slice(&[&entity.name, &entity.zip_code]);"));
+        assert!(result.contains("let query = format!(\"INSERT INTO users (name, \\\"zip code\\\") SELECT column1, column2 FROM (VALUES ((NULL::users).name, (NULL::users).\\\"zip code\\\", 0), {}) AS input WHERE column3 > 0 ORDER BY column3 RETURNING id, name, \\\"zip code\\\"\", rows.join(\", \"));"));
+        assert!(result.contains("users_new_to_db(entity: &NewUsers) -> NewUsers {\n    NewUsers {\n        name: "));
+        assert_eq!(InsertLayout::new(&["name", "zip code"], &["id", "name", "zip code"]).check(), Ok(()));
+
+        // Each way the parts can drift apart is caught
+        let mut swapped = InsertLayout::new(&["name", "zip code"], &["id", "name", "zip code"]);
+        swapped.values.reverse();
+        assert_eq!(swapped.check(), Err("column 'name' is bound to field 'zip_code'".to_string()));
+        let mut short = InsertLayout::new(&["name", "zip code"], &["id", "name", "zip code"]);
+        short.values.pop();
+        assert_eq!(short.check(), Err("2 column(s) but 1 value(s)".to_string()));
+        let unreturned = InsertLayout::new(&["name", "zip code"], &["id", "name"]);
+        assert_eq!(unreturned.check(), Err("column 'zip code' is inserted but not returned".to_string()));
+        let mut extra_field = InsertLayout::new(&["name"], &["id", "name"]);
+        extra_field.struct_fields.push("zip_code".to_string());
+        assert!(extra_field.check().unwrap_err().starts_with("RETURNING reads fields"));
+        let colliding = InsertLayout::new(&["zip code", "zip_code"], &["zip code", "zip_code"]);
+        assert_eq!(colliding.check(), Err("more than one column maps to field 'zip_code'".to_string()));
+    }
+
+    #[test]
+    fn test_generate_crud_operations_rejects_colliding_fields() {
+        let mut columns = HashMap::new();
+        columns.insert("zip code".to_string(), "text".to_string());
+        columns.insert("zip_code".to_string(), "text".to_string());
         let fixed_date = NaiveDate::from_ymd_opt(2024, 7, 24).unwrap();
         let result = generate_crud_operations("users", columns, "Tom Blanchard", "https://github.com/tomblanchard312/rust_orm_gen", fixed_date);
 
+        match result {
+            Err(OrmError::ParseError(message)) => assert_eq!(message, "Cannot generate inserts for table 'users': more than one column maps to field 'zip_code'"),
+            other => panic!("Expected colliding fields to be rejected, got {:?}", other.map(|_| ())),
+        }
+    }
+
+    #[test]
+    fn test_generate_crud_operations() {
+        let mut columns = HashMap::new();
+        columns.insert("id".to_string(), "integer".to_string());
+        columns.insert("name".to_string(), "text".to_string());
+        columns.insert("zip code".to_string(), "text".to_string());
+
+        let fixed_date = NaiveDate::from_ymd_opt(2024, 7, 24).unwrap();
+        let result = generate_crud_operations("users", columns, "Tom Blanchard", "https://github.com/tomblanchard312/rust_orm_gen", fixed_date).unwrap();
+
         // Basic checks for the presence of all CRUD operations
         assert!(result.contains("pub async fn create_users"));
+        assert!(result.contains("pub async fn create_users_batch(client: &Client, entities: &[Users]) -> Result<Vec<Users>, OrmError>"));
         assert!(result.contains("pub async fn get_users"));
         assert!(result.contains("pub async fn update_users"));
+        assert!(result.contains(".bind_param(entity.id)\n        .returning(&[\"id\", \"name\", \"zip code\"]);"));
         assert!(result.contains("pub async fn delete_users"));
         assert!(result.contains("pub async fn list_users"));
 
         // Check for the use of QueryBuilder
-        assert!(result.contains("use crate::query_builder::QueryBuilder;"));
+        assert!(result.contains("use crate::query_builder::{Model, QueryBuilder, SortDirection};"));
         assert!(result.contains("QueryBuilder::insert"));
+        assert!(result.contains(".columns(&[\"id\", \"name\", \"zip code\"])\n        .values(&[&entity.id, &entity.name, &entity.zip_code])"));
         assert!(result.contains("QueryBuilder::select"));
         assert!(result.contains("QueryBuilder::update"));
         assert!(result.contains("QueryBuilder::delete"));
 
-        // Check for proper handling of the "zip code" column
-        assert!(result.contains("zip_code: row.get(\"zip code\"),"));
+        // Check the batch insert returns every column and chunks on the parameter limit
+        assert!(result.contains("const COLUMNS_PER_ROW: usize = 3;"));
+        assert!(result.contains("entities.chunks(MAX_PARAMS / COLUMNS_PER_ROW)"));
+        assert!(result.contains("params.extend_from_slice(&[&entity.id, &entity.name, &entity.zip_code]);"));
+        assert!(result.contains("rows.push(format!(\"({}, {})\", placeholders, row_index + 1));"));
+        assert!(result.contains("let query = format!(\"INSERT INTO users (id, name, \\\"zip code\\\") SELECT column1, column2, column3 FROM (VALUES ((NULL::users).id, (NULL::users).name, (NULL::users).\\\"zip code\\\", 0), {}) AS input WHERE column4 > 0 ORDER BY column4 RETURNING id, name, \\\"zip code\\\"\", rows.join(\", \"));"));
+
+        // Check the returning delete yields the removed row, or None when nothing matched
+        assert!(result.contains("pub async fn delete_users_returning(client: &Client, id: i32) -> Result<Option<Users>, OrmError>"));
+        assert!(result.contains(".bind_param(id)\n        .returning(&[\"id\", \"name\", \"zip code\"]);"));
+        assert!(result.contains("let row = client.query_opt(&query, &params[..]).await?;"));
+
+        // Check the batched read keys the rows by id
+        assert!(result.contains("pub async fn get_users_by_ids(client: &Client, ids: &[i32]) -> Result<HashMap<i32, Users>, OrmError>"));
+        assert!(result.contains(".where_clause(\"id = ANY($1)\")\n        .bind_param(ids.to_vec());"));
+        assert!(result.contains("(entity.id, entity)"));
+
+        // Check the paginated listing decodes the cursor into the keyset and hands out the next one
+        assert!(result.contains("pub async fn list_users_page(client: &Client, cursor: Option<String>, limit: i64) -> Result<Page<Users>, OrmError>"));
+        assert!(result.contains("cursor.as_deref().map(decode_cursor).transpose()?"));
+        assert!(result.contains(".keyset_after(\"id\", after)"));
+        assert!(result.contains("items.last().map(|entity| encode_cursor(&entity.id))"));
+
+        // Check the refresh reloads the entity in place and fails once the row is gone
+        assert!(result.contains("pub async fn refresh_users(client: &Client, entity: &mut Users) -> Result<(), OrmError>"));
+        assert!(result.contains(".bind_param(entity.id);"));
+        assert!(result.contains(".ok_or_else(|| OrmError::QueryError(format!(\"No row in users where id = {:?}\", entity.id)))?;\n    *entity = Users::try_from_row(&row)?;"));
+
+        // Check the listings break ties on the key so offset pages are stable
+        assert!(result.contains("Result<Vec<Users>, OrmError> {\n    let mut query_builder = QueryBuilder::select::<Users>().tiebreak_by(\"id\");"));
+        assert!(result.contains("pub async fn list_users_ordered(client: &Client, order_by: &[(&str, bool)], limit: Option<i64>, offset: Option<i64>) -> Result<Vec<Users>, OrmError>"));
+        assert!(result.contains("query_builder = query_builder.order_by(column, *ascending);\n    }\n    query_builder = query_builder.tiebreak_by(\"id\");"));
+
+        // Check the NDJSON export streams rows into the writer
+        assert!(result.contains("pub async fn export_users_ndjson<W: std::io::Write>(client: &Client, writer: &mut W) -> Result<u64, OrmError>"));
+        assert!(result.contains("let rows = client.query_raw(&query, params).await?;"));
+        assert!(result.contains("write_ndjson(rows, writer, Users::try_from_row).await"));
+
+        // Check the filter struct and the count query built from its populated fields
+        assert!(result.contains("pub struct UsersFilter {\n    pub id: Option<i32>,\n    pub name: Option<String>,\n    pub zip_code: Option<String>,\n}"));
+        assert!(result.contains("pub async fn count_users(client: &Client, filter: UsersFilter) -> Result<i64, OrmError>"));
+        assert!(result.contains(".select_expr(\"COUNT(*)\", \"count\")\n        .where_eq_opt(\"id\", filter.id)\n        .where_eq_opt(\"name\", crate::transforms::to_db_value(\"users\", \"name\", &filter.name))\n        .where_eq_opt(\"zip code\", crate::transforms::to_db_value(\"users\", \"zip code\", &filter.zip_code));"));
+        assert!(!result.contains("param_index"));
+
+        // Check rows are mapped by the struct's Model impl rather than inline
+        assert!(result.contains("pub async fn get_users(client: &Client, id: i32) -> Result<Users, OrmError>"));
+        assert!(result.contains(".bind_param(id)\n        .fetch_one(client)\n        .await"));
+        assert!(result.contains("pub async fn list_users(client: &Client, limit: Option<i64>, offset: Option<i64>) -> Result<Vec<Users>, OrmError>"));
+        assert!(result.contains("query_builder.fetch_all(client).await"));
+        assert!(result.contains("    Users::try_from_row(&row)\n"));
+        assert!(result.contains("            created.push(Users::try_from_row(row)?);"));
+        assert!(result.contains("row.as_ref().map(Users::try_from_row).transpose()"));
+        assert!(!result.contains("row.get(\"zip code\")"));
+
+        // Check text columns go through their registered transforms on write
+        assert!(result.contains("fn users_to_db(entity: &Users) -> Users {\n    Users {\n        id: entity.id,\n        name: crate::transforms::to_db_value(\"users\", \"name\", &entity.name),"));
+        assert!(result.contains("let entity = &users_to_db(entity);"));
+        assert!(result.contains("let entities: Vec<Users> = entities.iter().map(users_to_db).collect();"));
 
         // Check for the correct use of &params[..]
         assert!(result.contains("client.query_one(&query, &params[..]).await?"));
         assert!(result.contains("client.execute(&query, &params[..]).await?"));
-        assert!(result.contains("client.query(&query, &params[..]).await?"));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_generate_crud_operations_inherent_impl() {
+        let mut columns = HashMap::new();
+        columns.insert("customer_id".to_string(), "integer".to_string());
+        columns.insert("email".to_string(), "text".to_string());
+
+        let config = GeneratorConfig { crud_style: CrudStyle::InherentImpl, ..GeneratorConfig::default() };
+        let fixed_date = NaiveDate::from_ymd_opt(2024, 7, 24).unwrap();
+        let result = generate_crud_operations_with_key("customer", columns, Some("customer_id"), &config, "Tom Blanchard", "https://github.com/tomblanchard312/rust_orm_gen", fixed_date).unwrap();
+
+        assert!(result.contains("impl Customer {\n"));
+        assert!(result.contains("    pub async fn create(client: &Client, entity: &Customer) -> Result<Customer, OrmError> {"));
+        assert!(result.contains("    pub async fn create_batch(client: &Client, entities: &[Customer])"));
+        assert!(result.contains("    pub async fn get(client: &Client, customer_id: i32)"));
+        assert!(result.contains("    pub async fn get_by_ids(client: &Client, ids: &[i32])"));
+        assert!(result.contains("    pub async fn get_for_update(transaction: &tokio_postgres::Transaction<'_>, customer_id: i32)"));
+        assert!(result.contains("    pub async fn refresh(&mut self, client: &Client) -> Result<(), OrmError> {"));
+        assert!(result.contains("        .bind_param(self.customer_id);"));
+        assert!(result.contains("    *self = Customer::try_from_row(&row)?;"));
+        assert!(result.contains("    pub async fn update(client: &Client, entity: &Customer)"));
+        assert!(result.contains("    pub async fn update_changes(client: &Client, original: &Customer, modified: &Customer)"));
+        assert!(result.contains("    pub async fn delete(client: &Client, customer_id: i32)"));
+        assert!(result.contains("    pub async fn delete_returning(client: &Client, customer_id: i32)"));
+        assert!(result.contains("    pub async fn list(client: &Client, limit: Option<i64>, offset: Option<i64>)"));
+        assert!(result.contains("    pub async fn list_page(client: &Client, cursor: Option<String>, limit: i64)"));
+        assert!(result.contains("    pub async fn count(client: &Client, filter: CustomerFilter)"));
+        assert!(result.contains("pub struct CustomerFilter {"));
+        assert!(!result.contains("create_customer"));
+        assert!(result.trim_end().ends_with("}\n}"));
+    }
+
+    #[test]
+    fn test_generate_crud_operations_with_named_primary_key() {
+        let mut columns = HashMap::new();
+        columns.insert("customer_id".to_string(), "integer".to_string());
+        columns.insert("email".to_string(), "text".to_string());
+
+        let fixed_date = NaiveDate::from_ymd_opt(2024, 7, 24).unwrap();
+        let result = generate_crud_operations_with_key("customer", columns.clone(), Some("customer_id"), &GeneratorConfig::default(), "Tom Blanchard", "https://github.com/tomblanchard312/rust_orm_gen", fixed_date).unwrap();
+
+        assert!(result.contains("pub async fn get_customer(client: &Client, customer_id: i32) -> Result<Customer, OrmError>"));
+        assert!(result.contains(".where_clause(\"customer_id = $1\")\n        .bind_param(customer_id)"));
+        assert!(result.contains("pub async fn get_customer_by_ids(client: &Client, ids: &[i32]) -> Result<HashMap<i32, Customer>, OrmError>"));
+        assert!(result.contains(".where_clause(\"customer_id = ANY($1)\")"));
+        assert!(result.contains("pub async fn get_customer_for_update(transaction: &tokio_postgres::Transaction<'_>, customer_id: i32) -> Result<Customer, OrmError>"));
+        assert!(result.contains(".bind_param(customer_id)\n        .for_update();\n    \n    let (query, params) = query_builder.try_build()?;"));
+        assert!(result.contains("let row = transaction.query_one(&query, &params[..]).await?;"));
+        assert!(result.contains("(entity.customer_id, entity)"));
+        assert!(result.contains(".bind_param(entity.customer_id)"));
+        assert!(result.contains("pub async fn update_customer_changes(client: &Client, original: &Customer, modified: &Customer) -> Result<Option<Customer>, OrmError>"));
+        assert!(result.contains("let written = &customer_to_db(modified);"));
+        assert!(result.contains("if modified.email != original.email {\n        changes.push((\"email\", &written.email));\n    }"));
+        assert!(result.contains(".set_values(&changes)\n        .where_clause(\"customer_id = $1\")\n        .bind_param(original.customer_id)"));
+        assert!(result.contains("pub async fn delete_customer(client: &Client, customer_id: i32)"));
+        assert!(result.contains(".keyset_after(\"customer_id\", after)"));
+        assert!(result.contains(".order_by(\"customer_id\", true)"));
+        assert!(!result.contains("\"id = $1\""));
+
+        // A text key is cloned out of the entity rather than moved
+        let result = generate_crud_operations_with_key("customer", columns.clone(), Some("email"), &GeneratorConfig::default(), "Tom Blanchard", "https://github.com/tomblanchard312/rust_orm_gen", fixed_date).unwrap();
+        assert!(result.contains("pub async fn get_customer(client: &Client, email: String)"));
+        assert!(result.contains(".bind_param(entity.email.clone())"));
+
+        // Without a primary key there is nothing to look a single row up by
+        let result = generate_crud_operations_with_key("customer", columns, None, &GeneratorConfig::default(), "Tom Blanchard", "https://github.com/tomblanchard312/rust_orm_gen", fixed_date).unwrap();
+        assert!(!result.contains("get_customer"));
+        assert!(!result.contains("update_customer"));
+        assert!(!result.contains("delete_customer"));
+        assert!(!result.contains("list_customer_page"));
+        assert!(result.contains("pub async fn create_customer("));
+        assert!(result.contains("pub async fn list_customer("));
+        assert!(result.contains("let query_builder = QueryBuilder::select::<Customer>();"));
+    }
+
+    #[test]
+    fn test_generate_sort_enum_maps_each_variant_to_order_by() {
+        let mut columns = HashMap::new();
+        columns.insert("id".to_string(), "integer".to_string());
+        columns.insert("name".to_string(), "text".to_string());
+        columns.insert("payload".to_string(), "json".to_string());
+        columns.insert("settings".to_string(), "jsonb".to_string());
+        columns.insert("tags".to_string(), "text[]".to_string());
+        columns.insert("location".to_string(), "point".to_string());
+
+        let fixed_date = NaiveDate::from_ymd_opt(2024, 7, 24).unwrap();
+        let result = generate_crud_operations("refreshed_customers", columns, "Tom Blanchard", "https://github.com/tomblanchard312/rust_orm_gen", fixed_date).unwrap();
+
+        // json, jsonb, arrays and points have no useful order, so they get no variant
+        assert!(result.contains("pub enum RefreshedCustomersSort {\n    Id(SortDirection),\n    Name(SortDirection),\n}"));
+        assert!(result.contains("pub async fn list_refreshed_customers_sorted(client: &Client, sort: RefreshedCustomersSort, limit: Option<i64>, offset: Option<i64>) -> Result<Vec<RefreshedCustomers>, OrmError>"));
+        assert!(result.contains("let (column, ascending) = sort.order_by();\n    let mut query_builder = QueryBuilder::select::<RefreshedCustomers>()\n        .order_by(column, ascending)\n        .tiebreak_by(\"id\");"));
+
+        for (variant, column) in [("Id", "id"), ("Name", "name")] {
+            assert!(result.contains(&format!("RefreshedCustomersSort::{}(direction) => (\"{}\", direction == SortDirection::Asc),", variant, column)));
+        }
+
+        // The checked-in generated enum, run through the builder the way the listing does
+        use customers::{crud::CustomersSort, Customers};
+        for (sort, expected) in [
+            (CustomersSort::Id(SortDirection::Asc), "ORDER BY id ASC"),
+            (CustomersSort::Id(SortDirection::Desc), "ORDER BY id DESC"),
+            (CustomersSort::ZipCode(SortDirection::Asc), "ORDER BY \"zip code\" ASC, id ASC"),
+            (CustomersSort::Balance(SortDirection::Desc), "ORDER BY balance DESC, id ASC"),
+        ] {
+            let (column, ascending) = sort.order_by();
+            let (query, _) = QueryBuilder::select::<Customers>()
+                .order_by(column, ascending)
+                .tiebreak_by("id")
+                .build();
+            assert!(query.ends_with(expected), "{}", query);
+        }
+    }
+
+    #[test]
+    fn test_generate_crud_operations_with_metrics() {
+        let mut columns = HashMap::new();
+        columns.insert("id".to_string(), "integer".to_string());
+        columns.insert("name".to_string(), "text".to_string());
+
+        let fixed_date = NaiveDate::from_ymd_opt(2024, 7, 24).unwrap();
+        let config = GeneratorConfig::default().with_metrics();
+        let result = generate_crud_operations_with_config("users", columns.clone(), &config, "Tom Blanchard", "https://github.com/tomblanchard312/rust_orm_gen", fixed_date).unwrap();
+
+        assert!(result.contains("pub async fn get_users(client: &Client, id: i32) -> Result<Users, OrmError> {\n    crate::telemetry::instrument(\"users\", \"get\", async move {\n        QueryBuilder::select::<Users>()\n            .where_clause(\"id = $1\")\n            .bind_param(id)\n            .fetch_one(client)\n            .await\n    }).await\n}"));
+        for operation in ["create", "create_batch", "get_by_ids", "get_for_update", "refresh", "update", "update_changes", "delete", "delete_returning", "list", "list_ordered", "list_sorted", "list_page", "export", "count"] {
+            assert!(result.contains(&format!("crate::telemetry::instrument(\"users\", \"{}\", async move {{", operation)), "{} is not instrumented", operation);
+        }
+
+        let plain = generate_crud_operations_with_config("users", columns, &GeneratorConfig::default(), "Tom Blanchard", "https://github.com/tomblanchard312/rust_orm_gen", fixed_date).unwrap();
+        assert!(!plain.contains("telemetry"));
+    }
+
+    #[test]
+    fn test_generate_truncate_only_when_destructive() {
+        let mut columns = HashMap::new();
+        columns.insert("id".to_string(), "integer".to_string());
+        columns.insert("email".to_string(), "text".to_string());
+
+        let fixed_date = NaiveDate::from_ymd_opt(2024, 7, 24).unwrap();
+        let result = generate_crud_operations_with_config("customer", columns.clone(), &GeneratorConfig::default(), "Tom Blanchard", "https://github.com/tomblanchard312/rust_orm_gen", fixed_date).unwrap();
+        assert!(!result.contains("TRUNCATE"));
+
+        let config = GeneratorConfig::default().with_destructive_helpers();
+        let result = generate_crud_operations_with_config("customer", columns, &config, "Tom Blanchard", "https://github.com/tomblanchard312/rust_orm_gen", fixed_date).unwrap();
+        assert!(result.contains("pub async fn truncate_customer(client: &Client, cascade: bool, restart_identity: bool) -> Result<(), OrmError> {"));
+        assert!(result.contains("let mut query = String::from(\"TRUNCATE TABLE customer\");"));
+        assert!(result.contains("if restart_identity {\n        query.push_str(\" RESTART IDENTITY\");\n    }"));
+        assert!(result.contains("if cascade {\n        query.push_str(\" CASCADE\");\n    }"));
+        assert!(result.find("RESTART IDENTITY").unwrap() < result.find("\" CASCADE\"").unwrap());
+    }
+
+    #[test]
+    fn test_generate_create_takes_insert_struct() {
+        let mut columns = HashMap::new();
+        columns.insert("id".to_string(), "integer".to_string());
+        columns.insert("name".to_string(), "text".to_string());
+        columns.insert("created_at".to_string(), "timestamp with time zone".to_string());
+
+        let fixed_date = NaiveDate::from_ymd_opt(2024, 7, 24).unwrap();
+        let config = GeneratorConfig::default().with_insert_structs().with_generated_columns("customer", &["id", "created_at"]);
+        let result = generate_crud_operations_with_config("customer", columns, &config, "Tom Blanchard", "https://github.com/tomblanchard312/rust_orm_gen", fixed_date).unwrap();
+
+        assert!(result.contains("fn customer_new_to_db(entity: &NewCustomer) -> NewCustomer {\n    NewCustomer {\n        name: crate::transforms::to_db_value(\"customer\", \"name\", &entity.name),\n    }\n}"));
+        assert!(result.contains("pub async fn create_customer(client: &Client, entity: &NewCustomer) -> Result<Customer, OrmError> {\n    let entity = &customer_new_to_db(entity);"));
+        assert!(result.contains(".columns(&[\"name\"])\n        .values(&[&entity.name])\n        .returning(&[\"created_at\", \"id\", \"name\"])"));
+        assert!(result.contains("pub async fn create_customer_batch(client: &Client, entities: &[NewCustomer]) -> Result<Vec<Customer>, OrmError>"));
+        assert!(result.contains("const COLUMNS_PER_ROW: usize = 1;"));
+        assert!(result.contains("INSERT INTO customer (name) SELECT column1 FROM (VALUES ((NULL::customer).name, 0), {}) AS input WHERE column2 > 0 ORDER BY column2 RETURNING created_at, id, name"));
+        // Updates still take the full entity
+        assert!(result.contains("pub async fn update_customer(client: &Client, entity: &Customer)"));
+
+        // With nothing left to bind, the inserts use the column defaults
+        let all_generated = config.with_generated_columns("customer", &["name"]);
+        let result = generate_crud_operations_with_config("customer", HashMap::from([("id".to_string(), "integer".to_string())]), &all_generated, "Tom Blanchard", "https://github.com/tomblanchard312/rust_orm_gen", fixed_date).unwrap();
+        assert!(!result.contains("customer_new_to_db"));
+        assert!(result.contains("client.query_one(\"INSERT INTO customer DEFAULT VALUES RETURNING id\", &[])"));
+        assert!(result.contains("client.query(\"INSERT INTO customer SELECT FROM generate_series(1, $1::bigint) AS input(ordinal) ORDER BY ordinal RETURNING id\", &[&(entities.len() as i64)])"));
+    }
+
+    #[test]
+    fn test_generate_blob_accessors_for_bytea_columns() {
+        let mut columns = HashMap::new();
+        columns.insert("id".to_string(), "integer".to_string());
+        columns.insert("content".to_string(), "bytea".to_string());
+        columns.insert("title".to_string(), "text".to_string());
+
+        let fixed_date = NaiveDate::from_ymd_opt(2024, 7, 24).unwrap();
+        let result = generate_crud_operations_with_config("document", columns.clone(), &GeneratorConfig::default(), "Tom Blanchard", "https://github.com/tomblanchard312/rust_orm_gen", fixed_date).unwrap();
+        assert!(!result.contains("crate::blob"));
+
+        let config = GeneratorConfig::default().with_blob_streaming(1024 * 1024);
+        let result = generate_crud_operations_with_config("document", columns, &config, "Tom Blanchard", "https://github.com/tomblanchard312/rust_orm_gen", fixed_date).unwrap();
+        assert!(result.contains("pub async fn read_document_content<W: tokio::io::AsyncWrite + Unpin>(client: &Client, id: i32, writer: &mut W) -> Result<u64, OrmError> {\n    crate::blob::read_bytea(client, \"document\", \"content\", \"id\", &id, 1048576, writer).await\n}"));
+        assert!(result.contains("pub async fn write_document_content<R: tokio::io::AsyncRead + Unpin>(client: &Client, id: i32, reader: &mut R) -> Result<u64, OrmError> {\n    crate::blob::write_bytea(client, \"document\", \"content\", \"id\", &id, 1048576, reader).await\n}"));
+        assert!(!result.contains("read_document_title"));
+    }
+
+    #[test]
+    fn test_generated_sql_quotes_identifiers() {
+        let mut columns = HashMap::new();
+        columns.insert("Item ID".to_string(), "integer".to_string());
+        columns.insert("zip code".to_string(), "text".to_string());
+        let config = GeneratorConfig::default().with_destructive_helpers().with_distinct_columns("order items", &["zip code"]);
+
+        let fixed_date = NaiveDate::from_ymd_opt(2024, 7, 24).unwrap();
+        let result = generate_crud_operations_with_key("order items", columns.clone(), Some("Item ID"), &config, "Tom Blanchard", "https://github.com/tomblanchard312/rust_orm_gen", fixed_date).unwrap();
+        assert!(result.contains(".where_clause(\"\\\"Item ID\\\" = $1\")"));
+        assert!(result.contains(".where_clause(\"\\\"Item ID\\\" = ANY($1)\")"));
+        assert!(result.contains("client.query(\"SELECT DISTINCT \\\"zip code\\\" FROM \\\"order items\\\" WHERE \\\"zip code\\\" IS NOT NULL ORDER BY \\\"zip code\\\"\", &[])"));
+        assert!(result.contains("String::from(\"TRUNCATE TABLE \\\"order items\\\"\")"));
+
+        let upserts = generate_upsert_operations("order items", &columns, &[vec!["zip code".to_string()]], &config);
+        assert!(upserts.contains("let query = \"INSERT INTO \\\"order items\\\" (\\\"Item ID\\\", \\\"zip code\\\") VALUES ($1, $2) ON CONFLICT (\\\"zip code\\\") DO UPDATE SET \\\"Item ID\\\" = EXCLUDED.\\\"Item ID\\\" RETURNING \\\"Item ID\\\", \\\"zip code\\\"\";"));
+    }
+
+    #[test]
+    fn test_generate_upsert_operations() {
+        let mut columns = HashMap::new();
+        columns.insert("id".to_string(), "integer".to_string());
+        columns.insert("email".to_string(), "text".to_string());
+        columns.insert("name".to_string(), "text".to_string());
+
+        let unique_keys = vec![vec!["email".to_string()]];
+        let result = generate_upsert_operations("customer", &columns, &unique_keys, &GeneratorConfig::default());
+
+        assert!(result.contains("pub async fn upsert_customer_by_email(client: &Client, entity: &Customer) -> Result<Customer, OrmError>"));
+        assert!(result.contains("INSERT INTO customer (email, id, name) VALUES ($1, $2, $3) ON CONFLICT (email) DO UPDATE SET id = EXCLUDED.id, name = EXCLUDED.name RETURNING email, id, name"));
+        assert!(!result.contains("email = EXCLUDED.email"));
+        assert!(result.contains("client.query_one(query, &[&entity.email, &entity.id, &entity.name]).await?"));
+
+        let config = GeneratorConfig { crud_style: CrudStyle::InherentImpl, ..GeneratorConfig::default() };
+        let composite = vec![vec!["email".to_string(), "name".to_string()]];
+        let result = generate_upsert_operations("customer", &columns, &composite, &config);
+        assert!(result.starts_with("\nimpl Customer {\n    pub async fn upsert_by_email_and_name("));
+        assert!(result.contains("ON CONFLICT (email, name) DO UPDATE SET id = EXCLUDED.id RETURNING"));
+    }
+
+    #[tokio::test]
+    async fn test_get_by_ids_query() {
+        let client = connect_to_customers("crud_by_ids_test").await;
+        client.batch_execute(
+            "INSERT INTO customers VALUES (1, 'Ada', '10115', 100), (2, 'Grace', '10115', 200), (3, 'Barbara', '20095', 300);"
+        ).await.expect("Failed to insert customers");
+
+        let customers = customers::crud::get_customers_by_ids(&client, &[1, 3, 42]).await;
+        client.batch_execute("DROP SCHEMA crud_by_ids_test CASCADE").await.unwrap();
+
+        let customers = customers.expect("Failed to get customers by ids");
+        assert_eq!(customers.len(), 2);
+        assert_eq!(customers[&1].name, "Ada");
+        assert_eq!(customers[&3].name, "Barbara");
+        assert!(!customers.contains_key(&42));
+    }
+
+    #[test]
+    fn test_generate_view_operations() {
+        let mut columns = HashMap::new();
+        columns.insert("id".to_string(), "integer".to_string());
+        columns.insert("name".to_string(), "text".to_string());
+        columns.insert("sid".to_string(), "smallint".to_string());
+
+        let fixed_date = NaiveDate::from_ymd_opt(2024, 7, 24).unwrap();
+        let result = generate_view_operations("staff_list", columns.clone(), Some("id"), &GeneratorConfig::default(), "Tom Blanchard", "https://github.com/tomblanchard312/rust_orm_gen", fixed_date);
+
+        assert!(result.contains("pub async fn get_staff_list(client: &Client, id: i32) -> Result<StaffList, OrmError>"));
+        assert!(result.contains(".where_clause(\"id = $1\")\n        .bind_param(id)\n        .fetch_one(client)"));
+        assert!(result.contains("pub async fn list_staff_list(client: &Client, limit: Option<i64>, offset: Option<i64>)"));
+        for verb in ["create", "update", "delete", "upsert"] {
+            assert!(!result.contains(&format!("pub async fn {}", verb)), "View should not get a {} function", verb);
+        }
+        assert!(!result.contains("QueryBuilder::insert") && !result.contains("QueryBuilder::update") && !result.contains("QueryBuilder::delete"));
+
+        let without_key = generate_view_operations("sales_by_store", columns, None, &GeneratorConfig::default(), "Tom Blanchard", "https://github.com/tomblanchard312/rust_orm_gen", fixed_date);
+        assert!(!without_key.contains("pub async fn get_sales_by_store"));
+        assert!(without_key.contains("pub async fn list_sales_by_store"));
+    }
+
+    #[tokio::test]
+    async fn test_delete_returning_query() {
+        use customers::Customers;
+
+        let client = connect_to_customers("crud_delete_returning_test").await;
+        client.batch_execute(
+            "INSERT INTO customers VALUES (1, 'Ada', '10115', 100), (2, 'Grace', '20095', 200);"
+        ).await.expect("Failed to insert customers");
+
+        let deleted = customers::crud::delete_customers_returning(&client, 1).await;
+        let missing = customers::crud::delete_customers_returning(&client, 42).await;
+        let remaining: i64 = client.query_one("SELECT COUNT(*) FROM customers", &[]).await.unwrap().get(0);
+        client.batch_execute("DROP SCHEMA crud_delete_returning_test CASCADE").await.unwrap();
+
+        let deleted = deleted.expect("Delete failed").expect("Expected the deleted row");
+        assert_eq!(deleted, Customers { id: 1, name: "Ada".to_string(), zip_code: "10115".to_string(), balance: 100 });
+        assert!(missing.expect("Delete failed").is_none());
+        assert_eq!(remaining, 1);
+    }
+
+    #[tokio::test]
+    async fn test_refresh_query_reloads_entity() {
+        use customers::Customers;
+
+        let client = connect_to_customers("crud_refresh_test").await;
+        client.batch_execute("INSERT INTO customers VALUES (1, 'Ada', '10115', 100);").await.expect("Failed to insert customers");
+
+        let mut customer = Customers { id: 1, name: "Ada".to_string(), zip_code: "10115".to_string(), balance: 100 };
+        client.batch_execute("UPDATE customers SET name = 'Ada Lovelace', \"zip code\" = '20095' WHERE id = 1").await.unwrap();
+        let refreshed = customers::crud::refresh_customers(&client, &mut customer).await;
+        client.batch_execute("DELETE FROM customers WHERE id = 1").await.unwrap();
+        let missing = customers::crud::refresh_customers(&client, &mut customer).await;
+        client.batch_execute("DROP SCHEMA crud_refresh_test CASCADE").await.unwrap();
+
+        refreshed.expect("Refresh failed");
+        assert_eq!(customer, Customers { id: 1, name: "Ada Lovelace".to_string(), zip_code: "20095".to_string(), balance: 100 });
+        assert!(matches!(missing, Err(OrmError::QueryError(message)) if message == "No row in customers where id = 1"));
+    }
+
+    #[tokio::test]
+    async fn test_get_for_update_serializes_concurrent_transactions() {
+        use crate::transactions::TransactionManager;
+        use std::future::Future;
+        use std::pin::Pin;
+        use std::time::Duration;
+
+        let mut first = connect_to_customers("crud_for_update_test").await;
+        first.batch_execute("INSERT INTO customers VALUES (1, 'Ada', '10115', 100);").await.expect("Failed to insert customers");
+        dotenv().ok();
+        let database_url = env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+        let mut second = PostgresConnectionManager::new(database_url).connect().await.expect("Failed to connect to database");
+        second.batch_execute("SET search_path TO crud_for_update_test").await.unwrap();
+
+        // Read, pause so the other transaction overlaps, then write back
+        fn deposit<'b>(tx: &'b mut tokio_postgres::Transaction<'_>) -> Pin<Box<dyn Future<Output = Result<(), OrmError>> + Send + 'b>> {
+            Box::pin(async move {
+                let customer = customers::crud::get_customers_for_update(tx, 1).await?;
+                tokio::time::sleep(Duration::from_millis(200)).await;
+                tx.execute("UPDATE customers SET balance = $1 WHERE id = $2", &[&(customer.balance + 10), &customer.id]).await?;
+                Ok(())
+            })
+        }
+
+        let mut first_transactions = TransactionManager::new(&mut first);
+        let mut second_transactions = TransactionManager::new(&mut second);
+        let (first_result, second_result) = tokio::join!(first_transactions.run_orm(deposit), second_transactions.run_orm(deposit));
+        let balance: i32 = first.query_one("SELECT balance FROM customers WHERE id = 1", &[]).await.unwrap().get(0);
+        first.batch_execute("DROP SCHEMA crud_for_update_test CASCADE").await.unwrap();
+
+        first_result.expect("First transaction failed");
+        second_result.expect("Second transaction failed");
+        assert_eq!(balance, 120, "The second read should wait for the first deposit to commit");
+    }
+
+    #[tokio::test]
+    async fn test_update_changes_query_sets_only_changed_columns() {
+        use customers::Customers;
+
+        let client = connect_to_customers("crud_update_changes_test").await;
+        client.batch_execute("INSERT INTO customers VALUES (1, 'Ada', '10115', 100);").await.expect("Failed to insert customers");
+
+        let original = Customers { id: 1, name: "Ada".to_string(), zip_code: "10115".to_string(), balance: 100 };
+        let modified = Customers { zip_code: "20095".to_string(), ..original.clone() };
+        // Written by someone else after `original` was read; untouched columns must keep it
+        client.batch_execute("UPDATE customers SET balance = 150 WHERE id = 1").await.unwrap();
+        let updated = customers::crud::update_customers_changes(&client, &original, &modified).await;
+        let unchanged = customers::crud::update_customers_changes(&client, &modified, &modified).await;
+        client.batch_execute("DROP SCHEMA crud_update_changes_test CASCADE").await.unwrap();
+
+        let updated = updated.expect("Update failed").expect("Expected the updated row");
+        assert_eq!(updated, Customers { zip_code: "20095".to_string(), balance: 150, ..original });
+        assert!(unchanged.expect("Update failed").is_none());
+    }
+
+    #[test]
+    fn test_distinct_function_names() {
+        let mut columns = HashMap::new();
+        columns.insert("id".to_string(), "integer".to_string());
+        columns.insert("store_id".to_string(), "integer".to_string());
+        columns.insert("region".to_string(), "text".to_string());
+        let config = GeneratorConfig::default().with_distinct_columns("dropdown_customer", &["store_id", "region"]);
+        let fixed_date = NaiveDate::from_ymd_opt(2024, 7, 24).unwrap();
+        let generated = generate_crud_operations_with_config("dropdown_customer", columns.clone(), &config, "Tom Blanchard", "https://github.com/tomblanchard312/rust_orm_gen", fixed_date).unwrap();
+
+        assert!(generated.contains("pub async fn distinct_dropdown_customer_store_id(client: &Client) -> Result<Vec<i32>, OrmError>"));
+        assert!(generated.contains("pub async fn distinct_dropdown_customer_region(client: &Client) -> Result<Vec<String>, OrmError>"));
+        assert!(!generated.contains("distinct_dropdown_customer_id("));
+
+        let inherent = generate_crud_operations_with_config("dropdown_customer", columns, &GeneratorConfig { crud_style: CrudStyle::InherentImpl, ..config }, "Tom Blanchard", "https://github.com/tomblanchard312/rust_orm_gen", fixed_date).unwrap();
+        assert!(inherent.contains("pub async fn distinct_store_id(client: &Client) -> Result<Vec<i32>, OrmError>"));
+    }
+
+    #[tokio::test]
+    async fn test_generated_distinct_functions_list_sorted_values() {
+        use customers::crud::{distinct_customers_balance, distinct_customers_name};
+
+        let client = connect_to_customers("crud_distinct_test").await;
+        client.batch_execute(
+            "INSERT INTO customers VALUES (1, 'Grace', '10115', 200), (2, 'Ada', '10115', 100), (3, 'Grace', '20095', 100);"
+        ).await.expect("Failed to insert customers");
+
+        let names = distinct_customers_name(&client).await;
+        let balances = distinct_customers_balance(&client).await;
+        client.batch_execute("DROP SCHEMA crud_distinct_test CASCADE").await.unwrap();
+
+        assert_eq!(names.expect("Distinct names failed"), vec!["Ada", "Grace"]);
+        assert_eq!(balances.expect("Distinct balances failed"), vec![100, 200]);
+    }
+
+    #[test]
+    fn test_long_table_names_yield_valid_unique_function_names() {
+        let table = "quarterly_regional_sales_performance_summary_by_department_and_cost_center";
+        let other_table = "quarterly_regional_sales_performance_summary_by_department_and_cost_centre";
+        let mut columns = HashMap::new();
+        columns.insert("id".to_string(), "integer".to_string());
+        columns.insert("name".to_string(), "text".to_string());
+
+        let fixed_date = NaiveDate::from_ymd_opt(2024, 7, 24).unwrap();
+        let function_names = |table_name: &str| -> Vec<String> {
+            let result = generate_crud_operations(table_name, columns.clone(), "Tom Blanchard", "https://github.com/tomblanchard312/rust_orm_gen", fixed_date).unwrap();
+            result.split("pub async fn ").skip(1)
+                .map(|rest| rest.split(['(', '<']).next().unwrap().to_string())
+                .collect()
+        };
+        let names = function_names(table);
+        let other_names = function_names(other_table);
+
+        for operation in ["create", "get", "update", "delete", "list", "count"] {
+            let prefix = format!("{}_quarterly_regional_sales", operation);
+            assert!(names.iter().any(|name| name.starts_with(&prefix)), "no function starting with '{}' in {:?}", prefix, names);
+        }
+        for name in names.iter().chain(&other_names) {
+            assert!(name.len() <= 63, "'{}' is longer than 63 bytes", name);
+            assert!(name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_'), "'{}' is not a valid identifier", name);
+        }
+        let mut distinct: Vec<&String> = names.iter().chain(&other_names).collect();
+        distinct.sort();
+        distinct.dedup();
+        assert_eq!(distinct.len(), names.len() + other_names.len());
+
+        // Long names keep as much of the name as fits, then the hash
+        let create = function_ident(&format!("create_{}", table));
+        assert_eq!(create.len(), 63);
+        assert!(create.starts_with("create_quarterly_regional_sales_performance_summary_by"), "'{}' lost its prefix", create);
+        assert!(create.ends_with("_277afcd7"), "'{}' does not end with the hash of the full name", create);
+        assert_ne!(create, function_ident(&format!("create_{}", other_table)));
+
+        // Short valid names are left alone; names that had to be changed get
+        // the hash too, so they cannot collide with a name that did not
+        assert_eq!(function_ident("get_customer"), "get_customer");
+        assert_eq!(function_ident("get_order-items"), "get_order_items_61fa8f49");
+        assert_eq!(function_ident("get_Order_Items"), "get_order_items_95d85a97");
+        assert_ne!(function_ident("get_order-items"), function_ident("get_order_items"));
+    }
+}
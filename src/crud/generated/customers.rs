@@ -0,0 +1,41 @@
+/*
+ * This code was generated by rust_orm_gen.
+ * GitHub: https://github.com/tomblanchard312/rust_orm_gen
+ * Date: 2024-07-24
+ * Author: Tom Blanchard
+ */
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Customers {
+    #[serde(rename = "balance")] pub balance: i32,
+    #[serde(rename = "id")] pub id: i32,
+    #[serde(rename = "name")] pub name: String,
+    #[serde(rename = "zip code")] pub zip_code: String,
+}
+
+pub const COLUMNS: &[&str] = &["balance", "id", "name", "zip code"];
+pub const COL_BALANCE: &str = "balance";
+pub const COL_ID: &str = "id";
+pub const COL_NAME: &str = "name";
+pub const COL_ZIP_CODE: &str = "zip code";
+
+impl crate::query_builder::Model for Customers {
+    fn table_name() -> &'static str {
+        "customers"
+    }
+
+    fn columns() -> &'static [&'static str] {
+        COLUMNS
+    }
+
+    fn from_row(row: &tokio_postgres::Row) -> Self {
+        Customers {
+            balance: row.get("balance"),
+            id: row.get("id"),
+            name: crate::transforms::from_db("customers", "name", row.get("name")),
+            zip_code: crate::transforms::from_db("customers", "zip code", row.get("zip code")),
+        }
+    }
+}
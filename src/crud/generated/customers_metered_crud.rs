@@ -0,0 +1,362 @@
+/*
+ * This code was generated by rust_orm_gen.
+ * GitHub: https://github.com/tomblanchard312/rust_orm_gen
+ * Date: 2024-07-24
+ * Author: Tom Blanchard
+ */
+
+use std::collections::HashMap;
+use tokio_postgres::Client;
+use crate::error::OrmError;
+use crate::export::write_ndjson;
+use crate::pagination::{decode_cursor, encode_cursor, Page};
+use crate::query_builder::{Model, QueryBuilder, SortDirection};
+
+/// Applies the registered column transforms before a write.
+fn customers_to_db(entity: &Customers) -> Customers {
+    Customers {
+        balance: entity.balance,
+        id: entity.id,
+        name: crate::transforms::to_db_value("customers", "name", &entity.name),
+        zip_code: crate::transforms::to_db_value("customers", "zip code", &entity.zip_code),
+    }
+}
+
+/// A column and direction for `list_customers_sorted`. Only these columns can be
+/// sorted on, so a sort taken from a request cannot inject SQL.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CustomersSort {
+    Balance(SortDirection),
+    Id(SortDirection),
+    Name(SortDirection),
+    ZipCode(SortDirection),
+}
+
+impl CustomersSort {
+    /// The column to order by and whether the order is ascending.
+    pub fn order_by(self) -> (&'static str, bool) {
+        match self {
+            CustomersSort::Balance(direction) => ("balance", direction == SortDirection::Asc),
+            CustomersSort::Id(direction) => ("id", direction == SortDirection::Asc),
+            CustomersSort::Name(direction) => ("name", direction == SortDirection::Asc),
+            CustomersSort::ZipCode(direction) => ("zip code", direction == SortDirection::Asc),
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct CustomersFilter {
+    pub balance: Option<i32>,
+    pub id: Option<i32>,
+    pub name: Option<String>,
+    pub zip_code: Option<String>,
+}
+
+pub async fn create_customers(client: &Client, entity: &Customers) -> Result<Customers, OrmError> {
+    crate::telemetry::instrument("customers", "create", async move {
+        let entity = &customers_to_db(entity);
+        let (query, params) = QueryBuilder::insert::<Customers>()
+            .columns(&["balance", "id", "name", "zip code"])
+            .values(&[&entity.balance, &entity.id, &entity.name, &entity.zip_code])
+            .returning(&["balance", "id", "name", "zip code"])
+            .build();
+        
+        let row = client.query_one(&query, &params[..]).await?;
+        
+        Customers::try_from_row(&row)
+    }).await
+}
+
+/// Inserts `entities` in as few statements as the parameter limit allows
+/// and returns the created rows, with the values the database filled in,
+/// in `entities` order.
+pub async fn create_customers_batch(client: &Client, entities: &[Customers]) -> Result<Vec<Customers>, OrmError> {
+    crate::telemetry::instrument("customers", "create_batch", async move {
+        // Postgres accepts at most 65535 bind parameters per statement
+        const MAX_PARAMS: usize = 65535;
+        const COLUMNS_PER_ROW: usize = 4;
+        let entities: Vec<Customers> = entities.iter().map(customers_to_db).collect();
+        let mut created = Vec::with_capacity(entities.len());
+        
+        for chunk in entities.chunks(MAX_PARAMS / COLUMNS_PER_ROW) {
+            let mut rows = Vec::with_capacity(chunk.len());
+            let mut params: Vec<&(dyn tokio_postgres::types::ToSql + Sync)> = Vec::with_capacity(chunk.len() * COLUMNS_PER_ROW);
+            for (row_index, entity) in chunk.iter().enumerate() {
+                let first = row_index * COLUMNS_PER_ROW;
+                let placeholders = (1..=COLUMNS_PER_ROW).map(|i| format!("${}", first + i)).collect::<Vec<_>>().join(", ");
+                rows.push(format!("({}, {})", placeholders, row_index + 1));
+                params.extend_from_slice(&[&entity.balance, &entity.id, &entity.name, &entity.zip_code]);
+            }
+            let query = format!("INSERT INTO customers (balance, id, name, \"zip code\") SELECT column1, column2, column3, column4 FROM (VALUES ((NULL::customers).balance, (NULL::customers).id, (NULL::customers).name, (NULL::customers).\"zip code\", 0), {}) AS input WHERE column5 > 0 ORDER BY column5 RETURNING balance, id, name, \"zip code\"", rows.join(", "));
+            
+            for row in &client.query(&query, &params[..]).await? {
+                created.push(Customers::try_from_row(row)?);
+            }
+        }
+        
+        Ok(created)
+    }).await
+}
+
+pub async fn get_customers(client: &Client, id: i32) -> Result<Customers, OrmError> {
+    crate::telemetry::instrument("customers", "get", async move {
+        QueryBuilder::select::<Customers>()
+            .where_clause("id = $1")
+            .bind_param(id)
+            .fetch_one(client)
+            .await
+    }).await
+}
+
+/// Reads the row and locks it until `transaction` ends, so other
+/// transactions locking or writing it wait for this one to finish.
+pub async fn get_customers_for_update(transaction: &tokio_postgres::Transaction<'_>, id: i32) -> Result<Customers, OrmError> {
+    crate::telemetry::instrument("customers", "get_for_update", async move {
+        let query_builder = QueryBuilder::select::<Customers>()
+            .where_clause("id = $1")
+            .bind_param(id)
+            .for_update();
+        
+        let (query, params) = query_builder.try_build()?;
+        
+        let row = transaction.query_one(&query, &params[..]).await?;
+        
+        Customers::try_from_row(&row)
+    }).await
+}
+
+pub async fn refresh_customers(client: &Client, entity: &mut Customers) -> Result<(), OrmError> {
+    crate::telemetry::instrument("customers", "refresh", async move {
+        let query_builder = QueryBuilder::select::<Customers>()
+            .where_clause("id = $1")
+            .bind_param(entity.id);
+        
+        let (query, params) = query_builder.build();
+        
+        // The row may have been deleted since the entity was loaded
+        let row = client.query_opt(&query, &params[..]).await?
+            .ok_or_else(|| OrmError::QueryError(format!("No row in customers where id = {:?}", entity.id)))?;
+        *entity = Customers::try_from_row(&row)?;
+        
+        Ok(())
+    }).await
+}
+
+pub async fn get_customers_by_ids(client: &Client, ids: &[i32]) -> Result<HashMap<i32, Customers>, OrmError> {
+    crate::telemetry::instrument("customers", "get_by_ids", async move {
+        let query_builder = QueryBuilder::select::<Customers>()
+            .where_clause("id = ANY($1)")
+            .bind_param(ids.to_vec());
+        
+        // Ids without a matching row are simply absent from the map
+        let entities = query_builder.fetch_all(client).await?;
+        
+        Ok(entities.into_iter().map(|entity| (entity.id, entity)).collect())
+    }).await
+}
+
+pub async fn update_customers(client: &Client, entity: &Customers) -> Result<Customers, OrmError> {
+    crate::telemetry::instrument("customers", "update", async move {
+        let entity = &customers_to_db(entity);
+        let query_builder = QueryBuilder::update::<Customers>()
+            .set_values(&[("balance", &entity.balance), ("id", &entity.id), ("name", &entity.name), ("zip code", &entity.zip_code)])
+            .where_clause("id = $1")
+            .bind_param(entity.id)
+            .returning(&["balance", "id", "name", "zip code"]);
+        
+        let (query, params) = query_builder.build();
+        
+        let row = client.query_one(&query, &params[..]).await?;
+        
+        Customers::try_from_row(&row)
+    }).await
+}
+
+pub async fn update_customers_changes(client: &Client, original: &Customers, modified: &Customers) -> Result<Option<Customers>, OrmError> {
+    crate::telemetry::instrument("customers", "update_changes", async move {
+        // Compared before the column transforms run, since a transform such as
+        // encryption with a random nonce need not give the same output twice
+        let written = &customers_to_db(modified);
+        let mut changes: Vec<(&str, &(dyn tokio_postgres::types::ToSql + Sync))> = Vec::new();
+        if modified.balance != original.balance {
+            changes.push(("balance", &written.balance));
+        }
+        if modified.id != original.id {
+            changes.push(("id", &written.id));
+        }
+        if modified.name != original.name {
+            changes.push(("name", &written.name));
+        }
+        if modified.zip_code != original.zip_code {
+            changes.push(("zip code", &written.zip_code));
+        }
+        
+        // Nothing changed, so there is nothing to write
+        if changes.is_empty() {
+            return Ok(None);
+        }
+        
+        let query_builder = QueryBuilder::update::<Customers>()
+            .set_values(&changes)
+            .where_clause("id = $1")
+            .bind_param(original.id)
+            .returning(&["balance", "id", "name", "zip code"]);
+        
+        let (query, params) = query_builder.build();
+        
+        let row = client.query_one(&query, &params[..]).await?;
+        
+        Customers::try_from_row(&row).map(Some)
+    }).await
+}
+
+pub async fn delete_customers(client: &Client, id: i32) -> Result<bool, tokio_postgres::Error> {
+    crate::telemetry::instrument("customers", "delete", async move {
+        let query_builder = QueryBuilder::delete::<Customers>()
+            .where_clause("id = $1")
+            .bind_param(id);
+        
+        let (query, params) = query_builder.build();
+        
+        let result = client.execute(&query, &params[..]).await?;
+        
+        Ok(result > 0)
+    }).await
+}
+
+pub async fn delete_customers_returning(client: &Client, id: i32) -> Result<Option<Customers>, OrmError> {
+    crate::telemetry::instrument("customers", "delete_returning", async move {
+        let query_builder = QueryBuilder::delete::<Customers>()
+            .where_clause("id = $1")
+            .bind_param(id)
+            .returning(&["balance", "id", "name", "zip code"]);
+        
+        let (query, params) = query_builder.build();
+        
+        let row = client.query_opt(&query, &params[..]).await?;
+        
+        row.as_ref().map(Customers::try_from_row).transpose()
+    }).await
+}
+
+pub async fn list_customers(client: &Client, limit: Option<i64>, offset: Option<i64>) -> Result<Vec<Customers>, OrmError> {
+    crate::telemetry::instrument("customers", "list", async move {
+        let mut query_builder = QueryBuilder::select::<Customers>().tiebreak_by("id");
+        
+        if let Some(limit_val) = limit {
+            query_builder = query_builder.limit(limit_val as usize);
+        }
+        
+        if let Some(offset_val) = offset {
+            query_builder = query_builder.offset(offset_val as usize);
+        }
+        
+        query_builder.fetch_all(client).await
+    }).await
+}
+
+pub async fn list_customers_ordered(client: &Client, order_by: &[(&str, bool)], limit: Option<i64>, offset: Option<i64>) -> Result<Vec<Customers>, OrmError> {
+    crate::telemetry::instrument("customers", "list_ordered", async move {
+        let mut query_builder = QueryBuilder::select::<Customers>();
+        for (column, ascending) in order_by {
+            query_builder = query_builder.order_by(column, *ascending);
+        }
+        query_builder = query_builder.tiebreak_by("id");
+        
+        if let Some(limit_val) = limit {
+            query_builder = query_builder.limit(limit_val as usize);
+        }
+        
+        if let Some(offset_val) = offset {
+            query_builder = query_builder.offset(offset_val as usize);
+        }
+        
+        query_builder.fetch_all(client).await
+    }).await
+}
+
+pub async fn list_customers_sorted(client: &Client, sort: CustomersSort, limit: Option<i64>, offset: Option<i64>) -> Result<Vec<Customers>, OrmError> {
+    crate::telemetry::instrument("customers", "list_sorted", async move {
+        let (column, ascending) = sort.order_by();
+        let mut query_builder = QueryBuilder::select::<Customers>()
+            .order_by(column, ascending)
+            .tiebreak_by("id");
+        
+        if let Some(limit_val) = limit {
+            query_builder = query_builder.limit(limit_val as usize);
+        }
+        
+        if let Some(offset_val) = offset {
+            query_builder = query_builder.offset(offset_val as usize);
+        }
+        
+        query_builder.fetch_all(client).await
+    }).await
+}
+
+pub async fn list_customers_page(client: &Client, cursor: Option<String>, limit: i64) -> Result<Page<Customers>, OrmError> {
+    crate::telemetry::instrument("customers", "list_page", async move {
+        let after: Option<i32> = cursor.as_deref().map(decode_cursor).transpose()?;
+        let query_builder = QueryBuilder::select::<Customers>()
+            .keyset_after("id", after)
+            .limit(limit as usize);
+        
+        let items = query_builder.fetch_all(client).await?;
+        
+        // A short page means there is nothing left to fetch
+        let next_cursor = if items.len() as i64 == limit {
+            items.last().map(|entity| encode_cursor(&entity.id))
+        } else {
+            None
+        };
+        
+        Ok(Page { items, next_cursor })
+    }).await
+}
+
+pub async fn export_customers_ndjson<W: std::io::Write>(client: &Client, writer: &mut W) -> Result<u64, OrmError> {
+    crate::telemetry::instrument("customers", "export", async move {
+        let query_builder = QueryBuilder::select::<Customers>()
+            .order_by("id", true);
+        
+        let (query, params) = query_builder.build();
+        
+        let rows = client.query_raw(&query, params).await?;
+        
+        write_ndjson(rows, writer, Customers::try_from_row).await
+    }).await
+}
+
+pub async fn distinct_customers_name(client: &Client) -> Result<Vec<String>, OrmError> {
+    crate::telemetry::instrument("customers", "distinct", async move {
+        let rows = client.query("SELECT DISTINCT name FROM customers WHERE name IS NOT NULL ORDER BY name", &[]).await?;
+        
+        Ok(rows.iter().map(|row| crate::transforms::from_db("customers", "name", row.get(0))).collect())
+    }).await
+}
+
+pub async fn distinct_customers_balance(client: &Client) -> Result<Vec<i32>, OrmError> {
+    crate::telemetry::instrument("customers", "distinct", async move {
+        let rows = client.query("SELECT DISTINCT balance FROM customers WHERE balance IS NOT NULL ORDER BY balance", &[]).await?;
+        
+        Ok(rows.iter().map(|row| row.get(0)).collect())
+    }).await
+}
+
+pub async fn count_customers(client: &Client, filter: CustomersFilter) -> Result<i64, OrmError> {
+    crate::telemetry::instrument("customers", "count", async move {
+        let query_builder = QueryBuilder::select::<Customers>()
+            .select(&[])
+            .select_expr("COUNT(*)", "count")
+            .where_eq_opt("balance", filter.balance)
+            .where_eq_opt("id", filter.id)
+            .where_eq_opt("name", crate::transforms::to_db_value("customers", "name", &filter.name))
+            .where_eq_opt("zip code", crate::transforms::to_db_value("customers", "zip code", &filter.zip_code));
+        
+        let (query, params) = query_builder.try_build()?;
+        
+        let row = client.query_one(&query, &params[..]).await?;
+        
+        Ok(row.get("count"))
+    }).await
+}
@@ -0,0 +1,36 @@
+/*
+ * This code was generated by rust_orm_gen.
+ * GitHub: https://github.com/tomblanchard312/rust_orm_gen
+ * Date: 2024-07-24
+ * Author: Tom Blanchard
+ */
+
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Invoices {
+    #[serde(rename = "id")] pub id: i32,
+    #[serde(rename = "total")] pub total: Decimal,
+}
+
+pub const COLUMNS: &[&str] = &["id", "total"];
+pub const COL_ID: &str = "id";
+pub const COL_TOTAL: &str = "total";
+
+impl crate::query_builder::Model for Invoices {
+    fn table_name() -> &'static str {
+        "invoices"
+    }
+
+    fn columns() -> &'static [&'static str] {
+        COLUMNS
+    }
+
+    fn from_row(row: &tokio_postgres::Row) -> Self {
+        Invoices {
+            id: row.get("id"),
+            total: row.get("total"),
+        }
+    }
+}
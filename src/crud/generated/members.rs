@@ -0,0 +1,35 @@
+/*
+ * This code was generated by rust_orm_gen.
+ * GitHub: https://github.com/tomblanchard312/rust_orm_gen
+ * Date: 2024-07-24
+ * Author: Tom Blanchard
+ */
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Members {
+    #[serde(rename = "email")] pub email: String,
+    #[serde(rename = "id")] pub id: i32,
+}
+
+pub const COLUMNS: &[&str] = &["email", "id"];
+pub const COL_EMAIL: &str = "email";
+pub const COL_ID: &str = "id";
+
+impl crate::query_builder::Model for Members {
+    fn table_name() -> &'static str {
+        "members"
+    }
+
+    fn columns() -> &'static [&'static str] {
+        COLUMNS
+    }
+
+    fn from_row(row: &tokio_postgres::Row) -> Self {
+        Members {
+            email: crate::transforms::from_db("members", "email", row.get("email")),
+            id: row.get("id"),
+        }
+    }
+}
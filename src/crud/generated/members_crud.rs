@@ -0,0 +1,298 @@
+/*
+ * This code was generated by rust_orm_gen.
+ * GitHub: https://github.com/tomblanchard312/rust_orm_gen
+ * Date: 2024-07-24
+ * Author: Tom Blanchard
+ */
+
+use std::collections::HashMap;
+use tokio_postgres::Client;
+use crate::error::OrmError;
+use crate::export::write_ndjson;
+use crate::pagination::{decode_cursor, encode_cursor, Page};
+use crate::query_builder::{Model, QueryBuilder, SortDirection};
+
+/// Applies the registered column transforms before a write.
+fn members_to_db(entity: &Members) -> Members {
+    Members {
+        email: crate::transforms::to_db_value("members", "email", &entity.email),
+        id: entity.id,
+    }
+}
+
+/// A column and direction for `list_members_sorted`. Only these columns can be
+/// sorted on, so a sort taken from a request cannot inject SQL.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MembersSort {
+    Email(SortDirection),
+    Id(SortDirection),
+}
+
+impl MembersSort {
+    /// The column to order by and whether the order is ascending.
+    pub fn order_by(self) -> (&'static str, bool) {
+        match self {
+            MembersSort::Email(direction) => ("email", direction == SortDirection::Asc),
+            MembersSort::Id(direction) => ("id", direction == SortDirection::Asc),
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct MembersFilter {
+    pub email: Option<String>,
+    pub id: Option<i32>,
+}
+
+pub async fn create_members(client: &Client, entity: &Members) -> Result<Members, OrmError> {
+    let entity = &members_to_db(entity);
+    let (query, params) = QueryBuilder::insert::<Members>()
+        .columns(&["email", "id"])
+        .values(&[&entity.email, &entity.id])
+        .returning(&["email", "id"])
+        .build();
+    
+    let row = client.query_one(&query, &params[..]).await?;
+    
+    Members::try_from_row(&row)
+}
+
+/// Inserts `entities` in as few statements as the parameter limit allows
+/// and returns the created rows, with the values the database filled in,
+/// in `entities` order.
+pub async fn create_members_batch(client: &Client, entities: &[Members]) -> Result<Vec<Members>, OrmError> {
+    // Postgres accepts at most 65535 bind parameters per statement
+    const MAX_PARAMS: usize = 65535;
+    const COLUMNS_PER_ROW: usize = 2;
+    let entities: Vec<Members> = entities.iter().map(members_to_db).collect();
+    let mut created = Vec::with_capacity(entities.len());
+    
+    for chunk in entities.chunks(MAX_PARAMS / COLUMNS_PER_ROW) {
+        let mut rows = Vec::with_capacity(chunk.len());
+        let mut params: Vec<&(dyn tokio_postgres::types::ToSql + Sync)> = Vec::with_capacity(chunk.len() * COLUMNS_PER_ROW);
+        for (row_index, entity) in chunk.iter().enumerate() {
+            let first = row_index * COLUMNS_PER_ROW;
+            let placeholders = (1..=COLUMNS_PER_ROW).map(|i| format!("${}", first + i)).collect::<Vec<_>>().join(", ");
+            rows.push(format!("({}, {})", placeholders, row_index + 1));
+            params.extend_from_slice(&[&entity.email, &entity.id]);
+        }
+        let query = format!("INSERT INTO members (email, id) SELECT column1, column2 FROM (VALUES ((NULL::members).email, (NULL::members).id, 0), {}) AS input WHERE column3 > 0 ORDER BY column3 RETURNING email, id", rows.join(", "));
+        
+        for row in &client.query(&query, &params[..]).await? {
+            created.push(Members::try_from_row(row)?);
+        }
+    }
+    
+    Ok(created)
+}
+
+pub async fn get_members(client: &Client, id: i32) -> Result<Members, OrmError> {
+    QueryBuilder::select::<Members>()
+        .where_clause("id = $1")
+        .bind_param(id)
+        .fetch_one(client)
+        .await
+}
+
+/// Reads the row and locks it until `transaction` ends, so other
+/// transactions locking or writing it wait for this one to finish.
+pub async fn get_members_for_update(transaction: &tokio_postgres::Transaction<'_>, id: i32) -> Result<Members, OrmError> {
+    let query_builder = QueryBuilder::select::<Members>()
+        .where_clause("id = $1")
+        .bind_param(id)
+        .for_update();
+    
+    let (query, params) = query_builder.try_build()?;
+    
+    let row = transaction.query_one(&query, &params[..]).await?;
+    
+    Members::try_from_row(&row)
+}
+
+pub async fn refresh_members(client: &Client, entity: &mut Members) -> Result<(), OrmError> {
+    let query_builder = QueryBuilder::select::<Members>()
+        .where_clause("id = $1")
+        .bind_param(entity.id);
+    
+    let (query, params) = query_builder.build();
+    
+    // The row may have been deleted since the entity was loaded
+    let row = client.query_opt(&query, &params[..]).await?
+        .ok_or_else(|| OrmError::QueryError(format!("No row in members where id = {:?}", entity.id)))?;
+    *entity = Members::try_from_row(&row)?;
+    
+    Ok(())
+}
+
+pub async fn get_members_by_ids(client: &Client, ids: &[i32]) -> Result<HashMap<i32, Members>, OrmError> {
+    let query_builder = QueryBuilder::select::<Members>()
+        .where_clause("id = ANY($1)")
+        .bind_param(ids.to_vec());
+    
+    // Ids without a matching row are simply absent from the map
+    let entities = query_builder.fetch_all(client).await?;
+    
+    Ok(entities.into_iter().map(|entity| (entity.id, entity)).collect())
+}
+
+pub async fn update_members(client: &Client, entity: &Members) -> Result<Members, OrmError> {
+    let entity = &members_to_db(entity);
+    let query_builder = QueryBuilder::update::<Members>()
+        .set_values(&[("email", &entity.email), ("id", &entity.id)])
+        .where_clause("id = $1")
+        .bind_param(entity.id)
+        .returning(&["email", "id"]);
+    
+    let (query, params) = query_builder.build();
+    
+    let row = client.query_one(&query, &params[..]).await?;
+    
+    Members::try_from_row(&row)
+}
+
+pub async fn update_members_changes(client: &Client, original: &Members, modified: &Members) -> Result<Option<Members>, OrmError> {
+    // Compared before the column transforms run, since a transform such as
+    // encryption with a random nonce need not give the same output twice
+    let written = &members_to_db(modified);
+    let mut changes: Vec<(&str, &(dyn tokio_postgres::types::ToSql + Sync))> = Vec::new();
+    if modified.email != original.email {
+        changes.push(("email", &written.email));
+    }
+    if modified.id != original.id {
+        changes.push(("id", &written.id));
+    }
+    
+    // Nothing changed, so there is nothing to write
+    if changes.is_empty() {
+        return Ok(None);
+    }
+    
+    let query_builder = QueryBuilder::update::<Members>()
+        .set_values(&changes)
+        .where_clause("id = $1")
+        .bind_param(original.id)
+        .returning(&["email", "id"]);
+    
+    let (query, params) = query_builder.build();
+    
+    let row = client.query_one(&query, &params[..]).await?;
+    
+    Members::try_from_row(&row).map(Some)
+}
+
+pub async fn delete_members(client: &Client, id: i32) -> Result<bool, tokio_postgres::Error> {
+    let query_builder = QueryBuilder::delete::<Members>()
+        .where_clause("id = $1")
+        .bind_param(id);
+    
+    let (query, params) = query_builder.build();
+    
+    let result = client.execute(&query, &params[..]).await?;
+    
+    Ok(result > 0)
+}
+
+pub async fn delete_members_returning(client: &Client, id: i32) -> Result<Option<Members>, OrmError> {
+    let query_builder = QueryBuilder::delete::<Members>()
+        .where_clause("id = $1")
+        .bind_param(id)
+        .returning(&["email", "id"]);
+    
+    let (query, params) = query_builder.build();
+    
+    let row = client.query_opt(&query, &params[..]).await?;
+    
+    row.as_ref().map(Members::try_from_row).transpose()
+}
+
+pub async fn list_members(client: &Client, limit: Option<i64>, offset: Option<i64>) -> Result<Vec<Members>, OrmError> {
+    let mut query_builder = QueryBuilder::select::<Members>().tiebreak_by("id");
+    
+    if let Some(limit_val) = limit {
+        query_builder = query_builder.limit(limit_val as usize);
+    }
+    
+    if let Some(offset_val) = offset {
+        query_builder = query_builder.offset(offset_val as usize);
+    }
+    
+    query_builder.fetch_all(client).await
+}
+
+pub async fn list_members_ordered(client: &Client, order_by: &[(&str, bool)], limit: Option<i64>, offset: Option<i64>) -> Result<Vec<Members>, OrmError> {
+    let mut query_builder = QueryBuilder::select::<Members>();
+    for (column, ascending) in order_by {
+        query_builder = query_builder.order_by(column, *ascending);
+    }
+    query_builder = query_builder.tiebreak_by("id");
+    
+    if let Some(limit_val) = limit {
+        query_builder = query_builder.limit(limit_val as usize);
+    }
+    
+    if let Some(offset_val) = offset {
+        query_builder = query_builder.offset(offset_val as usize);
+    }
+    
+    query_builder.fetch_all(client).await
+}
+
+pub async fn list_members_sorted(client: &Client, sort: MembersSort, limit: Option<i64>, offset: Option<i64>) -> Result<Vec<Members>, OrmError> {
+    let (column, ascending) = sort.order_by();
+    let mut query_builder = QueryBuilder::select::<Members>()
+        .order_by(column, ascending)
+        .tiebreak_by("id");
+    
+    if let Some(limit_val) = limit {
+        query_builder = query_builder.limit(limit_val as usize);
+    }
+    
+    if let Some(offset_val) = offset {
+        query_builder = query_builder.offset(offset_val as usize);
+    }
+    
+    query_builder.fetch_all(client).await
+}
+
+pub async fn list_members_page(client: &Client, cursor: Option<String>, limit: i64) -> Result<Page<Members>, OrmError> {
+    let after: Option<i32> = cursor.as_deref().map(decode_cursor).transpose()?;
+    let query_builder = QueryBuilder::select::<Members>()
+        .keyset_after("id", after)
+        .limit(limit as usize);
+    
+    let items = query_builder.fetch_all(client).await?;
+    
+    // A short page means there is nothing left to fetch
+    let next_cursor = if items.len() as i64 == limit {
+        items.last().map(|entity| encode_cursor(&entity.id))
+    } else {
+        None
+    };
+    
+    Ok(Page { items, next_cursor })
+}
+
+pub async fn export_members_ndjson<W: std::io::Write>(client: &Client, writer: &mut W) -> Result<u64, OrmError> {
+    let query_builder = QueryBuilder::select::<Members>()
+        .order_by("id", true);
+    
+    let (query, params) = query_builder.build();
+    
+    let rows = client.query_raw(&query, params).await?;
+    
+    write_ndjson(rows, writer, Members::try_from_row).await
+}
+
+pub async fn count_members(client: &Client, filter: MembersFilter) -> Result<i64, OrmError> {
+    let query_builder = QueryBuilder::select::<Members>()
+        .select(&[])
+        .select_expr("COUNT(*)", "count")
+        .where_eq_opt("email", crate::transforms::to_db_value("members", "email", &filter.email))
+        .where_eq_opt("id", filter.id);
+    
+    let (query, params) = query_builder.try_build()?;
+    
+    let row = client.query_one(&query, &params[..]).await?;
+    
+    Ok(row.get("count"))
+}
@@ -0,0 +1,91 @@
+/*
+ * This code was generated by rust_orm_gen.
+ * GitHub: https://github.com/tomblanchard312/rust_orm_gen
+ * Date: 2024-07-24
+ * Author: Tom Blanchard
+ */
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Orders {
+    #[serde(rename = "buyer")] pub buyer: i32,
+    #[serde(rename = "id")] pub id: i32,
+    #[serde(rename = "seller")] pub seller: Option<i32>,
+}
+
+pub const COLUMNS: &[&str] = &["buyer", "id", "seller"];
+pub const COL_BUYER: &str = "buyer";
+pub const COL_ID: &str = "id";
+pub const COL_SELLER: &str = "seller";
+
+impl crate::query_builder::Model for Orders {
+    fn table_name() -> &'static str {
+        "orders"
+    }
+
+    fn columns() -> &'static [&'static str] {
+        COLUMNS
+    }
+
+    fn from_row(row: &tokio_postgres::Row) -> Self {
+        Orders {
+            buyer: row.get("buyer"),
+            id: row.get("id"),
+            seller: row.get("seller"),
+        }
+    }
+}
+
+/// `Orders` plus the rows its foreign keys reference, each loaded on
+/// first access and then cached.
+pub struct OrdersWithRelations {
+    pub entity: Orders,
+    customers_by_buyer: crate::lazy_loading::LazyLoaded<std::sync::Arc<Customers>>,
+    customers_by_seller: crate::lazy_loading::LazyLoaded<Option<std::sync::Arc<Customers>>>,
+}
+
+impl Orders {
+    pub fn with_relations(self) -> OrdersWithRelations {
+        OrdersWithRelations {
+            entity: self,
+            customers_by_buyer: crate::lazy_loading::LazyLoaded::empty(),
+            customers_by_seller: crate::lazy_loading::LazyLoaded::empty(),
+        }
+    }
+}
+
+impl std::ops::Deref for OrdersWithRelations {
+    type Target = Orders;
+
+    fn deref(&self) -> &Orders {
+        &self.entity
+    }
+}
+
+impl OrdersWithRelations {
+    /// The `customers` row referenced by `buyer`, loaded on the first call. It is
+    /// shared rather than cloned, so `Customers` need not derive `Clone`.
+    pub async fn customers_by_buyer(&self, client: &tokio_postgres::Client) -> Result<std::sync::Arc<Customers>, crate::error::OrmError> {
+        self.customers_by_buyer.get_or_try_load(|| async {
+            crate::query_builder::QueryBuilder::select::<Customers>()
+                .where_eq("id", self.entity.buyer)
+                .fetch_one(client)
+                .await
+                .map(std::sync::Arc::new)
+        }).await
+    }
+
+    /// The `customers` row referenced by `seller`, or `None` if `seller` is NULL, loaded
+    /// on the first call. It is shared rather than cloned, so `Customers` need not
+    /// derive `Clone`.
+    pub async fn customers_by_seller(&self, client: &tokio_postgres::Client) -> Result<Option<std::sync::Arc<Customers>>, crate::error::OrmError> {
+        self.customers_by_seller.get_or_try_load(|| async {
+            crate::query_builder::QueryBuilder::select::<Customers>()
+                .where_eq("id", self.entity.seller)
+                .fetch_optional(client)
+                .await
+                .map(|row| row.map(std::sync::Arc::new))
+        }).await
+    }
+}
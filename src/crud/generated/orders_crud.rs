@@ -0,0 +1,306 @@
+/*
+ * This code was generated by rust_orm_gen.
+ * GitHub: https://github.com/tomblanchard312/rust_orm_gen
+ * Date: 2024-07-24
+ * Author: Tom Blanchard
+ */
+
+use std::collections::HashMap;
+use tokio_postgres::Client;
+use crate::error::OrmError;
+use crate::export::write_ndjson;
+use crate::pagination::{decode_cursor, encode_cursor, Page};
+use crate::query_builder::{Model, QueryBuilder, SortDirection};
+
+/// Applies the registered column transforms before a write.
+fn orders_to_db(entity: &Orders) -> Orders {
+    Orders {
+        buyer: entity.buyer,
+        id: entity.id,
+        seller: entity.seller,
+    }
+}
+
+/// A column and direction for `list_orders_sorted`. Only these columns can be
+/// sorted on, so a sort taken from a request cannot inject SQL.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OrdersSort {
+    Buyer(SortDirection),
+    Id(SortDirection),
+    Seller(SortDirection),
+}
+
+impl OrdersSort {
+    /// The column to order by and whether the order is ascending.
+    pub fn order_by(self) -> (&'static str, bool) {
+        match self {
+            OrdersSort::Buyer(direction) => ("buyer", direction == SortDirection::Asc),
+            OrdersSort::Id(direction) => ("id", direction == SortDirection::Asc),
+            OrdersSort::Seller(direction) => ("seller", direction == SortDirection::Asc),
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct OrdersFilter {
+    pub buyer: Option<i32>,
+    pub id: Option<i32>,
+    pub seller: Option<i32>,
+}
+
+pub async fn create_orders(client: &Client, entity: &Orders) -> Result<Orders, OrmError> {
+    let entity = &orders_to_db(entity);
+    let (query, params) = QueryBuilder::insert::<Orders>()
+        .columns(&["buyer", "id", "seller"])
+        .values(&[&entity.buyer, &entity.id, &entity.seller])
+        .returning(&["buyer", "id", "seller"])
+        .build();
+    
+    let row = client.query_one(&query, &params[..]).await?;
+    
+    Orders::try_from_row(&row)
+}
+
+/// Inserts `entities` in as few statements as the parameter limit allows
+/// and returns the created rows, with the values the database filled in,
+/// in `entities` order.
+pub async fn create_orders_batch(client: &Client, entities: &[Orders]) -> Result<Vec<Orders>, OrmError> {
+    // Postgres accepts at most 65535 bind parameters per statement
+    const MAX_PARAMS: usize = 65535;
+    const COLUMNS_PER_ROW: usize = 3;
+    let entities: Vec<Orders> = entities.iter().map(orders_to_db).collect();
+    let mut created = Vec::with_capacity(entities.len());
+    
+    for chunk in entities.chunks(MAX_PARAMS / COLUMNS_PER_ROW) {
+        let mut rows = Vec::with_capacity(chunk.len());
+        let mut params: Vec<&(dyn tokio_postgres::types::ToSql + Sync)> = Vec::with_capacity(chunk.len() * COLUMNS_PER_ROW);
+        for (row_index, entity) in chunk.iter().enumerate() {
+            let first = row_index * COLUMNS_PER_ROW;
+            let placeholders = (1..=COLUMNS_PER_ROW).map(|i| format!("${}", first + i)).collect::<Vec<_>>().join(", ");
+            rows.push(format!("({}, {})", placeholders, row_index + 1));
+            params.extend_from_slice(&[&entity.buyer, &entity.id, &entity.seller]);
+        }
+        let query = format!("INSERT INTO orders (buyer, id, seller) SELECT column1, column2, column3 FROM (VALUES ((NULL::orders).buyer, (NULL::orders).id, (NULL::orders).seller, 0), {}) AS input WHERE column4 > 0 ORDER BY column4 RETURNING buyer, id, seller", rows.join(", "));
+        
+        for row in &client.query(&query, &params[..]).await? {
+            created.push(Orders::try_from_row(row)?);
+        }
+    }
+    
+    Ok(created)
+}
+
+pub async fn get_orders(client: &Client, id: i32) -> Result<Orders, OrmError> {
+    QueryBuilder::select::<Orders>()
+        .where_clause("id = $1")
+        .bind_param(id)
+        .fetch_one(client)
+        .await
+}
+
+/// Reads the row and locks it until `transaction` ends, so other
+/// transactions locking or writing it wait for this one to finish.
+pub async fn get_orders_for_update(transaction: &tokio_postgres::Transaction<'_>, id: i32) -> Result<Orders, OrmError> {
+    let query_builder = QueryBuilder::select::<Orders>()
+        .where_clause("id = $1")
+        .bind_param(id)
+        .for_update();
+    
+    let (query, params) = query_builder.try_build()?;
+    
+    let row = transaction.query_one(&query, &params[..]).await?;
+    
+    Orders::try_from_row(&row)
+}
+
+pub async fn refresh_orders(client: &Client, entity: &mut Orders) -> Result<(), OrmError> {
+    let query_builder = QueryBuilder::select::<Orders>()
+        .where_clause("id = $1")
+        .bind_param(entity.id);
+    
+    let (query, params) = query_builder.build();
+    
+    // The row may have been deleted since the entity was loaded
+    let row = client.query_opt(&query, &params[..]).await?
+        .ok_or_else(|| OrmError::QueryError(format!("No row in orders where id = {:?}", entity.id)))?;
+    *entity = Orders::try_from_row(&row)?;
+    
+    Ok(())
+}
+
+pub async fn get_orders_by_ids(client: &Client, ids: &[i32]) -> Result<HashMap<i32, Orders>, OrmError> {
+    let query_builder = QueryBuilder::select::<Orders>()
+        .where_clause("id = ANY($1)")
+        .bind_param(ids.to_vec());
+    
+    // Ids without a matching row are simply absent from the map
+    let entities = query_builder.fetch_all(client).await?;
+    
+    Ok(entities.into_iter().map(|entity| (entity.id, entity)).collect())
+}
+
+pub async fn update_orders(client: &Client, entity: &Orders) -> Result<Orders, OrmError> {
+    let entity = &orders_to_db(entity);
+    let query_builder = QueryBuilder::update::<Orders>()
+        .set_values(&[("buyer", &entity.buyer), ("id", &entity.id), ("seller", &entity.seller)])
+        .where_clause("id = $1")
+        .bind_param(entity.id)
+        .returning(&["buyer", "id", "seller"]);
+    
+    let (query, params) = query_builder.build();
+    
+    let row = client.query_one(&query, &params[..]).await?;
+    
+    Orders::try_from_row(&row)
+}
+
+pub async fn update_orders_changes(client: &Client, original: &Orders, modified: &Orders) -> Result<Option<Orders>, OrmError> {
+    // Compared before the column transforms run, since a transform such as
+    // encryption with a random nonce need not give the same output twice
+    let written = &orders_to_db(modified);
+    let mut changes: Vec<(&str, &(dyn tokio_postgres::types::ToSql + Sync))> = Vec::new();
+    if modified.buyer != original.buyer {
+        changes.push(("buyer", &written.buyer));
+    }
+    if modified.id != original.id {
+        changes.push(("id", &written.id));
+    }
+    if modified.seller != original.seller {
+        changes.push(("seller", &written.seller));
+    }
+    
+    // Nothing changed, so there is nothing to write
+    if changes.is_empty() {
+        return Ok(None);
+    }
+    
+    let query_builder = QueryBuilder::update::<Orders>()
+        .set_values(&changes)
+        .where_clause("id = $1")
+        .bind_param(original.id)
+        .returning(&["buyer", "id", "seller"]);
+    
+    let (query, params) = query_builder.build();
+    
+    let row = client.query_one(&query, &params[..]).await?;
+    
+    Orders::try_from_row(&row).map(Some)
+}
+
+pub async fn delete_orders(client: &Client, id: i32) -> Result<bool, tokio_postgres::Error> {
+    let query_builder = QueryBuilder::delete::<Orders>()
+        .where_clause("id = $1")
+        .bind_param(id);
+    
+    let (query, params) = query_builder.build();
+    
+    let result = client.execute(&query, &params[..]).await?;
+    
+    Ok(result > 0)
+}
+
+pub async fn delete_orders_returning(client: &Client, id: i32) -> Result<Option<Orders>, OrmError> {
+    let query_builder = QueryBuilder::delete::<Orders>()
+        .where_clause("id = $1")
+        .bind_param(id)
+        .returning(&["buyer", "id", "seller"]);
+    
+    let (query, params) = query_builder.build();
+    
+    let row = client.query_opt(&query, &params[..]).await?;
+    
+    row.as_ref().map(Orders::try_from_row).transpose()
+}
+
+pub async fn list_orders(client: &Client, limit: Option<i64>, offset: Option<i64>) -> Result<Vec<Orders>, OrmError> {
+    let mut query_builder = QueryBuilder::select::<Orders>().tiebreak_by("id");
+    
+    if let Some(limit_val) = limit {
+        query_builder = query_builder.limit(limit_val as usize);
+    }
+    
+    if let Some(offset_val) = offset {
+        query_builder = query_builder.offset(offset_val as usize);
+    }
+    
+    query_builder.fetch_all(client).await
+}
+
+pub async fn list_orders_ordered(client: &Client, order_by: &[(&str, bool)], limit: Option<i64>, offset: Option<i64>) -> Result<Vec<Orders>, OrmError> {
+    let mut query_builder = QueryBuilder::select::<Orders>();
+    for (column, ascending) in order_by {
+        query_builder = query_builder.order_by(column, *ascending);
+    }
+    query_builder = query_builder.tiebreak_by("id");
+    
+    if let Some(limit_val) = limit {
+        query_builder = query_builder.limit(limit_val as usize);
+    }
+    
+    if let Some(offset_val) = offset {
+        query_builder = query_builder.offset(offset_val as usize);
+    }
+    
+    query_builder.fetch_all(client).await
+}
+
+pub async fn list_orders_sorted(client: &Client, sort: OrdersSort, limit: Option<i64>, offset: Option<i64>) -> Result<Vec<Orders>, OrmError> {
+    let (column, ascending) = sort.order_by();
+    let mut query_builder = QueryBuilder::select::<Orders>()
+        .order_by(column, ascending)
+        .tiebreak_by("id");
+    
+    if let Some(limit_val) = limit {
+        query_builder = query_builder.limit(limit_val as usize);
+    }
+    
+    if let Some(offset_val) = offset {
+        query_builder = query_builder.offset(offset_val as usize);
+    }
+    
+    query_builder.fetch_all(client).await
+}
+
+pub async fn list_orders_page(client: &Client, cursor: Option<String>, limit: i64) -> Result<Page<Orders>, OrmError> {
+    let after: Option<i32> = cursor.as_deref().map(decode_cursor).transpose()?;
+    let query_builder = QueryBuilder::select::<Orders>()
+        .keyset_after("id", after)
+        .limit(limit as usize);
+    
+    let items = query_builder.fetch_all(client).await?;
+    
+    // A short page means there is nothing left to fetch
+    let next_cursor = if items.len() as i64 == limit {
+        items.last().map(|entity| encode_cursor(&entity.id))
+    } else {
+        None
+    };
+    
+    Ok(Page { items, next_cursor })
+}
+
+pub async fn export_orders_ndjson<W: std::io::Write>(client: &Client, writer: &mut W) -> Result<u64, OrmError> {
+    let query_builder = QueryBuilder::select::<Orders>()
+        .order_by("id", true);
+    
+    let (query, params) = query_builder.build();
+    
+    let rows = client.query_raw(&query, params).await?;
+    
+    write_ndjson(rows, writer, Orders::try_from_row).await
+}
+
+pub async fn count_orders(client: &Client, filter: OrdersFilter) -> Result<i64, OrmError> {
+    let query_builder = QueryBuilder::select::<Orders>()
+        .select(&[])
+        .select_expr("COUNT(*)", "count")
+        .where_eq_opt("buyer", filter.buyer)
+        .where_eq_opt("id", filter.id)
+        .where_eq_opt("seller", filter.seller);
+    
+    let (query, params) = query_builder.try_build()?;
+    
+    let row = client.query_one(&query, &params[..]).await?;
+    
+    Ok(row.get("count"))
+}
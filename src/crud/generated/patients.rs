@@ -0,0 +1,38 @@
+/*
+ * This code was generated by rust_orm_gen.
+ * GitHub: https://github.com/tomblanchard312/rust_orm_gen
+ * Date: 2024-07-24
+ * Author: Tom Blanchard
+ */
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Patients {
+    #[serde(rename = "id")] pub id: i32,
+    #[serde(rename = "name")] pub name: String,
+    #[serde(rename = "ssn")] pub ssn: String,
+}
+
+pub const COLUMNS: &[&str] = &["id", "name", "ssn"];
+pub const COL_ID: &str = "id";
+pub const COL_NAME: &str = "name";
+pub const COL_SSN: &str = "ssn";
+
+impl crate::query_builder::Model for Patients {
+    fn table_name() -> &'static str {
+        "patients"
+    }
+
+    fn columns() -> &'static [&'static str] {
+        COLUMNS
+    }
+
+    fn from_row(row: &tokio_postgres::Row) -> Self {
+        Patients {
+            id: row.get("id"),
+            name: crate::transforms::from_db("patients", "name", row.get("name")),
+            ssn: crate::transforms::from_db("patients", "ssn", row.get("ssn")),
+        }
+    }
+}
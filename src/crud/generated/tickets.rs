@@ -0,0 +1,44 @@
+/*
+ * This code was generated by rust_orm_gen.
+ * GitHub: https://github.com/tomblanchard312/rust_orm_gen
+ * Date: 2024-07-24
+ * Author: Tom Blanchard
+ */
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Tickets {
+    #[serde(rename = "id")] pub id: i32,
+    #[serde(rename = "priority")] pub priority: i32,
+    #[serde(rename = "title")] pub title: String,
+}
+
+/// The columns of `Tickets` to insert; the database fills in the rest.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NewTickets {
+    #[serde(rename = "title")] pub title: String,
+}
+
+pub const COLUMNS: &[&str] = &["id", "priority", "title"];
+pub const COL_ID: &str = "id";
+pub const COL_PRIORITY: &str = "priority";
+pub const COL_TITLE: &str = "title";
+
+impl crate::query_builder::Model for Tickets {
+    fn table_name() -> &'static str {
+        "tickets"
+    }
+
+    fn columns() -> &'static [&'static str] {
+        COLUMNS
+    }
+
+    fn from_row(row: &tokio_postgres::Row) -> Self {
+        Tickets {
+            id: row.get("id"),
+            priority: row.get("priority"),
+            title: crate::transforms::from_db("tickets", "title", row.get("title")),
+        }
+    }
+}
@@ -0,0 +1,313 @@
+/*
+ * This code was generated by rust_orm_gen.
+ * GitHub: https://github.com/tomblanchard312/rust_orm_gen
+ * Date: 2024-07-24
+ * Author: Tom Blanchard
+ */
+
+use std::collections::HashMap;
+use tokio_postgres::Client;
+use crate::error::OrmError;
+use crate::export::write_ndjson;
+use crate::pagination::{decode_cursor, encode_cursor, Page};
+use crate::query_builder::{Model, QueryBuilder, SortDirection};
+
+/// Applies the registered column transforms before a write.
+fn tickets_to_db(entity: &Tickets) -> Tickets {
+    Tickets {
+        id: entity.id,
+        priority: entity.priority,
+        title: crate::transforms::to_db_value("tickets", "title", &entity.title),
+    }
+}
+
+/// Applies the registered column transforms before a write.
+fn tickets_new_to_db(entity: &NewTickets) -> NewTickets {
+    NewTickets {
+        title: crate::transforms::to_db_value("tickets", "title", &entity.title),
+    }
+}
+
+/// A column and direction for `list_tickets_sorted`. Only these columns can be
+/// sorted on, so a sort taken from a request cannot inject SQL.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TicketsSort {
+    Id(SortDirection),
+    Priority(SortDirection),
+    Title(SortDirection),
+}
+
+impl TicketsSort {
+    /// The column to order by and whether the order is ascending.
+    pub fn order_by(self) -> (&'static str, bool) {
+        match self {
+            TicketsSort::Id(direction) => ("id", direction == SortDirection::Asc),
+            TicketsSort::Priority(direction) => ("priority", direction == SortDirection::Asc),
+            TicketsSort::Title(direction) => ("title", direction == SortDirection::Asc),
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct TicketsFilter {
+    pub id: Option<i32>,
+    pub priority: Option<i32>,
+    pub title: Option<String>,
+}
+
+pub async fn create_tickets(client: &Client, entity: &NewTickets) -> Result<Tickets, OrmError> {
+    let entity = &tickets_new_to_db(entity);
+    let (query, params) = QueryBuilder::insert::<Tickets>()
+        .columns(&["title"])
+        .values(&[&entity.title])
+        .returning(&["id", "priority", "title"])
+        .build();
+    
+    let row = client.query_one(&query, &params[..]).await?;
+    
+    Tickets::try_from_row(&row)
+}
+
+/// Inserts `entities` in as few statements as the parameter limit allows
+/// and returns the created rows, with the values the database filled in,
+/// in `entities` order.
+pub async fn create_tickets_batch(client: &Client, entities: &[NewTickets]) -> Result<Vec<Tickets>, OrmError> {
+    // Postgres accepts at most 65535 bind parameters per statement
+    const MAX_PARAMS: usize = 65535;
+    const COLUMNS_PER_ROW: usize = 1;
+    let entities: Vec<NewTickets> = entities.iter().map(tickets_new_to_db).collect();
+    let mut created = Vec::with_capacity(entities.len());
+    
+    for chunk in entities.chunks(MAX_PARAMS / COLUMNS_PER_ROW) {
+        let mut rows = Vec::with_capacity(chunk.len());
+        let mut params: Vec<&(dyn tokio_postgres::types::ToSql + Sync)> = Vec::with_capacity(chunk.len() * COLUMNS_PER_ROW);
+        for (row_index, entity) in chunk.iter().enumerate() {
+            let first = row_index * COLUMNS_PER_ROW;
+            let placeholders = (1..=COLUMNS_PER_ROW).map(|i| format!("${}", first + i)).collect::<Vec<_>>().join(", ");
+            rows.push(format!("({}, {})", placeholders, row_index + 1));
+            params.extend_from_slice(&[&entity.title]);
+        }
+        let query = format!("INSERT INTO tickets (title) SELECT column1 FROM (VALUES ((NULL::tickets).title, 0), {}) AS input WHERE column2 > 0 ORDER BY column2 RETURNING id, priority, title", rows.join(", "));
+        
+        for row in &client.query(&query, &params[..]).await? {
+            created.push(Tickets::try_from_row(row)?);
+        }
+    }
+    
+    Ok(created)
+}
+
+pub async fn get_tickets(client: &Client, id: i32) -> Result<Tickets, OrmError> {
+    QueryBuilder::select::<Tickets>()
+        .where_clause("id = $1")
+        .bind_param(id)
+        .fetch_one(client)
+        .await
+}
+
+/// Reads the row and locks it until `transaction` ends, so other
+/// transactions locking or writing it wait for this one to finish.
+pub async fn get_tickets_for_update(transaction: &tokio_postgres::Transaction<'_>, id: i32) -> Result<Tickets, OrmError> {
+    let query_builder = QueryBuilder::select::<Tickets>()
+        .where_clause("id = $1")
+        .bind_param(id)
+        .for_update();
+    
+    let (query, params) = query_builder.try_build()?;
+    
+    let row = transaction.query_one(&query, &params[..]).await?;
+    
+    Tickets::try_from_row(&row)
+}
+
+pub async fn refresh_tickets(client: &Client, entity: &mut Tickets) -> Result<(), OrmError> {
+    let query_builder = QueryBuilder::select::<Tickets>()
+        .where_clause("id = $1")
+        .bind_param(entity.id);
+    
+    let (query, params) = query_builder.build();
+    
+    // The row may have been deleted since the entity was loaded
+    let row = client.query_opt(&query, &params[..]).await?
+        .ok_or_else(|| OrmError::QueryError(format!("No row in tickets where id = {:?}", entity.id)))?;
+    *entity = Tickets::try_from_row(&row)?;
+    
+    Ok(())
+}
+
+pub async fn get_tickets_by_ids(client: &Client, ids: &[i32]) -> Result<HashMap<i32, Tickets>, OrmError> {
+    let query_builder = QueryBuilder::select::<Tickets>()
+        .where_clause("id = ANY($1)")
+        .bind_param(ids.to_vec());
+    
+    // Ids without a matching row are simply absent from the map
+    let entities = query_builder.fetch_all(client).await?;
+    
+    Ok(entities.into_iter().map(|entity| (entity.id, entity)).collect())
+}
+
+pub async fn update_tickets(client: &Client, entity: &Tickets) -> Result<Tickets, OrmError> {
+    let entity = &tickets_to_db(entity);
+    let query_builder = QueryBuilder::update::<Tickets>()
+        .set_values(&[("id", &entity.id), ("priority", &entity.priority), ("title", &entity.title)])
+        .where_clause("id = $1")
+        .bind_param(entity.id)
+        .returning(&["id", "priority", "title"]);
+    
+    let (query, params) = query_builder.build();
+    
+    let row = client.query_one(&query, &params[..]).await?;
+    
+    Tickets::try_from_row(&row)
+}
+
+pub async fn update_tickets_changes(client: &Client, original: &Tickets, modified: &Tickets) -> Result<Option<Tickets>, OrmError> {
+    // Compared before the column transforms run, since a transform such as
+    // encryption with a random nonce need not give the same output twice
+    let written = &tickets_to_db(modified);
+    let mut changes: Vec<(&str, &(dyn tokio_postgres::types::ToSql + Sync))> = Vec::new();
+    if modified.id != original.id {
+        changes.push(("id", &written.id));
+    }
+    if modified.priority != original.priority {
+        changes.push(("priority", &written.priority));
+    }
+    if modified.title != original.title {
+        changes.push(("title", &written.title));
+    }
+    
+    // Nothing changed, so there is nothing to write
+    if changes.is_empty() {
+        return Ok(None);
+    }
+    
+    let query_builder = QueryBuilder::update::<Tickets>()
+        .set_values(&changes)
+        .where_clause("id = $1")
+        .bind_param(original.id)
+        .returning(&["id", "priority", "title"]);
+    
+    let (query, params) = query_builder.build();
+    
+    let row = client.query_one(&query, &params[..]).await?;
+    
+    Tickets::try_from_row(&row).map(Some)
+}
+
+pub async fn delete_tickets(client: &Client, id: i32) -> Result<bool, tokio_postgres::Error> {
+    let query_builder = QueryBuilder::delete::<Tickets>()
+        .where_clause("id = $1")
+        .bind_param(id);
+    
+    let (query, params) = query_builder.build();
+    
+    let result = client.execute(&query, &params[..]).await?;
+    
+    Ok(result > 0)
+}
+
+pub async fn delete_tickets_returning(client: &Client, id: i32) -> Result<Option<Tickets>, OrmError> {
+    let query_builder = QueryBuilder::delete::<Tickets>()
+        .where_clause("id = $1")
+        .bind_param(id)
+        .returning(&["id", "priority", "title"]);
+    
+    let (query, params) = query_builder.build();
+    
+    let row = client.query_opt(&query, &params[..]).await?;
+    
+    row.as_ref().map(Tickets::try_from_row).transpose()
+}
+
+pub async fn list_tickets(client: &Client, limit: Option<i64>, offset: Option<i64>) -> Result<Vec<Tickets>, OrmError> {
+    let mut query_builder = QueryBuilder::select::<Tickets>().tiebreak_by("id");
+    
+    if let Some(limit_val) = limit {
+        query_builder = query_builder.limit(limit_val as usize);
+    }
+    
+    if let Some(offset_val) = offset {
+        query_builder = query_builder.offset(offset_val as usize);
+    }
+    
+    query_builder.fetch_all(client).await
+}
+
+pub async fn list_tickets_ordered(client: &Client, order_by: &[(&str, bool)], limit: Option<i64>, offset: Option<i64>) -> Result<Vec<Tickets>, OrmError> {
+    let mut query_builder = QueryBuilder::select::<Tickets>();
+    for (column, ascending) in order_by {
+        query_builder = query_builder.order_by(column, *ascending);
+    }
+    query_builder = query_builder.tiebreak_by("id");
+    
+    if let Some(limit_val) = limit {
+        query_builder = query_builder.limit(limit_val as usize);
+    }
+    
+    if let Some(offset_val) = offset {
+        query_builder = query_builder.offset(offset_val as usize);
+    }
+    
+    query_builder.fetch_all(client).await
+}
+
+pub async fn list_tickets_sorted(client: &Client, sort: TicketsSort, limit: Option<i64>, offset: Option<i64>) -> Result<Vec<Tickets>, OrmError> {
+    let (column, ascending) = sort.order_by();
+    let mut query_builder = QueryBuilder::select::<Tickets>()
+        .order_by(column, ascending)
+        .tiebreak_by("id");
+    
+    if let Some(limit_val) = limit {
+        query_builder = query_builder.limit(limit_val as usize);
+    }
+    
+    if let Some(offset_val) = offset {
+        query_builder = query_builder.offset(offset_val as usize);
+    }
+    
+    query_builder.fetch_all(client).await
+}
+
+pub async fn list_tickets_page(client: &Client, cursor: Option<String>, limit: i64) -> Result<Page<Tickets>, OrmError> {
+    let after: Option<i32> = cursor.as_deref().map(decode_cursor).transpose()?;
+    let query_builder = QueryBuilder::select::<Tickets>()
+        .keyset_after("id", after)
+        .limit(limit as usize);
+    
+    let items = query_builder.fetch_all(client).await?;
+    
+    // A short page means there is nothing left to fetch
+    let next_cursor = if items.len() as i64 == limit {
+        items.last().map(|entity| encode_cursor(&entity.id))
+    } else {
+        None
+    };
+    
+    Ok(Page { items, next_cursor })
+}
+
+pub async fn export_tickets_ndjson<W: std::io::Write>(client: &Client, writer: &mut W) -> Result<u64, OrmError> {
+    let query_builder = QueryBuilder::select::<Tickets>()
+        .order_by("id", true);
+    
+    let (query, params) = query_builder.build();
+    
+    let rows = client.query_raw(&query, params).await?;
+    
+    write_ndjson(rows, writer, Tickets::try_from_row).await
+}
+
+pub async fn count_tickets(client: &Client, filter: TicketsFilter) -> Result<i64, OrmError> {
+    let query_builder = QueryBuilder::select::<Tickets>()
+        .select(&[])
+        .select_expr("COUNT(*)", "count")
+        .where_eq_opt("id", filter.id)
+        .where_eq_opt("priority", filter.priority)
+        .where_eq_opt("title", crate::transforms::to_db_value("tickets", "title", &filter.title));
+    
+    let (query, params) = query_builder.try_build()?;
+    
+    let row = client.query_one(&query, &params[..]).await?;
+    
+    Ok(row.get("count"))
+}
@@ -0,0 +1,40 @@
+/*
+ * This code was generated by rust_orm_gen.
+ * GitHub: https://github.com/tomblanchard312/rust_orm_gen
+ * Date: 2024-07-24
+ * Author: Tom Blanchard
+ */
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Visits {
+    #[serde(rename = "hits")] pub hits: i32,
+    #[serde(rename = "id")] pub id: i32,
+}
+
+/// The columns of `Visits` to insert; the database fills in the rest.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NewVisits {
+}
+
+pub const COLUMNS: &[&str] = &["hits", "id"];
+pub const COL_HITS: &str = "hits";
+pub const COL_ID: &str = "id";
+
+impl crate::query_builder::Model for Visits {
+    fn table_name() -> &'static str {
+        "visits"
+    }
+
+    fn columns() -> &'static [&'static str] {
+        COLUMNS
+    }
+
+    fn from_row(row: &tokio_postgres::Row) -> Self {
+        Visits {
+            hits: row.get("hits"),
+            id: row.get("id"),
+        }
+    }
+}
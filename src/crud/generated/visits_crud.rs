@@ -0,0 +1,270 @@
+/*
+ * This code was generated by rust_orm_gen.
+ * GitHub: https://github.com/tomblanchard312/rust_orm_gen
+ * Date: 2024-07-24
+ * Author: Tom Blanchard
+ */
+
+use std::collections::HashMap;
+use tokio_postgres::Client;
+use crate::error::OrmError;
+use crate::export::write_ndjson;
+use crate::pagination::{decode_cursor, encode_cursor, Page};
+use crate::query_builder::{Model, QueryBuilder, SortDirection};
+
+/// Applies the registered column transforms before a write.
+fn visits_to_db(entity: &Visits) -> Visits {
+    Visits {
+        hits: entity.hits,
+        id: entity.id,
+    }
+}
+
+/// A column and direction for `list_visits_sorted`. Only these columns can be
+/// sorted on, so a sort taken from a request cannot inject SQL.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VisitsSort {
+    Hits(SortDirection),
+    Id(SortDirection),
+}
+
+impl VisitsSort {
+    /// The column to order by and whether the order is ascending.
+    pub fn order_by(self) -> (&'static str, bool) {
+        match self {
+            VisitsSort::Hits(direction) => ("hits", direction == SortDirection::Asc),
+            VisitsSort::Id(direction) => ("id", direction == SortDirection::Asc),
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct VisitsFilter {
+    pub hits: Option<i32>,
+    pub id: Option<i32>,
+}
+
+pub async fn create_visits(client: &Client, _entity: &NewVisits) -> Result<Visits, OrmError> {
+    let row = client.query_one("INSERT INTO visits DEFAULT VALUES RETURNING hits, id", &[]).await?;
+    
+    Visits::try_from_row(&row)
+}
+
+/// Inserts one row per entry of `entities` in a single statement and
+/// returns the created rows in `entities` order.
+pub async fn create_visits_batch(client: &Client, entities: &[NewVisits]) -> Result<Vec<Visits>, OrmError> {
+    // Every column takes its default, as with DEFAULT VALUES
+    let rows = client.query("INSERT INTO visits SELECT FROM generate_series(1, $1::bigint) AS input(ordinal) ORDER BY ordinal RETURNING hits, id", &[&(entities.len() as i64)]).await?;
+    rows.iter().map(Visits::try_from_row).collect()
+}
+
+pub async fn get_visits(client: &Client, id: i32) -> Result<Visits, OrmError> {
+    QueryBuilder::select::<Visits>()
+        .where_clause("id = $1")
+        .bind_param(id)
+        .fetch_one(client)
+        .await
+}
+
+/// Reads the row and locks it until `transaction` ends, so other
+/// transactions locking or writing it wait for this one to finish.
+pub async fn get_visits_for_update(transaction: &tokio_postgres::Transaction<'_>, id: i32) -> Result<Visits, OrmError> {
+    let query_builder = QueryBuilder::select::<Visits>()
+        .where_clause("id = $1")
+        .bind_param(id)
+        .for_update();
+    
+    let (query, params) = query_builder.try_build()?;
+    
+    let row = transaction.query_one(&query, &params[..]).await?;
+    
+    Visits::try_from_row(&row)
+}
+
+pub async fn refresh_visits(client: &Client, entity: &mut Visits) -> Result<(), OrmError> {
+    let query_builder = QueryBuilder::select::<Visits>()
+        .where_clause("id = $1")
+        .bind_param(entity.id);
+    
+    let (query, params) = query_builder.build();
+    
+    // The row may have been deleted since the entity was loaded
+    let row = client.query_opt(&query, &params[..]).await?
+        .ok_or_else(|| OrmError::QueryError(format!("No row in visits where id = {:?}", entity.id)))?;
+    *entity = Visits::try_from_row(&row)?;
+    
+    Ok(())
+}
+
+pub async fn get_visits_by_ids(client: &Client, ids: &[i32]) -> Result<HashMap<i32, Visits>, OrmError> {
+    let query_builder = QueryBuilder::select::<Visits>()
+        .where_clause("id = ANY($1)")
+        .bind_param(ids.to_vec());
+    
+    // Ids without a matching row are simply absent from the map
+    let entities = query_builder.fetch_all(client).await?;
+    
+    Ok(entities.into_iter().map(|entity| (entity.id, entity)).collect())
+}
+
+pub async fn update_visits(client: &Client, entity: &Visits) -> Result<Visits, OrmError> {
+    let entity = &visits_to_db(entity);
+    let query_builder = QueryBuilder::update::<Visits>()
+        .set_values(&[("hits", &entity.hits), ("id", &entity.id)])
+        .where_clause("id = $1")
+        .bind_param(entity.id)
+        .returning(&["hits", "id"]);
+    
+    let (query, params) = query_builder.build();
+    
+    let row = client.query_one(&query, &params[..]).await?;
+    
+    Visits::try_from_row(&row)
+}
+
+pub async fn update_visits_changes(client: &Client, original: &Visits, modified: &Visits) -> Result<Option<Visits>, OrmError> {
+    // Compared before the column transforms run, since a transform such as
+    // encryption with a random nonce need not give the same output twice
+    let written = &visits_to_db(modified);
+    let mut changes: Vec<(&str, &(dyn tokio_postgres::types::ToSql + Sync))> = Vec::new();
+    if modified.hits != original.hits {
+        changes.push(("hits", &written.hits));
+    }
+    if modified.id != original.id {
+        changes.push(("id", &written.id));
+    }
+    
+    // Nothing changed, so there is nothing to write
+    if changes.is_empty() {
+        return Ok(None);
+    }
+    
+    let query_builder = QueryBuilder::update::<Visits>()
+        .set_values(&changes)
+        .where_clause("id = $1")
+        .bind_param(original.id)
+        .returning(&["hits", "id"]);
+    
+    let (query, params) = query_builder.build();
+    
+    let row = client.query_one(&query, &params[..]).await?;
+    
+    Visits::try_from_row(&row).map(Some)
+}
+
+pub async fn delete_visits(client: &Client, id: i32) -> Result<bool, tokio_postgres::Error> {
+    let query_builder = QueryBuilder::delete::<Visits>()
+        .where_clause("id = $1")
+        .bind_param(id);
+    
+    let (query, params) = query_builder.build();
+    
+    let result = client.execute(&query, &params[..]).await?;
+    
+    Ok(result > 0)
+}
+
+pub async fn delete_visits_returning(client: &Client, id: i32) -> Result<Option<Visits>, OrmError> {
+    let query_builder = QueryBuilder::delete::<Visits>()
+        .where_clause("id = $1")
+        .bind_param(id)
+        .returning(&["hits", "id"]);
+    
+    let (query, params) = query_builder.build();
+    
+    let row = client.query_opt(&query, &params[..]).await?;
+    
+    row.as_ref().map(Visits::try_from_row).transpose()
+}
+
+pub async fn list_visits(client: &Client, limit: Option<i64>, offset: Option<i64>) -> Result<Vec<Visits>, OrmError> {
+    let mut query_builder = QueryBuilder::select::<Visits>().tiebreak_by("id");
+    
+    if let Some(limit_val) = limit {
+        query_builder = query_builder.limit(limit_val as usize);
+    }
+    
+    if let Some(offset_val) = offset {
+        query_builder = query_builder.offset(offset_val as usize);
+    }
+    
+    query_builder.fetch_all(client).await
+}
+
+pub async fn list_visits_ordered(client: &Client, order_by: &[(&str, bool)], limit: Option<i64>, offset: Option<i64>) -> Result<Vec<Visits>, OrmError> {
+    let mut query_builder = QueryBuilder::select::<Visits>();
+    for (column, ascending) in order_by {
+        query_builder = query_builder.order_by(column, *ascending);
+    }
+    query_builder = query_builder.tiebreak_by("id");
+    
+    if let Some(limit_val) = limit {
+        query_builder = query_builder.limit(limit_val as usize);
+    }
+    
+    if let Some(offset_val) = offset {
+        query_builder = query_builder.offset(offset_val as usize);
+    }
+    
+    query_builder.fetch_all(client).await
+}
+
+pub async fn list_visits_sorted(client: &Client, sort: VisitsSort, limit: Option<i64>, offset: Option<i64>) -> Result<Vec<Visits>, OrmError> {
+    let (column, ascending) = sort.order_by();
+    let mut query_builder = QueryBuilder::select::<Visits>()
+        .order_by(column, ascending)
+        .tiebreak_by("id");
+    
+    if let Some(limit_val) = limit {
+        query_builder = query_builder.limit(limit_val as usize);
+    }
+    
+    if let Some(offset_val) = offset {
+        query_builder = query_builder.offset(offset_val as usize);
+    }
+    
+    query_builder.fetch_all(client).await
+}
+
+pub async fn list_visits_page(client: &Client, cursor: Option<String>, limit: i64) -> Result<Page<Visits>, OrmError> {
+    let after: Option<i32> = cursor.as_deref().map(decode_cursor).transpose()?;
+    let query_builder = QueryBuilder::select::<Visits>()
+        .keyset_after("id", after)
+        .limit(limit as usize);
+    
+    let items = query_builder.fetch_all(client).await?;
+    
+    // A short page means there is nothing left to fetch
+    let next_cursor = if items.len() as i64 == limit {
+        items.last().map(|entity| encode_cursor(&entity.id))
+    } else {
+        None
+    };
+    
+    Ok(Page { items, next_cursor })
+}
+
+pub async fn export_visits_ndjson<W: std::io::Write>(client: &Client, writer: &mut W) -> Result<u64, OrmError> {
+    let query_builder = QueryBuilder::select::<Visits>()
+        .order_by("id", true);
+    
+    let (query, params) = query_builder.build();
+    
+    let rows = client.query_raw(&query, params).await?;
+    
+    write_ndjson(rows, writer, Visits::try_from_row).await
+}
+
+pub async fn count_visits(client: &Client, filter: VisitsFilter) -> Result<i64, OrmError> {
+    let query_builder = QueryBuilder::select::<Visits>()
+        .select(&[])
+        .select_expr("COUNT(*)", "count")
+        .where_eq_opt("hits", filter.hits)
+        .where_eq_opt("id", filter.id);
+    
+    let (query, params) = query_builder.try_build()?;
+    
+    let row = client.query_one(&query, &params[..]).await?;
+    
+    Ok(row.get("count"))
+}
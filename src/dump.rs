@@ -0,0 +1,748 @@
+use crate::error::OrmError;
+use crate::metadata::{ColumnInfo, ForeignKey};
+use crate::schema::{SchemaDocument, TableSchema};
+
+impl SchemaDocument {
+    /// Reads the tables of the `public` schema from the DDL in a `pg_dump
+    /// --schema-only` file, for generating code without database access.
+    /// Understands `CREATE TABLE` with column and table constraints, and
+    /// primary and foreign keys added with `ALTER TABLE ... ADD CONSTRAINT`;
+    /// other statements are skipped, and so are composite foreign keys, as
+    /// `metadata::get_foreign_keys` does. Types are spelled the way
+    /// `metadata::get_columns` reports them, e.g. `character varying` without
+    /// its length, so the document matches an introspected one.
+    pub fn from_dump(sql: &str) -> Result<Self, OrmError> {
+        let tokens = tokenize(sql)?;
+        let mut tables: Vec<TableSchema> = Vec::new();
+        let mut foreign_keys: Vec<PendingForeignKey> = Vec::new();
+
+        for statement in tokens.split(|spanned| spanned.token == Token::Symbol(';')) {
+            let mut cursor = Cursor::new(statement);
+            if cursor.eat_word("create") {
+                create_table(&mut cursor, sql, &mut tables, &mut foreign_keys)?;
+            } else if cursor.eat_words(&["alter", "table"]) {
+                alter_table(&mut cursor, sql, &mut tables, &mut foreign_keys)?;
+            }
+        }
+
+        for foreign_key in foreign_keys {
+            let foreign_columns = match foreign_key.foreign_columns {
+                Some(columns) => columns,
+                // REFERENCES without columns means the referenced primary key
+                None => tables.iter()
+                    .find(|table| table.name == foreign_key.foreign_table && !table.primary_key.is_empty())
+                    .map(|table| table.primary_key.clone())
+                    .ok_or_else(|| OrmError::ParseError(format!(
+                        "Foreign key on {}({}) does not name the columns it references, and '{}' has no primary key in the dump",
+                        foreign_key.table, foreign_key.columns.join(", "), foreign_key.foreign_table
+                    )))?,
+            };
+            if foreign_columns.len() != foreign_key.columns.len() {
+                return Err(OrmError::ParseError(format!(
+                    "Foreign key on {}({}) references {} column(s) of '{}'",
+                    foreign_key.table, foreign_key.columns.join(", "), foreign_columns.len(), foreign_key.foreign_table
+                )));
+            }
+            let ([column], [foreign_column]) = (foreign_key.columns.as_slice(), foreign_columns.as_slice()) else { continue };
+            table_mut(&mut tables, &foreign_key.table)?.foreign_keys.push(
+                ForeignKey::new(column, &foreign_key.foreign_table, foreign_column)
+                    .on_delete(&foreign_key.on_delete)
+                    .on_update(&foreign_key.on_update),
+            );
+        }
+
+        // Primary key columns are NOT NULL whether or not the DDL says so
+        for table in &mut tables {
+            let primary_key = table.primary_key.clone();
+            for column in table.columns.iter_mut().filter(|column| primary_key.contains(&column.name)) {
+                column.is_nullable = false;
+            }
+        }
+        tables.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(SchemaDocument { tables })
+    }
+}
+
+/// A foreign key whose referenced columns may only be known once the whole
+/// dump has been read.
+struct PendingForeignKey {
+    table: String,
+    columns: Vec<String>,
+    foreign_table: String,
+    foreign_columns: Option<Vec<String>>,
+    on_delete: String,
+    on_update: String,
+}
+
+enum Constraint {
+    PrimaryKey(Vec<String>),
+    ForeignKey(PendingForeignKey),
+}
+
+/// Words that end a column's type and start its constraints.
+const COLUMN_CONSTRAINT_WORDS: &[&str] = &["not", "null", "default", "constraint", "primary", "references", "unique", "check", "generated", "collate"];
+
+/// Words that start a table constraint rather than a column definition.
+const TABLE_CONSTRAINT_WORDS: &[&str] = &["constraint", "primary", "foreign", "unique", "check", "exclude", "like"];
+
+fn create_table(cursor: &mut Cursor, sql: &str, tables: &mut Vec<TableSchema>, foreign_keys: &mut Vec<PendingForeignKey>) -> Result<(), OrmError> {
+    for modifier in ["global", "local", "temporary", "temp", "unlogged"] {
+        cursor.eat_word(modifier);
+    }
+    if !cursor.eat_word("table") {
+        return Ok(());
+    }
+    cursor.eat_words(&["if", "not", "exists"]);
+    let Some((schema, name)) = cursor.qualified_name() else {
+        return Err(parse_error("CREATE TABLE without a table name", cursor, sql));
+    };
+    // Partitions, typed tables and CREATE TABLE AS have no column list
+    let Some(body) = cursor.group() else { return Ok(()) };
+    if !is_public(&schema) {
+        return Ok(());
+    }
+
+    let mut table = TableSchema { name: name.clone(), ..TableSchema::default() };
+    for element in split_top_level(body) {
+        let mut element_cursor = Cursor::new(element);
+        let is_constraint = matches!(element.first(), Some(Spanned { token: Token::Word(word), .. }) if TABLE_CONSTRAINT_WORDS.contains(&word.as_str()));
+        if is_constraint {
+            match table_constraint(&mut element_cursor, sql, &name)? {
+                Some(Constraint::PrimaryKey(columns)) => table.primary_key = columns,
+                Some(Constraint::ForeignKey(foreign_key)) => foreign_keys.push(foreign_key),
+                None => {}
+            }
+        } else {
+            let (column, is_primary_key, foreign_key) = column_definition(&mut element_cursor, sql, &name)?;
+            if is_primary_key {
+                table.primary_key = vec![column.name.clone()];
+            }
+            foreign_keys.extend(foreign_key);
+            table.columns.push(column);
+        }
+    }
+    tables.push(table);
+    Ok(())
+}
+
+fn alter_table(cursor: &mut Cursor, sql: &str, tables: &mut [TableSchema], foreign_keys: &mut Vec<PendingForeignKey>) -> Result<(), OrmError> {
+    cursor.eat_words(&["if", "exists"]);
+    cursor.eat_word("only");
+    let Some((schema, name)) = cursor.qualified_name() else {
+        return Err(parse_error("ALTER TABLE without a table name", cursor, sql));
+    };
+    if !is_public(&schema) {
+        return Ok(());
+    }
+    for action in split_top_level(cursor.rest()) {
+        let mut action_cursor = Cursor::new(action);
+        if !action_cursor.eat_word("add") {
+            continue;
+        }
+        match table_constraint(&mut action_cursor, sql, &name)? {
+            Some(Constraint::PrimaryKey(columns)) => table_mut(tables, &name)?.primary_key = columns,
+            Some(Constraint::ForeignKey(foreign_key)) => {
+                table_mut(tables, &name)?;
+                foreign_keys.push(foreign_key);
+            }
+            None => {}
+        }
+    }
+    Ok(())
+}
+
+/// Parses `name type [constraints]`, returning the column, whether it is
+/// the primary key, and its foreign key if it has a `REFERENCES` clause.
+fn column_definition(cursor: &mut Cursor, sql: &str, table: &str) -> Result<(ColumnInfo, bool, Option<PendingForeignKey>), OrmError> {
+    let Some(name) = cursor.ident() else {
+        return Err(parse_error(&format!("Expected a column name in table '{}'", table), cursor, sql));
+    };
+
+    let mut type_words: Vec<String> = Vec::new();
+    let mut array_suffix = String::new();
+    while let Some(token) = cursor.peek() {
+        match token {
+            Token::Word(word) if COLUMN_CONSTRAINT_WORDS.contains(&word.as_str()) => break,
+            Token::Word(word) if word == "array" => {
+                array_suffix.push_str("[]");
+                cursor.advance();
+            }
+            Token::Word(word) | Token::Quoted(word) => {
+                type_words.push(word.clone());
+                cursor.advance();
+            }
+            // Drop the schema of a qualified type name such as public.mood
+            Token::Symbol('.') => {
+                type_words.clear();
+                cursor.advance();
+            }
+            // Type modifiers such as the length in character varying(100)
+            Token::Symbol('(') => {
+                cursor.group();
+            }
+            Token::Symbol('[') => {
+                while !matches!(cursor.advance(), Some(Token::Symbol(']')) | None) {}
+                array_suffix.push_str("[]");
+            }
+            _ => return Err(parse_error(&format!("Unexpected token in the type of {}.{}", table, name), cursor, sql)),
+        }
+    }
+    if type_words.is_empty() {
+        return Err(parse_error(&format!("Column {}.{} has no type", table, name), cursor, sql));
+    }
+    let mut column = ColumnInfo::new(&name, &format!("{}{}", canonical_type(&type_words.join(" ")), array_suffix)).nullable();
+
+    let mut is_primary_key = false;
+    let mut foreign_key = None;
+    while cursor.peek().is_some() {
+        if cursor.eat_words(&["not", "null"]) {
+            column.is_nullable = false;
+        } else if cursor.eat_words(&["primary", "key"]) {
+            is_primary_key = true;
+        } else if cursor.eat_word("references") {
+            foreign_key = Some(references(cursor, sql, table, vec![name.clone()])?);
+        } else if cursor.eat_words(&["generated", "always", "as"]) {
+            // GENERATED ALWAYS AS IDENTITY has no expression and is skipped below
+            if let Some(expression) = cursor.group() {
+                if cursor.eat_word("stored") {
+                    column.generation_expression = Some(source_text(sql, expression));
+                }
+            }
+        } else if cursor.eat_word("constraint") {
+            cursor.ident();
+        } else if cursor.group().is_none() {
+            cursor.advance();
+        }
+    }
+    Ok((column, is_primary_key, foreign_key))
+}
+
+/// Parses a table constraint, returning `None` for the kinds a
+/// `SchemaDocument` does not record, such as `UNIQUE` and `CHECK`.
+fn table_constraint(cursor: &mut Cursor, sql: &str, table: &str) -> Result<Option<Constraint>, OrmError> {
+    if cursor.eat_word("constraint") {
+        cursor.ident();
+    }
+    if cursor.eat_words(&["primary", "key"]) {
+        return match cursor.ident_list() {
+            Some(columns) => Ok(Some(Constraint::PrimaryKey(columns))),
+            None => Err(parse_error(&format!("PRIMARY KEY of '{}' has no column list", table), cursor, sql)),
+        };
+    }
+    if cursor.eat_words(&["foreign", "key"]) {
+        let Some(columns) = cursor.ident_list() else {
+            return Err(parse_error(&format!("FOREIGN KEY of '{}' has no column list", table), cursor, sql));
+        };
+        if !cursor.eat_word("references") {
+            return Err(parse_error(&format!("FOREIGN KEY of '{}' has no REFERENCES clause", table), cursor, sql));
+        }
+        return Ok(Some(Constraint::ForeignKey(references(cursor, sql, table, columns)?)));
+    }
+    Ok(None)
+}
+
+/// Parses what follows `REFERENCES`: the table, its columns if given, and
+/// the referential actions.
+fn references(cursor: &mut Cursor, sql: &str, table: &str, columns: Vec<String>) -> Result<PendingForeignKey, OrmError> {
+    let Some((_, foreign_table)) = cursor.qualified_name() else {
+        return Err(parse_error(&format!("REFERENCES in '{}' has no table name", table), cursor, sql));
+    };
+    let mut foreign_key = PendingForeignKey {
+        table: table.to_string(),
+        columns,
+        foreign_table,
+        foreign_columns: cursor.ident_list(),
+        on_delete: "NO ACTION".to_string(),
+        on_update: "NO ACTION".to_string(),
+    };
+    loop {
+        if cursor.eat_word("match") {
+            cursor.ident();
+        } else if cursor.eat_words(&["on", "delete"]) {
+            foreign_key.on_delete = referential_action(cursor, sql)?;
+        } else if cursor.eat_words(&["on", "update"]) {
+            foreign_key.on_update = referential_action(cursor, sql)?;
+        } else {
+            return Ok(foreign_key);
+        }
+    }
+}
+
+fn referential_action(cursor: &mut Cursor, sql: &str) -> Result<String, OrmError> {
+    for action in [["no", "action"], ["set", "null"], ["set", "default"]] {
+        if cursor.eat_words(&action) {
+            // SET NULL (column, ...) limits the action to some columns
+            cursor.group();
+            return Ok(action.join(" ").to_uppercase());
+        }
+    }
+    for action in ["cascade", "restrict"] {
+        if cursor.eat_word(action) {
+            return Ok(action.to_uppercase());
+        }
+    }
+    Err(parse_error("Unknown referential action", cursor, sql))
+}
+
+/// The name `information_schema` reports for a type, so aliases such as
+/// `varchar` and `int4` match what introspection returns.
+fn canonical_type(name: &str) -> &str {
+    match name {
+        "int" | "int4" | "serial" | "serial4" => "integer",
+        "int8" | "bigserial" | "serial8" => "bigint",
+        "int2" | "smallserial" | "serial2" => "smallint",
+        "varchar" => "character varying",
+        "char" | "bpchar" => "character",
+        "bool" => "boolean",
+        "float4" => "real",
+        "float8" => "double precision",
+        "decimal" => "numeric",
+        "timestamp" => "timestamp without time zone",
+        "timestamptz" => "timestamp with time zone",
+        "time" => "time without time zone",
+        "timetz" => "time with time zone",
+        other => other,
+    }
+}
+
+fn is_public(schema: &Option<String>) -> bool {
+    schema.as_deref().is_none_or(|schema| schema == "public")
+}
+
+fn table_mut<'a>(tables: &'a mut [TableSchema], name: &str) -> Result<&'a mut TableSchema, OrmError> {
+    tables.iter_mut()
+        .find(|table| table.name == name)
+        .ok_or_else(|| OrmError::ParseError(format!("ALTER TABLE on '{}', which the dump does not create", name)))
+}
+
+fn source_text(sql: &str, tokens: &[Spanned]) -> String {
+    match (tokens.first(), tokens.last()) {
+        (Some(first), Some(last)) => sql[first.start..last.end].to_string(),
+        _ => String::new(),
+    }
+}
+
+fn parse_error(message: &str, cursor: &Cursor, sql: &str) -> OrmError {
+    match cursor.tokens.get(cursor.pos).or(cursor.tokens.last()) {
+        Some(spanned) => OrmError::ParseError(format!("{} at line {}", message, sql[..spanned.start].lines().count().max(1))),
+        None => OrmError::ParseError(message.to_string()),
+    }
+}
+
+/// Splits a parenthesized list at its top-level commas.
+fn split_top_level(tokens: &[Spanned]) -> Vec<&[Spanned]> {
+    let mut parts = Vec::new();
+    let mut depth = 0;
+    let mut start = 0;
+    for (index, spanned) in tokens.iter().enumerate() {
+        match spanned.token {
+            Token::Symbol('(') | Token::Symbol('[') => depth += 1,
+            Token::Symbol(')') | Token::Symbol(']') => depth -= 1,
+            Token::Symbol(',') if depth == 0 => {
+                parts.push(&tokens[start..index]);
+                start = index + 1;
+            }
+            _ => {}
+        }
+    }
+    parts.push(&tokens[start..]);
+    parts.retain(|part| !part.is_empty());
+    parts
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    /// An unquoted identifier or keyword, lowercased as Postgres folds it.
+    Word(String),
+    /// A double-quoted identifier, with its case kept.
+    Quoted(String),
+    /// A string, number or dollar-quoted body; only its span matters.
+    Literal,
+    Symbol(char),
+}
+
+struct Spanned {
+    token: Token,
+    start: usize,
+    end: usize,
+}
+
+/// Splits `sql` into tokens, dropping whitespace and comments. Strings and
+/// dollar-quoted bodies are single tokens, so the `;` and keywords inside a
+/// function body are never mistaken for statement boundaries.
+fn tokenize(sql: &str) -> Result<Vec<Spanned>, OrmError> {
+    let bytes = sql.as_bytes();
+    let mut tokens: Vec<Spanned> = Vec::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        let start = i;
+        let token = match bytes[i] {
+            byte if byte.is_ascii_whitespace() => {
+                i += 1;
+                continue;
+            }
+            b'-' if bytes.get(i + 1) == Some(&b'-') => {
+                i = sql[i..].find('\n').map_or(bytes.len(), |end| i + end);
+                continue;
+            }
+            b'/' if bytes.get(i + 1) == Some(&b'*') => {
+                i = block_comment_end(bytes, i).ok_or_else(|| unterminated("comment", sql, start))?;
+                continue;
+            }
+            b'\'' => {
+                // E'...' strings allow backslash escapes
+                let escapes = tokens.last().is_some_and(|last| last.end == i && last.token == Token::Word("e".to_string()));
+                i = string_end(bytes, i, b'\'', escapes).ok_or_else(|| unterminated("string", sql, start))?;
+                Token::Literal
+            }
+            b'"' => {
+                i = string_end(bytes, i, b'"', false).ok_or_else(|| unterminated("quoted identifier", sql, start))?;
+                Token::Quoted(sql[start + 1..i - 1].replace("\"\"", "\""))
+            }
+            b'$' => match dollar_tag(sql, i) {
+                Some(tag) => {
+                    let body = i + tag.len();
+                    let close = sql[body..].find(tag).ok_or_else(|| unterminated("dollar-quoted string", sql, start))?;
+                    i = body + close + tag.len();
+                    Token::Literal
+                }
+                None => {
+                    i += 1;
+                    Token::Symbol('$')
+                }
+            },
+            byte if byte.is_ascii_digit() => {
+                i += sql[i..].find(|c: char| !(c.is_ascii_digit() || c == '.')).unwrap_or(sql.len() - i);
+                Token::Literal
+            }
+            byte if byte.is_ascii_alphabetic() || byte == b'_' || !byte.is_ascii() => {
+                i += sql[i..].find(|c: char| !(c.is_alphanumeric() || c == '_' || c == '$')).unwrap_or(sql.len() - i);
+                Token::Word(sql[start..i].to_lowercase())
+            }
+            _ => {
+                let symbol = sql[i..].chars().next().unwrap_or_default();
+                i += symbol.len_utf8();
+                Token::Symbol(symbol)
+            }
+        };
+        tokens.push(Spanned { token, start, end: i });
+    }
+    Ok(tokens)
+}
+
+/// The end of the string or quoted identifier opened at `start`, where a
+/// doubled `quote` stands for itself.
+fn string_end(bytes: &[u8], start: usize, quote: u8, escapes: bool) -> Option<usize> {
+    let mut i = start + 1;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\\' if escapes => i += 2,
+            byte if byte == quote && bytes.get(i + 1) == Some(&quote) => i += 2,
+            byte if byte == quote => return Some(i + 1),
+            _ => i += 1,
+        }
+    }
+    None
+}
+
+/// The end of the comment opened at `start`; block comments nest.
+fn block_comment_end(bytes: &[u8], start: usize) -> Option<usize> {
+    let mut depth = 0;
+    let mut i = start;
+    while i < bytes.len() {
+        if bytes[i..].starts_with(b"/*") {
+            depth += 1;
+            i += 2;
+        } else if bytes[i..].starts_with(b"*/") {
+            depth -= 1;
+            i += 2;
+            if depth == 0 {
+                return Some(i);
+            }
+        } else {
+            i += 1;
+        }
+    }
+    None
+}
+
+/// The `$tag$` opening a dollar-quoted string at `start`, if there is one.
+fn dollar_tag(sql: &str, start: usize) -> Option<&str> {
+    let rest = &sql[start + 1..];
+    let length = rest.find(|c: char| !(c.is_alphanumeric() || c == '_')).unwrap_or(rest.len());
+    if rest.starts_with(|c: char| c.is_ascii_digit()) || !rest[length..].starts_with('$') {
+        return None;
+    }
+    Some(&sql[start..start + length + 2])
+}
+
+fn unterminated(what: &str, sql: &str, start: usize) -> OrmError {
+    OrmError::ParseError(format!("Unterminated {} starting at line {}", what, sql[..start].lines().count().max(1)))
+}
+
+struct Cursor<'a> {
+    tokens: &'a [Spanned],
+    pos: usize,
+}
+
+impl<'a> Cursor<'a> {
+    fn new(tokens: &'a [Spanned]) -> Self {
+        Cursor { tokens, pos: 0 }
+    }
+
+    fn peek(&self) -> Option<&'a Token> {
+        self.tokens.get(self.pos).map(|spanned| &spanned.token)
+    }
+
+    fn advance(&mut self) -> Option<&'a Token> {
+        let token = self.peek();
+        self.pos += 1;
+        token
+    }
+
+    fn rest(&self) -> &'a [Spanned] {
+        &self.tokens[self.pos.min(self.tokens.len())..]
+    }
+
+    fn eat_word(&mut self, word: &str) -> bool {
+        self.eat_words(&[word])
+    }
+
+    /// Consumes `words` if the next tokens are exactly those keywords.
+    fn eat_words(&mut self, words: &[&str]) -> bool {
+        let matches = words.iter().enumerate().all(|(offset, word)| {
+            matches!(self.tokens.get(self.pos + offset), Some(Spanned { token: Token::Word(next), .. }) if next == word)
+        });
+        if matches {
+            self.pos += words.len();
+        }
+        matches
+    }
+
+    fn ident(&mut self) -> Option<String> {
+        match self.peek()? {
+            Token::Word(name) | Token::Quoted(name) => {
+                self.pos += 1;
+                Some(name.clone())
+            }
+            _ => None,
+        }
+    }
+
+    /// Parses `name` or `schema.name`.
+    fn qualified_name(&mut self) -> Option<(Option<String>, String)> {
+        let first = self.ident()?;
+        if self.peek() == Some(&Token::Symbol('.')) {
+            self.pos += 1;
+            let name = self.ident()?;
+            return Some((Some(first), name));
+        }
+        Some((None, first))
+    }
+
+    /// Consumes a parenthesized group and returns the tokens inside it.
+    fn group(&mut self) -> Option<&'a [Spanned]> {
+        if self.peek() != Some(&Token::Symbol('(')) {
+            return None;
+        }
+        let open = self.pos;
+        let mut depth = 0;
+        while let Some(token) = self.advance() {
+            match token {
+                Token::Symbol('(') => depth += 1,
+                Token::Symbol(')') => {
+                    depth -= 1;
+                    if depth == 0 {
+                        return Some(&self.tokens[open + 1..self.pos - 1]);
+                    }
+                }
+                _ => {}
+            }
+        }
+        Some(&self.tokens[open + 1..])
+    }
+
+    /// Parses `(name, ...)`.
+    fn ident_list(&mut self) -> Option<Vec<String>> {
+        let group = self.group()?;
+        split_top_level(group).into_iter()
+            .map(|part| Cursor::new(part).ident())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::PostgresConnectionManager;
+    use dotenv::dotenv;
+    use std::env;
+
+    #[test]
+    fn test_from_dump_reads_tables_and_constraints() {
+        let dump = r#"
+--
+-- PostgreSQL database dump
+--
+SET statement_timeout = 0;
+SELECT pg_catalog.set_config('search_path', '', false);
+
+CREATE TYPE public.mood AS ENUM ('happy', 'sad');
+
+/* A function whose body looks like DDL; it must not be parsed as such */
+CREATE FUNCTION public.touch() RETURNS trigger
+    LANGUAGE plpgsql
+    AS $_$
+BEGIN
+    CREATE TABLE not_a_table (id integer);
+    RETURN NEW;
+END;
+$_$;
+
+CREATE TABLE public.customers (
+    id integer NOT NULL,
+    name character varying(100) NOT NULL,
+    "Zip Code" text,
+    tags text[],
+    created_at timestamp(3) with time zone DEFAULT now() NOT NULL,
+    note text DEFAULT 'it''s; not (a) separator'::text,
+    current_mood public.mood
+);
+
+ALTER TABLE public.customers OWNER TO postgres;
+
+CREATE TABLE public.orders (
+    id bigint NOT NULL,
+    customer_id integer,
+    total numeric(10,2) DEFAULT 0.00,
+    total_with_tax numeric GENERATED ALWAYS AS ((total * 1.2)) STORED
+);
+
+CREATE TABLE order_lines (
+    order_id int8 REFERENCES orders ON DELETE CASCADE,
+    line int NOT NULL,
+    sku varchar(20),
+    CONSTRAINT order_lines_pkey PRIMARY KEY (order_id, line),
+    CHECK (line > 0)
+);
+
+CREATE TABLE public.shipments (
+    id integer NOT NULL,
+    order_id bigint,
+    line integer,
+    customer_id integer REFERENCES public.customers(id),
+    FOREIGN KEY (order_id, line) REFERENCES public.order_lines(order_id, line)
+);
+
+CREATE TABLE audit.events (
+    id integer NOT NULL
+);
+
+ALTER TABLE ONLY public.customers
+    ADD CONSTRAINT customers_pkey PRIMARY KEY (id);
+
+ALTER TABLE ONLY public.orders
+    ADD CONSTRAINT orders_pkey PRIMARY KEY (id);
+
+ALTER TABLE ONLY public.orders
+    ADD CONSTRAINT orders_customer_id_fkey FOREIGN KEY (customer_id) REFERENCES public.customers(id) ON UPDATE CASCADE ON DELETE SET NULL;
+
+ALTER TABLE ONLY audit.events
+    ADD CONSTRAINT events_pkey PRIMARY KEY (id);
+"#;
+
+        let document = SchemaDocument::from_dump(dump).expect("Failed to parse the dump");
+
+        let names: Vec<&str> = document.tables.iter().map(|table| table.name.as_str()).collect();
+        assert_eq!(names, vec!["customers", "order_lines", "orders", "shipments"]);
+
+        let customers = &document.tables[0];
+        assert_eq!(customers.columns, vec![
+            ColumnInfo::new("id", "integer"),
+            ColumnInfo::new("name", "character varying"),
+            ColumnInfo::new("Zip Code", "text").nullable(),
+            ColumnInfo::new("tags", "text[]").nullable(),
+            ColumnInfo::new("created_at", "timestamp with time zone"),
+            ColumnInfo::new("note", "text").nullable(),
+            ColumnInfo::new("current_mood", "mood").nullable(),
+        ]);
+        assert_eq!(customers.primary_key, vec!["id".to_string()]);
+        assert!(customers.foreign_keys.is_empty());
+
+        let order_lines = &document.tables[1];
+        assert_eq!(order_lines.columns, vec![
+            ColumnInfo::new("order_id", "bigint"),
+            ColumnInfo::new("line", "integer"),
+            ColumnInfo::new("sku", "character varying").nullable(),
+        ]);
+        assert_eq!(order_lines.primary_key, vec!["order_id".to_string(), "line".to_string()]);
+        assert_eq!(order_lines.foreign_keys, vec![ForeignKey::new("order_id", "orders", "id").on_delete("CASCADE")]);
+
+        let orders = &document.tables[2];
+        assert_eq!(orders.columns[3], ColumnInfo::new("total_with_tax", "numeric").nullable().with_generation_expression("(total * 1.2)"));
+        assert_eq!(orders.primary_key, vec!["id".to_string()]);
+        assert_eq!(orders.foreign_keys, vec![ForeignKey::new("customer_id", "customers", "id").on_delete("SET NULL").on_update("CASCADE")]);
+
+        // The two-column key to order_lines is skipped, as introspection does
+        let shipments = &document.tables[3];
+        assert_eq!(shipments.foreign_keys, vec![ForeignKey::new("customer_id", "customers", "id")]);
+    }
+
+    #[test]
+    fn test_from_dump_reports_malformed_ddl() {
+        let unterminated = SchemaDocument::from_dump("CREATE TABLE t (\n    note text DEFAULT 'oops\n);");
+        assert!(matches!(unterminated, Err(OrmError::ParseError(message)) if message == "Unterminated string starting at line 2"));
+
+        let unknown_table = SchemaDocument::from_dump("ALTER TABLE ONLY public.missing ADD CONSTRAINT missing_pkey PRIMARY KEY (id);");
+        assert!(matches!(unknown_table, Err(OrmError::ParseError(message)) if message.contains("'missing', which the dump does not create")));
+
+        let no_key = SchemaDocument::from_dump("CREATE TABLE a (id integer);\nCREATE TABLE b (a_id integer REFERENCES a);");
+        assert!(matches!(no_key, Err(OrmError::ParseError(message)) if message.contains("'a' has no primary key in the dump")));
+    }
+
+    #[tokio::test]
+    async fn test_from_dump_matches_introspection() {
+        dotenv().ok();
+        let database_url = env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+        let manager = PostgresConnectionManager::new(database_url);
+        let client = manager.connect().await.expect("Failed to connect to database");
+        // Spelled the way pg_dump writes it, so it both runs and parses
+        let dump = "CREATE TYPE public.dump_mood AS ENUM ('happy', 'sad');
+            CREATE TABLE public.dump_customers (
+                id integer NOT NULL,
+                name character varying(100) NOT NULL,
+                \"Zip Code\" text,
+                nicknames character varying(20)[],
+                created_at timestamp(3) with time zone DEFAULT now() NOT NULL,
+                moods public.dump_mood[]
+            );
+            CREATE TABLE public.dump_orders (
+                id bigint NOT NULL,
+                dump_customer_id integer,
+                customer_name character varying(100),
+                total numeric(10,2) DEFAULT 0.00,
+                total_with_tax numeric GENERATED ALWAYS AS ((total * 1.2)) STORED
+            );
+            ALTER TABLE ONLY public.dump_customers
+                ADD CONSTRAINT dump_customers_pkey PRIMARY KEY (id);
+            ALTER TABLE ONLY public.dump_customers
+                ADD CONSTRAINT dump_customers_id_name_key UNIQUE (id, name);
+            ALTER TABLE ONLY public.dump_orders
+                ADD CONSTRAINT dump_orders_pkey PRIMARY KEY (id);
+            ALTER TABLE ONLY public.dump_orders
+                ADD CONSTRAINT dump_orders_dump_customer_id_fkey FOREIGN KEY (dump_customer_id) REFERENCES public.dump_customers(id) ON DELETE SET NULL;
+            ALTER TABLE ONLY public.dump_orders
+                ADD CONSTRAINT dump_orders_customer_fkey FOREIGN KEY (dump_customer_id, customer_name) REFERENCES public.dump_customers(id, name);";
+        client.batch_execute(
+            "DROP TABLE IF EXISTS dump_orders, dump_customers;
+             DROP TYPE IF EXISTS dump_mood;"
+        ).await.unwrap();
+        client.batch_execute(dump).await.expect("Failed to run the dump");
+
+        let introspected = SchemaDocument::introspect_tables(&client, &["dump_customers", "dump_orders"]).await;
+        client.batch_execute("DROP TABLE dump_orders, dump_customers; DROP TYPE dump_mood;").await.unwrap();
+
+        let parsed = SchemaDocument::from_dump(dump).expect("Failed to parse the dump");
+        assert_eq!(parsed, introspected.expect("Failed to introspect"));
+    }
+}
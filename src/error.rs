@@ -24,7 +24,16 @@ impl fmt::Display for OrmError {
     }
 }
 
-impl std::error::Error for OrmError {}
+impl std::error::Error for OrmError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            OrmError::DatabaseError(e) => Some(e),
+            OrmError::IoError(e) => Some(e),
+            OrmError::EnvError(e) => Some(e),
+            OrmError::ConnectionError(_) | OrmError::QueryError(_) | OrmError::ParseError(_) => None,
+        }
+    }
+}
 
 impl From<PgError> for OrmError {
     fn from(err: PgError) -> OrmError {
@@ -0,0 +1,74 @@
+use crate::error::OrmError;
+use futures_util::TryStreamExt;
+use serde::Serialize;
+use std::io::Write;
+use std::pin::pin;
+use tokio_postgres::{Row, RowStream};
+
+/// Writes each row of `rows` to `writer` as one JSON object per line, as it
+/// arrives, so exporting a large table never holds more than one row in
+/// memory. `to_entity` turns a row into the serializable struct; its first
+/// error stops the export. Returns the number of rows written.
+pub async fn write_ndjson<T, W, F>(rows: RowStream, writer: &mut W, to_entity: F) -> Result<u64, OrmError>
+where
+    T: Serialize,
+    W: Write,
+    F: Fn(&Row) -> Result<T, OrmError>,
+{
+    let mut rows = pin!(rows);
+    let mut written = 0;
+    while let Some(row) = rows.try_next().await? {
+        serde_json::to_writer(&mut *writer, &to_entity(&row)?)
+            .map_err(|e| OrmError::ParseError(format!("Failed to serialize row {} as JSON: {}", written + 1, e)))?;
+        writer.write_all(b"\n")?;
+        written += 1;
+    }
+    writer.flush()?;
+    Ok(written)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::PostgresConnectionManager;
+    use dotenv::dotenv;
+    use serde::Deserialize;
+    use std::env;
+    use tokio_postgres::types::ToSql;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct ExportCustomer {
+        id: i32,
+        name: String,
+    }
+
+    #[tokio::test]
+    async fn test_write_ndjson() {
+        dotenv().ok();
+        let database_url = env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+        let manager = PostgresConnectionManager::new(database_url);
+        let client = manager.connect().await.expect("Failed to connect to database");
+        client.batch_execute(
+            "DROP TABLE IF EXISTS export_customers;
+             CREATE TABLE export_customers (id INTEGER PRIMARY KEY, name TEXT NOT NULL);
+             INSERT INTO export_customers VALUES (1, 'Alice'), (2, 'Bob \"the builder\"'), (3, 'Carol');"
+        ).await.unwrap();
+
+        let rows = client.query_raw("SELECT id, name FROM export_customers ORDER BY id", std::iter::empty::<&(dyn ToSql + Sync)>()).await.unwrap();
+        let mut output = Vec::new();
+        let written = write_ndjson(rows, &mut output, |row| Ok(ExportCustomer { id: row.get("id"), name: row.get("name") })).await;
+        client.batch_execute("DROP TABLE export_customers").await.unwrap();
+
+        assert_eq!(written.unwrap(), 3);
+        let output = String::from_utf8(output).unwrap();
+        let customers: Vec<ExportCustomer> = output.lines()
+            .map(|line| serde_json::from_str(line).expect("each line should be one JSON object"))
+            .collect();
+        assert!(output.ends_with('\n'));
+        assert_eq!(customers, vec![
+            ExportCustomer { id: 1, name: "Alice".to_string() },
+            ExportCustomer { id: 2, name: "Bob \"the builder\"".to_string() },
+            ExportCustomer { id: 3, name: "Carol".to_string() },
+        ]);
+    }
+}
@@ -0,0 +1,187 @@
+use crate::error::OrmError;
+use crate::query_builder::quote_identifier;
+use crate::relationships::{DependencyGraph, TableDependency};
+use crate::transforms;
+use serde_json::Value;
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::Path;
+use tokio_postgres::Client;
+
+/// Loads a JSON fixtures file of the form `{"table": [{"column": value}, ...]}`.
+///
+/// Tables are filled in foreign-key order, so parents are inserted before the
+/// rows that reference them regardless of their order in the file. Everything
+/// runs in one transaction with constraints deferred, which also lets
+/// mutually-referencing tables load as long as their keys are deferrable.
+///
+/// Rows do not go through the generated `create_*` functions: each table is
+/// filled with one `INSERT ... SELECT` from `json_populate_recordset`, and
+/// columns missing from every row get their defaults. String values are
+/// still passed through any transform registered for their column (see
+/// `transforms::register_transform`), as `create_*` would, so fixtures hold
+/// the values as the application sees them.
+pub async fn load_fixtures(client: &mut Client, path: impl AsRef<Path>) -> Result<(), OrmError> {
+    let contents = fs::read_to_string(path)?;
+    let fixtures: serde_json::Map<String, Value> = serde_json::from_str(&contents)
+        .map_err(|e| OrmError::ParseError(format!("Invalid fixtures file: {}", e)))?;
+
+    let tables: Vec<String> = fixtures.keys().cloned().collect();
+    let mut graph = DependencyGraph::new();
+    for table in &tables {
+        graph.add_table(table);
+    }
+    for dependency in get_fixture_dependencies(client, &tables).await? {
+        graph.add_dependency(dependency);
+    }
+
+    let transaction = client.transaction().await?;
+    transaction.batch_execute("SET CONSTRAINTS ALL DEFERRED").await?;
+
+    for table in graph.creation_plan().order {
+        let rows = match fixtures.get(&table) {
+            Some(Value::Array(rows)) => rows,
+            Some(_) => return Err(OrmError::ParseError(format!("Fixtures for table '{}' must be an array of rows", table))),
+            None => continue,
+        };
+        if rows.is_empty() {
+            continue;
+        }
+
+        let mut columns = BTreeSet::new();
+        for row in rows {
+            match row {
+                Value::Object(fields) => columns.extend(fields.keys().map(|c| quote_identifier(c))),
+                _ => return Err(OrmError::ParseError(format!("Fixture row for table '{}' must be an object", table))),
+            }
+        }
+        let columns: Vec<String> = columns.into_iter().collect();
+        let rows: Vec<Value> = rows.iter().map(|row| match row {
+            Value::Object(fields) => Value::Object(fields.iter().map(|(column, value)| {
+                let value = match value {
+                    Value::String(text) => Value::String(transforms::to_db(&table, column, text)),
+                    other => other.clone(),
+                };
+                (column.clone(), value)
+            }).collect()),
+            other => other.clone(),
+        }).collect();
+        let query = format!(
+            "INSERT INTO {table} ({columns}) SELECT {columns} FROM json_populate_recordset(NULL::{table}, $1::text::json)",
+            table = quote_identifier(&table),
+            columns = columns.join(", ")
+        );
+        transaction.execute(&query, &[&Value::Array(rows).to_string()]).await?;
+    }
+
+    transaction.commit().await?;
+    Ok(())
+}
+
+async fn get_fixture_dependencies(client: &Client, tables: &[String]) -> Result<Vec<TableDependency>, OrmError> {
+    let rows = client
+        .query(
+            "SELECT cl.relname::text, att.attname::text, ref.relname::text, ref_att.attname::text
+             FROM pg_constraint con
+             JOIN pg_class cl ON cl.oid = con.conrelid
+             JOIN pg_class ref ON ref.oid = con.confrelid
+             JOIN pg_namespace ns ON ns.oid = cl.relnamespace
+             CROSS JOIN LATERAL unnest(con.conkey, con.confkey) AS keys(attnum, ref_attnum)
+             JOIN pg_attribute att ON att.attrelid = con.conrelid AND att.attnum = keys.attnum
+             JOIN pg_attribute ref_att ON ref_att.attrelid = con.confrelid AND ref_att.attnum = keys.ref_attnum
+             WHERE con.contype = 'f' AND ns.nspname = 'public' AND cl.relname = ANY($1)",
+            &[&tables],
+        )
+        .await?;
+    Ok(rows
+        .iter()
+        .filter(|row| tables.contains(&row.get::<_, String>(2)))
+        .map(|row| TableDependency::new(row.get(0), row.get(1), row.get(2), row.get(3)))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio;
+    use dotenv::dotenv;
+    use std::env;
+    use crate::db::PostgresConnectionManager;
+
+    #[tokio::test]
+    async fn test_load_fixtures_in_dependency_order() {
+        dotenv().ok();
+        let database_url = env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+        let manager = PostgresConnectionManager::new(database_url);
+        let mut client = manager.connect().await.expect("Failed to connect to database");
+
+        client.batch_execute(
+            "DROP TABLE IF EXISTS fixture_albums, fixture_artists;
+             CREATE TABLE fixture_artists (id INTEGER PRIMARY KEY, name TEXT NOT NULL);
+             CREATE TABLE fixture_albums (id SERIAL PRIMARY KEY, artist_id INTEGER NOT NULL REFERENCES fixture_artists (id), title TEXT NOT NULL);"
+        ).await.expect("Failed to create fixture tables");
+
+        // `fixture_albums` sorts before `fixture_artists`, so loading in file
+        // order would violate the foreign key
+        let path = env::temp_dir().join(format!("rust_orm_gen_fixtures_{}.json", std::process::id()));
+        fs::write(&path, r#"{
+            "fixture_albums": [
+                {"artist_id": 1, "title": "First"},
+                {"artist_id": 2, "title": "Second"}
+            ],
+            "fixture_artists": [
+                {"id": 1, "name": "Ada"},
+                {"id": 2, "name": "Grace"}
+            ]
+        }"#).unwrap();
+
+        let result = load_fixtures(&mut client, &path).await;
+        fs::remove_file(&path).ok();
+
+        let artists: i64 = client.query_one("SELECT COUNT(*) FROM fixture_artists", &[]).await.unwrap().get(0);
+        let albums: i64 = client.query_one("SELECT COUNT(*) FROM fixture_albums", &[]).await.unwrap().get(0);
+        client.batch_execute("DROP TABLE fixture_albums, fixture_artists").await.unwrap();
+
+        assert!(result.is_ok(), "Failed to load fixtures: {:?}", result.err());
+        assert_eq!(artists, 2);
+        assert_eq!(albums, 2);
+    }
+
+    /// Reverses the string, standing in for real encryption.
+    struct Reverse;
+
+    impl transforms::ColumnTransform for Reverse {
+        fn to_db(&self, value: &str) -> String {
+            value.chars().rev().collect()
+        }
+
+        fn from_db(&self, value: &str) -> String {
+            value.chars().rev().collect()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_load_fixtures_applies_registered_transforms() {
+        dotenv().ok();
+        let database_url = env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+        let manager = PostgresConnectionManager::new(database_url);
+        let mut client = manager.connect().await.expect("Failed to connect to database");
+        client.batch_execute(
+            "DROP TABLE IF EXISTS fixture_patients;
+             CREATE TABLE fixture_patients (id INTEGER PRIMARY KEY, ssn TEXT NOT NULL);"
+        ).await.expect("Failed to create fixture table");
+        transforms::register_transform("fixture_patients", "ssn", Reverse);
+
+        let path = env::temp_dir().join(format!("rust_orm_gen_fixtures_transform_{}.json", std::process::id()));
+        fs::write(&path, r#"{"fixture_patients": [{"id": 1, "ssn": "123-45-6789"}]}"#).unwrap();
+        let result = load_fixtures(&mut client, &path).await;
+        fs::remove_file(&path).ok();
+        transforms::unregister_transform("fixture_patients", "ssn");
+
+        let stored: Vec<String> = client.query("SELECT ssn FROM fixture_patients", &[]).await.unwrap().iter().map(|row| row.get(0)).collect();
+        client.batch_execute("DROP TABLE fixture_patients").await.unwrap();
+
+        assert!(result.is_ok(), "Failed to load fixtures: {:?}", result.err());
+        assert_eq!(stored, vec!["9876-54-321".to_string()]);
+    }
+}
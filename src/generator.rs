@@ -1,7 +1,13 @@
 use tokio_postgres::{NoTls, Error};
 use convert_case::{Case, Casing};
 use chrono::NaiveDate;
-use std::collections::HashMap;
+use log::warn;
+use std::borrow::Cow;
+use std::collections::{BTreeSet, HashMap};
+use std::fmt;
+use std::sync::Arc;
+use crate::metadata::{ColumnInfo, CompositeType, EnumType};
+use crate::relationships::TableDependency;
 
 async fn get_tables(client: &tokio_postgres::Client) -> Result<Vec<String>, Error> {
     let rows = client
@@ -11,10 +17,8 @@ async fn get_tables(client: &tokio_postgres::Client) -> Result<Vec<String>, Erro
 }
 
 async fn get_columns(client: &tokio_postgres::Client, table_name: &str) -> Result<HashMap<String, String>, Error> {
-    let query = format!(
-        "SELECT column_name, data_type FROM information_schema.columns WHERE table_name = $1"
-    );
-    let rows = client.query(&query, &[&table_name]).await?;
+    let query = "SELECT column_name, data_type FROM information_schema.columns WHERE table_name = $1";
+    let rows = client.query(query, &[&table_name]).await?;
     Ok(rows.iter().map(|row| (row.get(0), row.get(1))).collect())
 }
 
@@ -38,50 +42,662 @@ pub async fn generate_structs(database_url: &str) -> Result<(), Error> {
     Ok(())
 }
 
+/// A trait that can be added to the derive list of generated structs on top of
+/// the always-present `Debug, Serialize, Deserialize`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Derive {
+    Clone,
+    PartialEq,
+    Eq,
+    Hash,
+    PartialOrd,
+    Ord,
+}
+
+impl fmt::Display for Derive {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Derive::Clone => write!(f, "Clone"),
+            Derive::PartialEq => write!(f, "PartialEq"),
+            Derive::Eq => write!(f, "Eq"),
+            Derive::Hash => write!(f, "Hash"),
+            Derive::PartialOrd => write!(f, "PartialOrd"),
+            Derive::Ord => write!(f, "Ord"),
+        }
+    }
+}
+
+/// How generated CRUD operations are exposed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CrudStyle {
+    /// Module-level functions such as `create_customer(client, entity)`.
+    FreeFunctions,
+    /// Associated functions in an `impl Customer` block, such as
+    /// `Customer::create(client, entity)`.
+    InherentImpl,
+}
+
+/// How generated structs name their fields in JSON. The Rust field is
+/// always the column name with spaces replaced by underscores (see
+/// `field_name`), and queries always use the column name itself.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JsonNaming {
+    /// The column name as is, e.g. `zip code`.
+    Column,
+    /// The column name in camelCase, e.g. `zipCode`, for JavaScript clients.
+    CamelCase,
+}
+
+/// A hook that rewrites the contents of each generated file before it is
+/// written, e.g. to add a license header or `#![allow(...)]` attributes.
+pub type PostProcess = Arc<dyn Fn(&str) -> String + Send + Sync>;
+
+/// Options controlling the shape of generated code.
+#[derive(Clone)]
+pub struct GeneratorConfig {
+    /// Extra derives to request for every struct, none by default; see
+    /// `with_derives`. Derives that a field type cannot satisfy (e.g. `Eq`
+    /// on a float) are dropped per struct.
+    pub derives: Vec<Derive>,
+    pub crud_style: CrudStyle,
+    pub post_process: Option<PostProcess>,
+    /// Low-cardinality columns, keyed by table, that get a
+    /// `distinct_{table}_{column}s` function listing their distinct values.
+    pub distinct_columns: HashMap<String, Vec<String>>,
+    /// Reads every field with `try_get`, so a NULL in a non-`Option` field
+    /// (e.g. from an outer join) becomes a `ParseError` naming the column
+    /// instead of a panic.
+    pub fallible_rows: bool,
+    /// Spells external field types out in full (e.g. `chrono::NaiveDate`)
+    /// instead of importing them with `use` at the top of the struct file.
+    pub qualified_type_paths: bool,
+    /// Wraps every generated CRUD function in `telemetry::instrument`, which
+    /// counts and times calls per table. The generated code then needs the
+    /// `metrics` feature.
+    pub metrics: bool,
+    /// Also generates destructive helpers such as `truncate_{table}`, meant
+    /// for test setup and teardown.
+    pub generate_destructive: bool,
+    /// Composite types, as named in the catalog, that have a generated
+    /// struct (see `generate_composite_type`). Columns of these types, and
+    /// arrays of them, use that struct instead of `String`.
+    pub composite_types: Vec<String>,
+    /// Enum types, as named in the catalog, that have a generated enum (see
+    /// `generate_enum_type`). Columns of these types, and arrays of them,
+    /// use that enum instead of `String`.
+    pub enum_types: Vec<String>,
+    /// When set, `bytea` columns also get `read_{table}_{column}` and
+    /// `write_{table}_{column}` functions that move the value through
+    /// `blob` this many bytes per query instead of all at once.
+    pub blob_chunk_size: Option<usize>,
+    /// Also generates a `New{Struct}` without the columns the database fills
+    /// in (see `generated_columns`), which `create_*` then takes instead of
+    /// the full entity.
+    pub insert_structs: bool,
+    /// Columns, keyed by table, that the database fills in on insert, e.g.
+    /// serial keys and `DEFAULT now()` timestamps. `reverse_engineer` reads
+    /// them with `metadata::get_generated_columns`.
+    pub generated_columns: HashMap<String, Vec<String>>,
+    pub json_naming: JsonNaming,
+}
+
+impl GeneratorConfig {
+    /// Adds `derives` to every generated struct, e.g. `Clone` and
+    /// `PartialEq` to compare entities in tests or `Hash` to key maps.
+    pub fn with_derives(mut self, derives: &[Derive]) -> Self {
+        self.derives.extend_from_slice(derives);
+        self
+    }
+
+    pub fn with_post_process<F>(mut self, post_process: F) -> Self
+    where
+        F: Fn(&str) -> String + Send + Sync + 'static,
+    {
+        self.post_process = Some(Arc::new(post_process));
+        self
+    }
+
+    /// Generates a `distinct_{table}_{column}` function for each of
+    /// `columns`, e.g. to fill filter dropdowns.
+    pub fn with_distinct_columns(mut self, table: &str, columns: &[&str]) -> Self {
+        self.distinct_columns.entry(table.to_string()).or_default().extend(columns.iter().map(|c| c.to_string()));
+        self
+    }
+
+    /// Generates a `try_from_row` that reports unreadable columns as errors.
+    pub fn with_fallible_rows(mut self) -> Self {
+        self.fallible_rows = true;
+        self
+    }
+
+    /// Keeps external field types fully qualified rather than imported.
+    pub fn with_qualified_type_paths(mut self) -> Self {
+        self.qualified_type_paths = true;
+        self
+    }
+
+    /// Records a call count and latency for every generated CRUD function.
+    pub fn with_metrics(mut self) -> Self {
+        self.metrics = true;
+        self
+    }
+
+    /// Generates `truncate_{table}` alongside the other CRUD functions.
+    pub fn with_destructive_helpers(mut self) -> Self {
+        self.generate_destructive = true;
+        self
+    }
+
+    /// Maps columns of the composite types `names` to their generated structs.
+    pub fn with_composite_types(mut self, names: &[&str]) -> Self {
+        self.composite_types.extend(names.iter().map(|name| name.to_string()));
+        self
+    }
+
+    /// Maps columns of the enum types `names` to their generated enums.
+    pub fn with_enum_types(mut self, names: &[&str]) -> Self {
+        self.enum_types.extend(names.iter().map(|name| name.to_string()));
+        self
+    }
+
+    /// Generates chunked accessors for `bytea` columns, for values too large
+    /// to load whole; values up to `chunk_size` bytes still take one query.
+    pub fn with_blob_streaming(mut self, chunk_size: usize) -> Self {
+        self.blob_chunk_size = Some(chunk_size);
+        self
+    }
+
+    /// Generates `New{Struct}` insert payloads for `create_*`.
+    pub fn with_insert_structs(mut self) -> Self {
+        self.insert_structs = true;
+        self
+    }
+
+    /// Marks `columns` of `table` as filled in by the database on insert.
+    pub fn with_generated_columns(mut self, table: &str, columns: &[&str]) -> Self {
+        self.generated_columns.entry(table.to_string()).or_default().extend(columns.iter().map(|c| c.to_string()));
+        self
+    }
+
+    /// Names struct fields in camelCase when (de)serializing.
+    pub fn with_camel_case_json(mut self) -> Self {
+        self.json_naming = JsonNaming::CamelCase;
+        self
+    }
+
+    /// The serde name of the field for `column`.
+    pub fn json_name(&self, column: &str) -> String {
+        match self.json_naming {
+            JsonNaming::Column => column.to_string(),
+            JsonNaming::CamelCase => column.to_case(Case::Camel),
+        }
+    }
+
+    /// The columns of `table` that `New{Struct}` keeps, or `None` when the
+    /// mode is off. Empty when the database fills in every column, in which
+    /// case the generated inserts use the column defaults only.
+    pub(crate) fn insert_columns<'a>(&self, table: &str, columns: &[&'a str]) -> Option<Vec<&'a str>> {
+        if !self.insert_structs {
+            return None;
+        }
+        let generated = self.generated_columns.get(table);
+        let kept: Vec<&str> = columns.iter().copied().filter(|column| !generated.is_some_and(|generated| generated.iter().any(|name| name == column))).collect();
+        Some(kept)
+    }
+
+    /// Applies `post_process`, if any, to the contents of a generated file.
+    pub fn finish_file(&self, contents: String) -> String {
+        match &self.post_process {
+            Some(post_process) => post_process(&contents),
+            None => contents,
+        }
+    }
+}
+
+impl fmt::Debug for GeneratorConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("GeneratorConfig")
+            .field("derives", &self.derives)
+            .field("crud_style", &self.crud_style)
+            .field("post_process", &self.post_process.as_ref().map(|_| "Fn(&str) -> String"))
+            .field("distinct_columns", &self.distinct_columns)
+            .field("fallible_rows", &self.fallible_rows)
+            .field("qualified_type_paths", &self.qualified_type_paths)
+            .field("metrics", &self.metrics)
+            .field("generate_destructive", &self.generate_destructive)
+            .field("composite_types", &self.composite_types)
+            .field("enum_types", &self.enum_types)
+            .field("blob_chunk_size", &self.blob_chunk_size)
+            .field("insert_structs", &self.insert_structs)
+            .field("generated_columns", &self.generated_columns)
+            .field("json_naming", &self.json_naming)
+            .finish()
+    }
+}
+
+impl Default for GeneratorConfig {
+    fn default() -> Self {
+        GeneratorConfig {
+            derives: Vec::new(),
+            crud_style: CrudStyle::FreeFunctions,
+            post_process: None,
+            distinct_columns: HashMap::new(),
+            fallible_rows: false,
+            qualified_type_paths: false,
+            metrics: false,
+            generate_destructive: false,
+            composite_types: Vec::new(),
+            enum_types: Vec::new(),
+            blob_chunk_size: None,
+            insert_structs: false,
+            generated_columns: HashMap::new(),
+            json_naming: JsonNaming::Column,
+        }
+    }
+}
+
+/// The name of the struct field that holds `column`.
+pub fn field_name(column: &str) -> String {
+    column.replace(' ', "_")
+}
+
 pub fn generate_struct(table_name: &str, columns: HashMap<String, String>, author: &str, github_link: &str, date: NaiveDate) -> String {
+    generate_struct_with_config(table_name, columns, &GeneratorConfig::default(), author, github_link, date)
+}
+
+pub fn generate_struct_with_config(table_name: &str, columns: HashMap<String, String>, config: &GeneratorConfig, author: &str, github_link: &str, date: NaiveDate) -> String {
+    let columns: Vec<ColumnInfo> = columns.iter().map(|(name, data_type)| ColumnInfo::new(name, data_type)).collect();
+    generate_struct_from_columns(table_name, &columns, config, author, github_link, date)
+}
+
+/// Like `generate_struct_with_config`, but takes the full column metadata so
+/// generated columns are documented with their expression.
+pub fn generate_struct_from_columns(table_name: &str, columns: &[ColumnInfo], config: &GeneratorConfig, author: &str, github_link: &str, date: NaiveDate) -> String {
     let header = format!(
         "/*\n * This code was generated by rust_orm_gen.\n * GitHub: {}\n * Date: {}\n * Author: {}\n */\n\n",
         github_link, date.format("%Y-%m-%d"), author
     );
     let struct_name = table_name.to_case(Case::Pascal);
-    let mut struct_def = format!("{}#[derive(Debug, Serialize, Deserialize)]\npub struct {} {{\n", header, struct_name);
 
-    let mut sorted_columns: Vec<_> = columns.into_iter().collect();
-    sorted_columns.sort_by(|a, b| a.0.cmp(&b.0));
+    let mut sorted_columns: Vec<&ColumnInfo> = columns.iter().collect();
+    sorted_columns.sort_by(|a, b| a.name.cmp(&b.name));
+
+    let rust_types: Vec<Cow<'static, str>> = sorted_columns.iter().map(|column| column_type_to_rust(&column.data_type, config)).collect();
+    let mut derives = vec!["Debug".to_string()];
+    let derive_types: Vec<&str> = rust_types.iter().map(|rust_type| rust_type.as_ref()).collect();
+    derives.extend(resolve_derives(config, &derive_types).iter().map(|d| d.to_string()));
+    derives.push("Serialize".to_string());
+    derives.push("Deserialize".to_string());
+
+    // Import the external types the fields use, unless one would shadow the struct itself
+    let mut imports: BTreeSet<&str> = BTreeSet::from(["serde::{Deserialize, Serialize}"]);
+    let mut field_types: Vec<String> = rust_types.iter().map(|rust_type| rust_type.to_string()).collect();
+    if !config.qualified_type_paths {
+        for field_type in field_types.iter_mut() {
+            if let Some((paths, short)) = imported_type(field_type) {
+                if paths.iter().all(|path| !path.ends_with(&format!("::{}", struct_name))) {
+                    imports.extend(paths.iter().copied());
+                    *field_type = short.to_string();
+                }
+            }
+        }
+    }
+    let uses: String = imports.iter().map(|path| format!("use {};\n", path)).collect();
+    let mut struct_def = format!("{}{}\n#[derive({})]\npub struct {} {{\n", header, uses, derives.join(", "), struct_name);
 
-    for (col_name, data_type) in sorted_columns {
-        let rust_field_name = col_name.replace(" ", "_");
-        let rust_type = map_data_type(&data_type);
+    for (index, column) in sorted_columns.iter().enumerate() {
+        let rust_type = &field_types[index];
+        if let Some(expression) = &column.generation_expression {
+            struct_def.push_str(&format!("    /// Computed by the database: GENERATED ALWAYS AS ({}) STORED\n", expression));
+        }
+        let field_type = if column.is_nullable { format!("Option<{}>", rust_type) } else { rust_type.to_string() };
         struct_def.push_str(&format!(
             "    #[serde(rename = \"{}\")] pub {}: {},\n",
-            col_name, rust_field_name, rust_type
+            config.json_name(&column.name), field_name(&column.name), field_type
         ));
     }
 
     struct_def.push_str("}\n");
+
+    // Insert payload without the columns the database fills in
+    let column_names: Vec<&str> = sorted_columns.iter().map(|column| column.name.as_str()).collect();
+    if let Some(insert_columns) = config.insert_columns(table_name, &column_names) {
+        struct_def.push_str(&format!(
+            "\n/// The columns of `{struct_name}` to insert; the database fills in the rest.\n#[derive({})]\npub struct New{struct_name} {{\n",
+            derives.join(", ")
+        ));
+        for (index, column) in sorted_columns.iter().enumerate().filter(|(_, column)| insert_columns.contains(&column.name.as_str())) {
+            let field_type = if column.is_nullable { format!("Option<{}>", field_types[index]) } else { field_types[index].clone() };
+            struct_def.push_str(&format!("    #[serde(rename = \"{}\")] pub {}: {},\n", config.json_name(&column.name), field_name(&column.name), field_type));
+        }
+        struct_def.push_str("}\n");
+    }
+
+    // Column name constants for the query builder's string-based APIs
+    struct_def.push_str(&format!(
+        "\npub const COLUMNS: &[&str] = &[{}];\n",
+        column_names.iter().map(|name| format!("\"{}\"", name)).collect::<Vec<_>>().join(", ")
+    ));
+    for name in &column_names {
+        struct_def.push_str(&format!("pub const COL_{}: &str = \"{}\";\n", name.replace(" ", "_").to_uppercase(), name));
+    }
+
+    // Lets the query builder map rows itself through fetch_all/fetch_one
+    let fields = sorted_columns.iter()
+        .map(|column| row_field(table_name, column, config))
+        .collect::<Vec<_>>()
+        .join("\n            ");
+    let row_fns = if config.fallible_rows {
+        format!(
+            "    fn from_row(row: &tokio_postgres::Row) -> Self {{
+        Self::try_from_row(row).unwrap_or_else(|e| panic!(\"{{}}\", e))
+    }}
+
+    fn try_from_row(row: &tokio_postgres::Row) -> Result<Self, crate::error::OrmError> {{
+        Ok({struct_name} {{
+            {fields}
+        }})
+    }}"
+        )
+    } else {
+        format!(
+            "    fn from_row(row: &tokio_postgres::Row) -> Self {{
+        {struct_name} {{
+            {fields}
+        }}
+    }}"
+        )
+    };
+    struct_def.push_str(&format!(
+        "\nimpl crate::query_builder::Model for {struct_name} {{
+    fn table_name() -> &'static str {{
+        \"{table_name}\"
+    }}
+
+    fn columns() -> &'static [&'static str] {{
+        COLUMNS
+    }}
+
+{row_fns}
+}}\n"
+    ));
     struct_def
 }
 
-fn map_data_type(data_type: &str) -> &str {
-    match data_type {
+/// Renders `field: row.get("column"),` for `from_row`, passing text columns
+/// through their registered `transforms::from_db`. With `fallible_rows` the
+/// value is read with `query_builder::try_get` and `?` instead. Nullable
+/// columns are read as `Option`, so NULL becomes `None` rather than a panic.
+fn row_field(table_name: &str, column: &ColumnInfo, config: &GeneratorConfig) -> String {
+    let name = &column.name;
+    let field = field_name(name);
+    let is_text = map_column_type(&column.data_type, config) == "String";
+    // Nullable text is read as Option<&str> for from_db, which needs the type spelled out
+    let get = match (config.fallible_rows, is_text && column.is_nullable) {
+        (true, true) => format!("crate::query_builder::try_get::<Option<&str>>(row, \"{name}\")?"),
+        (true, false) => format!("crate::query_builder::try_get(row, \"{name}\")?"),
+        (false, true) => format!("row.get::<_, Option<&str>>(\"{name}\")"),
+        (false, false) => format!("row.get(\"{name}\")"),
+    };
+    match (is_text, column.is_nullable) {
+        (true, true) => format!("{field}: {get}.map(|value| crate::transforms::from_db(\"{table_name}\", \"{name}\", value)),"),
+        (true, false) => format!("{field}: crate::transforms::from_db(\"{table_name}\", \"{name}\", {get}),"),
+        (false, _) => format!("{field}: {get},"),
+    }
+}
+
+/// Generates `{Struct}WithRelations`, which wraps an entity and adds one
+/// method per foreign key (e.g. `post.author(client)` for `author_id`) that
+/// loads the referenced row on first call and caches it in a `LazyLoaded`.
+/// A nullable key's method returns an `Option`. Keys referencing the same
+/// table get methods named after their columns, e.g. `users_by_editor`, as
+/// does a key whose method would be named `entity` like the wrapped field.
+/// Returns an empty string if `foreign_keys` is empty.
+pub fn generate_relationship_methods(table_name: &str, columns: &[ColumnInfo], foreign_keys: &[TableDependency]) -> String {
+    if foreign_keys.is_empty() {
+        return String::new();
+    }
+    let struct_name = table_name.to_case(Case::Pascal);
+    let wrapper = format!("{struct_name}WithRelations");
+    let mut relations: Vec<(String, String, &TableDependency)> = foreign_keys.iter()
+        .map(|fk| {
+            let method = match fk.column.strip_suffix("_id") {
+                Some(name) if !name.is_empty() => name.replace(" ", "_"),
+                _ => fk.referenced_table.to_case(Case::Snake),
+            };
+            (method, fk.referenced_table.to_case(Case::Pascal), fk)
+        })
+        .collect();
+    // Two keys referencing the same table would otherwise share a method, and
+    // the cache field of an `entity` method would clash with `pub entity`
+    let methods: Vec<String> = relations.iter().map(|(method, _, _)| method.clone()).collect();
+    for (method, _, fk) in &mut relations {
+        if method == "entity" || methods.iter().filter(|other| *other == method).count() > 1 {
+            *method = format!("{}_by_{}", fk.referenced_table.to_case(Case::Snake), field_name(&fk.column));
+        }
+    }
+    // A nullable key may reference no row, so its relation is optional
+    let is_nullable = |fk: &TableDependency| columns.iter().any(|c| c.name == fk.column && c.is_nullable);
+
+    let mut code = format!(
+        "\n/// `{struct_name}` plus the rows its foreign keys reference, each loaded on\n/// first access and then cached.\npub struct {wrapper} {{\n    pub entity: {struct_name},\n"
+    );
+    for (method, referenced, fk) in &relations {
+        let loaded = if is_nullable(fk) { format!("Option<std::sync::Arc<{referenced}>>") } else { format!("std::sync::Arc<{referenced}>") };
+        code.push_str(&format!("    {method}: crate::lazy_loading::LazyLoaded<{loaded}>,\n"));
+    }
+    code.push_str(&format!(
+        "}}\n\nimpl {struct_name} {{\n    pub fn with_relations(self) -> {wrapper} {{\n        {wrapper} {{\n            entity: self,\n"
+    ));
+    for (method, _, _) in &relations {
+        code.push_str(&format!("            {method}: crate::lazy_loading::LazyLoaded::empty(),\n"));
+    }
+    code.push_str(&format!(
+        "        }}\n    }}\n}}\n\nimpl std::ops::Deref for {wrapper} {{\n    type Target = {struct_name};\n\n    fn deref(&self) -> &{struct_name} {{\n        &self.entity\n    }}\n}}\n\nimpl {wrapper} {{"
+    ));
+    for (method, referenced, fk) in &relations {
+        let field = field_name(&fk.column);
+        let rust_type = columns.iter().find(|c| c.name == fk.column).map_or("String", |c| map_data_type(&c.data_type));
+        let value = if is_copy_type(rust_type) { format!("self.entity.{field}") } else { format!("self.entity.{field}.clone()") };
+        // Comparing with a NULL key matches no row, so `fetch_optional` gives `None`
+        let (table, column) = (&fk.referenced_table, &fk.column);
+        let (loaded, fetch, wrap, doc) = if is_nullable(fk) {
+            (
+                format!("Option<std::sync::Arc<{referenced}>>"), "fetch_optional", "|row| row.map(std::sync::Arc::new)",
+                format!("The `{table}` row referenced by `{column}`, or `None` if `{column}` is NULL, loaded\n    /// on the first call. It is shared rather than cloned, so `{referenced}` need not\n    /// derive `Clone`."),
+            )
+        } else {
+            (
+                format!("std::sync::Arc<{referenced}>"), "fetch_one", "std::sync::Arc::new",
+                format!("The `{table}` row referenced by `{column}`, loaded on the first call. It is\n    /// shared rather than cloned, so `{referenced}` need not derive `Clone`."),
+            )
+        };
+        code.push_str(&format!(
+            "
+    /// {doc}
+    pub async fn {method}(&self, client: &tokio_postgres::Client) -> Result<{loaded}, crate::error::OrmError> {{
+        self.{method}.get_or_try_load(|| async {{
+            crate::query_builder::QueryBuilder::select::<{referenced}>()
+                .where_eq(\"{}\", {value})
+                .{fetch}(client)
+                .await
+                .map({wrap})
+        }}).await
+    }}\n",
+            fk.referenced_column
+        ));
+    }
+    code.push_str("}\n");
+    code
+}
+
+pub(crate) fn is_copy_type(rust_type: &str) -> bool {
+    matches!(rust_type, "i16" | "i32" | "i64" | "bool" | "f32" | "f64" | "uuid::Uuid" | "rust_decimal::Decimal") || rust_type.starts_with("chrono::")
+}
+
+/// Filters `config.derives` down to the ones every field type supports,
+/// then drops derives whose supertraits did not survive (`Eq` needs
+/// `PartialEq`, `Ord` needs `Eq` and `PartialOrd`).
+fn resolve_derives(config: &GeneratorConfig, rust_types: &[&str]) -> Vec<Derive> {
+    let mut derives: Vec<Derive> = Vec::new();
+    for derive in &config.derives {
+        if !derives.contains(derive) && rust_types.iter().all(|t| type_supports_derive(t, *derive, config)) {
+            derives.push(*derive);
+        }
+    }
+    if !derives.contains(&Derive::PartialEq) {
+        derives.retain(|d| *d != Derive::Eq);
+    }
+    if !derives.contains(&Derive::Eq) || !derives.contains(&Derive::PartialOrd) {
+        derives.retain(|d| *d != Derive::Ord);
+    }
+    derives
+}
+
+fn type_supports_derive(rust_type: &str, derive: Derive, config: &GeneratorConfig) -> bool {
+    if let Some(element) = rust_type.strip_prefix("Vec<").and_then(|rest| rest.strip_suffix('>')) {
+        return type_supports_derive(element, derive, config);
+    }
+    // Generated enums derive everything; composite structs only Clone and PartialEq
+    if config.enum_types.iter().any(|name| name.to_case(Case::Pascal) == rust_type) {
+        return true;
+    }
+    let is_composite = rust_type.starts_with(|c: char| c.is_ascii_uppercase()) && rust_type != "String" && !rust_type.contains("::");
+    if is_composite {
+        return matches!(derive, Derive::Clone | Derive::PartialEq);
+    }
+    match rust_type {
+        "f32" | "f64" => !matches!(derive, Derive::Eq | Derive::Hash | Derive::Ord),
+        "serde_json::Value" => !matches!(derive, Derive::Hash | Derive::PartialOrd | Derive::Ord),
+        "crate::types::TimeTz" => !matches!(derive, Derive::PartialOrd | Derive::Ord),
+        _ => true,
+    }
+}
+
+/// The Rust type generated for a Postgres `data_type` as reported by
+/// `information_schema.columns`. Unknown types fall back to `String`, with a
+/// warning, since the text representation can always be read.
+pub fn pg_type_to_rust(data_type: &str) -> &'static str {
+    known_rust_type(data_type).unwrap_or_else(|| {
+        warn!("No Rust type mapping for Postgres type '{}', generating String", data_type);
+        "String"
+    })
+}
+
+/// Like `pg_type_to_rust`, without the warning, for code that looks up the
+/// type of a column whose struct field has already been generated.
+pub(crate) fn map_data_type(data_type: &str) -> &'static str {
+    known_rust_type(data_type).unwrap_or("String")
+}
+
+/// Like `pg_type_to_rust`, but also maps arrays (`integer[]` becomes
+/// `Vec<i32>`) and the composite types in `config.composite_types`, which
+/// become their generated struct, e.g. `address[]` becomes `Vec<Address>`.
+pub fn column_type_to_rust(data_type: &str, config: &GeneratorConfig) -> Cow<'static, str> {
+    column_type(data_type, config, pg_type_to_rust)
+}
+
+/// Like `column_type_to_rust`, without the warning for unknown types.
+pub(crate) fn map_column_type(data_type: &str, config: &GeneratorConfig) -> Cow<'static, str> {
+    column_type(data_type, config, map_data_type)
+}
+
+fn column_type(data_type: &str, config: &GeneratorConfig, scalar_type: fn(&str) -> &'static str) -> Cow<'static, str> {
+    if let Some(element) = data_type.strip_suffix("[]") {
+        return Cow::Owned(format!("Vec<{}>", column_type(element, config, scalar_type)));
+    }
+    if config.composite_types.iter().chain(&config.enum_types).any(|name| name == data_type) {
+        return Cow::Owned(data_type.to_case(Case::Pascal));
+    }
+    Cow::Borrowed(scalar_type(data_type))
+}
+
+/// Generates the struct for a composite type, readable and writable as a
+/// column value through the `postgres-types` derives. Generated code using
+/// it needs `postgres-types` with the `derive` feature. Fields may refer to
+/// other composite types in `config.composite_types`.
+pub fn generate_composite_type(composite: &CompositeType, config: &GeneratorConfig) -> String {
+    let struct_name = composite.name.to_case(Case::Pascal);
+    let fields: String = composite.fields.iter().map(|field| {
+        let rust_name = field_name(&field.name);
+        let mut rename = if rust_name == field.name { String::new() } else { format!("#[postgres(name = \"{}\")] ", field.name) };
+        let json_name = config.json_name(&field.name);
+        if json_name != rust_name {
+            rename.push_str(&format!("#[serde(rename = \"{json_name}\")] "));
+        }
+        format!("    {rename}pub {rust_name}: Option<{}>,\n", column_type_to_rust(&field.data_type, config))
+    }).collect();
+    format!(
+        "#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize, postgres_types::ToSql, postgres_types::FromSql)]\n#[postgres(name = \"{}\")]\npub struct {struct_name} {{\n{fields}}}\n",
+        composite.name
+    )
+}
+
+/// Generates the Rust enum for an enum type, with one variant per label in
+/// the same order, so the derived `Ord` sorts like Postgres does. Values
+/// are read and written through the `postgres-types` derives, like
+/// `generate_composite_type`, and serialize to JSON as their label.
+pub fn generate_enum_type(enum_type: &EnumType) -> String {
+    let enum_name = enum_type.name.to_case(Case::Pascal);
+    let variants: String = enum_type.labels.iter().map(|label| {
+        format!("    #[postgres(name = \"{label}\")]\n    #[serde(rename = \"{label}\")]\n    {},\n", variant_name(label))
+    }).collect();
+    format!(
+        "#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, serde::Serialize, serde::Deserialize, postgres_types::ToSql, postgres_types::FromSql)]\n#[postgres(name = \"{}\")]\npub enum {enum_name} {{\n{variants}}}\n",
+        enum_type.name
+    )
+}
+
+/// A variant name for an enum label or column, e.g. `InProgress` for `in
+/// progress`. Labels are free text, so anything that is not alphanumeric is
+/// dropped and a leading digit gets a `V` prefix.
+pub(crate) fn variant_name(label: &str) -> String {
+    let name: String = label.to_case(Case::Pascal).chars().filter(|c| c.is_ascii_alphanumeric()).collect();
+    match name.chars().next() {
+        Some(first) if first.is_ascii_alphabetic() => name,
+        _ => format!("V{}", name),
+    }
+}
+
+/// The `use` paths and the short name for a type from one of the crates
+/// generated code depends on, e.g. `chrono::NaiveDate` becomes `NaiveDate`.
+fn imported_type(rust_type: &str) -> Option<(&'static [&'static str], &'static str)> {
+    Some(match rust_type {
+        "chrono::NaiveDate" => (&["chrono::NaiveDate"], "NaiveDate"),
+        "chrono::NaiveDateTime" => (&["chrono::NaiveDateTime"], "NaiveDateTime"),
+        "chrono::NaiveTime" => (&["chrono::NaiveTime"], "NaiveTime"),
+        "chrono::DateTime<chrono::Utc>" => (&["chrono::DateTime", "chrono::Utc"], "DateTime<Utc>"),
+        "rust_decimal::Decimal" => (&["rust_decimal::Decimal"], "Decimal"),
+        "uuid::Uuid" => (&["uuid::Uuid"], "Uuid"),
+        "serde_json::Value" => (&["serde_json::Value"], "Value"),
+        _ => return None,
+    })
+}
+
+fn known_rust_type(data_type: &str) -> Option<&'static str> {
+    Some(match data_type {
         "integer" | "serial" => "i32",
         "bigint" | "bigserial" => "i64",
         "smallint" => "i16",
         "boolean" => "bool",
-        "text" | "varchar" | "char" => "String",
+        "text" | "varchar" | "character varying" | "char" | "character" => "String",
         "date" => "chrono::NaiveDate",
-        "timestamp" => "chrono::NaiveDateTime",
-        "timestamptz" | "timetz" => "chrono::DateTime<chrono::Utc>",
-        "time" => "chrono::NaiveTime",
-        "float4" => "f32",
-        "float8" => "f64",
-        "numeric" => "bigdecimal::BigDecimal",
+        "timestamp" | "timestamp without time zone" => "chrono::NaiveDateTime",
+        "timestamptz" | "timestamp with time zone" => "chrono::DateTime<chrono::Utc>",
+        "time" | "time without time zone" => "chrono::NaiveTime",
+        // chrono has no time-of-day type that keeps an offset
+        "timetz" | "time with time zone" => "crate::types::TimeTz",
+        "float4" | "real" => "f32",
+        "float8" | "double precision" => "f64",
+        "numeric" => "rust_decimal::Decimal",
+        "money" => "crate::types::PgMoney",
         "uuid" => "uuid::Uuid",
         "json" | "jsonb" => "serde_json::Value",
         "bytea" => "Vec<u8>",
-        _ => "String", // Default fallback
-    }
+        _ => return None,
+    })
 }
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -102,4 +718,332 @@ mod tests {
         assert!(result.contains("pub name: String,"), "Type conversion for 'name' is incorrect or missing");
         assert!(result.contains("pub zip_code: String,"), "Type conversion for 'zip code' is incorrect or missing");
     }
+
+    #[test]
+    fn test_generate_struct_model_impl() {
+        let mut columns = HashMap::new();
+        columns.insert("id".to_string(), "integer".to_string());
+        columns.insert("zip code".to_string(), "text".to_string());
+
+        let date = NaiveDate::from_ymd_opt(2024, 7, 24).unwrap();
+        let result = generate_struct("users", columns, "Tom Blanchard", "https://github.com/tomblanchard312/rust_orm_gen", date);
+
+        assert!(result.contains("impl crate::query_builder::Model for Users {"));
+        assert!(result.contains("    fn table_name() -> &'static str {\n        \"users\"\n    }"));
+        assert!(result.contains("    fn columns() -> &'static [&'static str] {\n        COLUMNS\n    }"));
+        assert!(result.contains("    fn from_row(row: &tokio_postgres::Row) -> Self {\n        Users {\n            id: row.get(\"id\"),\n            zip_code: crate::transforms::from_db(\"users\", \"zip code\", row.get(\"zip code\")),\n        }\n    }"));
+    }
+
+    #[test]
+    fn test_generate_struct_fallible_rows() {
+        let columns = vec![ColumnInfo::new("id", "integer"), ColumnInfo::new("zip code", "text")];
+        let config = GeneratorConfig::default().with_fallible_rows();
+        let date = NaiveDate::from_ymd_opt(2024, 7, 24).unwrap();
+        let result = generate_struct_from_columns("users", &columns, &config, "Tom Blanchard", "https://github.com/tomblanchard312/rust_orm_gen", date);
+
+        assert!(result.contains("    fn from_row(row: &tokio_postgres::Row) -> Self {\n        Self::try_from_row(row).unwrap_or_else(|e| panic!(\"{}\", e))\n    }"));
+        assert!(result.contains("    fn try_from_row(row: &tokio_postgres::Row) -> Result<Self, crate::error::OrmError> {\n        Ok(Users {\n            id: crate::query_builder::try_get(row, \"id\")?,\n            zip_code: crate::transforms::from_db(\"users\", \"zip code\", crate::query_builder::try_get(row, \"zip code\")?),\n        })\n    }"));
+        assert!(!result.contains("row.get("));
+    }
+
+    #[test]
+    fn test_generate_struct_nullable_columns() {
+        let columns = vec![
+            ColumnInfo::new("id", "integer"),
+            ColumnInfo::new("content", "text").nullable(),
+            ColumnInfo::new("rating", "smallint").nullable(),
+        ];
+        let date = NaiveDate::from_ymd_opt(2024, 7, 24).unwrap();
+        let result = generate_struct_from_columns("posts", &columns, &GeneratorConfig::default(), "Tom Blanchard", "https://github.com/tomblanchard312/rust_orm_gen", date);
+
+        assert!(result.contains("pub content: Option<String>,"));
+        assert!(result.contains("pub rating: Option<i16>,"));
+        assert!(result.contains("pub id: i32,"));
+        assert!(result.contains("            content: row.get::<_, Option<&str>>(\"content\").map(|value| crate::transforms::from_db(\"posts\", \"content\", value)),\n"));
+        assert!(result.contains("            rating: row.get(\"rating\"),\n"));
+
+        let fallible = generate_struct_from_columns("posts", &columns, &GeneratorConfig::default().with_fallible_rows(), "Tom Blanchard", "https://github.com/tomblanchard312/rust_orm_gen", date);
+        assert!(fallible.contains("            content: crate::query_builder::try_get::<Option<&str>>(row, \"content\")?.map(|value| crate::transforms::from_db(\"posts\", \"content\", value)),\n"));
+        assert!(fallible.contains("            rating: crate::query_builder::try_get(row, \"rating\")?,\n"));
+    }
+
+    #[test]
+    fn test_generate_relationship_methods() {
+        let columns = vec![
+            ColumnInfo::new("id", "integer"),
+            ColumnInfo::new("author_id", "integer"),
+            ColumnInfo::new("editor", "text"),
+            ColumnInfo::new("reviewer", "text").nullable(),
+        ];
+        let foreign_keys = vec![
+            TableDependency::new("posts", "author_id", "users", "id"),
+            TableDependency::new("posts", "editor", "staff_members", "login"),
+            TableDependency::new("posts", "reviewer", "staff_members", "login"),
+        ];
+        let result = generate_relationship_methods("posts", &columns, &foreign_keys);
+
+        assert!(result.contains("pub struct PostsWithRelations {\n    pub entity: Posts,\n    author: crate::lazy_loading::LazyLoaded<std::sync::Arc<Users>>,\n    staff_members_by_editor: crate::lazy_loading::LazyLoaded<std::sync::Arc<StaffMembers>>,\n    staff_members_by_reviewer: crate::lazy_loading::LazyLoaded<Option<std::sync::Arc<StaffMembers>>>,\n}"));
+        assert!(result.contains("    pub fn with_relations(self) -> PostsWithRelations {"));
+        assert!(result.contains("impl std::ops::Deref for PostsWithRelations {\n    type Target = Posts;"));
+        assert!(result.contains("    pub async fn author(&self, client: &tokio_postgres::Client) -> Result<std::sync::Arc<Users>, crate::error::OrmError> {\n        self.author.get_or_try_load(|| async {\n            crate::query_builder::QueryBuilder::select::<Users>()\n                .where_eq(\"id\", self.entity.author_id)\n                .fetch_one(client)\n                .await\n                .map(std::sync::Arc::new)\n        }).await\n    }"));
+        assert!(result.contains(".where_eq(\"login\", self.entity.editor.clone())"));
+        assert!(result.contains("    pub async fn staff_members_by_editor(&self, client: &tokio_postgres::Client) -> Result<std::sync::Arc<StaffMembers>, crate::error::OrmError> {"));
+        assert!(result.contains("    pub async fn staff_members_by_reviewer(&self, client: &tokio_postgres::Client) -> Result<Option<std::sync::Arc<StaffMembers>>, crate::error::OrmError> {\n        self.staff_members_by_reviewer.get_or_try_load(|| async {\n            crate::query_builder::QueryBuilder::select::<StaffMembers>()\n                .where_eq(\"login\", self.entity.reviewer.clone())\n                .fetch_optional(client)\n                .await\n                .map(|row| row.map(std::sync::Arc::new))\n        }).await\n    }"));
+        assert_eq!(generate_relationship_methods("posts", &columns, &[]), "");
+    }
+
+    #[test]
+    fn test_relationship_method_does_not_clash_with_entity_field() {
+        let columns = vec![ColumnInfo::new("id", "integer"), ColumnInfo::new("entity_id", "integer")];
+        let foreign_keys = vec![TableDependency::new("audit_logs", "entity_id", "entities", "id")];
+        let result = generate_relationship_methods("audit_logs", &columns, &foreign_keys);
+
+        assert!(result.contains("pub struct AuditLogsWithRelations {\n    pub entity: AuditLogs,\n    entities_by_entity_id: crate::lazy_loading::LazyLoaded<std::sync::Arc<Entities>>,\n}"));
+        assert!(result.contains("    pub async fn entities_by_entity_id(&self, client: &tokio_postgres::Client) -> Result<std::sync::Arc<Entities>, crate::error::OrmError> {\n        self.entities_by_entity_id.get_or_try_load(|| async {"));
+        assert!(result.contains(".where_eq(\"id\", self.entity.entity_id)"));
+        assert!(!result.contains("fn entity("));
+    }
+
+    #[test]
+    fn test_generate_struct_column_constants() {
+        let mut columns = HashMap::new();
+        columns.insert("customer_id".to_string(), "integer".to_string());
+        columns.insert("email".to_string(), "text".to_string());
+        columns.insert("zip code".to_string(), "text".to_string());
+
+        let date = NaiveDate::from_ymd_opt(2024, 7, 24).unwrap();
+        let result = generate_struct("customer", columns, "Tom Blanchard", "https://github.com/tomblanchard312/rust_orm_gen", date);
+
+        assert!(result.contains("pub const COLUMNS: &[&str] = &[\"customer_id\", \"email\", \"zip code\"];\n"));
+        assert!(result.contains("pub const COL_CUSTOMER_ID: &str = \"customer_id\";\n"));
+        assert!(result.contains("pub const COL_EMAIL: &str = \"email\";\n"));
+        assert!(result.contains("pub const COL_ZIP_CODE: &str = \"zip code\";\n"));
+        assert_eq!(result.matches("pub const COL_").count(), 3);
+    }
+
+    #[test]
+    fn test_map_temporal_types() {
+        assert_eq!(map_data_type("date"), "chrono::NaiveDate");
+        assert_eq!(map_data_type("time without time zone"), "chrono::NaiveTime");
+        assert_eq!(map_data_type("time"), "chrono::NaiveTime");
+        assert_eq!(map_data_type("time with time zone"), "crate::types::TimeTz");
+        assert_eq!(map_data_type("timetz"), "crate::types::TimeTz");
+        assert_eq!(map_data_type("timestamp without time zone"), "chrono::NaiveDateTime");
+        assert_eq!(map_data_type("timestamp"), "chrono::NaiveDateTime");
+        assert_eq!(map_data_type("timestamp with time zone"), "chrono::DateTime<chrono::Utc>");
+        assert_eq!(map_data_type("timestamptz"), "chrono::DateTime<chrono::Utc>");
+    }
+
+    #[test]
+    fn test_pg_type_to_rust() {
+        let mappings = [
+            ("integer", "i32"),
+            ("bigint", "i64"),
+            ("smallint", "i16"),
+            ("text", "String"),
+            ("varchar", "String"),
+            ("character varying", "String"),
+            ("boolean", "bool"),
+            ("timestamp", "chrono::NaiveDateTime"),
+            ("timestamp without time zone", "chrono::NaiveDateTime"),
+            ("date", "chrono::NaiveDate"),
+            ("numeric", "rust_decimal::Decimal"),
+            ("uuid", "uuid::Uuid"),
+            ("jsonb", "serde_json::Value"),
+            ("bytea", "Vec<u8>"),
+            ("double precision", "f64"),
+        ];
+        for (data_type, rust_type) in mappings {
+            assert_eq!(pg_type_to_rust(data_type), rust_type, "Wrong mapping for {}", data_type);
+        }
+        assert_eq!(pg_type_to_rust("tsvector"), "String");
+        assert_eq!(map_data_type("tsvector"), "String");
+    }
+
+    #[test]
+    fn test_generate_struct_imports_external_types() {
+        let mut columns = HashMap::new();
+        columns.insert("id".to_string(), "uuid".to_string());
+        columns.insert("born".to_string(), "date".to_string());
+        columns.insert("updated_at".to_string(), "timestamp with time zone".to_string());
+
+        let date = NaiveDate::from_ymd_opt(2024, 7, 24).unwrap();
+        let result = generate_struct("authors", columns.clone(), "Tom Blanchard", "https://github.com/tomblanchard312/rust_orm_gen", date);
+
+        assert!(result.contains("use chrono::DateTime;\nuse chrono::NaiveDate;\nuse chrono::Utc;\nuse serde::{Deserialize, Serialize};\nuse uuid::Uuid;\n\n#[derive("));
+        assert!(result.contains("pub born: NaiveDate,"));
+        assert!(result.contains("pub id: Uuid,"));
+        assert!(result.contains("pub updated_at: DateTime<Utc>,"));
+
+        // A struct named like an imported type keeps that type qualified
+        let result = generate_struct("uuid", columns.clone(), "Tom Blanchard", "https://github.com/tomblanchard312/rust_orm_gen", date);
+        assert!(!result.contains("use uuid::Uuid;"));
+        assert!(result.contains("pub id: uuid::Uuid,"));
+        assert!(result.contains("pub born: NaiveDate,"));
+
+        let config = GeneratorConfig::default().with_qualified_type_paths();
+        let result = generate_struct_with_config("authors", columns, &config, "Tom Blanchard", "https://github.com/tomblanchard312/rust_orm_gen", date);
+        assert!(!result.contains("use chrono"));
+        assert!(result.contains("use serde::{Deserialize, Serialize};"));
+        assert!(result.contains("pub born: chrono::NaiveDate,"));
+    }
+
+    #[test]
+    fn test_generate_insert_struct_omits_generated_columns() {
+        let columns = vec![
+            ColumnInfo::new("id", "integer"),
+            ColumnInfo::new("name", "text"),
+            ColumnInfo::new("email", "text").nullable(),
+            ColumnInfo::new("created_at", "timestamp with time zone"),
+        ];
+        let config = GeneratorConfig::default().with_insert_structs().with_generated_columns("customer", &["id", "created_at"]);
+
+        let date = NaiveDate::from_ymd_opt(2024, 7, 24).unwrap();
+        let result = generate_struct_from_columns("customer", &columns, &config, "Tom Blanchard", "https://github.com/tomblanchard312/rust_orm_gen", date);
+
+        let (entity, insert) = result.split_once("pub struct NewCustomer {").expect("NewCustomer was not generated");
+        let insert = &insert[..insert.find('}').unwrap()];
+        assert!(entity.contains("pub struct Customer {"));
+        assert!(entity.contains("pub id: i32,"));
+        assert!(entity.contains("pub created_at: DateTime<Utc>,"));
+        assert!(insert.contains("#[serde(rename = \"email\")] pub email: Option<String>,"));
+        assert!(insert.contains("#[serde(rename = \"name\")] pub name: String,"));
+        assert!(!insert.contains("pub id"));
+        assert!(!insert.contains("pub created_at"));
+
+        // Off by default, and empty when the database fills in every column
+        let plain = generate_struct_from_columns("customer", &columns, &GeneratorConfig::default(), "Tom Blanchard", "https://github.com/tomblanchard312/rust_orm_gen", date);
+        assert!(!plain.contains("NewCustomer"));
+        let all_generated = config.with_generated_columns("customer", &["name", "email"]);
+        let result = generate_struct_from_columns("customer", &columns, &all_generated, "Tom Blanchard", "https://github.com/tomblanchard312/rust_orm_gen", date);
+        assert!(result.contains("pub struct NewCustomer {\n}\n"));
+    }
+
+    #[test]
+    fn test_camel_case_json_keeps_field_and_column_names() {
+        let columns = vec![
+            ColumnInfo::new("id", "integer"),
+            ColumnInfo::new("zip code", "text"),
+            ColumnInfo::new("created_at", "date"),
+        ];
+        let config = GeneratorConfig::default().with_camel_case_json().with_insert_structs().with_generated_columns("address", &["id"]);
+
+        let date = NaiveDate::from_ymd_opt(2024, 7, 24).unwrap();
+        let result = generate_struct_from_columns("address", &columns, &config, "Tom Blanchard", "https://github.com/tomblanchard312/rust_orm_gen", date);
+
+        // JSON name in camelCase, Rust field in snake_case, column as is
+        assert!(result.contains("#[serde(rename = \"zipCode\")] pub zip_code: String,"));
+        assert!(result.contains("#[serde(rename = \"createdAt\")] pub created_at: NaiveDate,"));
+        assert!(result.contains("zip_code: crate::transforms::from_db(\"address\", \"zip code\", row.get(\"zip code\")),"));
+        assert!(result.contains("pub const COL_ZIP_CODE: &str = \"zip code\";"));
+        let (_, insert) = result.split_once("pub struct NewAddress {").expect("NewAddress was not generated");
+        assert!(insert.contains("#[serde(rename = \"zipCode\")] pub zip_code: String,"));
+
+        let mut crud_columns = HashMap::new();
+        crud_columns.insert("id".to_string(), "integer".to_string());
+        crud_columns.insert("zip code".to_string(), "text".to_string());
+        let crud = crate::crud::generate_crud_operations_with_config("address", crud_columns, &config, "Tom Blanchard", "https://github.com/tomblanchard312/rust_orm_gen", date).unwrap();
+        assert!(crud.contains("(\"zip code\", &entity.zip_code)"));
+
+        // The column name is kept by default
+        let plain = generate_struct_from_columns("address", &columns, &GeneratorConfig::default(), "Tom Blanchard", "https://github.com/tomblanchard312/rust_orm_gen", date);
+        assert!(plain.contains("#[serde(rename = \"zip code\")] pub zip_code: String,"));
+        assert!(plain.contains("#[serde(rename = \"created_at\")] pub created_at: NaiveDate,"));
+    }
+
+    #[test]
+    fn test_generate_struct_maps_composite_arrays() {
+        let columns = vec![
+            ColumnInfo::new("id", "integer"),
+            ColumnInfo::new("addresses", "address[]"),
+            ColumnInfo::new("home", "address").nullable(),
+            ColumnInfo::new("tags", "text[]"),
+        ];
+        let config = GeneratorConfig::default().with_composite_types(&["address"]);
+
+        let date = NaiveDate::from_ymd_opt(2024, 7, 24).unwrap();
+        let result = generate_struct_from_columns("customers", &columns, &config, "Tom Blanchard", "https://github.com/tomblanchard312/rust_orm_gen", date);
+
+        assert!(result.contains("pub addresses: Vec<Address>,"));
+        assert!(result.contains("pub home: Option<Address>,"));
+        assert!(result.contains("pub tags: Vec<String>,"));
+        assert!(result.contains("tags: row.get(\"tags\"),"));
+        assert!(!result.contains("Hash"));
+
+        let composite = CompositeType {
+            name: "address".to_string(),
+            fields: vec![ColumnInfo::new("street", "text").nullable(), ColumnInfo::new("previous", "address[]").nullable()],
+        };
+        let result = generate_composite_type(&composite, &config);
+        assert!(result.contains("postgres_types::ToSql, postgres_types::FromSql)]\n#[postgres(name = \"address\")]\npub struct Address {"));
+        assert!(result.contains("pub street: Option<String>,"));
+        assert!(result.contains("pub previous: Option<Vec<Address>>,"));
+    }
+
+    #[test]
+    fn test_generate_enum_type_and_enum_array_fields() {
+        let columns = vec![
+            ColumnInfo::new("id", "integer"),
+            ColumnInfo::new("moods", "mood[]"),
+            ColumnInfo::new("current", "mood").nullable(),
+        ];
+        let config = GeneratorConfig::default()
+            .with_enum_types(&["mood"])
+            .with_derives(&[Derive::Clone, Derive::PartialEq, Derive::Eq, Derive::Hash]);
+
+        let date = NaiveDate::from_ymd_opt(2024, 7, 24).unwrap();
+        let result = generate_struct_from_columns("diaries", &columns, &config, "Tom Blanchard", "https://github.com/tomblanchard312/rust_orm_gen", date);
+
+        assert!(result.contains("pub moods: Vec<Mood>,"));
+        assert!(result.contains("pub current: Option<Mood>,"));
+        assert!(result.contains("moods: row.get(\"moods\"),"));
+        // Unlike composite structs, enums keep the configured derives
+        assert!(result.contains("#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]"));
+
+        let enum_type = EnumType { name: "task_state".to_string(), labels: vec!["queued".to_string(), "in progress".to_string(), "1st try".to_string()] };
+        let result = generate_enum_type(&enum_type);
+        assert!(result.contains("postgres_types::ToSql, postgres_types::FromSql)]\n#[postgres(name = \"task_state\")]\npub enum TaskState {"));
+        assert!(result.contains("    #[postgres(name = \"queued\")]\n    #[serde(rename = \"queued\")]\n    Queued,\n"));
+        assert!(result.contains("    #[postgres(name = \"in progress\")]\n    #[serde(rename = \"in progress\")]\n    InProgress,\n"));
+        assert!(result.contains("    V1StTry,\n"));
+    }
+
+    #[test]
+    fn test_map_money_type() {
+        assert_eq!(map_data_type("money"), "crate::types::PgMoney");
+    }
+
+    #[test]
+    fn test_generate_struct_configured_derives() {
+        let mut columns = HashMap::new();
+        columns.insert("id".to_string(), "integer".to_string());
+        columns.insert("name".to_string(), "text".to_string());
+
+        let config = GeneratorConfig::default()
+            .with_derives(&[Derive::Clone, Derive::PartialEq, Derive::Eq, Derive::Hash, Derive::PartialOrd, Derive::Ord]);
+        let date = NaiveDate::from_ymd_opt(2024, 7, 24).unwrap();
+        let configured = generate_struct_with_config("users", columns.clone(), &config, "Tom Blanchard", "https://github.com/tomblanchard312/rust_orm_gen", date);
+        let default = generate_struct_with_config("users", columns, &GeneratorConfig::default(), "Tom Blanchard", "https://github.com/tomblanchard312/rust_orm_gen", date);
+
+        assert!(configured.contains("#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]"));
+        assert!(default.contains("#[derive(Debug, Serialize, Deserialize)]"));
+    }
+
+    #[test]
+    fn test_generate_struct_float_omits_eq() {
+        let mut columns = HashMap::new();
+        columns.insert("id".to_string(), "integer".to_string());
+        columns.insert("price".to_string(), "float8".to_string());
+
+        let config = GeneratorConfig {
+            derives: vec![Derive::Clone, Derive::PartialEq, Derive::Eq, Derive::Hash, Derive::PartialOrd, Derive::Ord],
+            ..GeneratorConfig::default()
+        };
+        let date = NaiveDate::from_ymd_opt(2024, 7, 24).unwrap();
+        let result = generate_struct_with_config("products", columns, &config, "Tom Blanchard", "https://github.com/tomblanchard312/rust_orm_gen", date);
+
+        assert!(result.contains("#[derive(Debug, Clone, PartialEq, PartialOrd, Serialize, Deserialize)]"));
+        assert!(!result.contains(" Eq,"));
+        assert!(!result.contains("Hash"));
+    }
 }
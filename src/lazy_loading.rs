@@ -1,9 +1,10 @@
+use std::future::Future;
 use std::sync::Arc;
 use tokio::sync::Mutex;
 
 pub struct LazyLoaded<T> {
     value: Arc<Mutex<Option<T>>>,
-    loader: Box<dyn Fn() -> T + Send + Sync>,
+    loader: Option<Box<dyn Fn() -> T + Send + Sync>>,
 }
 
 impl<T> LazyLoaded<T> {
@@ -13,7 +14,16 @@ impl<T> LazyLoaded<T> {
     {
         LazyLoaded {
             value: Arc::new(Mutex::new(None)),
-            loader: Box::new(loader),
+            loader: Some(Box::new(loader)),
+        }
+    }
+
+    /// A cache without a stored loader, for values that need an async load
+    /// (e.g. a database query) through `get_or_try_load`.
+    pub fn empty() -> Self {
+        LazyLoaded {
+            value: Arc::new(Mutex::new(None)),
+            loader: None,
         }
     }
 
@@ -23,8 +33,57 @@ impl<T> LazyLoaded<T> {
     {
         let mut value = self.value.lock().await;
         if value.is_none() {
-            *value = Some((self.loader)());
+            let loader = self.loader.as_ref().expect("LazyLoaded::get needs a loader; use get_or_try_load on an empty() cache");
+            *value = Some(loader());
         }
         value.as_ref().unwrap().clone()
     }
-}
\ No newline at end of file
+
+    /// Returns the cached value, running `load` first if nothing is cached
+    /// yet. A failed load is not cached, so the next call tries again.
+    /// Concurrent callers wait for the first load instead of repeating it.
+    pub async fn get_or_try_load<F, Fut, E>(&self, load: F) -> Result<T, E>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<T, E>>,
+        T: Clone,
+    {
+        let mut value = self.value.lock().await;
+        if value.is_none() {
+            *value = Some(load().await?);
+        }
+        Ok(value.as_ref().unwrap().clone())
+    }
+}
+
+impl<T> Default for LazyLoaded<T> {
+    fn default() -> Self {
+        Self::empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[tokio::test]
+    async fn test_get_or_try_load_caches_the_first_success() {
+        let author: LazyLoaded<String> = LazyLoaded::empty();
+        let loads = AtomicUsize::new(0);
+        let load = || async {
+            let attempt = loads.fetch_add(1, Ordering::SeqCst);
+            if attempt == 0 {
+                Err("connection reset")
+            } else {
+                Ok(format!("author loaded on attempt {}", attempt))
+            }
+        };
+
+        assert_eq!(author.get_or_try_load(load).await, Err("connection reset"));
+        assert_eq!(author.get_or_try_load(load).await, Ok("author loaded on attempt 1".to_string()));
+        assert_eq!(author.get_or_try_load(load).await, Ok("author loaded on attempt 1".to_string()));
+        assert_eq!(author.get_or_try_load(load).await, Ok("author loaded on attempt 1".to_string()));
+        assert_eq!(loads.load(Ordering::SeqCst), 2);
+    }
+}
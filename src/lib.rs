@@ -2,6 +2,7 @@ pub mod context;
 pub mod crud;
 pub mod db;
 pub mod error;
+pub mod export;
 pub mod generator;
 pub mod metadata;
 pub mod query_builder;
@@ -10,6 +11,23 @@ pub mod migrations;
 pub mod lazy_loading;
 pub mod cache;
 pub mod validation;
+pub mod fixtures;
+pub mod types;
+pub mod comments;
+pub mod repository;
+pub mod pagination;
+pub mod transactions;
+pub mod transforms;
+pub mod schema;
+pub mod blob;
+pub mod cli;
+pub mod dump;
+#[cfg(feature = "test-db")]
+pub mod test_db;
+#[cfg(feature = "arrow")]
+pub mod record_batch;
+#[cfg(feature = "metrics")]
+pub mod telemetry;
 
 pub use query_builder::QueryBuilder;
 pub use relationships::HasRelationships;
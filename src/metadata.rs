@@ -1,19 +1,516 @@
+use log::warn;
+use serde::Serialize;
+use tokio_postgres::error::SqlState;
 use tokio_postgres::Client;
 use crate::error::OrmError;
+use crate::relationships::TableDependency;
 
+/// Tables that well-known extensions (PostGIS, pg_stat_statements, ...)
+/// create in `public`. They are skipped unless requested explicitly.
+pub const DEFAULT_EXCLUDED_TABLES: &[&str] = &[
+    "spatial_ref_sys",
+    "geometry_columns",
+    "geography_columns",
+    "raster_columns",
+    "raster_overviews",
+    "pg_stat_statements",
+    "pg_stat_statements_info",
+    "pg_buffercache",
+];
+
+pub fn is_excluded_by_default(table_name: &str) -> bool {
+    DEFAULT_EXCLUDED_TABLES.contains(&table_name)
+}
+
+/// Lists the tables to generate code for. Partitions, temporary tables,
+/// extension-owned tables and `DEFAULT_EXCLUDED_TABLES` are skipped.
 pub async fn get_tables(client: &Client) -> Result<Vec<String>, OrmError> {
+    get_tables_including(client, &[]).await
+}
+
+/// Like `get_tables`, but keeps the tables named in `include` even when they
+/// would be skipped by default.
+pub async fn get_tables_including(client: &Client, include: &[&str]) -> Result<Vec<String>, OrmError> {
+    check_schema_usage(client).await?;
+    // pg_catalog lists every table, whereas information_schema.tables hides
+    // the ones the current user has no privileges on
+    let rows = client
+        .query(
+            "SELECT c.relname::text, c.relispartition OR c.relpersistence = 't' OR EXISTS (
+                 SELECT 1 FROM pg_catalog.pg_depend d
+                 WHERE d.classid = 'pg_catalog.pg_class'::regclass AND d.objid = c.oid AND d.deptype = 'e'
+             )
+             FROM pg_catalog.pg_class c
+             JOIN pg_catalog.pg_namespace n ON n.oid = c.relnamespace
+             WHERE n.nspname = 'public' AND c.relkind IN ('r', 'p')",
+            &[],
+        )
+        .await
+        .map_err(|e| privilege_error(e, "list tables"))?;
+    let tables: Vec<String> = rows
+        .iter()
+        .filter_map(|row| {
+            let table: String = row.get(0);
+            let system: bool = row.get(1);
+            let skipped = system || is_excluded_by_default(&table);
+            (!skipped || include.contains(&table.as_str())).then_some(table)
+        })
+        .collect();
+
+    #[cfg(feature = "timescaledb")]
+    let tables = exclude_hypertable_chunks(tables, &get_hypertable_chunks(client).await?);
+
+    Ok(tables)
+}
+
+/// Returns the chunk tables TimescaleDB created in `public` for its
+/// hypertables, or nothing when the extension is not installed.
+#[cfg(feature = "timescaledb")]
+pub async fn get_hypertable_chunks(client: &Client) -> Result<Vec<String>, OrmError> {
+    let installed: bool = client
+        .query_one("SELECT to_regclass('timescaledb_information.chunks') IS NOT NULL", &[])
+        .await?
+        .get(0);
+    if !installed {
+        return Ok(Vec::new());
+    }
+    let rows = client
+        .query(
+            "SELECT chunk_name::text FROM timescaledb_information.chunks WHERE chunk_schema = 'public'",
+            &[],
+        )
+        .await
+        .map_err(|e| privilege_error(e, "list hypertable chunks"))?;
+    Ok(rows.iter().map(|row| row.get(0)).collect())
+}
+
+/// Drops hypertable chunks from `tables`, keeping the hypertables themselves.
+#[cfg(feature = "timescaledb")]
+pub fn exclude_hypertable_chunks(tables: Vec<String>, chunks: &[String]) -> Vec<String> {
+    tables.into_iter().filter(|table| !chunks.contains(table)).collect()
+}
+
+/// Lists the views in `public`. Their code is generated read-only, see
+/// `infer_view_key`.
+pub async fn get_views(client: &Client) -> Result<Vec<String>, OrmError> {
+    check_schema_usage(client).await?;
     let rows = client
-        .query("SELECT table_name FROM information_schema.tables WHERE table_schema = 'public'", &[])
+        .query(
+            "SELECT c.relname::text FROM pg_catalog.pg_class c
+             JOIN pg_catalog.pg_namespace n ON n.oid = c.relnamespace
+             WHERE n.nspname = 'public' AND c.relkind = 'v'",
+            &[],
+        )
+        .await
+        .map_err(|e| privilege_error(e, "list views"))?;
+    Ok(rows.iter().map(|row| row.get(0)).collect())
+}
+
+/// Views have no primary key, so guess the column that identifies a row:
+/// `id`, then `<view>_id`, then the first other `*_id` column.
+pub fn infer_view_key<'a>(view_name: &str, columns: &'a [ColumnInfo]) -> Option<&'a ColumnInfo> {
+    let prefixed = format!("{}_id", view_name);
+    columns.iter().find(|c| c.name == "id")
+        .or_else(|| columns.iter().find(|c| c.name == prefixed))
+        .or_else(|| columns.iter().filter(|c| c.name.ends_with("_id")).min_by(|a, b| a.name.cmp(&b.name)))
+}
+
+/// A column of a table as reported by `information_schema.columns`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct ColumnInfo {
+    pub name: String,
+    pub data_type: String,
+    /// The expression of a `GENERATED ALWAYS AS (...) STORED` column.
+    pub generation_expression: Option<String>,
+    /// Whether the column accepts NULL; its field is then an `Option`.
+    pub is_nullable: bool,
+}
+
+impl ColumnInfo {
+    pub fn new(name: &str, data_type: &str) -> Self {
+        Self { name: name.to_string(), data_type: data_type.to_string(), ..Self::default() }
+    }
+
+    pub fn with_generation_expression(mut self, expression: &str) -> Self {
+        self.generation_expression = Some(expression.to_string());
+        self
+    }
+
+    pub fn nullable(mut self) -> Self {
+        self.is_nullable = true;
+        self
+    }
+}
+
+/// Returns the columns of `table_name`. Arrays and user-defined types are
+/// reported by type name, e.g. `integer[]` or `address`, as `pg_catalog`
+/// spells them.
+///
+/// `information_schema.columns` only shows columns the current user has
+/// privileges on, so when it comes back empty the columns are read from
+/// `pg_catalog` instead. A table with no visible columns at all is an error,
+/// rather than silently generating an empty struct.
+pub async fn get_columns(client: &Client, table_name: &str) -> Result<Vec<ColumnInfo>, OrmError> {
+    let query = "SELECT column_name::text,
+                        CASE WHEN data_type IN ('ARRAY', 'USER-DEFINED')
+                             THEN (quote_ident(udt_schema) || '.' || quote_ident(udt_name))::regtype::text
+                             ELSE data_type::text END,
+                        NULLIF(generation_expression, '')::text, is_nullable = 'YES'
+                 FROM information_schema.columns WHERE table_name = $1 ORDER BY ordinal_position";
+    let mut rows = client.query(query, &[&table_name]).await
+        .map_err(|e| privilege_error(e, &format!("read the columns of '{}'", table_name)))?;
+
+    if rows.is_empty() {
+        let fallback = "SELECT a.attname::text, pg_catalog.format_type(a.atttypid, NULL)::text,
+                            CASE WHEN a.attgenerated = 's' THEN pg_catalog.pg_get_expr(d.adbin, d.adrelid) END,
+                            NOT a.attnotnull
+                        FROM pg_catalog.pg_attribute a
+                        JOIN pg_catalog.pg_class c ON c.oid = a.attrelid
+                        JOIN pg_catalog.pg_namespace n ON n.oid = c.relnamespace
+                        LEFT JOIN pg_catalog.pg_attrdef d ON d.adrelid = a.attrelid AND d.adnum = a.attnum
+                        WHERE n.nspname = 'public' AND c.relname = $1 AND a.attnum > 0 AND NOT a.attisdropped
+                        ORDER BY a.attnum";
+        rows = client.query(fallback, &[&table_name]).await
+            .map_err(|e| privilege_error(e, &format!("read the columns of '{}'", table_name)))?;
+    }
+
+    if rows.is_empty() {
+        return Err(OrmError::QueryError(format!(
+            "No columns found for table '{}'. Check that it exists in the public schema and that the current user can see it, e.g. GRANT USAGE ON SCHEMA public TO <user>; GRANT SELECT ON public.{} TO <user>;",
+            table_name, table_name
+        )));
+    }
+
+    Ok(rows
+        .iter()
+        .map(|row| ColumnInfo { name: row.get(0), data_type: row.get(1), generation_expression: row.get(2), is_nullable: row.get(3) })
+        .collect())
+}
+
+/// Returns the columns of `table_name` the database fills in on insert:
+/// identity and generated columns, serial keys, and defaults that take the
+/// current time or a random UUID. Plain constant defaults are not included,
+/// since callers usually want to set those.
+pub async fn get_generated_columns(client: &Client, table_name: &str) -> Result<Vec<String>, OrmError> {
+    let rows = client
+        .query(
+            "SELECT a.attname::text
+             FROM pg_catalog.pg_attribute a
+             JOIN pg_catalog.pg_class c ON c.oid = a.attrelid
+             JOIN pg_catalog.pg_namespace n ON n.oid = c.relnamespace
+             LEFT JOIN pg_catalog.pg_attrdef d ON d.adrelid = a.attrelid AND d.adnum = a.attnum
+             WHERE n.nspname = 'public' AND c.relname = $1 AND a.attnum > 0 AND NOT a.attisdropped
+               AND (a.attidentity <> '' OR a.attgenerated <> ''
+                    OR pg_catalog.pg_get_expr(d.adbin, d.adrelid) ~* '^(nextval|now|(transaction|statement|clock)_timestamp|gen_random_uuid|uuid_generate_v[14])\\(|^(CURRENT_(TIMESTAMP|DATE|TIME)|LOCAL(TIMESTAMP|TIME))\\M')
+             ORDER BY a.attnum",
+            &[&table_name],
+        )
+        .await
+        .map_err(|e| privilege_error(e, &format!("read the generated columns of '{}'", table_name)))?;
+    Ok(rows.iter().map(|row| row.get(0)).collect())
+}
+
+/// Like `get_columns`, but `table_name` may differ in case from the stored
+/// name, e.g. `Customer` finds `customer`. See `resolve_table_name`.
+pub async fn get_columns_ignoring_case(client: &Client, table_name: &str) -> Result<Vec<ColumnInfo>, OrmError> {
+    match resolve_table_name(client, table_name).await? {
+        Some(stored_name) => get_columns(client, &stored_name).await,
+        None => get_columns(client, table_name).await,
+    }
+}
+
+/// Returns the stored name of the table or view in `public` matching
+/// `table_name` with `lower(...) = lower(...)`. When several names differ
+/// only in case (quoted identifiers), an exact match wins, then the
+/// lowercase name an unquoted identifier would resolve to, then the first
+/// by name, and the ambiguity is logged.
+pub async fn resolve_table_name(client: &Client, table_name: &str) -> Result<Option<String>, OrmError> {
+    let rows = client
+        .query(
+            "SELECT c.relname::text FROM pg_catalog.pg_class c
+             JOIN pg_catalog.pg_namespace n ON n.oid = c.relnamespace
+             WHERE n.nspname = 'public' AND c.relkind IN ('r', 'p', 'v', 'm') AND lower(c.relname) = lower($1)
+             ORDER BY c.relname = $1 DESC, c.relname = lower($1) DESC, c.relname COLLATE \"C\"",
+            &[&table_name],
+        )
+        .await
+        .map_err(|e| privilege_error(e, &format!("look up table '{}'", table_name)))?;
+    let names: Vec<String> = rows.iter().map(|row| row.get(0)).collect();
+    if names.len() > 1 {
+        warn!("Table name '{}' matches {} tables ignoring case: {}", table_name, names.len(), names.join(", "));
+    }
+    Ok(names.into_iter().next())
+}
+
+async fn check_schema_usage(client: &Client) -> Result<(), OrmError> {
+    let row = client
+        .query_one("SELECT current_user::text, pg_catalog.has_schema_privilege('public', 'USAGE')", &[])
+        .await?;
+    let has_usage: bool = row.get(1);
+    if !has_usage {
+        let user: String = row.get(0);
+        return Err(OrmError::QueryError(format!(
+            "User '{}' cannot access the public schema. Grant it with: GRANT USAGE ON SCHEMA public TO {};",
+            user, user
+        )));
+    }
+    Ok(())
+}
+
+/// Turns a permission failure into an error naming the grants to ask for.
+fn privilege_error(error: tokio_postgres::Error, action: &str) -> OrmError {
+    if error.code() == Some(&SqlState::INSUFFICIENT_PRIVILEGE) {
+        OrmError::QueryError(format!(
+            "Insufficient privileges to {}: {}. The user needs USAGE on the schema and SELECT on the catalog views, e.g. GRANT USAGE ON SCHEMA public TO <user>;",
+            action, error
+        ))
+    } else {
+        OrmError::DatabaseError(error)
+    }
+}
+
+/// Returns the columns of each UNIQUE constraint on `table_name`, in
+/// constraint definition order. The primary key is not included.
+pub async fn get_unique_constraints(client: &Client, table_name: &str) -> Result<Vec<Vec<String>>, OrmError> {
+    let rows = client
+        .query(
+            "SELECT ARRAY(
+                 SELECT att.attname::text
+                 FROM unnest(con.conkey) WITH ORDINALITY AS keys(attnum, position)
+                 JOIN pg_catalog.pg_attribute att ON att.attrelid = con.conrelid AND att.attnum = keys.attnum
+                 ORDER BY keys.position
+             )
+             FROM pg_catalog.pg_constraint con
+             JOIN pg_catalog.pg_class cl ON cl.oid = con.conrelid
+             JOIN pg_catalog.pg_namespace ns ON ns.oid = cl.relnamespace
+             WHERE con.contype = 'u' AND ns.nspname = 'public' AND cl.relname = $1
+             ORDER BY con.conname",
+            &[&table_name],
+        )
         .await?;
     Ok(rows.iter().map(|row| row.get(0)).collect())
 }
 
-pub async fn get_columns(client: &Client, table_name: &str) -> Result<Vec<(String, String)>, OrmError> {
-    let query = format!(
-        "SELECT column_name, data_type FROM information_schema.columns WHERE table_name = $1"
-    );
-    let rows = client.query(&query, &[&table_name]).await?;
-    Ok(rows.iter().map(|row| (row.get(0), row.get(1))).collect())
+/// Returns the primary key columns of `table_name` in key order, or an
+/// empty vector when the table has no primary key. Like the other key
+/// lookups, this reads `pg_constraint`, since `information_schema` only
+/// lists the constraints of tables the current user owns.
+pub async fn get_primary_keys(client: &Client, table_name: &str) -> Result<Vec<String>, OrmError> {
+    let rows = client
+        .query(
+            "SELECT att.attname::text
+             FROM pg_catalog.pg_constraint con
+             JOIN pg_catalog.pg_class cl ON cl.oid = con.conrelid
+             JOIN pg_catalog.pg_namespace ns ON ns.oid = cl.relnamespace
+             CROSS JOIN unnest(con.conkey) WITH ORDINALITY AS keys(attnum, position)
+             JOIN pg_catalog.pg_attribute att ON att.attrelid = con.conrelid AND att.attnum = keys.attnum
+             WHERE con.contype = 'p' AND ns.nspname = 'public' AND cl.relname = $1
+             ORDER BY keys.position",
+            &[&table_name],
+        )
+        .await
+        .map_err(|e| privilege_error(e, &format!("read the primary key of '{}'", table_name)))?;
+    Ok(rows.iter().map(|row| row.get(0)).collect())
+}
+
+/// A composite type created with `CREATE TYPE name AS (...)`. Its fields
+/// are always nullable, since composite attributes cannot be `NOT NULL`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CompositeType {
+    pub name: String,
+    pub fields: Vec<ColumnInfo>,
+}
+
+/// Returns the composite types of the `public` schema ordered by name, with
+/// their fields in declaration order. The row types Postgres creates for
+/// every table are not included.
+pub async fn get_composite_types(client: &Client) -> Result<Vec<CompositeType>, OrmError> {
+    let rows = client
+        .query(
+            "SELECT t.typname::text, a.attname::text, pg_catalog.format_type(a.atttypid, NULL)::text
+             FROM pg_catalog.pg_type t
+             JOIN pg_catalog.pg_namespace n ON n.oid = t.typnamespace
+             JOIN pg_catalog.pg_class c ON c.oid = t.typrelid AND c.relkind = 'c'
+             JOIN pg_catalog.pg_attribute a ON a.attrelid = c.oid AND a.attnum > 0 AND NOT a.attisdropped
+             WHERE n.nspname = 'public' AND t.typtype = 'c'
+             ORDER BY t.typname, a.attnum",
+            &[],
+        )
+        .await
+        .map_err(|e| privilege_error(e, "list composite types"))?;
+    let mut composite_types: Vec<CompositeType> = Vec::new();
+    for row in &rows {
+        let name: String = row.get(0);
+        let field = ColumnInfo::new(row.get(1), row.get(2)).nullable();
+        match composite_types.last_mut() {
+            Some(composite) if composite.name == name => composite.fields.push(field),
+            _ => composite_types.push(CompositeType { name, fields: vec![field] }),
+        }
+    }
+    Ok(composite_types)
+}
+
+/// An enum type created with `CREATE TYPE name AS ENUM (...)`, with its
+/// labels in sort order.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EnumType {
+    pub name: String,
+    pub labels: Vec<String>,
+}
+
+/// Returns the enum types of the `public` schema ordered by name.
+pub async fn get_enum_types(client: &Client) -> Result<Vec<EnumType>, OrmError> {
+    let rows = client
+        .query(
+            "SELECT t.typname::text, e.enumlabel::text
+             FROM pg_catalog.pg_type t
+             JOIN pg_catalog.pg_namespace n ON n.oid = t.typnamespace
+             JOIN pg_catalog.pg_enum e ON e.enumtypid = t.oid
+             WHERE n.nspname = 'public'
+             ORDER BY t.typname, e.enumsortorder",
+            &[],
+        )
+        .await
+        .map_err(|e| privilege_error(e, "list enum types"))?;
+    let mut enum_types: Vec<EnumType> = Vec::new();
+    for row in &rows {
+        let name: String = row.get(0);
+        let label: String = row.get(1);
+        match enum_types.last_mut() {
+            Some(enum_type) if enum_type.name == name => enum_type.labels.push(label),
+            _ => enum_types.push(EnumType { name, labels: vec![label] }),
+        }
+    }
+    Ok(enum_types)
+}
+
+/// One key of an index: a plain column, or an expression such as
+/// `lower(email)` that has no column of its own.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IndexKey {
+    Column(String),
+    Expression(String),
+}
+
+/// An index on a table, with its keys in index order.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IndexInfo {
+    pub name: String,
+    pub keys: Vec<IndexKey>,
+    pub is_unique: bool,
+    pub is_primary: bool,
+    /// The full `CREATE INDEX` statement, including any `WHERE` predicate.
+    pub definition: String,
+}
+
+/// Returns the indexes on `table_name`, ordered by name. Expression keys are
+/// reported as written back by `pg_get_indexdef`, e.g. `lower(email)`.
+pub async fn get_indexes(client: &Client, table_name: &str) -> Result<Vec<IndexInfo>, OrmError> {
+    let rows = client
+        .query(
+            "SELECT i.relname::text, ix.indisunique, ix.indisprimary, pg_catalog.pg_get_indexdef(ix.indexrelid),
+                    ARRAY(SELECT pg_catalog.pg_get_indexdef(ix.indexrelid, k, true) FROM generate_series(1, ix.indnkeyatts) AS k ORDER BY k),
+                    ARRAY(SELECT ix.indkey[k - 1] = 0 FROM generate_series(1, ix.indnkeyatts) AS k ORDER BY k)
+             FROM pg_catalog.pg_index ix
+             JOIN pg_catalog.pg_class i ON i.oid = ix.indexrelid
+             JOIN pg_catalog.pg_class t ON t.oid = ix.indrelid
+             JOIN pg_catalog.pg_namespace ns ON ns.oid = t.relnamespace
+             WHERE ns.nspname = 'public' AND t.relname = $1
+             ORDER BY i.relname",
+            &[&table_name],
+        )
+        .await
+        .map_err(|e| privilege_error(e, &format!("read the indexes of '{}'", table_name)))?;
+    Ok(rows
+        .iter()
+        .map(|row| {
+            let keys: Vec<String> = row.get(4);
+            let is_expression: Vec<bool> = row.get(5);
+            IndexInfo {
+                name: row.get(0),
+                keys: keys.into_iter().zip(is_expression)
+                    .map(|(key, is_expression)| if is_expression { IndexKey::Expression(key) } else { IndexKey::Column(key) })
+                    .collect(),
+                is_unique: row.get(1),
+                is_primary: row.get(2),
+                definition: row.get(3),
+            }
+        })
+        .collect())
+}
+
+/// A single-column foreign key as declared on its referencing table.
+/// `on_delete` and `on_update` use the spelling of
+/// `information_schema.referential_constraints`, e.g. `"CASCADE"` or
+/// `"NO ACTION"`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ForeignKey {
+    pub column: String,
+    pub foreign_table: String,
+    pub foreign_column: String,
+    pub on_delete: String,
+    pub on_update: String,
+}
+
+impl ForeignKey {
+    pub fn new(column: &str, foreign_table: &str, foreign_column: &str) -> Self {
+        ForeignKey {
+            column: column.to_string(),
+            foreign_table: foreign_table.to_string(),
+            foreign_column: foreign_column.to_string(),
+            on_delete: "NO ACTION".to_string(),
+            on_update: "NO ACTION".to_string(),
+        }
+    }
+
+    pub fn on_delete(mut self, action: &str) -> Self {
+        self.on_delete = action.to_string();
+        self
+    }
+
+    pub fn on_update(mut self, action: &str) -> Self {
+        self.on_update = action.to_string();
+        self
+    }
+
+    /// The edge this key adds to a `DependencyGraph`.
+    pub fn dependency(&self, table_name: &str) -> TableDependency {
+        TableDependency::new(table_name, &self.column, &self.foreign_table, &self.foreign_column)
+    }
+}
+
+/// Returns the single-column foreign keys declared on `table_name`, ordered
+/// by column. Composite foreign keys are skipped. The keys are read from
+/// `pg_constraint`, since `information_schema.constraint_column_usage` only
+/// lists tables the current user owns.
+pub async fn get_foreign_keys(client: &Client, table_name: &str) -> Result<Vec<ForeignKey>, OrmError> {
+    let rows = client
+        .query(
+            "SELECT att.attname::text, ref.relname::text, ref_att.attname::text,
+                    CASE con.confdeltype WHEN 'c' THEN 'CASCADE' WHEN 'n' THEN 'SET NULL' WHEN 'd' THEN 'SET DEFAULT' WHEN 'r' THEN 'RESTRICT' ELSE 'NO ACTION' END,
+                    CASE con.confupdtype WHEN 'c' THEN 'CASCADE' WHEN 'n' THEN 'SET NULL' WHEN 'd' THEN 'SET DEFAULT' WHEN 'r' THEN 'RESTRICT' ELSE 'NO ACTION' END
+             FROM pg_catalog.pg_constraint con
+             JOIN pg_catalog.pg_class cl ON cl.oid = con.conrelid
+             JOIN pg_catalog.pg_class ref ON ref.oid = con.confrelid
+             JOIN pg_catalog.pg_namespace ns ON ns.oid = cl.relnamespace
+             JOIN pg_catalog.pg_attribute att ON att.attrelid = con.conrelid AND att.attnum = con.conkey[1]
+             JOIN pg_catalog.pg_attribute ref_att ON ref_att.attrelid = con.confrelid AND ref_att.attnum = con.confkey[1]
+             WHERE con.contype = 'f' AND ns.nspname = 'public' AND cl.relname = $1 AND cardinality(con.conkey) = 1
+             ORDER BY att.attname",
+            &[&table_name],
+        )
+        .await
+        .map_err(|e| privilege_error(e, &format!("read the foreign keys of '{}'", table_name)))?;
+    Ok(rows
+        .iter()
+        .map(|row| ForeignKey {
+            column: row.get(0),
+            foreign_table: row.get(1),
+            foreign_column: row.get(2),
+            on_delete: row.get(3),
+            on_update: row.get(4),
+        })
+        .collect())
 }
 
 #[cfg(test)]
@@ -35,6 +532,92 @@ mod tests {
         assert!(tables.is_ok(), "Failed to get tables: {:?}", tables.err());
     }
 
+    #[test]
+    fn test_extension_tables_excluded_by_default() {
+        assert!(is_excluded_by_default("spatial_ref_sys"));
+        assert!(is_excluded_by_default("pg_stat_statements"));
+        assert!(!is_excluded_by_default("customer"));
+    }
+
+    #[tokio::test]
+    async fn test_get_tables_skips_extension_and_partition_tables() {
+        dotenv().ok();
+        let database_url = env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+        let manager = PostgresConnectionManager::new(database_url);
+        let client = manager.connect().await.expect("Failed to connect to database");
+
+        client.batch_execute(
+            "DROP TABLE IF EXISTS spatial_ref_sys, skip_readings;
+             CREATE TABLE spatial_ref_sys (srid INTEGER PRIMARY KEY);
+             CREATE TABLE skip_readings (taken_on DATE NOT NULL) PARTITION BY RANGE (taken_on);
+             CREATE TABLE skip_readings_2024 PARTITION OF skip_readings FOR VALUES FROM ('2024-01-01') TO ('2025-01-01');"
+        ).await.expect("Failed to create test tables");
+
+        let tables = get_tables(&client).await;
+        let with_extension_table = get_tables_including(&client, &["spatial_ref_sys"]).await;
+        client.batch_execute("DROP TABLE spatial_ref_sys, skip_readings").await.unwrap();
+
+        let tables = tables.expect("Failed to get tables");
+        assert!(!tables.contains(&"spatial_ref_sys".to_string()));
+        assert!(!tables.contains(&"skip_readings_2024".to_string()));
+        assert!(tables.contains(&"skip_readings".to_string()));
+        assert!(with_extension_table.expect("Failed to get tables").contains(&"spatial_ref_sys".to_string()));
+    }
+
+    #[cfg(feature = "timescaledb")]
+    #[test]
+    fn test_hypertable_chunks_excluded() {
+        let tables = vec![
+            "conditions".to_string(),
+            "_hyper_1_1_chunk".to_string(),
+            "_hyper_1_2_chunk".to_string(),
+            "devices".to_string(),
+        ];
+        let chunks = vec!["_hyper_1_1_chunk".to_string(), "_hyper_1_2_chunk".to_string()];
+
+        assert_eq!(exclude_hypertable_chunks(tables, &chunks), vec!["conditions".to_string(), "devices".to_string()]);
+    }
+
+    #[test]
+    fn test_infer_view_key() {
+        let staff_list = [ColumnInfo::new("name", "text"), ColumnInfo::new("id", "integer"), ColumnInfo::new("sid", "smallint")];
+        assert_eq!(infer_view_key("staff_list", &staff_list).map(|c| c.name.as_str()), Some("id"));
+
+        let sales = [ColumnInfo::new("store_id", "integer"), ColumnInfo::new("sales_id", "integer")];
+        assert_eq!(infer_view_key("sales", &sales).map(|c| c.name.as_str()), Some("sales_id"));
+
+        let film_list = [ColumnInfo::new("title", "text"), ColumnInfo::new("fid", "integer"), ColumnInfo::new("film_id", "integer")];
+        assert_eq!(infer_view_key("film_list", &film_list).map(|c| c.name.as_str()), Some("film_id"));
+
+        let totals = [ColumnInfo::new("category", "text"), ColumnInfo::new("total_sales", "numeric")];
+        assert!(infer_view_key("totals", &totals).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_get_views_separate_from_tables() {
+        dotenv().ok();
+        let database_url = env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+        let manager = PostgresConnectionManager::new(database_url);
+        let client = manager.connect().await.expect("Failed to connect to database");
+
+        client.batch_execute(
+            "DROP VIEW IF EXISTS view_staff_list;
+             DROP TABLE IF EXISTS view_staff;
+             CREATE TABLE view_staff (staff_id INTEGER PRIMARY KEY, name TEXT NOT NULL);
+             CREATE VIEW view_staff_list AS SELECT staff_id AS id, name FROM view_staff;"
+        ).await.expect("Failed to create test view");
+
+        let tables = get_tables(&client).await;
+        let views = get_views(&client).await;
+        client.batch_execute("DROP VIEW view_staff_list; DROP TABLE view_staff;").await.unwrap();
+
+        let tables = tables.expect("Failed to get tables");
+        let views = views.expect("Failed to get views");
+        assert!(tables.contains(&"view_staff".to_string()));
+        assert!(!tables.contains(&"view_staff_list".to_string()));
+        assert!(views.contains(&"view_staff_list".to_string()));
+    }
+
     #[tokio::test]
     async fn test_get_columns() {
         dotenv().ok();
@@ -42,7 +625,410 @@ mod tests {
         let manager = PostgresConnectionManager::new(database_url);
         let client = manager.connect().await.expect("Failed to connect to database");
 
-        let columns = get_columns(&client, "your_table_name").await;
-        assert!(columns.is_ok(), "Failed to get columns: {:?}", columns.err());
+        // A missing (or invisible) table is reported instead of yielding no columns
+        match get_columns(&client, "your_table_name").await {
+            Err(OrmError::QueryError(message)) => {
+                assert!(message.contains("No columns found for table 'your_table_name'"), "{}", message);
+                assert!(message.contains("GRANT SELECT ON public.your_table_name"), "{}", message);
+            }
+            other => panic!("Expected QueryError, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_get_generated_columns() {
+        dotenv().ok();
+        let database_url = env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+        let manager = PostgresConnectionManager::new(database_url);
+        let client = manager.connect().await.expect("Failed to connect to database");
+
+        client.batch_execute(
+            "DROP TABLE IF EXISTS generated_invoices;
+             CREATE TABLE generated_invoices (
+                 id SERIAL PRIMARY KEY,
+                 number INTEGER GENERATED ALWAYS AS IDENTITY,
+                 customer TEXT NOT NULL,
+                 status TEXT NOT NULL DEFAULT 'open',
+                 amount NUMERIC NOT NULL,
+                 amount_with_tax NUMERIC GENERATED ALWAYS AS (amount * 1.2) STORED,
+                 created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+                 issued_on DATE DEFAULT CURRENT_DATE
+             );"
+        ).await.expect("Failed to create test table");
+
+        let generated = get_generated_columns(&client, "generated_invoices").await;
+        client.batch_execute("DROP TABLE generated_invoices").await.unwrap();
+
+        assert_eq!(generated.expect("Failed to get generated columns"), vec!["id", "number", "amount_with_tax", "created_at", "issued_on"]);
+    }
+
+    #[tokio::test]
+    async fn test_get_columns_ignoring_case() {
+        dotenv().ok();
+        let database_url = env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+        let manager = PostgresConnectionManager::new(database_url);
+        let client = manager.connect().await.expect("Failed to connect to database");
+
+        client.batch_execute(
+            "DROP TABLE IF EXISTS case_customer, \"Case_Order\", case_order, \"Case_Item\", \"CASE_ITEM\";
+             CREATE TABLE case_customer (id INTEGER PRIMARY KEY, name TEXT NOT NULL);
+             CREATE TABLE \"Case_Order\" (id INTEGER PRIMARY KEY);
+             CREATE TABLE case_order (id INTEGER PRIMARY KEY, total NUMERIC);
+             CREATE TABLE \"Case_Item\" (id INTEGER PRIMARY KEY);
+             CREATE TABLE \"CASE_ITEM\" (id INTEGER PRIMARY KEY);"
+        ).await.expect("Failed to create test tables");
+
+        let columns = get_columns_ignoring_case(&client, "Case_Customer").await;
+        let exact_case = get_columns(&client, "Case_Customer").await;
+        let ambiguous = resolve_table_name(&client, "CASE_ORDER").await;
+        let exact_match = resolve_table_name(&client, "Case_Order").await;
+        let no_lowercase = resolve_table_name(&client, "case_item").await;
+        let missing = resolve_table_name(&client, "Case_Nothing").await;
+        client.batch_execute("DROP TABLE case_customer, \"Case_Order\", case_order, \"Case_Item\", \"CASE_ITEM\"").await.unwrap();
+
+        assert_eq!(columns.expect("Failed to get columns"), vec![ColumnInfo::new("id", "integer"), ColumnInfo::new("name", "text")]);
+        assert!(exact_case.is_err());
+        // Without an exact match, the name Postgres gives an unquoted identifier wins
+        assert_eq!(ambiguous.unwrap(), Some("case_order".to_string()));
+        assert_eq!(exact_match.unwrap(), Some("Case_Order".to_string()));
+        // Then the first by byte order, where uppercase sorts first
+        assert_eq!(no_lowercase.unwrap(), Some("CASE_ITEM".to_string()));
+        assert_eq!(missing.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_get_columns_falls_back_to_pg_catalog() {
+        dotenv().ok();
+        let database_url = env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+        let manager = PostgresConnectionManager::new(database_url);
+        let client = manager.connect().await.expect("Failed to connect to database");
+
+        client.batch_execute(
+            "DROP TABLE IF EXISTS locked_down_accounts;
+             CREATE TABLE locked_down_accounts (id INTEGER PRIMARY KEY, email VARCHAR(255) NOT NULL);
+             DO $$ BEGIN
+                 IF NOT EXISTS (SELECT 1 FROM pg_roles WHERE rolname = 'orm_gen_no_privileges') THEN
+                     CREATE ROLE orm_gen_no_privileges;
+                 END IF;
+             END $$;
+             GRANT USAGE ON SCHEMA public TO orm_gen_no_privileges;"
+        ).await.expect("Failed to create test table");
+
+        // Without SELECT on the table, information_schema.columns is empty for this role
+        client.batch_execute("SET ROLE orm_gen_no_privileges").await.unwrap();
+        let columns = get_columns(&client, "locked_down_accounts").await;
+        let tables = get_tables(&client).await;
+        client.batch_execute("RESET ROLE; DROP TABLE locked_down_accounts;").await.unwrap();
+
+        let columns = columns.expect("Failed to get columns through pg_catalog");
+        assert_eq!(columns, vec![ColumnInfo::new("id", "integer"), ColumnInfo::new("email", "character varying")]);
+        assert!(tables.expect("Failed to get tables").contains(&"locked_down_accounts".to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_get_columns_generation_expression() {
+        dotenv().ok();
+        let database_url = env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+        let manager = PostgresConnectionManager::new(database_url);
+        let client = manager.connect().await.expect("Failed to connect to database");
+
+        client.batch_execute(
+            "DROP TABLE IF EXISTS generated_line_items;
+             CREATE TABLE generated_line_items (
+                 id INTEGER PRIMARY KEY,
+                 quantity INTEGER NOT NULL,
+                 unit_price INTEGER NOT NULL,
+                 total INTEGER GENERATED ALWAYS AS (quantity * unit_price) STORED
+             );"
+        ).await.expect("Failed to create test table");
+
+        let columns = get_columns(&client, "generated_line_items").await;
+        client.batch_execute("DROP TABLE generated_line_items").await.unwrap();
+
+        let columns = columns.expect("Failed to get columns");
+        let total = columns.iter().find(|c| c.name == "total").expect("Missing generated column");
+        assert_eq!(total.generation_expression.as_deref(), Some("(quantity * unit_price)"));
+        assert!(columns.iter().filter(|c| c.name != "total").all(|c| c.generation_expression.is_none()));
+
+        let date = chrono::NaiveDate::from_ymd_opt(2024, 7, 24).unwrap();
+        let struct_def = crate::generator::generate_struct_from_columns(
+            "generated_line_items", &columns, &crate::generator::GeneratorConfig::default(),
+            "Tom Blanchard", "https://github.com/tomblanchard312/rust_orm_gen", date,
+        );
+        assert!(struct_def.contains("    /// Computed by the database: GENERATED ALWAYS AS ((quantity * unit_price)) STORED\n    #[serde(rename = \"total\")] pub total: Option<i32>,"));
+    }
+
+    #[tokio::test]
+    async fn test_get_columns_nullability() {
+        dotenv().ok();
+        let database_url = env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+        let manager = PostgresConnectionManager::new(database_url);
+        let client = manager.connect().await.expect("Failed to connect to database");
+
+        client.batch_execute(
+            "DROP TABLE IF EXISTS nullable_posts;
+             CREATE TABLE nullable_posts (id INTEGER PRIMARY KEY, title TEXT NOT NULL, content TEXT);"
+        ).await.expect("Failed to create test table");
+
+        let columns = get_columns(&client, "nullable_posts").await;
+        client.batch_execute("DROP TABLE nullable_posts").await.unwrap();
+
+        let mut columns = columns.expect("Failed to get columns");
+        columns.sort_by(|a, b| a.name.cmp(&b.name));
+        assert_eq!(columns, vec![
+            ColumnInfo::new("content", "text").nullable(),
+            ColumnInfo::new("id", "integer"),
+            ColumnInfo::new("title", "text"),
+        ]);
+
+        let date = chrono::NaiveDate::from_ymd_opt(2024, 7, 24).unwrap();
+        let struct_def = crate::generator::generate_struct_from_columns(
+            "nullable_posts", &columns, &crate::generator::GeneratorConfig::default(),
+            "Tom Blanchard", "https://github.com/tomblanchard312/rust_orm_gen", date,
+        );
+        assert!(struct_def.contains("pub content: Option<String>,"));
+        assert!(struct_def.contains("pub title: String,"));
+    }
+
+    #[tokio::test]
+    async fn test_get_foreign_keys() {
+        dotenv().ok();
+        let database_url = env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+        let manager = PostgresConnectionManager::new(database_url);
+        let client = manager.connect().await.expect("Failed to connect to database");
+
+        client.batch_execute(
+            "DROP TABLE IF EXISTS fk_posts, fk_users;
+             CREATE TABLE fk_users (id INTEGER PRIMARY KEY, tenant INTEGER NOT NULL, UNIQUE (id, tenant));
+             CREATE TABLE fk_posts (
+                 id INTEGER PRIMARY KEY,
+                 author_id INTEGER NOT NULL REFERENCES fk_users (id),
+                 tenant INTEGER NOT NULL,
+                 FOREIGN KEY (author_id, tenant) REFERENCES fk_users (id, tenant)
+             );"
+        ).await.expect("Failed to create test tables");
+
+        let foreign_keys = get_foreign_keys(&client, "fk_posts").await;
+        let none = get_foreign_keys(&client, "fk_users").await;
+        client.batch_execute("DROP TABLE fk_posts, fk_users").await.unwrap();
+
+        let foreign_keys = foreign_keys.expect("Failed to get foreign keys");
+        assert_eq!(foreign_keys, vec![ForeignKey::new("author_id", "fk_users", "id")]);
+        assert_eq!(foreign_keys[0].dependency("fk_posts"), TableDependency::new("fk_posts", "author_id", "fk_users", "id"));
+        assert_eq!(none.expect("Failed to get foreign keys"), vec![]);
+    }
+
+    #[tokio::test]
+    async fn test_get_foreign_key_actions() {
+        dotenv().ok();
+        let database_url = env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+        let manager = PostgresConnectionManager::new(database_url);
+        let client = manager.connect().await.expect("Failed to connect to database");
+
+        client.batch_execute(
+            "DROP TABLE IF EXISTS fk_order_items, fk_orders, fk_products;
+             CREATE TABLE fk_orders (id INTEGER PRIMARY KEY);
+             CREATE TABLE fk_products (sku TEXT PRIMARY KEY);
+             CREATE TABLE fk_order_items (
+                 order_id INTEGER NOT NULL REFERENCES fk_orders (id) ON DELETE CASCADE,
+                 product_sku TEXT REFERENCES fk_products (sku) ON DELETE SET NULL ON UPDATE CASCADE
+             );"
+        ).await.expect("Failed to create test tables");
+
+        let foreign_keys = get_foreign_keys(&client, "fk_order_items").await;
+        client.batch_execute("DROP TABLE fk_order_items, fk_orders, fk_products").await.unwrap();
+
+        assert_eq!(foreign_keys.expect("Failed to get foreign keys"), vec![
+            ForeignKey::new("order_id", "fk_orders", "id").on_delete("CASCADE"),
+            ForeignKey::new("product_sku", "fk_products", "sku").on_delete("SET NULL").on_update("CASCADE"),
+        ]);
+    }
+
+    #[tokio::test]
+    async fn test_get_indexes_captures_expressions() {
+        dotenv().ok();
+        let database_url = env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+        let manager = PostgresConnectionManager::new(database_url);
+        let client = manager.connect().await.expect("Failed to connect to database");
+
+        client.batch_execute(
+            "DROP TABLE IF EXISTS idx_customers;
+             CREATE TABLE idx_customers (id INTEGER PRIMARY KEY, email TEXT NOT NULL, store_id INTEGER NOT NULL);
+             CREATE UNIQUE INDEX idx_customers_email_lower ON idx_customers (lower(email));
+             CREATE INDEX idx_customers_store_email ON idx_customers (store_id, lower(email)) WHERE store_id > 0;"
+        ).await.expect("Failed to create test table");
+
+        let indexes = get_indexes(&client, "idx_customers").await;
+        client.batch_execute("DROP TABLE idx_customers").await.unwrap();
+
+        let indexes = indexes.expect("Failed to get indexes");
+        assert_eq!(indexes.len(), 3);
+        assert_eq!(indexes[0].name, "idx_customers_email_lower");
+        assert_eq!(indexes[0].keys, vec![IndexKey::Expression("lower(email)".to_string())]);
+        assert!(indexes[0].is_unique && !indexes[0].is_primary);
+        assert_eq!(indexes[1].keys, vec![IndexKey::Column("id".to_string())]);
+        assert!(indexes[1].is_primary);
+        assert_eq!(indexes[2].keys, vec![IndexKey::Column("store_id".to_string()), IndexKey::Expression("lower(email)".to_string())]);
+        assert!(indexes[2].definition.ends_with("WHERE (store_id > 0)"), "{}", indexes[2].definition);
+    }
+
+    #[tokio::test]
+    async fn test_get_composite_types_and_array_columns() {
+        dotenv().ok();
+        let database_url = env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+        let manager = PostgresConnectionManager::new(database_url);
+        let client = manager.connect().await.expect("Failed to connect to database");
+
+        client.batch_execute(
+            "DROP TABLE IF EXISTS composite_customers;
+             DROP TYPE IF EXISTS composite_address;
+             CREATE TYPE composite_address AS (street TEXT, zip VARCHAR(10), floor SMALLINT);
+             CREATE TABLE composite_customers (
+                 id INTEGER PRIMARY KEY,
+                 home composite_address,
+                 previous composite_address[],
+                 tags TEXT[] NOT NULL
+             );"
+        ).await.expect("Failed to create test type");
+
+        let composite_types = get_composite_types(&client).await;
+        let columns = get_columns(&client, "composite_customers").await;
+        client.batch_execute("DROP TABLE composite_customers; DROP TYPE composite_address;").await.unwrap();
+
+        let composite = composite_types.expect("Failed to get composite types")
+            .into_iter()
+            .find(|composite| composite.name == "composite_address")
+            .expect("composite_address not found");
+        assert_eq!(composite.fields, vec![
+            ColumnInfo::new("street", "text").nullable(),
+            ColumnInfo::new("zip", "character varying").nullable(),
+            ColumnInfo::new("floor", "smallint").nullable(),
+        ]);
+        let columns = columns.expect("Failed to get columns");
+        let data_types: Vec<(&str, &str)> = columns.iter().map(|column| (column.name.as_str(), column.data_type.as_str())).collect();
+        assert_eq!(data_types, vec![("id", "integer"), ("home", "composite_address"), ("previous", "composite_address[]"), ("tags", "text[]")]);
     }
-}
\ No newline at end of file
+
+    #[tokio::test]
+    async fn test_get_enum_types_and_enum_array_round_trip() {
+        use crate::generator::{generate_struct_from_columns, GeneratorConfig};
+        use bytes::BytesMut;
+        use std::error::Error;
+        use tokio_postgres::types::{FromSql, IsNull, ToSql, Type};
+
+        // What the postgres-types derives generate for `enum_mood`
+        #[derive(Debug, Clone, Copy, PartialEq)]
+        enum EnumMood {
+            Happy,
+            Sad,
+        }
+        impl ToSql for EnumMood {
+            fn to_sql(&self, _: &Type, out: &mut BytesMut) -> Result<IsNull, Box<dyn Error + Sync + Send>> {
+                out.extend_from_slice(match self { EnumMood::Happy => b"happy", EnumMood::Sad => b"sad" });
+                Ok(IsNull::No)
+            }
+            fn accepts(ty: &Type) -> bool {
+                ty.name() == "enum_mood"
+            }
+            tokio_postgres::types::to_sql_checked!();
+        }
+        impl<'a> FromSql<'a> for EnumMood {
+            fn from_sql(_: &Type, raw: &'a [u8]) -> Result<Self, Box<dyn Error + Sync + Send>> {
+                match raw {
+                    b"happy" => Ok(EnumMood::Happy),
+                    b"sad" => Ok(EnumMood::Sad),
+                    _ => Err("unknown enum_mood label".into()),
+                }
+            }
+            fn accepts(ty: &Type) -> bool {
+                ty.name() == "enum_mood"
+            }
+        }
+
+        dotenv().ok();
+        let database_url = env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+        let manager = PostgresConnectionManager::new(database_url);
+        let client = manager.connect().await.expect("Failed to connect to database");
+
+        client.batch_execute(
+            "DROP TABLE IF EXISTS enum_diaries;
+             DROP TYPE IF EXISTS enum_mood;
+             CREATE TYPE enum_mood AS ENUM ('happy', 'sad');
+             CREATE TABLE enum_diaries (id INTEGER PRIMARY KEY, moods enum_mood[] NOT NULL);"
+        ).await.expect("Failed to create test type");
+
+        let enum_types = get_enum_types(&client).await;
+        let columns = get_columns(&client, "enum_diaries").await;
+        let moods = vec![EnumMood::Sad, EnumMood::Happy];
+        let inserted = client.execute("INSERT INTO enum_diaries VALUES (1, $1)", &[&moods]).await;
+        let read_back = client.query_one("SELECT moods FROM enum_diaries WHERE id = 1", &[]).await.map(|row| row.get::<_, Vec<EnumMood>>(0));
+        client.batch_execute("DROP TABLE enum_diaries; DROP TYPE enum_mood;").await.unwrap();
+
+        let enum_type = enum_types.expect("Failed to get enum types")
+            .into_iter()
+            .find(|enum_type| enum_type.name == "enum_mood")
+            .expect("enum_mood not found");
+        assert_eq!(enum_type.labels, vec!["happy".to_string(), "sad".to_string()]);
+        let columns = columns.expect("Failed to get columns");
+        assert_eq!(columns[1], ColumnInfo::new("moods", "enum_mood[]"));
+
+        let config = GeneratorConfig::default().with_enum_types(&["enum_mood"]);
+        let date = chrono::NaiveDate::from_ymd_opt(2024, 7, 24).unwrap();
+        let struct_def = generate_struct_from_columns("enum_diaries", &columns, &config, "Tom Blanchard", "https://github.com/tomblanchard312/rust_orm_gen", date);
+        assert!(struct_def.contains("pub moods: Vec<EnumMood>,"));
+
+        inserted.expect("Failed to insert an enum array");
+        assert_eq!(read_back.expect("Failed to read an enum array"), moods);
+    }
+
+    #[tokio::test]
+    async fn test_get_unique_constraints() {
+        dotenv().ok();
+        let database_url = env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+        let manager = PostgresConnectionManager::new(database_url);
+        let client = manager.connect().await.expect("Failed to connect to database");
+
+        client.batch_execute(
+            "DROP TABLE IF EXISTS unique_customers;
+             CREATE TABLE unique_customers (
+                 id INTEGER PRIMARY KEY,
+                 email TEXT NOT NULL UNIQUE,
+                 store_id INTEGER NOT NULL,
+                 code TEXT NOT NULL,
+                 UNIQUE (store_id, code)
+             );"
+        ).await.expect("Failed to create test table");
+
+        let keys = get_unique_constraints(&client, "unique_customers").await;
+        client.batch_execute("DROP TABLE unique_customers").await.unwrap();
+
+        assert_eq!(keys.expect("Failed to get unique constraints"), vec![
+            vec!["email".to_string()],
+            vec!["store_id".to_string(), "code".to_string()],
+        ]);
+    }
+
+    #[tokio::test]
+    async fn test_get_primary_keys() {
+        dotenv().ok();
+        let database_url = env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+        let manager = PostgresConnectionManager::new(database_url);
+        let client = manager.connect().await.expect("Failed to connect to database");
+
+        client.batch_execute(
+            "DROP TABLE IF EXISTS pk_customers, pk_order_lines, pk_audit_log;
+             CREATE TABLE pk_customers (customer_id SERIAL PRIMARY KEY, name TEXT);
+             CREATE TABLE pk_order_lines (line_no INTEGER, order_id INTEGER, PRIMARY KEY (order_id, line_no));
+             CREATE TABLE pk_audit_log (message TEXT);"
+        ).await.expect("Failed to create test tables");
+
+        let customers = get_primary_keys(&client, "pk_customers").await;
+        let order_lines = get_primary_keys(&client, "pk_order_lines").await;
+        let audit_log = get_primary_keys(&client, "pk_audit_log").await;
+        client.batch_execute("DROP TABLE pk_customers, pk_order_lines, pk_audit_log").await.unwrap();
+
+        assert_eq!(customers.expect("Failed to get primary key"), vec!["customer_id".to_string()]);
+        assert_eq!(order_lines.expect("Failed to get primary key"), vec!["order_id".to_string(), "line_no".to_string()]);
+        assert!(audit_log.expect("Failed to get primary key").is_empty());
+    }
+}
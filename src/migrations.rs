@@ -1,23 +1,607 @@
 use tokio_postgres::Client;
 use std::error::Error;
+use chrono::{DateTime, Utc};
+use sha2::{Digest, Sha256};
+use crate::error::OrmError;
 
 pub struct Migration {
     pub version: i32,
     pub up: String,
     pub down: String,
+    /// Runs `up` and records the version in one transaction, so a failure
+    /// leaves neither behind. Turn off with `without_transaction` for
+    /// statements Postgres refuses to run in one, e.g. `CREATE INDEX
+    /// CONCURRENTLY`.
+    pub transactional: bool,
 }
 
+impl Migration {
+    pub fn new(version: i32, up: &str, down: &str) -> Self {
+        Migration { version, up: up.to_string(), down: down.to_string(), transactional: true }
+    }
+
+    /// Runs `up` outside a transaction. If it fails partway the version is
+    /// left marked dirty and later runs refuse to continue until
+    /// `resolve_dirty_migration` is called.
+    pub fn without_transaction(mut self) -> Self {
+        self.transactional = false;
+        self
+    }
+
+    /// The hex SHA-256 of `up`, recorded when the migration is applied so
+    /// later runs can tell if it was edited afterwards.
+    pub fn checksum(&self) -> String {
+        format!("{:x}", Sha256::digest(self.up.as_bytes()))
+    }
+}
+
+/// Whether a migration has been applied, as reported by `migration_status`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MigrationStatus {
+    pub version: i32,
+    pub applied: bool,
+    /// When the migration finished applying. `None` if it is pending, or if
+    /// it was applied before the migrations table recorded the time.
+    pub applied_at: Option<DateTime<Utc>>,
+    /// Whether a run left the migration failed partway, see
+    /// `resolve_dirty_migration`. A dirty migration is not `applied`.
+    pub dirty: bool,
+}
+
+/// Creates the migrations table, and adds the columns later versions of
+/// this module track to one created by an earlier version. Rows applied
+/// before `applied_at` or `checksum` existed keep them NULL.
+const MIGRATIONS_TABLE_SQL: &str = "CREATE TABLE IF NOT EXISTS migrations (version INTEGER PRIMARY KEY);
+     ALTER TABLE migrations ADD COLUMN IF NOT EXISTS dirty BOOLEAN NOT NULL DEFAULT false;
+     ALTER TABLE migrations ADD COLUMN IF NOT EXISTS applied_at TIMESTAMPTZ;
+     ALTER TABLE migrations ALTER COLUMN applied_at SET DEFAULT now();
+     ALTER TABLE migrations ADD COLUMN IF NOT EXISTS checksum TEXT;";
+
+/// Key of the session-level advisory lock held while migrations run.
+pub const MIGRATION_LOCK_KEY: i64 = 0x7275_7374_5f6f_726d;
+
+/// Applies the migrations that have not been applied yet. Runs hold an
+/// advisory lock, so when several processes migrate the same database at
+/// once the others wait until the first has finished instead of racing it.
+/// Fails with `OrmError::ParseError` before applying anything if an applied
+/// migration's `up` no longer matches the checksum recorded for it.
 pub async fn run_migrations(client: &Client, migrations: &[Migration]) -> Result<(), Box<dyn Error>> {
-    // Create migrations table if it doesn't exist
-    client.execute("CREATE TABLE IF NOT EXISTS migrations (version INTEGER PRIMARY KEY)", &[]).await?;
+    client.execute("SELECT pg_advisory_lock($1)", &[&MIGRATION_LOCK_KEY]).await?;
+    let result = apply_migrations(client, migrations).await;
+    client.execute("SELECT pg_advisory_unlock($1)", &[&MIGRATION_LOCK_KEY]).await?;
+    result
+}
+
+async fn apply_migrations(client: &Client, migrations: &[Migration]) -> Result<(), Box<dyn Error>> {
+    prepare_migrations_table(client).await?;
+
+    let rows = client.query("SELECT version, checksum FROM migrations", &[]).await?;
+    let applied: Vec<(i32, Option<String>)> = rows.iter().map(|row| (row.get(0), row.get(1))).collect();
+    for migration in migrations {
+        // Migrations applied before checksums were recorded have none to compare
+        if let Some((_, Some(recorded))) = applied.iter().find(|(version, _)| *version == migration.version) {
+            let checksum = migration.checksum();
+            if *recorded != checksum {
+                return Err(OrmError::ParseError(format!(
+                    "Migration {} was changed after it was applied: its up SQL has checksum {}, but {} was recorded",
+                    migration.version, checksum, recorded
+                )).into());
+            }
+        }
+    }
 
     for migration in migrations {
-        let version: i32 = client.query_one("SELECT version FROM migrations WHERE version = $1", &[&migration.version]).await?.get(0);
-        if version == 0 {
-            client.execute(&migration.up, &[]).await?;
-            client.execute("INSERT INTO migrations (version) VALUES ($1)", &[&migration.version]).await?;
+        if !applied.iter().any(|(version, _)| *version == migration.version) {
+            if migration.transactional {
+                apply_in_transaction(client, migration).await?;
+            } else {
+                client.execute("INSERT INTO migrations (version, dirty, checksum) VALUES ($1, true, $2)", &[&migration.version, &migration.checksum()]).await?;
+                client.batch_execute(&migration.up).await?;
+                client.execute("UPDATE migrations SET dirty = false, applied_at = now() WHERE version = $1", &[&migration.version]).await?;
+            }
         }
     }
 
     Ok(())
-}
\ No newline at end of file
+}
+
+/// Creates the migrations table if needed and refuses to go on while a
+/// migration is marked dirty.
+async fn prepare_migrations_table(client: &Client) -> Result<(), OrmError> {
+    client.batch_execute(MIGRATIONS_TABLE_SQL).await?;
+
+    // A migration that failed partway leaves the schema in an unknown state
+    if let Some(row) = client.query_opt("SELECT version FROM migrations WHERE dirty ORDER BY version LIMIT 1", &[]).await? {
+        let version: i32 = row.get(0);
+        return Err(OrmError::QueryError(format!(
+            "Migration {} failed partway and is marked dirty; repair the schema by hand, then call resolve_dirty_migration",
+            version
+        )));
+    }
+    Ok(())
+}
+
+async fn apply_in_transaction(client: &Client, migration: &Migration) -> Result<(), OrmError> {
+    client.batch_execute("BEGIN").await?;
+    let applied = async {
+        client.batch_execute(&migration.up).await?;
+        client.execute("INSERT INTO migrations (version, checksum) VALUES ($1, $2)", &[&migration.version, &migration.checksum()]).await?;
+        Ok::<_, OrmError>(())
+    }.await;
+    match applied {
+        Ok(()) => Ok(client.batch_execute("COMMIT").await?),
+        Err(e) => {
+            // Report the migration's error rather than a failed rollback
+            client.batch_execute("ROLLBACK").await.ok();
+            Err(e)
+        }
+    }
+}
+
+/// Rolls back every applied migration with a version above `target_version`,
+/// newest first, running its `down` and removing its record. Each step
+/// commits on its own, so a failing `down` stops the rollback with the
+/// migrations below it still applied. Migrations made `without_transaction`
+/// are rolled back outside one, and marked dirty if their `down` fails.
+/// Every version to roll back must be in `migrations`, or nothing is run.
+pub async fn rollback_migrations(client: &Client, migrations: &[Migration], target_version: i32) -> Result<(), Box<dyn Error>> {
+    client.execute("SELECT pg_advisory_lock($1)", &[&MIGRATION_LOCK_KEY]).await?;
+    let result = revert_migrations(client, migrations, target_version).await;
+    client.execute("SELECT pg_advisory_unlock($1)", &[&MIGRATION_LOCK_KEY]).await?;
+    result
+}
+
+async fn revert_migrations(client: &Client, migrations: &[Migration], target_version: i32) -> Result<(), Box<dyn Error>> {
+    prepare_migrations_table(client).await?;
+
+    let rows = client.query("SELECT version FROM migrations WHERE version > $1 ORDER BY version DESC", &[&target_version]).await?;
+    let mut steps = Vec::with_capacity(rows.len());
+    for row in &rows {
+        let version: i32 = row.get(0);
+        match migrations.iter().find(|migration| migration.version == version) {
+            Some(migration) => steps.push(migration),
+            None => return Err(OrmError::QueryError(format!("Cannot roll back migration {}: it is not in the given migrations", version)).into()),
+        }
+    }
+
+    for migration in steps {
+        if migration.transactional {
+            revert_in_transaction(client, migration).await?;
+        } else {
+            client.execute("UPDATE migrations SET dirty = true WHERE version = $1", &[&migration.version]).await?;
+            client.batch_execute(&migration.down).await?;
+            client.execute("DELETE FROM migrations WHERE version = $1", &[&migration.version]).await?;
+        }
+    }
+
+    Ok(())
+}
+
+async fn revert_in_transaction(client: &Client, migration: &Migration) -> Result<(), OrmError> {
+    client.batch_execute("BEGIN").await?;
+    let reverted = async {
+        client.batch_execute(&migration.down).await?;
+        client.execute("DELETE FROM migrations WHERE version = $1", &[&migration.version]).await?;
+        Ok::<_, OrmError>(())
+    }.await;
+    match reverted {
+        Ok(()) => Ok(client.batch_execute("COMMIT").await?),
+        Err(e) => {
+            client.batch_execute("ROLLBACK").await.ok();
+            Err(e)
+        }
+    }
+}
+
+/// Clears the dirty flag left by a migration that failed partway, once the
+/// schema has been repaired by hand. With `applied` the migration is kept as
+/// applied; otherwise its record is removed so the next run retries it.
+pub async fn resolve_dirty_migration(client: &Client, version: i32, applied: bool) -> Result<(), OrmError> {
+    let query = if applied {
+        "UPDATE migrations SET dirty = false WHERE version = $1 AND dirty"
+    } else {
+        "DELETE FROM migrations WHERE version = $1 AND dirty"
+    };
+    match client.execute(query, &[&version]).await? {
+        0 => Err(OrmError::QueryError(format!("Migration {} is not marked dirty", version))),
+        _ => Ok(()),
+    }
+}
+
+/// The rows of the migrations table as `(version, applied_at, dirty)`, in
+/// ascending order. The table is only read: columns missing from one created
+/// by an earlier version of this module read as NULL and not dirty, and are
+/// left for `run_migrations` to add. Empty if there is no migrations table.
+async fn migration_records(client: &Client) -> Result<Vec<(i32, Option<DateTime<Utc>>, bool)>, OrmError> {
+    let rows = client.query(
+        "SELECT attname::text FROM pg_attribute WHERE attrelid = to_regclass('migrations') AND attnum > 0 AND NOT attisdropped",
+        &[],
+    ).await?;
+    let columns: Vec<String> = rows.iter().map(|row| row.get(0)).collect();
+    if columns.is_empty() {
+        return Ok(Vec::new());
+    }
+    let has_column = |name: &str| columns.iter().any(|column| column == name);
+    let applied_at = if has_column("applied_at") { "applied_at" } else { "NULL::timestamptz" };
+    let dirty = if has_column("dirty") { "dirty" } else { "false" };
+    let rows = client.query(&format!("SELECT version, {}, {} FROM migrations ORDER BY version", applied_at, dirty), &[]).await?;
+    Ok(rows.iter().map(|row| (row.get(0), row.get(1), row.get(2))).collect())
+}
+
+/// The versions recorded by `run_migrations`, in ascending order. Empty if
+/// no migration has been run against this database yet. A migration left
+/// dirty by a failed run is not included.
+pub async fn applied_migrations(client: &Client) -> Result<Vec<i32>, OrmError> {
+    let records = migration_records(client).await?;
+    Ok(records.into_iter().filter(|(_, _, dirty)| !dirty).map(|(version, _, _)| version).collect())
+}
+
+/// The status of each of `migrations`, in the order given. A migration left
+/// dirty by a failed run counts as not applied, and has `dirty` set.
+pub async fn migration_status(client: &Client, migrations: &[Migration]) -> Result<Vec<MigrationStatus>, OrmError> {
+    let records = migration_records(client).await?;
+    Ok(migrations.iter().map(|migration| {
+        let record = records.iter().find(|(version, _, _)| *version == migration.version);
+        let dirty = record.is_some_and(|(_, _, dirty)| *dirty);
+        MigrationStatus {
+            version: migration.version,
+            applied: record.is_some() && !dirty,
+            applied_at: record.filter(|_| !dirty).and_then(|(_, applied_at, _)| *applied_at),
+            dirty,
+        }
+    }).collect())
+}
+
+/// The entries of `migrations` that have not been applied yet, in the order
+/// given. A migration left dirty by a failed run is included.
+pub async fn pending_migrations<'a>(client: &Client, migrations: &'a [Migration]) -> Result<Vec<&'a Migration>, OrmError> {
+    let applied = applied_migrations(client).await?;
+    Ok(migrations.iter().filter(|migration| !applied.contains(&migration.version)).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::PostgresConnectionManager;
+    use dotenv::dotenv;
+    use std::env;
+
+    #[tokio::test]
+    async fn test_applied_and_pending_migrations() {
+        dotenv().ok();
+        let database_url = env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+        let manager = PostgresConnectionManager::new(database_url);
+        let client = manager.connect().await.expect("Failed to connect to database");
+        let migrations = vec![
+            Migration::new(513_001, "CREATE TABLE migration_status_a (id INTEGER PRIMARY KEY)", "DROP TABLE migration_status_a"),
+            Migration::new(513_002, "CREATE TABLE migration_status_b (id INTEGER PRIMARY KEY)", "DROP TABLE migration_status_b"),
+        ];
+        client.batch_execute(
+            "DROP TABLE IF EXISTS migration_status_a, migration_status_b;
+             CREATE TABLE IF NOT EXISTS migrations (version INTEGER PRIMARY KEY);
+             DELETE FROM migrations WHERE version IN (513001, 513002);"
+        ).await.unwrap();
+
+        run_migrations(&client, &migrations[..1]).await.expect("Failed to run migrations");
+        let applied = applied_migrations(&client).await;
+        let pending = pending_migrations(&client, &migrations).await;
+        client.batch_execute(
+            "DROP TABLE migration_status_a;
+             DELETE FROM migrations WHERE version IN (513001, 513002);"
+        ).await.unwrap();
+
+        let applied = applied.expect("Failed to list applied migrations");
+        assert!(applied.contains(&513_001));
+        assert!(!applied.contains(&513_002));
+        let pending: Vec<i32> = pending.expect("Failed to list pending migrations").iter().map(|m| m.version).collect();
+        assert_eq!(pending, vec![513_002]);
+    }
+
+    #[tokio::test]
+    async fn test_edited_migration_fails_checksum() {
+        dotenv().ok();
+        let database_url = env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+        let manager = PostgresConnectionManager::new(database_url);
+        let client = manager.connect().await.expect("Failed to connect to database");
+        client.batch_execute(
+            "DROP TABLE IF EXISTS migration_checksum_a, migration_checksum_b;
+             CREATE TABLE IF NOT EXISTS migrations (version INTEGER PRIMARY KEY);
+             DELETE FROM migrations WHERE version IN (537001, 537002);"
+        ).await.unwrap();
+        let original = vec![Migration::new(537_001, "CREATE TABLE migration_checksum_a (id INTEGER PRIMARY KEY)", "DROP TABLE migration_checksum_a")];
+        let edited = vec![
+            Migration::new(537_001, "CREATE TABLE migration_checksum_a (id BIGINT PRIMARY KEY)", "DROP TABLE migration_checksum_a"),
+            Migration::new(537_002, "CREATE TABLE migration_checksum_b (id INTEGER PRIMARY KEY)", "DROP TABLE migration_checksum_b"),
+        ];
+
+        let first = run_migrations(&client, &original).await.map_err(|e| e.to_string());
+        let rerun = run_migrations(&client, &original).await.map_err(|e| e.to_string());
+        let drifted = run_migrations(&client, &edited).await;
+        let applied = applied_migrations(&client).await.unwrap();
+        client.batch_execute(
+            "DROP TABLE IF EXISTS migration_checksum_a, migration_checksum_b;
+             DELETE FROM migrations WHERE version IN (537001, 537002);"
+        ).await.unwrap();
+
+        assert!(first.is_ok(), "{:?}", first);
+        assert!(rerun.is_ok(), "An unchanged migration should pass its checksum: {:?}", rerun);
+        match drifted.map_err(|e| e.downcast::<OrmError>().map(|e| *e)) {
+            Err(Ok(OrmError::ParseError(message))) => {
+                assert!(message.contains("Migration 537001 was changed after it was applied"), "{}", message);
+                assert!(message.contains(&edited[0].checksum()) && message.contains(&original[0].checksum()), "{}", message);
+            }
+            other => panic!("Expected a checksum mismatch, got {:?}", other),
+        }
+        assert!(!applied.contains(&537_002), "Nothing should be applied after a checksum mismatch");
+    }
+
+    #[tokio::test]
+    async fn test_migration_status_reports_applied_and_pending() {
+        dotenv().ok();
+        let database_url = env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+        let manager = PostgresConnectionManager::new(database_url);
+        let client = manager.connect().await.expect("Failed to connect to database");
+        let migrations = vec![
+            Migration::new(536_001, "CREATE TABLE migration_report_a (id INTEGER PRIMARY KEY)", "DROP TABLE migration_report_a"),
+            Migration::new(536_002, "CREATE TABLE migration_report_b (id INTEGER PRIMARY KEY)", "DROP TABLE migration_report_b").without_transaction(),
+            Migration::new(536_003, "CREATE TABLE migration_report_c (id INTEGER PRIMARY KEY)", "DROP TABLE migration_report_c"),
+        ];
+        client.batch_execute(
+            "DROP TABLE IF EXISTS migration_report_a, migration_report_b, migration_report_c;
+             CREATE TABLE IF NOT EXISTS migrations (version INTEGER PRIMARY KEY);
+             DELETE FROM migrations WHERE version IN (536001, 536002, 536003);"
+        ).await.unwrap();
+
+        let started = Utc::now();
+        run_migrations(&client, &migrations[..2]).await.expect("Failed to run migrations");
+        let status = migration_status(&client, &migrations).await;
+        client.batch_execute(
+            "DROP TABLE IF EXISTS migration_report_a, migration_report_b, migration_report_c;
+             DELETE FROM migrations WHERE version IN (536001, 536002, 536003);"
+        ).await.unwrap();
+
+        let status = status.expect("Failed to get migration status");
+        let flags: Vec<(i32, bool)> = status.iter().map(|status| (status.version, status.applied)).collect();
+        assert_eq!(flags, vec![(536_001, true), (536_002, true), (536_003, false)]);
+        for applied in &status[..2] {
+            let applied_at = applied.applied_at.expect("An applied migration should have applied_at");
+            // Allow for clock differences between the test and the server
+            assert!(applied_at > started - chrono::Duration::seconds(60), "{:?}", applied);
+        }
+        assert_eq!(status[2].applied_at, None);
+        assert!(status.iter().all(|status| !status.dirty));
+    }
+
+    #[tokio::test]
+    async fn test_migration_status_reads_older_and_dirty_tables() {
+        dotenv().ok();
+        let database_url = env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+        let manager = PostgresConnectionManager::new(database_url);
+        let client = manager.connect().await.expect("Failed to connect to database");
+        // A schema of its own, so its migrations table can lack columns
+        // without affecting the other tests
+        client.batch_execute(
+            "DROP SCHEMA IF EXISTS migration_legacy_test CASCADE;
+             CREATE SCHEMA migration_legacy_test;
+             SET search_path TO migration_legacy_test;
+             CREATE TABLE migrations (version INTEGER PRIMARY KEY);
+             INSERT INTO migrations VALUES (1);"
+        ).await.unwrap();
+        let migrations = vec![
+            Migration::new(1, "SELECT 1", "SELECT 1"),
+            Migration::new(2, "SELECT 2", "SELECT 2"),
+        ];
+
+        let legacy = migration_status(&client, &migrations).await;
+        let columns: i64 = client.query_one("SELECT COUNT(*) FROM information_schema.columns WHERE table_schema = 'migration_legacy_test' AND table_name = 'migrations'", &[]).await.unwrap().get(0);
+        client.batch_execute(
+            "ALTER TABLE migrations ADD COLUMN dirty BOOLEAN NOT NULL DEFAULT false;
+             INSERT INTO migrations VALUES (2, true);"
+        ).await.unwrap();
+        let with_dirty = migration_status(&client, &migrations).await;
+        let applied = applied_migrations(&client).await;
+        let pending = pending_migrations(&client, &migrations).await;
+        client.batch_execute("DROP SCHEMA migration_legacy_test CASCADE").await.unwrap();
+
+        let legacy = legacy.expect("Failed to get migration status");
+        assert_eq!(legacy[0], MigrationStatus { version: 1, applied: true, applied_at: None, dirty: false });
+        assert_eq!(legacy[1], MigrationStatus { version: 2, applied: false, applied_at: None, dirty: false });
+        assert_eq!(columns, 1, "migration_status should not add columns");
+        let with_dirty = with_dirty.expect("Failed to get migration status");
+        assert_eq!(with_dirty[1], MigrationStatus { version: 2, applied: false, applied_at: None, dirty: true });
+        assert_eq!(applied.expect("Failed to list applied migrations"), vec![1]);
+        let pending: Vec<i32> = pending.expect("Failed to list pending migrations").iter().map(|m| m.version).collect();
+        assert_eq!(pending, vec![2]);
+    }
+
+    #[tokio::test]
+    async fn test_rollback_migrations_runs_down_to_target() {
+        dotenv().ok();
+        let database_url = env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+        let manager = PostgresConnectionManager::new(database_url);
+        let client = manager.connect().await.expect("Failed to connect to database");
+        client.batch_execute(
+            "DROP TABLE IF EXISTS migration_rollback_a, migration_rollback_b;
+             CREATE TABLE IF NOT EXISTS migrations (version INTEGER PRIMARY KEY);
+             DELETE FROM migrations WHERE version IN (532001, 532002);"
+        ).await.unwrap();
+        let migrations = vec![
+            Migration::new(532_001, "CREATE TABLE migration_rollback_a (id INTEGER PRIMARY KEY)", "DROP TABLE migration_rollback_a"),
+            // The down fails the first time because the view still depends on the table
+            Migration::new(
+                532_002,
+                "CREATE TABLE migration_rollback_b (id INTEGER PRIMARY KEY); CREATE VIEW migration_rollback_v AS SELECT id FROM migration_rollback_b;",
+                "DROP TABLE migration_rollback_b",
+            ),
+        ];
+        let table_exists = |name: &'static str| {
+            let client = &client;
+            async move { client.query_one("SELECT to_regclass($1) IS NOT NULL", &[&name]).await.unwrap().get::<_, bool>(0) }
+        };
+
+        run_migrations(&client, &migrations).await.expect("Failed to run migrations");
+        let failed = rollback_migrations(&client, &migrations, 532_001).await.map_err(|e| e.to_string());
+        let applied_after_failure = applied_migrations(&client).await.unwrap();
+        client.batch_execute("DROP VIEW migration_rollback_v").await.unwrap();
+        let partial = rollback_migrations(&client, &migrations, 532_001).await.map_err(|e| e.to_string());
+        let applied_after_partial = applied_migrations(&client).await.unwrap();
+        let (a_after_partial, b_after_partial) = (table_exists("migration_rollback_a").await, table_exists("migration_rollback_b").await);
+        let full = rollback_migrations(&client, &migrations, 532_000).await.map_err(|e| e.to_string());
+        let applied_after_full = applied_migrations(&client).await.unwrap();
+        let a_after_full = table_exists("migration_rollback_a").await;
+        client.batch_execute(
+            "DROP VIEW IF EXISTS migration_rollback_v;
+             DROP TABLE IF EXISTS migration_rollback_a, migration_rollback_b;
+             DELETE FROM migrations WHERE version IN (532001, 532002);"
+        ).await.unwrap();
+
+        assert!(failed.is_err());
+        assert!(applied_after_failure.contains(&532_002), "A failed down should leave its migration applied");
+        assert!(partial.is_ok(), "{:?}", partial);
+        assert!(applied_after_partial.contains(&532_001));
+        assert!(!applied_after_partial.contains(&532_002));
+        assert!(a_after_partial);
+        assert!(!b_after_partial);
+        assert!(full.is_ok(), "{:?}", full);
+        assert!(!applied_after_full.contains(&532_001));
+        assert!(!a_after_full);
+    }
+
+    #[tokio::test]
+    async fn test_multi_statement_migration() {
+        dotenv().ok();
+        let database_url = env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+        let manager = PostgresConnectionManager::new(database_url);
+        let client = manager.connect().await.expect("Failed to connect to database");
+        client.batch_execute(
+            "DROP TABLE IF EXISTS migration_multi_a;
+             CREATE TABLE IF NOT EXISTS migrations (version INTEGER PRIMARY KEY);
+             DELETE FROM migrations WHERE version = 517001;"
+        ).await.unwrap();
+        let migrations = vec![Migration::new(
+            517_001,
+            "CREATE TABLE migration_multi_a (id INTEGER PRIMARY KEY, note TEXT);
+             INSERT INTO migration_multi_a VALUES (1, 'first; not a separator');",
+            "DROP TABLE migration_multi_a",
+        )];
+
+        let result = run_migrations(&client, &migrations).await.map_err(|e| e.to_string());
+        let notes = client.query("SELECT note FROM migration_multi_a", &[]).await.map(|rows| rows.iter().map(|row| row.get::<_, String>(0)).collect::<Vec<_>>());
+        client.batch_execute(
+            "DROP TABLE IF EXISTS migration_multi_a;
+             DELETE FROM migrations WHERE version = 517001;"
+        ).await.unwrap();
+
+        assert!(result.is_ok(), "{:?}", result);
+        assert_eq!(notes.expect("Both statements should have run"), vec!["first; not a separator".to_string()]);
+    }
+
+    #[tokio::test]
+    async fn test_failed_migration_is_rolled_back() {
+        dotenv().ok();
+        let database_url = env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+        let manager = PostgresConnectionManager::new(database_url);
+        let client = manager.connect().await.expect("Failed to connect to database");
+        client.batch_execute(
+            "DROP TABLE IF EXISTS migration_atomic_a;
+             CREATE TABLE IF NOT EXISTS migrations (version INTEGER PRIMARY KEY);
+             DELETE FROM migrations WHERE version = 516001;"
+        ).await.unwrap();
+        // The second statement fails after the first has created its table
+        let migrations = vec![Migration::new(
+            516_001,
+            "CREATE TABLE migration_atomic_a (id INTEGER PRIMARY KEY); ALTER TABLE migration_atomic_missing ADD COLUMN note TEXT;",
+            "DROP TABLE migration_atomic_a",
+        )];
+
+        let result = run_migrations(&client, &migrations).await;
+        let applied = applied_migrations(&client).await.unwrap();
+        let table_created: bool = client.query_one("SELECT to_regclass('migration_atomic_a') IS NOT NULL", &[]).await.unwrap().get(0);
+        let dirty: i64 = client.query_one("SELECT COUNT(*) FROM migrations WHERE dirty AND version = 516001", &[]).await.unwrap().get(0);
+        client.batch_execute(
+            "DROP TABLE IF EXISTS migration_atomic_a;
+             DELETE FROM migrations WHERE version = 516001;"
+        ).await.unwrap();
+
+        assert!(result.is_err());
+        assert!(!applied.contains(&516_001));
+        assert!(!table_created, "The partially applied DDL should have been rolled back");
+        assert_eq!(dirty, 0);
+    }
+
+    #[tokio::test]
+    async fn test_dirty_migration_blocks_later_runs() {
+        dotenv().ok();
+        let database_url = env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+        let manager = PostgresConnectionManager::new(database_url);
+        let client = manager.connect().await.expect("Failed to connect to database");
+        // Advisory locks are re-entrant, so holding it keeps other tests from
+        // migrating while the dirty record exists
+        client.execute("SELECT pg_advisory_lock($1)", &[&MIGRATION_LOCK_KEY]).await.unwrap();
+        client.batch_execute(
+            "DROP TABLE IF EXISTS migration_dirty_a;
+             CREATE TABLE IF NOT EXISTS migrations (version INTEGER PRIMARY KEY);
+             DELETE FROM migrations WHERE version IN (515001, 515002);"
+        ).await.unwrap();
+        let failing = vec![
+            Migration::new(515_001, "ALTER TABLE migration_dirty_missing ADD COLUMN note TEXT", "ALTER TABLE migration_dirty_missing DROP COLUMN note")
+                .without_transaction(),
+        ];
+        let next = vec![Migration::new(515_002, "CREATE TABLE migration_dirty_a (id INTEGER PRIMARY KEY)", "DROP TABLE migration_dirty_a")];
+
+        let failed = run_migrations(&client, &failing).await.map_err(|e| e.to_string());
+        let blocked = run_migrations(&client, &next).await.map_err(|e| e.to_string());
+        let table_created: bool = client.query_one("SELECT to_regclass('migration_dirty_a') IS NOT NULL", &[]).await.unwrap().get(0);
+        let resolved = resolve_dirty_migration(&client, 515_001, false).await;
+        let resumed = run_migrations(&client, &next).await.map_err(|e| e.to_string());
+        let applied = applied_migrations(&client).await.unwrap();
+        client.batch_execute(
+            "DROP TABLE IF EXISTS migration_dirty_a;
+             DELETE FROM migrations WHERE version IN (515001, 515002);"
+        ).await.unwrap();
+        client.execute("SELECT pg_advisory_unlock($1)", &[&MIGRATION_LOCK_KEY]).await.unwrap();
+
+        assert!(failed.is_err());
+        match blocked {
+            Err(message) => assert!(message.contains("Migration 515001 failed partway and is marked dirty"), "{}", message),
+            Ok(()) => panic!("A dirty migration should block later runs"),
+        }
+        assert!(!table_created);
+        assert!(resolved.is_ok(), "{:?}", resolved);
+        assert!(resumed.is_ok(), "{:?}", resumed);
+        assert!(applied.contains(&515_002) && !applied.contains(&515_001));
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_run_waits_for_lock() {
+        dotenv().ok();
+        let database_url = env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+        let manager = PostgresConnectionManager::new(database_url);
+        let first = manager.connect().await.expect("Failed to connect to database");
+        let second = manager.connect().await.expect("Failed to connect to database");
+        let migrations = vec![Migration::new(514_001, "CREATE TABLE migration_lock_a (id INTEGER PRIMARY KEY)", "DROP TABLE migration_lock_a")];
+        first.batch_execute(
+            "DROP TABLE IF EXISTS migration_lock_a;
+             CREATE TABLE IF NOT EXISTS migrations (version INTEGER PRIMARY KEY);
+             DELETE FROM migrations WHERE version = 514001;"
+        ).await.unwrap();
+
+        // The first process is mid-run while it holds the lock
+        first.execute("SELECT pg_advisory_lock($1)", &[&MIGRATION_LOCK_KEY]).await.unwrap();
+        let mut second_run = std::pin::pin!(run_migrations(&second, &migrations));
+        let blocked = tokio::time::timeout(std::time::Duration::from_millis(300), &mut second_run).await.is_err();
+        let applied_while_blocked = applied_migrations(&first).await.unwrap().contains(&514_001);
+        first.execute("SELECT pg_advisory_unlock($1)", &[&MIGRATION_LOCK_KEY]).await.unwrap();
+        let result = second_run.await;
+        let applied = applied_migrations(&first).await.unwrap().contains(&514_001);
+        first.batch_execute(
+            "DROP TABLE IF EXISTS migration_lock_a;
+             DELETE FROM migrations WHERE version = 514001;"
+        ).await.unwrap();
+
+        assert!(blocked, "The second run should wait while the lock is held");
+        assert!(!applied_while_blocked);
+        assert!(result.is_ok(), "{:?}", result.err());
+        assert!(applied);
+    }
+}
@@ -0,0 +1,75 @@
+use crate::error::OrmError;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+/// One page of a keyset-paginated listing. `next_cursor` is `None` once the
+/// last page has been reached.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    pub next_cursor: Option<String>,
+}
+
+/// Encodes the key of the last row of a page into an opaque, URL-safe token.
+pub fn encode_cursor<K: Serialize>(key: &K) -> String {
+    let json = serde_json::to_vec(key).expect("cursor keys serialize to JSON");
+    URL_SAFE_NO_PAD.encode(json)
+}
+
+/// Decodes a token produced by `encode_cursor` back into the keyset value.
+pub fn decode_cursor<K: DeserializeOwned>(token: &str) -> Result<K, OrmError> {
+    let json = URL_SAFE_NO_PAD
+        .decode(token)
+        .map_err(|e| OrmError::ParseError(format!("Invalid cursor token: {}", e)))?;
+    serde_json::from_slice(&json).map_err(|e| OrmError::ParseError(format!("Invalid cursor token: {}", e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::query_builder::{Model, QueryBuilder};
+    use tokio_postgres::Row;
+
+    struct Customer;
+
+    impl Model for Customer {
+        fn table_name() -> &'static str {
+            "customer"
+        }
+
+        fn columns() -> &'static [&'static str] {
+            &["customer_id", "email"]
+        }
+
+        fn from_row(_row: &Row) -> Self {
+            Customer
+        }
+    }
+
+    #[test]
+    fn test_cursor_round_trip_drives_keyset() {
+        let token = encode_cursor(&42);
+        assert!(token.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_'));
+
+        let after: Option<i32> = Some(decode_cursor(&token).unwrap());
+        assert_eq!(after, Some(42));
+
+        let query_builder = QueryBuilder::select::<Customer>()
+            .keyset_after("customer_id", after)
+            .limit(10);
+        let (query, params) = query_builder.build();
+
+        assert_eq!(query, "SELECT * FROM customer WHERE customer_id > $1 ORDER BY customer_id ASC LIMIT 10");
+        assert_eq!(params.len(), 1);
+    }
+
+    #[test]
+    fn test_invalid_cursor_is_a_parse_error() {
+        match decode_cursor::<i32>("not a cursor!") {
+            Err(OrmError::ParseError(message)) => assert!(message.starts_with("Invalid cursor token")),
+            other => panic!("Expected ParseError, got {:?}", other),
+        }
+    }
+}
@@ -1,10 +1,29 @@
+use std::collections::BTreeSet;
 use std::marker::PhantomData;
 use std::fmt;
-use tokio_postgres::types::ToSql;
+use tokio_postgres::types::{FromSql, ToSql};
+use tokio_postgres::{Client, Row};
+use crate::error::OrmError;
 
 pub trait Model {
     fn table_name() -> &'static str;
     fn columns() -> &'static [&'static str];
+    /// Builds the model from a row holding (at least) its columns.
+    fn from_row(row: &Row) -> Self where Self: Sized;
+
+    /// Like `from_row`, but reports a value that cannot be read (e.g. a NULL
+    /// from an outer join in a non-`Option` field) as an error instead of
+    /// panicking. Defaults to `from_row`.
+    fn try_from_row(row: &Row) -> Result<Self, OrmError> where Self: Sized {
+        Ok(Self::from_row(row))
+    }
+}
+
+/// Reads `column` from `row`, turning a failure such as an unexpected NULL
+/// into a `ParseError` that names the column. Used by `try_from_row`.
+pub fn try_get<'a, T: FromSql<'a>>(row: &'a Row, column: &str) -> Result<T, OrmError> {
+    row.try_get(column)
+        .map_err(|e| OrmError::ParseError(format!("Failed to read column '{}': {}", column, e)))
 }
 
 pub enum JoinType {
@@ -25,6 +44,34 @@ impl fmt::Display for JoinType {
     }
 }
 
+/// The direction of a variant of a generated `{Table}Sort`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SortDirection {
+    #[default]
+    Asc,
+    Desc,
+}
+
+/// A row-locking clause for `Select::lock`, rendered after LIMIT/OFFSET.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LockMode {
+    ForUpdate,
+    ForShare,
+    ForUpdateSkipLocked,
+    ForUpdateNowait,
+}
+
+impl fmt::Display for LockMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LockMode::ForUpdate => write!(f, "FOR UPDATE"),
+            LockMode::ForShare => write!(f, "FOR SHARE"),
+            LockMode::ForUpdateSkipLocked => write!(f, "FOR UPDATE SKIP LOCKED"),
+            LockMode::ForUpdateNowait => write!(f, "FOR UPDATE NOWAIT"),
+        }
+    }
+}
+
 pub enum AggregateFunction {
     Count,
     Sum,
@@ -45,17 +92,70 @@ impl fmt::Display for AggregateFunction {
     }
 }
 
+/// A boolean filter tree. Nested `And`/`Or` groups are parenthesized when
+/// rendered, so `And(vec![Or(vec![a, b]), c])` becomes `(a OR b) AND c`.
+/// An empty `And` is `true` and an empty `Or` is `false`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Condition {
+    And(Vec<Condition>),
+    Or(Vec<Condition>),
+    Raw(String),
+}
+
+impl Condition {
+    pub fn raw(sql: &str) -> Self {
+        Condition::Raw(sql.to_string())
+    }
+
+    fn fmt_joined(f: &mut fmt::Formatter<'_>, conditions: &[Condition], separator: &str) -> fmt::Result {
+        for (i, condition) in conditions.iter().enumerate() {
+            if i > 0 {
+                write!(f, " {} ", separator)?;
+            }
+            match condition {
+                Condition::And(children) | Condition::Or(children) if children.len() > 1 => write!(f, "({})", condition)?,
+                _ => write!(f, "{}", condition)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Condition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Condition::Raw(sql) => write!(f, "{}", sql),
+            Condition::And(children) if children.is_empty() => write!(f, "true"),
+            Condition::Or(children) if children.is_empty() => write!(f, "false"),
+            Condition::And(children) => Condition::fmt_joined(f, children, "AND"),
+            Condition::Or(children) => Condition::fmt_joined(f, children, "OR"),
+        }
+    }
+}
+
 pub struct Select<T: Model> {
     fields: Vec<String>,
     table: String,
     joins: Vec<(JoinType, String, String)>,
-    conditions: Vec<String>,
+    /// Table aliases introduced by `join_as`.
+    aliases: Vec<String>,
+    /// Common table expressions as `(name, subquery)`, rendered as `WITH`.
+    ctes: Vec<(String, String)>,
+    conditions: Vec<Condition>,
     order_by: Vec<String>,
     group_by: Vec<String>,
     having: Vec<String>,
     limit: Option<usize>,
     offset: Option<usize>,
-    params: Vec<Box<dyn ToSql + Sync>>,
+    lock: Option<&'static str>,
+    lock_wait: Option<&'static str>,
+    /// Bound values by placeholder number; `None` marks a number reserved by
+    /// a raw clause whose `bind_param` has not been called yet. They are
+    /// `Send` so a query can be held across an `await` inside a
+    /// `TransactionManager::run` closure.
+    params: Vec<Option<Box<dyn ToSql + Sync + Send>>>,
+    /// Highest placeholder number used so far by any clause.
+    param_index: usize,
     _phantom: PhantomData<T>,
 }
 
@@ -65,24 +165,41 @@ impl<T: Model> Select<T> {
             fields: vec!["*".to_string()],
             table: T::table_name().to_string(),
             joins: Vec::new(),
+            aliases: Vec::new(),
+            ctes: Vec::new(),
             conditions: Vec::new(),
             order_by: Vec::new(),
             group_by: Vec::new(),
             having: Vec::new(),
             limit: None,
             offset: None,
+            lock: None,
+            lock_wait: None,
             params: Vec::new(),
+            param_index: 0,
             _phantom: PhantomData,
         }
     }
 
     pub fn select(mut self, fields: &[&str]) -> Self {
         for field in fields {
-            if !T::columns().contains(field) {
-                panic!("Field '{}' does not exist in table '{}'", field, T::table_name());
-            }
+            self.check_field(field);
+        }
+        self.fields = fields.iter().map(|field| self.quoted_field(field)).collect();
+        self
+    }
+
+    /// Adds a computed column such as `EXTRACT(YEAR FROM create_date)` to the
+    /// SELECT list as `expr AS alias`.
+    ///
+    /// Unlike `select`, the expression is not checked against `T::columns()`
+    /// and is inserted verbatim, so it must never contain user input; bind
+    /// values with `bind_param` instead. The alias must be a plain identifier.
+    pub fn select_expr(mut self, expr: &str, alias: &str) -> Self {
+        if alias.is_empty() || !alias.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+            panic!("Alias '{}' for expression '{}' must be a plain identifier", alias, expr);
         }
-        self.fields = fields.iter().map(|&s| s.to_string()).collect();
+        self.fields.push(format!("{} AS {}", expr, alias));
         self
     }
 
@@ -91,35 +208,155 @@ impl<T: Model> Select<T> {
         self
     }
 
-    pub fn where_clause(mut self, condition: &str) -> Self {
-        self.conditions.push(condition.to_string());
+    /// Joins `table AS alias`, e.g. to join the same table twice. Columns
+    /// qualified with the alias (`alias.column`) are then accepted by
+    /// `select`, `order_by` and the other column-taking methods without being
+    /// checked, since the joined table's columns are not known here.
+    pub fn join_as(mut self, join_type: JoinType, table: &str, alias: &str, condition: &str) -> Self {
+        if alias.is_empty() || !alias.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+            panic!("Alias '{}' for table '{}' must be a plain identifier", alias, table);
+        }
+        self.joins.push((join_type, format!("{} AS {}", table, alias), condition.to_string()));
+        self.aliases.push(alias.to_string());
         self
     }
 
-    pub fn order_by(mut self, field: &str, asc: bool) -> Self {
-        if !T::columns().contains(&field) {
-            panic!("Field '{}' does not exist in table '{}'", field, T::table_name());
+    /// Adds a common table expression, rendered as `WITH name AS (subquery)`
+    /// before the main `SELECT` and usable in `join` like a table. Placeholders
+    /// in `subquery` are numbered by hand, as with `where_clause`.
+    pub fn with(mut self, name: &str, subquery: &str) -> Self {
+        if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+            panic!("CTE name '{}' must be a plain identifier", name);
+        }
+        self.param_index = self.param_index.max(placeholders(subquery).last().copied().unwrap_or(0));
+        self.ctes.push((name.to_string(), subquery.to_string()));
+        self
+    }
+
+    /// Like `with`, but takes a built `Select` whose bound values are moved
+    /// into this query, with its placeholders renumbered after the ones used
+    /// so far.
+    pub fn with_select<U: Model>(self, name: &str, select: Select<U>) -> Self {
+        let subquery = match select.try_build() {
+            Ok((subquery, _)) => subquery,
+            Err(e) => panic!("{}", e),
+        };
+        let offset = self.param_index.max(self.params.len());
+        let mut query = self.with(name, &renumber_placeholders(&subquery, offset));
+        query.params.resize_with(offset, || None);
+        query.params.extend(select.params);
+        query
+    }
+
+    pub fn where_clause(self, condition: &str) -> Self {
+        self.filter(Condition::raw(condition))
+    }
+
+    /// Adds a condition tree, ANDed with the other conditions. Placeholders
+    /// in it are numbered by hand, as with `where_clause`.
+    pub fn filter(mut self, condition: Condition) -> Self {
+        self.param_index = self.param_index.max(placeholders(&condition.to_string()).last().copied().unwrap_or(0));
+        self.conditions.push(condition);
+        self
+    }
+
+    /// Appends `field = $N` and binds `value` to it, numbering the placeholder
+    /// after every placeholder used so far, so it can be mixed freely with
+    /// raw `where_clause`/`having` conditions and `bind_param`. The column is
+    /// quoted when needed, so e.g. `zip code` can be compared.
+    pub fn where_eq<P: ToSql + Sync + Send + 'static>(mut self, field: &str, value: P) -> Self {
+        self.check_field(field);
+        let index = self.push_numbered_param(value);
+        self.conditions.push(Condition::Raw(format!("{} = ${}", self.quoted_field(field), index)));
+        self
+    }
+
+    /// Like `where_eq`, but only adds the condition when `value` is `Some`,
+    /// so optional filters can be chained without branching. The field is
+    /// checked either way.
+    pub fn where_eq_opt<P: ToSql + Sync + Send + 'static>(self, field: &str, value: Option<P>) -> Self {
+        self.check_field(field);
+        match value {
+            Some(value) => self.where_eq(field, value),
+            None => self,
         }
+    }
+
+    /// Appends `field IN ($N, ...)` with one auto-numbered placeholder per
+    /// value. An empty list matches no rows and renders as `false`.
+    pub fn where_in<P: ToSql + Sync + Send + 'static>(mut self, field: &str, values: Vec<P>) -> Self {
+        self.check_field(field);
+        let indices: Vec<usize> = values.into_iter().map(|value| self.push_numbered_param(value)).collect();
+        self.conditions.push(Condition::Raw(in_list(&self.quoted_field(field), &indices)));
+        self
+    }
+
+    /// Appends `field ILIKE $N`, a case-insensitive `LIKE`; `%` and `_` in
+    /// `pattern` are wildcards.
+    pub fn where_ilike(mut self, field: &str, pattern: &str) -> Self {
+        self.check_field(field);
+        let index = self.push_numbered_param(pattern.to_string());
+        self.conditions.push(Condition::Raw(format!("{} ILIKE ${}", self.quoted_field(field), index)));
+        self
+    }
+
+    /// Appends a full-text match of the text in `field` against `query`,
+    /// which is read as plain words (all must match) rather than tsquery
+    /// syntax. Both sides use the server's `default_text_search_config`.
+    pub fn where_text_search(mut self, field: &str, query: &str) -> Self {
+        self.check_field(field);
+        let index = self.push_numbered_param(query.to_string());
+        self.conditions.push(Condition::Raw(format!("to_tsvector({}) @@ plainto_tsquery(${})", self.quoted_field(field), index)));
+        self
+    }
+
+    pub fn order_by(mut self, field: &str, asc: bool) -> Self {
+        self.check_field(field);
         let direction = if asc { "ASC" } else { "DESC" };
-        self.order_by.push(format!("{} {}", field, direction));
+        self.order_by.push(format!("{} {}", self.quoted_field(field), direction));
         self
     }
 
     pub fn group_by(mut self, fields: &[&str]) -> Self {
         for field in fields {
-            if !T::columns().contains(field) {
-                panic!("Field '{}' does not exist in table '{}'", field, T::table_name());
-            }
+            self.check_field(field);
         }
-        self.group_by.extend(fields.iter().map(|&s| s.to_string()));
+        let fields: Vec<String> = fields.iter().map(|field| self.quoted_field(field)).collect();
+        self.group_by.extend(fields);
         self
     }
 
     pub fn having(mut self, condition: &str) -> Self {
+        self.param_index = self.param_index.max(placeholders(condition).last().copied().unwrap_or(0));
         self.having.push(condition.to_string());
         self
     }
 
+    /// Keyset pagination: orders by `field` and, when `after` is given, only
+    /// returns rows whose `field` is greater than it. Unlike `offset`, later
+    /// pages cost the same as the first one.
+    pub fn keyset_after<P: ToSql + Sync + Send + 'static>(mut self, field: &str, after: Option<P>) -> Self {
+        self.check_field(field);
+        if let Some(after) = after {
+            let index = self.push_numbered_param(after);
+            self.conditions.push(Condition::Raw(format!("{} > ${}", self.quoted_field(field), index)));
+        }
+        self.order_by(field, true)
+    }
+
+    /// Appends `key` as the last sort column unless the order already uses
+    /// it, so rows that tie on a non-unique column still come back in the
+    /// same order and `limit`/`offset` pages neither skip nor repeat rows.
+    pub fn tiebreak_by(self, key: &str) -> Self {
+        let quoted_key = self.quoted_field(key);
+        let already_ordered = self.order_by.iter().any(|order| order.rsplit_once(' ').is_some_and(|(field, _)| field == quoted_key));
+        if already_ordered {
+            self
+        } else {
+            self.order_by(key, true)
+        }
+    }
+
     pub fn limit(mut self, limit: usize) -> Self {
         self.limit = Some(limit);
         self
@@ -130,10 +367,47 @@ impl<T: Model> Select<T> {
         self
     }
 
+    /// Locks the selected rows, e.g. for a read-modify-write inside a
+    /// `TransactionManager` transaction. Replaces any earlier lock setting.
+    pub fn lock(mut self, mode: LockMode) -> Self {
+        let (lock, lock_wait) = match mode {
+            LockMode::ForUpdate => ("FOR UPDATE", None),
+            LockMode::ForShare => ("FOR SHARE", None),
+            LockMode::ForUpdateSkipLocked => ("FOR UPDATE", Some("SKIP LOCKED")),
+            LockMode::ForUpdateNowait => ("FOR UPDATE", Some("NOWAIT")),
+        };
+        self.lock = Some(lock);
+        self.lock_wait = lock_wait;
+        self
+    }
+
+    pub fn for_update(mut self) -> Self {
+        self.lock = Some("FOR UPDATE");
+        self
+    }
+
+    pub fn for_share(mut self) -> Self {
+        self.lock = Some("FOR SHARE");
+        self
+    }
+
+    /// Skips rows locked by other transactions. Only rendered together with
+    /// `for_update` or `for_share`.
+    pub fn skip_locked(mut self) -> Self {
+        self.lock_wait = Some("SKIP LOCKED");
+        self
+    }
+
+    /// Fails immediately instead of waiting on rows locked by other
+    /// transactions. Only rendered together with `for_update` or `for_share`.
+    pub fn nowait(mut self) -> Self {
+        self.lock_wait = Some("NOWAIT");
+        self
+    }
+
     pub fn aggregate(mut self, function: AggregateFunction, field: &str, alias: Option<&str>) -> Self {
-        if !T::columns().contains(&field) {
-            panic!("Field '{}' does not exist in table '{}'", field, T::table_name());
-        }
+        self.check_field(field);
+        let field = self.quoted_field(field);
         let agg_field = match alias {
             Some(a) => format!("{}({}) AS {}", function, field, a),
             None => format!("{}({})", function, field),
@@ -142,20 +416,97 @@ impl<T: Model> Select<T> {
         self
     }
 
-    pub fn bind_param<P: ToSql + Sync + 'static>(mut self, param: P) -> Self {
-        self.params.push(Box::new(param));
+    /// Adds `function(field) OVER (PARTITION BY ... ORDER BY ... frame) AS alias`
+    /// to the SELECT list. `frame` is an optional frame clause such as
+    /// `ROWS UNBOUNDED PRECEDING`.
+    pub fn window(mut self, function: AggregateFunction, field: &str, partition_by: &[&str], order_by: &[&str], frame: Option<&str>, alias: &str) -> Self {
+        for column in std::iter::once(&field).chain(partition_by).chain(order_by) {
+            self.check_field(column);
+        }
+        let quoted = |columns: &[&str]| columns.iter().map(|column| self.quoted_field(column)).collect::<Vec<_>>().join(", ");
+        let mut over = Vec::new();
+        if !partition_by.is_empty() {
+            over.push(format!("PARTITION BY {}", quoted(partition_by)));
+        }
+        if !order_by.is_empty() {
+            over.push(format!("ORDER BY {}", quoted(order_by)));
+        }
+        if let Some(frame) = frame {
+            over.push(frame.to_string());
+        }
+        let field = self.quoted_field(field);
+        self.fields.push(format!("{}({}) OVER ({}) AS {}", function, field, over.join(" "), alias));
         self
     }
 
-    pub fn build(&self) -> (String, Vec<&(dyn ToSql + Sync)>) {
-        let mut query = format!("SELECT {} FROM {}", self.fields.join(", "), self.table);
+    /// Adds a cumulative `SUM(field)` that restarts for each `partition_by`
+    /// group and accumulates in `order_by` order.
+    pub fn running_sum(self, field: &str, partition_by: &[&str], order_by: &[&str], alias: &str) -> Self {
+        self.window(AggregateFunction::Sum, field, partition_by, order_by, Some("ROWS UNBOUNDED PRECEDING"), alias)
+    }
+
+    /// Binds the value of the next raw placeholder that has none yet.
+    pub fn bind_param<P: ToSql + Sync + Send + 'static>(mut self, param: P) -> Self {
+        match self.params.iter_mut().find(|slot| slot.is_none()) {
+            Some(slot) => *slot = Some(Box::new(param)),
+            None => self.params.push(Some(Box::new(param))),
+        }
+        self
+    }
+
+    /// Binds `param` to a fresh placeholder numbered after everything used
+    /// or bound so far, and returns that number.
+    fn push_numbered_param<P: ToSql + Sync + Send + 'static>(&mut self, param: P) -> usize {
+        let index = self.param_index.max(self.params.len()) + 1;
+        self.params.resize_with(index - 1, || None);
+        self.params.push(Some(Box::new(param)));
+        self.param_index = index;
+        index
+    }
+
+    /// Panics unless `field` is a column of `T`, optionally qualified with
+    /// its table name, or is qualified with a `join_as` alias.
+    fn check_field(&self, field: &str) {
+        let known = match field.split_once('.') {
+            Some((qualifier, column)) => {
+                self.aliases.iter().any(|alias| alias == qualifier)
+                    || (qualifier == T::table_name() && T::columns().contains(&column))
+            }
+            None => T::columns().contains(&field),
+        };
+        if !known {
+            panic!("Field '{}' does not exist in table '{}'", field, T::table_name());
+        }
+    }
+
+    /// Quotes a `field` accepted by `check_field`. A `join_as` alias is left
+    /// as written, since it was declared unquoted.
+    fn quoted_field(&self, field: &str) -> String {
+        match field.split_once('.') {
+            Some((alias, column)) if self.aliases.iter().any(|known| known == alias) => format!("{}.{}", alias, quote_identifier(column)),
+            Some((table, column)) if table == T::table_name() => format!("{}.{}", quote_identifier(table), quote_identifier(column)),
+            _ => quote_identifier(field),
+        }
+    }
+
+    /// Builds the statement, rejecting placeholders that do not line up with
+    /// the bound parameters.
+    pub fn try_build(&self) -> Result<(String, Vec<&(dyn ToSql + Sync)>), OrmError> {
+        let mut query = String::new();
+        if !self.ctes.is_empty() {
+            let ctes: Vec<String> = self.ctes.iter().map(|(name, subquery)| format!("{} AS ({})", name, subquery)).collect();
+            query += &format!("WITH {} ", ctes.join(", "));
+        }
+        query += &format!("SELECT {} FROM {}", self.fields.join(", "), quote_identifier(&self.table));
 
         for (join_type, table, condition) in &self.joins {
             query += &format!(" {} {} ON {}", join_type, table, condition);
         }
 
-        if !self.conditions.is_empty() {
-            query += &format!(" WHERE {}", self.conditions.join(" AND "));
+        match self.conditions.as_slice() {
+            [] => {}
+            [condition] => query += &format!(" WHERE {}", condition),
+            conditions => query += &format!(" WHERE {}", Condition::And(conditions.to_vec())),
         }
 
         if !self.group_by.is_empty() {
@@ -178,56 +529,1395 @@ impl<T: Model> Select<T> {
             query += &format!(" OFFSET {}", offset);
         }
 
-        let params: Vec<&(dyn ToSql + Sync)> = self.params.iter().map(|p| p.as_ref()).collect();
-        (query, params)
+        if let Some(lock) = self.lock {
+            query += &format!(" {}", lock);
+            if let Some(lock_wait) = self.lock_wait {
+                query += &format!(" {}", lock_wait);
+            }
+        }
+
+        check_placeholders(&query, self.params.len())?;
+        let params = self.params.iter().enumerate()
+            .map(|(i, param)| param.as_deref().map(|p| p as &(dyn ToSql + Sync)).ok_or_else(|| OrmError::QueryError(format!(
+                "Placeholder ${} in '{}' has no value; call bind_param for it", i + 1, query
+            ))))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok((query, params))
+    }
+
+    /// Like `try_build`, but panics on an invalid statement.
+    pub fn build(&self) -> (String, Vec<&(dyn ToSql + Sync)>) {
+        match self.try_build() {
+            Ok(built) => built,
+            Err(e) => panic!("{}", e),
+        }
+    }
+
+    /// Runs the query and maps every row with `T::try_from_row`.
+    pub async fn fetch_all(self, client: &Client) -> Result<Vec<T>, OrmError> {
+        let (query, params) = self.try_build()?;
+        let rows = client.query(&query, &params[..]).await?;
+        rows.iter().map(T::try_from_row).collect()
+    }
+
+    /// Runs the query and maps its single row with `T::try_from_row`. Fails
+    /// if the query returns no rows or more than one.
+    pub async fn fetch_one(self, client: &Client) -> Result<T, OrmError> {
+        let (query, params) = self.try_build()?;
+        let row = client.query_one(&query, &params[..]).await?;
+        T::try_from_row(&row)
+    }
+
+    /// Runs the query and maps its row, if it returns one, with
+    /// `T::try_from_row`. Fails if the query returns more than one row.
+    pub async fn fetch_optional(self, client: &Client) -> Result<Option<T>, OrmError> {
+        let (query, params) = self.try_build()?;
+        let row = client.query_opt(&query, &params[..]).await?;
+        row.as_ref().map(T::try_from_row).transpose()
     }
 }
 
-pub struct QueryBuilder;
+impl<T: Model> Default for Select<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
 
-impl QueryBuilder {
-    pub fn select<T: Model>() -> Select<T> {
-        Select::new()
+pub struct Update<'a, T: Model> {
+    table: String,
+    assignments: Vec<(String, &'a (dyn ToSql + Sync))>,
+    conditions: Vec<String>,
+    params: Vec<Box<dyn ToSql + Sync>>,
+    returning: Vec<String>,
+    _phantom: PhantomData<T>,
+}
+
+impl<'a, T: Model> Update<'a, T> {
+    pub fn new() -> Self {
+        Update {
+            table: T::table_name().to_string(),
+            assignments: Vec::new(),
+            conditions: Vec::new(),
+            params: Vec::new(),
+            returning: Vec::new(),
+            _phantom: PhantomData,
+        }
+    }
+
+    pub fn set_values(mut self, values: &[(&str, &'a (dyn ToSql + Sync))]) -> Self {
+        for (field, _) in values {
+            if !T::columns().contains(field) {
+                panic!("Field '{}' does not exist in table '{}'", field, T::table_name());
+            }
+        }
+        self.assignments.extend(values.iter().map(|&(field, value)| (field.to_string(), value)));
+        self
+    }
+
+    /// Adds a raw condition. Placeholders are numbered from `$1` relative to
+    /// `bind_param` calls; they are shifted past the SET values when built.
+    pub fn where_clause(mut self, condition: &str) -> Self {
+        self.conditions.push(condition.to_string());
+        self
+    }
+
+    pub fn bind_param<P: ToSql + Sync + 'static>(mut self, param: P) -> Self {
+        self.params.push(Box::new(param));
+        self
+    }
+
+    /// Appends `field IN ($N, ...)`, numbering the placeholders after the
+    /// values bound so far. An empty list matches no rows and renders as `false`.
+    pub fn where_in<P: ToSql + Sync + 'static>(mut self, field: &str, values: Vec<P>) -> Self {
+        if !T::columns().contains(&field) {
+            panic!("Field '{}' does not exist in table '{}'", field, T::table_name());
+        }
+        let first = self.params.len() + 1;
+        let indices: Vec<usize> = (first..first + values.len()).collect();
+        self.params.extend(values.into_iter().map(|value| Box::new(value) as Box<dyn ToSql + Sync>));
+        self.conditions.push(in_list(&quote_identifier(field), &indices));
+        self
+    }
+
+    /// Appends `RETURNING` with `fields`, so the affected rows come back
+    /// without a second query.
+    pub fn returning(mut self, fields: &[&str]) -> Self {
+        for field in fields {
+            if !T::columns().contains(field) {
+                panic!("Field '{}' does not exist in table '{}'", field, T::table_name());
+            }
+        }
+        self.returning.extend(fields.iter().map(|field| field.to_string()));
+        self
+    }
+
+    /// Builds the statement, rejecting an empty SET list instead of emitting
+    /// invalid SQL.
+    pub fn try_build(&self) -> Result<(String, Vec<&(dyn ToSql + Sync)>), OrmError> {
+        if self.assignments.is_empty() {
+            return Err(OrmError::QueryError(format!(
+                "UPDATE on table '{}' has no values to set; call set_values with at least one column",
+                self.table
+            )));
+        }
+
+        let set_list: Vec<String> = self.assignments.iter().enumerate()
+            .map(|(i, (field, _))| format!("{} = ${}", quote_identifier(field), i + 1))
+            .collect();
+        let mut query = format!("UPDATE {} SET {}", quote_identifier(&self.table), set_list.join(", "));
+
+        if !self.conditions.is_empty() {
+            let conditions: Vec<String> = self.conditions.iter()
+                .map(|c| renumber_placeholders(c, self.assignments.len()))
+                .collect();
+            query += &format!(" WHERE {}", conditions.join(" AND "));
+        }
+
+        if !self.returning.is_empty() {
+            query += &format!(" RETURNING {}", quote_identifiers(&self.returning));
+        }
+
+        let mut params: Vec<&(dyn ToSql + Sync)> = self.assignments.iter().map(|(_, value)| *value).collect();
+        params.extend(self.params.iter().map(|p| p.as_ref() as &(dyn ToSql + Sync)));
+        check_placeholders(&query, params.len())?;
+        Ok((query, params))
+    }
+
+    /// Like `try_build`, but panics on an invalid statement, matching the
+    /// validation style of `Select`.
+    pub fn build(&self) -> (String, Vec<&(dyn ToSql + Sync)>) {
+        match self.try_build() {
+            Ok(built) => built,
+            Err(e) => panic!("{}", e),
+        }
     }
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+impl<T: Model> Default for Update<'_, T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
 
-    struct TestModel;
+/// What an `Insert` does when a row conflicts with an existing one.
+enum ConflictAction<'a> {
+    Nothing,
+    Update(Vec<(String, &'a (dyn ToSql + Sync))>),
+}
 
-    impl Model for TestModel {
-        fn table_name() -> &'static str {
-            "users"
+pub struct Insert<'a, T: Model> {
+    table: String,
+    columns: Vec<String>,
+    values: Vec<&'a (dyn ToSql + Sync)>,
+    conflict_target: Vec<String>,
+    conflict_action: Option<ConflictAction<'a>>,
+    returning: Vec<String>,
+    _phantom: PhantomData<T>,
+}
+
+impl<'a, T: Model> Insert<'a, T> {
+    pub fn new() -> Self {
+        Insert {
+            table: T::table_name().to_string(),
+            columns: T::columns().iter().map(|&c| c.to_string()).collect(),
+            values: Vec::new(),
+            conflict_target: Vec::new(),
+            conflict_action: None,
+            returning: Vec::new(),
+            _phantom: PhantomData,
         }
+    }
 
-        fn columns() -> &'static [&'static str] {
-            &["id", "name", "email", "age"]
+    /// Sets the columns `values` are inserted into. Defaults to all of
+    /// `T::columns()`, in that order.
+    pub fn columns(mut self, columns: &[&str]) -> Self {
+        for column in columns {
+            if !T::columns().contains(column) {
+                panic!("Field '{}' does not exist in table '{}'", column, T::table_name());
+            }
         }
+        self.columns = columns.iter().map(|&c| c.to_string()).collect();
+        self
     }
 
-    #[test]
-    fn test_select_query_builder() {
-        let query_builder = QueryBuilder::select::<TestModel>()
-            .select(&["name", "email"])
-            .join(JoinType::Inner, "orders", "users.id = orders.user_id")
-            .where_clause("age > $1")
-            .group_by(&["name", "email"])
-            .having("COUNT(orders.id) > $2")
-            .order_by("name", true)
-            .limit(10)
-            .offset(5)
-            .aggregate(AggregateFunction::Count, "id", Some("user_count"))
-            .bind_param(18)
-            .bind_param(5);
+    /// Binds one value per column; placeholders are numbered in this order.
+    pub fn values(mut self, values: &[&'a (dyn ToSql + Sync)]) -> Self {
+        self.values.extend_from_slice(values);
+        self
+    }
 
-        let (query, params) = query_builder.build();
+    /// Sets the conflict target of an `ON CONFLICT` clause, i.e. the columns
+    /// of the unique key that may conflict. Follow it with `do_nothing` or
+    /// `do_update_set`.
+    pub fn on_conflict(mut self, columns: &[&str]) -> Self {
+        for column in columns {
+            if !T::columns().contains(column) {
+                panic!("Field '{}' does not exist in table '{}'", column, T::table_name());
+            }
+        }
+        self.conflict_target = columns.iter().map(|&c| c.to_string()).collect();
+        self
+    }
 
-        assert_eq!(
-            query,
-            "SELECT name, email, COUNT(id) AS user_count FROM users INNER JOIN orders ON users.id = orders.user_id WHERE age > $1 GROUP BY name, email HAVING COUNT(orders.id) > $2 ORDER BY name ASC LIMIT 10 OFFSET 5"
-        );
-        assert_eq!(params.len(), 2);
-    }   
+    /// Skips rows that conflict instead of failing.
+    pub fn do_nothing(mut self) -> Self {
+        self.conflict_action = Some(ConflictAction::Nothing);
+        self
+    }
+
+    /// Updates the conflicting row instead. Placeholders for these values are
+    /// numbered on from the VALUES clause.
+    pub fn do_update_set(mut self, values: &[(&str, &'a (dyn ToSql + Sync))]) -> Self {
+        for (field, _) in values {
+            if !T::columns().contains(field) {
+                panic!("Field '{}' does not exist in table '{}'", field, T::table_name());
+            }
+        }
+        let assignments = values.iter().map(|&(field, value)| (field.to_string(), value));
+        match &mut self.conflict_action {
+            Some(ConflictAction::Update(existing)) => existing.extend(assignments),
+            _ => self.conflict_action = Some(ConflictAction::Update(assignments.collect())),
+        }
+        self
+    }
+
+    pub fn returning(mut self, fields: &[&str]) -> Self {
+        for field in fields {
+            if !T::columns().contains(field) {
+                panic!("Field '{}' does not exist in table '{}'", field, T::table_name());
+            }
+        }
+        self.returning.extend(fields.iter().map(|&s| s.to_string()));
+        self
+    }
+
+    /// Builds the statement, rejecting a missing or mismatched value list
+    /// instead of emitting invalid SQL.
+    pub fn try_build(&self) -> Result<(String, Vec<&'a (dyn ToSql + Sync)>), OrmError> {
+        if self.values.is_empty() {
+            return Err(OrmError::QueryError(format!(
+                "INSERT into table '{}' has no values; call values with one value per column",
+                self.table
+            )));
+        }
+        if self.values.len() != self.columns.len() {
+            return Err(OrmError::QueryError(format!(
+                "INSERT into table '{}' has {} value(s) for {} column(s) ({})",
+                self.table,
+                self.values.len(),
+                self.columns.len(),
+                self.columns.join(", ")
+            )));
+        }
+
+        let placeholders: Vec<String> = (1..=self.values.len()).map(|i| format!("${}", i)).collect();
+        let mut query = format!("INSERT INTO {} ({}) VALUES ({})", quote_identifier(&self.table), quote_identifiers(&self.columns), placeholders.join(", "));
+        let mut params = self.values.clone();
+
+        let target = match self.conflict_target.as_slice() {
+            [] => String::new(),
+            columns => format!(" ({})", quote_identifiers(columns)),
+        };
+        match &self.conflict_action {
+            None if !self.conflict_target.is_empty() => {
+                return Err(OrmError::QueryError(format!(
+                    "INSERT into table '{}' has an ON CONFLICT target but no action; call do_nothing or do_update_set",
+                    self.table
+                )));
+            }
+            None => {}
+            Some(ConflictAction::Nothing) => query += &format!(" ON CONFLICT{} DO NOTHING", target),
+            Some(ConflictAction::Update(_)) if self.conflict_target.is_empty() => {
+                return Err(OrmError::QueryError(format!(
+                    "ON CONFLICT DO UPDATE on table '{}' needs a conflict target; call on_conflict with the unique key columns",
+                    self.table
+                )));
+            }
+            Some(ConflictAction::Update(assignments)) => {
+                let set_list: Vec<String> = assignments.iter().enumerate()
+                    .map(|(i, (field, _))| format!("{} = ${}", quote_identifier(field), params.len() + i + 1))
+                    .collect();
+                query += &format!(" ON CONFLICT{} DO UPDATE SET {}", target, set_list.join(", "));
+                params.extend(assignments.iter().map(|(_, value)| *value));
+            }
+        }
+
+        if !self.returning.is_empty() {
+            query += &format!(" RETURNING {}", quote_identifiers(&self.returning));
+        }
+
+        Ok((query, params))
+    }
+
+    /// Like `try_build`, but panics on an invalid statement.
+    pub fn build(&self) -> (String, Vec<&'a (dyn ToSql + Sync)>) {
+        match self.try_build() {
+            Ok(built) => built,
+            Err(e) => panic!("{}", e),
+        }
+    }
+}
+
+impl<T: Model> Default for Insert<'_, T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+pub struct Delete<T: Model> {
+    table: String,
+    conditions: Vec<String>,
+    params: Vec<Box<dyn ToSql + Sync>>,
+    returning: Vec<String>,
+    _phantom: PhantomData<T>,
+}
+
+impl<T: Model> Delete<T> {
+    pub fn new() -> Self {
+        Delete {
+            table: T::table_name().to_string(),
+            conditions: Vec::new(),
+            params: Vec::new(),
+            returning: Vec::new(),
+            _phantom: PhantomData,
+        }
+    }
+
+    pub fn where_clause(mut self, condition: &str) -> Self {
+        self.conditions.push(condition.to_string());
+        self
+    }
+
+    pub fn bind_param<P: ToSql + Sync + 'static>(mut self, param: P) -> Self {
+        self.params.push(Box::new(param));
+        self
+    }
+
+    /// Appends `field IN ($N, ...)`, numbering the placeholders after the
+    /// values bound so far. An empty list matches no rows and renders as `false`.
+    pub fn where_in<P: ToSql + Sync + 'static>(mut self, field: &str, values: Vec<P>) -> Self {
+        if !T::columns().contains(&field) {
+            panic!("Field '{}' does not exist in table '{}'", field, T::table_name());
+        }
+        let first = self.params.len() + 1;
+        let indices: Vec<usize> = (first..first + values.len()).collect();
+        self.params.extend(values.into_iter().map(|value| Box::new(value) as Box<dyn ToSql + Sync>));
+        self.conditions.push(in_list(&quote_identifier(field), &indices));
+        self
+    }
+
+    /// Appends `RETURNING` with `fields`, so the affected rows come back
+    /// without a second query.
+    pub fn returning(mut self, fields: &[&str]) -> Self {
+        for field in fields {
+            if !T::columns().contains(field) {
+                panic!("Field '{}' does not exist in table '{}'", field, T::table_name());
+            }
+        }
+        self.returning.extend(fields.iter().map(|field| field.to_string()));
+        self
+    }
+
+    /// Builds the statement, refusing a DELETE without a WHERE clause so a
+    /// forgotten condition cannot empty the table. Use `build_unchecked` to
+    /// delete every row on purpose.
+    pub fn try_build(&self) -> Result<(String, Vec<&(dyn ToSql + Sync)>), OrmError> {
+        if self.conditions.is_empty() {
+            return Err(OrmError::QueryError(format!(
+                "DELETE on table '{}' has no WHERE clause and would delete every row; add a where_clause or use build_unchecked",
+                self.table
+            )));
+        }
+        self.build_unchecked()
+    }
+
+    /// Like `try_build`, but panics on an invalid statement.
+    pub fn build(&self) -> (String, Vec<&(dyn ToSql + Sync)>) {
+        match self.try_build() {
+            Ok(built) => built,
+            Err(e) => panic!("{}", e),
+        }
+    }
+
+    /// Builds the statement even without a WHERE clause.
+    pub fn build_unchecked(&self) -> Result<(String, Vec<&(dyn ToSql + Sync)>), OrmError> {
+        let mut query = format!("DELETE FROM {}", quote_identifier(&self.table));
+
+        if !self.conditions.is_empty() {
+            query += &format!(" WHERE {}", self.conditions.join(" AND "));
+        }
+
+        if !self.returning.is_empty() {
+            query += &format!(" RETURNING {}", quote_identifiers(&self.returning));
+        }
+
+        check_placeholders(&query, self.params.len())?;
+        let params: Vec<&(dyn ToSql + Sync)> = self.params.iter().map(|p| p.as_ref()).collect();
+        Ok((query, params))
+    }
+}
+
+impl<T: Model> Default for Delete<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Checks that the `$N` placeholders in `query` are exactly `$1` to
+/// `$param_count`, so a missing or extra `bind_param` is caught before the
+/// statement reaches the server. Quoted literals are ignored.
+fn check_placeholders(query: &str, param_count: usize) -> Result<(), OrmError> {
+    let used = placeholders(query);
+    if used.len() == param_count && used.iter().copied().eq(1..=param_count) {
+        return Ok(());
+    }
+    let numbered: Vec<String> = used.iter().map(|n| format!("${}", n)).collect();
+    Err(OrmError::QueryError(format!(
+        "Placeholder mismatch in '{}': it uses {} distinct placeholder(s) [{}] but {} parameter(s) were bound; \
+         placeholders must run from $1 to ${} with one bind_param each",
+        query,
+        used.len(),
+        numbered.join(", "),
+        param_count,
+        param_count
+    )))
+}
+
+/// The distinct `$N` placeholder numbers in `sql`, ignoring quoted literals.
+fn placeholders(sql: &str) -> BTreeSet<usize> {
+    let mut used = BTreeSet::new();
+    let mut in_literal = false;
+    let mut chars = sql.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\'' => in_literal = !in_literal,
+            '$' if !in_literal => {
+                let mut digits = String::new();
+                while let Some(d) = chars.peek().filter(|d| d.is_ascii_digit()) {
+                    digits.push(*d);
+                    chars.next();
+                }
+                if let Ok(n) = digits.parse::<usize>() {
+                    used.insert(n);
+                }
+            }
+            _ => {}
+        }
+    }
+    used
+}
+
+/// Renders `field IN ($a, $b, ...)`, or `false` for an empty list since
+/// `IN ()` is not valid SQL.
+fn in_list(field: &str, indices: &[usize]) -> String {
+    if indices.is_empty() {
+        return "false".to_string();
+    }
+    let placeholders: Vec<String> = indices.iter().map(|i| format!("${}", i)).collect();
+    format!("{} IN ({})", field, placeholders.join(", "))
+}
+
+/// Double-quotes `name` unless it is a plain lowercase identifier, which
+/// Postgres would read the same way unquoted. Used for every table and
+/// column name this crate writes into SQL, including generated code.
+pub(crate) fn quote_identifier(name: &str) -> String {
+    let is_plain = name.chars().next().is_some_and(|c| c.is_ascii_lowercase() || c == '_')
+        && name.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_');
+    if is_plain {
+        name.to_string()
+    } else {
+        format!("\"{}\"", name.replace('"', "\"\""))
+    }
+}
+
+/// Quotes each of `names` and joins them into a column list.
+fn quote_identifiers(names: &[String]) -> String {
+    names.iter().map(|name| quote_identifier(name)).collect::<Vec<_>>().join(", ")
+}
+
+/// Shifts every `$N` placeholder in `sql` by `offset`, leaving quoted
+/// literals alone like `placeholders` does. An escaped `''` toggles the
+/// literal off and straight back on.
+fn renumber_placeholders(sql: &str, offset: usize) -> String {
+    let mut result = String::with_capacity(sql.len());
+    let mut in_literal = false;
+    let mut chars = sql.chars().peekable();
+    while let Some(c) = chars.next() {
+        result.push(c);
+        if c == '\'' {
+            in_literal = !in_literal;
+        } else if c == '$' && !in_literal {
+            let mut digits = String::new();
+            while let Some(d) = chars.peek().filter(|d| d.is_ascii_digit()) {
+                digits.push(*d);
+                chars.next();
+            }
+            match digits.parse::<usize>() {
+                Ok(n) => result += &(n + offset).to_string(),
+                Err(_) => result += &digits,
+            }
+        }
+    }
+    result
+}
+
+pub struct QueryBuilder;
+
+impl QueryBuilder {
+    pub fn select<T: Model>() -> Select<T> {
+        Select::new()
+    }
+
+    pub fn insert<'a, T: Model>() -> Insert<'a, T> {
+        Insert::new()
+    }
+
+    pub fn update<'a, T: Model>() -> Update<'a, T> {
+        Update::new()
+    }
+
+    pub fn delete<T: Model>() -> Delete<T> {
+        Delete::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct TestModel;
+
+    impl Model for TestModel {
+        fn table_name() -> &'static str {
+            "users"
+        }
+
+        fn columns() -> &'static [&'static str] {
+            &["id", "name", "email", "age"]
+        }
+
+        fn from_row(_row: &Row) -> Self {
+            TestModel
+        }
+    }
+
+    #[test]
+    fn test_select_query_builder() {
+        let query_builder = QueryBuilder::select::<TestModel>()
+            .select(&["name", "email"])
+            .join(JoinType::Inner, "orders", "users.id = orders.user_id")
+            .where_clause("age > $1")
+            .group_by(&["name", "email"])
+            .having("COUNT(orders.id) > $2")
+            .order_by("name", true)
+            .limit(10)
+            .offset(5)
+            .aggregate(AggregateFunction::Count, "id", Some("user_count"))
+            .bind_param(18)
+            .bind_param(5);
+
+        let (query, params) = query_builder.build();
+
+        assert_eq!(
+            query,
+            "SELECT name, email, COUNT(id) AS user_count FROM users INNER JOIN orders ON users.id = orders.user_id WHERE age > $1 GROUP BY name, email HAVING COUNT(orders.id) > $2 ORDER BY name ASC LIMIT 10 OFFSET 5"
+        );
+        assert_eq!(params.len(), 2);
+    }
+
+    #[test]
+    fn test_tiebreak_by_appends_key_once() {
+        let (query, _) = QueryBuilder::select::<TestModel>()
+            .order_by("age", false)
+            .tiebreak_by("id")
+            .limit(10)
+            .build();
+        assert_eq!(query, "SELECT * FROM users ORDER BY age DESC, id ASC LIMIT 10");
+
+        let (query, _) = QueryBuilder::select::<TestModel>()
+            .order_by("id", false)
+            .tiebreak_by("id")
+            .build();
+        assert_eq!(query, "SELECT * FROM users ORDER BY id DESC");
+    }
+
+    #[test]
+    fn test_select_expression() {
+        let (query, _) = QueryBuilder::select::<TestModel>()
+            .select(&["name"])
+            .select_expr("EXTRACT(YEAR FROM created_at)", "yr")
+            .build();
+
+        assert_eq!(query, "SELECT name, EXTRACT(YEAR FROM created_at) AS yr FROM users");
+    }
+
+    #[test]
+    fn test_select_with_cte() {
+        let adults = QueryBuilder::select::<TestModel>()
+            .select(&["id"])
+            .where_clause("age >= $1")
+            .bind_param(18);
+        let query_builder = QueryBuilder::select::<TestModel>()
+            .where_eq("name", "Alice".to_string())
+            .with_select("adults", adults)
+            .with("gmail", "SELECT id FROM users WHERE email LIKE '%@gmail.com'")
+            .join(JoinType::Inner, "adults", "adults.id = users.id")
+            .join(JoinType::Inner, "gmail", "gmail.id = users.id")
+            .where_eq("email", "alice@gmail.com".to_string());
+
+        let (query, params) = query_builder.build();
+
+        assert_eq!(
+            query,
+            "WITH adults AS (SELECT id FROM users WHERE age >= $2), gmail AS (SELECT id FROM users WHERE email LIKE '%@gmail.com') \
+             SELECT * FROM users INNER JOIN adults ON adults.id = users.id INNER JOIN gmail ON gmail.id = users.id WHERE name = $1 AND email = $3"
+        );
+        assert_eq!(params.len(), 3);
+        assert_eq!(format!("{:?}", params[0]), "\"Alice\"");
+        assert_eq!(format!("{:?}", params[1]), "18");
+        assert_eq!(format!("{:?}", params[2]), "\"alice@gmail.com\"");
+    }
+
+    #[test]
+    fn test_select_with_cte_leaves_literal_dollars_alone() {
+        let priced = QueryBuilder::select::<TestModel>()
+            .select(&["id"])
+            .where_clause("name <> '$1' AND age >= $1")
+            .bind_param(18);
+        let query_builder = QueryBuilder::select::<TestModel>()
+            .where_eq("name", "Alice".to_string())
+            .with_select("priced", priced)
+            .join(JoinType::Inner, "priced", "priced.id = users.id");
+
+        let (query, params) = query_builder.build();
+
+        assert_eq!(
+            query,
+            "WITH priced AS (SELECT id FROM users WHERE name <> '$1' AND age >= $2) SELECT * FROM users INNER JOIN priced ON priced.id = users.id WHERE name = $1"
+        );
+        assert_eq!(params.len(), 2);
+        assert_eq!(format!("{:?}", params[1]), "18");
+    }
+
+    #[test]
+    fn test_select_with_raw_cte_reserves_placeholders() {
+        let query_builder = QueryBuilder::select::<TestModel>()
+            .with("recent", "SELECT id FROM users WHERE age < $1")
+            .join(JoinType::Inner, "recent", "recent.id = users.id")
+            .where_eq("name", "Bob".to_string())
+            .bind_param(30);
+
+        let (query, params) = query_builder.build();
+
+        assert_eq!(query, "WITH recent AS (SELECT id FROM users WHERE age < $1) SELECT * FROM users INNER JOIN recent ON recent.id = users.id WHERE name = $2");
+        assert_eq!(format!("{:?}", params[0]), "30");
+        assert_eq!(format!("{:?}", params[1]), "\"Bob\"");
+    }
+
+    #[test]
+    fn test_select_self_join_with_aliases() {
+        let (query, _) = QueryBuilder::select::<TestModel>()
+            .join_as(JoinType::Inner, "users", "managers", "managers.id = users.age")
+            .join_as(JoinType::Left, "users", "mentors", "mentors.id = managers.age")
+            .select(&["users.name", "managers.name", "mentors.email"])
+            .order_by("managers.name", true)
+            .order_by("name", false)
+            .build();
+
+        assert_eq!(
+            query,
+            "SELECT users.name, managers.name, mentors.email FROM users INNER JOIN users AS managers ON managers.id = users.age LEFT JOIN users AS mentors ON mentors.id = managers.age ORDER BY managers.name ASC, name DESC"
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Field 'bosses.name' does not exist in table 'users'")]
+    fn test_select_rejects_unknown_alias() {
+        QueryBuilder::select::<TestModel>()
+            .join_as(JoinType::Inner, "users", "managers", "managers.id = users.age")
+            .select(&["bosses.name"]);
+    }
+
+    #[test]
+    #[should_panic(expected = "Field 'users.nickname' does not exist in table 'users'")]
+    fn test_select_rejects_unknown_qualified_column() {
+        QueryBuilder::select::<TestModel>().select(&["users.nickname"]);
+    }
+
+    #[test]
+    #[should_panic(expected = "must be a plain identifier")]
+    fn test_select_expression_rejects_unsafe_alias() {
+        QueryBuilder::select::<TestModel>().select_expr("1", "x; DROP TABLE users");
+    }
+
+    #[test]
+    fn test_select_running_sum() {
+        let (query, _) = QueryBuilder::select::<TestModel>()
+            .select(&["name", "age"])
+            .running_sum("age", &["email"], &["id"], "running_age")
+            .build();
+
+        assert_eq!(
+            query,
+            "SELECT name, age, SUM(age) OVER (PARTITION BY email ORDER BY id ROWS UNBOUNDED PRECEDING) AS running_age FROM users"
+        );
+    }
+
+    #[test]
+    fn test_select_window_without_partition() {
+        let (query, _) = QueryBuilder::select::<TestModel>()
+            .select(&["id"])
+            .window(AggregateFunction::Count, "id", &[], &["age"], None, "seen")
+            .build();
+
+        assert_eq!(query, "SELECT id, COUNT(id) OVER (ORDER BY age) AS seen FROM users");
+    }
+
+    #[test]
+    fn test_select_for_update() {
+        let query_builder = QueryBuilder::select::<TestModel>()
+            .where_clause("id = $1")
+            .limit(1)
+            .offset(2)
+            .for_update()
+            .bind_param(1);
+
+        let (query, _) = query_builder.build();
+
+        assert_eq!(query, "SELECT * FROM users WHERE id = $1 LIMIT 1 OFFSET 2 FOR UPDATE");
+    }
+
+    #[test]
+    fn test_select_for_share_skip_locked() {
+        let (query, _) = QueryBuilder::select::<TestModel>()
+            .limit(10)
+            .for_share()
+            .skip_locked()
+            .build();
+
+        assert_eq!(query, "SELECT * FROM users LIMIT 10 FOR SHARE SKIP LOCKED");
+
+        let (query, _) = QueryBuilder::select::<TestModel>().skip_locked().build();
+        assert_eq!(query, "SELECT * FROM users");
+    }
+
+    #[test]
+    fn test_select_lock_mode() {
+        let query_builder = QueryBuilder::select::<TestModel>()
+            .where_eq("id", 1)
+            .order_by("id", true)
+            .limit(5)
+            .offset(10)
+            .lock(LockMode::ForUpdateSkipLocked);
+        let (query, _) = query_builder.build();
+        assert_eq!(query, "SELECT * FROM users WHERE id = $1 ORDER BY id ASC LIMIT 5 OFFSET 10 FOR UPDATE SKIP LOCKED");
+        assert!(query.ends_with(&LockMode::ForUpdateSkipLocked.to_string()));
+
+        let (query, _) = QueryBuilder::select::<TestModel>().lock(LockMode::ForUpdateNowait).lock(LockMode::ForShare).build();
+        assert_eq!(query, "SELECT * FROM users FOR SHARE");
+
+        let (query, _) = QueryBuilder::select::<TestModel>().limit(1).build();
+        assert!(!query.contains("FOR "));
+    }
+
+    #[test]
+    fn test_insert_query_builder() {
+        let name = "Alice".to_string();
+        let email = "alice@example.com".to_string();
+        let age = 30;
+        let query_builder = QueryBuilder::insert::<TestModel>()
+            .columns(&["name", "email", "age"])
+            .values(&[&name, &email, &age])
+            .returning(&["id", "name"]);
+
+        let (query, params) = query_builder.build();
+
+        assert_eq!(query, "INSERT INTO users (name, email, age) VALUES ($1, $2, $3) RETURNING id, name");
+        assert_eq!(params.len(), 3);
+    }
+
+    #[test]
+    fn test_insert_defaults_to_all_columns() {
+        let (id, name, email, age) = (1, "Alice".to_string(), "alice@example.com".to_string(), 30);
+        let (query, params) = QueryBuilder::insert::<TestModel>()
+            .values(&[&id, &name, &email, &age])
+            .build();
+
+        assert_eq!(query, "INSERT INTO users (id, name, email, age) VALUES ($1, $2, $3, $4)");
+        assert_eq!(params.len(), 4);
+    }
+
+    #[test]
+    fn test_insert_value_count_mismatch_is_rejected() {
+        let name = "Alice".to_string();
+        let query_builder = QueryBuilder::insert::<TestModel>()
+            .columns(&["name", "age"])
+            .values(&[&name]);
+
+        match query_builder.try_build() {
+            Err(OrmError::QueryError(message)) => {
+                assert!(message.contains("has 1 value(s) for 2 column(s) (name, age)"), "unexpected message: {}", message);
+            }
+            other => panic!("Expected QueryError, got {:?}", other.map(|(query, _)| query)),
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "has no values")]
+    fn test_insert_build_without_values_panics() {
+        QueryBuilder::insert::<TestModel>().build();
+    }
+
+    #[test]
+    #[should_panic(expected = "Field 'nickname' does not exist in table 'users'")]
+    fn test_insert_rejects_unknown_column() {
+        QueryBuilder::insert::<TestModel>().columns(&["nickname"]);
+    }
+
+    #[test]
+    fn test_insert_on_conflict_do_nothing() {
+        let (email, name) = ("alice@example.com".to_string(), "Alice".to_string());
+        let query_builder = QueryBuilder::insert::<TestModel>()
+            .columns(&["email", "name"])
+            .values(&[&email, &name])
+            .on_conflict(&["email"])
+            .do_nothing()
+            .returning(&["id"]);
+
+        let (query, params) = query_builder.build();
+
+        assert_eq!(query, "INSERT INTO users (email, name) VALUES ($1, $2) ON CONFLICT (email) DO NOTHING RETURNING id");
+        assert_eq!(params.len(), 2);
+    }
+
+    #[test]
+    fn test_insert_on_conflict_do_update_set() {
+        let (email, name, age) = ("alice@example.com".to_string(), "Alice".to_string(), 30);
+        let (new_name, new_age) = ("Alice Smith".to_string(), 31);
+        let query_builder = QueryBuilder::insert::<TestModel>()
+            .columns(&["email", "name", "age"])
+            .values(&[&email, &name, &age])
+            .on_conflict(&["email", "name"])
+            .do_update_set(&[("name", &new_name), ("age", &new_age)])
+            .returning(&["id", "name"]);
+
+        let (query, params) = query_builder.build();
+
+        assert_eq!(query, "INSERT INTO users (email, name, age) VALUES ($1, $2, $3) ON CONFLICT (email, name) DO UPDATE SET name = $4, age = $5 RETURNING id, name");
+        assert_eq!(params.len(), 5);
+        assert_eq!(format!("{:?}", params[3]), "\"Alice Smith\"");
+        assert_eq!(format!("{:?}", params[4]), "31");
+    }
+
+    #[test]
+    fn test_insert_do_update_without_target_is_rejected() {
+        let (email, age) = ("alice@example.com".to_string(), 30);
+        let query_builder = QueryBuilder::insert::<TestModel>()
+            .columns(&["email"])
+            .values(&[&email])
+            .do_update_set(&[("age", &age)]);
+
+        match query_builder.try_build() {
+            Err(OrmError::QueryError(message)) => assert!(message.contains("needs a conflict target"), "unexpected message: {}", message),
+            other => panic!("Expected QueryError, got {:?}", other.map(|(query, _)| query)),
+        }
+    }
+
+    #[test]
+    fn test_update_query_builder() {
+        let name = "Alice".to_string();
+        let age = 30;
+        let query_builder = QueryBuilder::update::<TestModel>()
+            .set_values(&[("name", &name), ("age", &age)])
+            .where_clause("id = $1")
+            .bind_param(7);
+
+        let (query, params) = query_builder.build();
+
+        assert_eq!(query, "UPDATE users SET name = $1, age = $2 WHERE id = $3");
+        assert_eq!(params.len(), 3);
+    }
+
+    #[test]
+    fn test_update_where_leaves_literal_dollars_alone() {
+        let name = "Alice".to_string();
+        let query_builder = QueryBuilder::update::<TestModel>()
+            .set_values(&[("name", &name)])
+            .where_clause("id = $1 AND note <> 'costs $5, it''s $6' AND age > $2")
+            .bind_param(7)
+            .bind_param(18);
+
+        let (query, params) = query_builder.build();
+
+        assert_eq!(query, "UPDATE users SET name = $1 WHERE id = $2 AND note <> 'costs $5, it''s $6' AND age > $3");
+        assert_eq!(params.len(), 3);
+    }
+
+    #[test]
+    fn test_update_without_values_is_rejected() {
+        let query_builder = QueryBuilder::update::<TestModel>()
+            .set_values(&[])
+            .where_clause("id = $1")
+            .bind_param(7);
+
+        match query_builder.try_build() {
+            Err(OrmError::QueryError(message)) => {
+                assert!(message.contains("UPDATE on table 'users' has no values to set"), "unexpected message: {}", message);
+            }
+            other => panic!("Expected QueryError, got {:?}", other.map(|(query, _)| query)),
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "has no values to set")]
+    fn test_update_build_without_values_panics() {
+        QueryBuilder::update::<TestModel>().build();
+    }
+
+    #[test]
+    fn test_select_missing_bind_param_is_rejected() {
+        let query_builder = QueryBuilder::select::<TestModel>()
+            .where_clause("age > $1")
+            .where_clause("name = $2")
+            .bind_param(18);
+
+        match query_builder.try_build() {
+            Err(OrmError::QueryError(message)) => {
+                assert!(message.contains("uses 2 distinct placeholder(s) [$1, $2] but 1 parameter(s) were bound"), "unexpected message: {}", message);
+            }
+            other => panic!("Expected QueryError, got {:?}", other.map(|(query, _)| query)),
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "Placeholder mismatch")]
+    fn test_select_build_with_gap_in_placeholders_panics() {
+        QueryBuilder::select::<TestModel>()
+            .where_clause("age > $1 AND id = $3")
+            .bind_param(18)
+            .bind_param(7)
+            .build();
+    }
+
+    #[test]
+    fn test_placeholders_in_literals_and_repeats() {
+        let query_builder = QueryBuilder::select::<TestModel>()
+            .where_clause("name <> 'costs $5'")
+            .where_clause("(age > $1 OR id = $1)")
+            .bind_param(18);
+        assert!(query_builder.try_build().is_ok());
+
+        let query_builder = QueryBuilder::update::<TestModel>()
+            .set_values(&[("name", &"Ada")])
+            .where_clause("id = $1");
+        assert!(query_builder.try_build().is_err());
+    }
+
+    #[test]
+    fn test_delete_query_builder() {
+        let query_builder = QueryBuilder::delete::<TestModel>()
+            .where_clause("id = $1")
+            .where_clause("age < $2")
+            .bind_param(7)
+            .bind_param(18);
+
+        let (query, params) = query_builder.build();
+
+        assert_eq!(query, "DELETE FROM users WHERE id = $1 AND age < $2");
+        assert_eq!(params.len(), 2);
+    }
+
+    #[test]
+    fn test_delete_without_where_is_rejected() {
+        let query_builder = QueryBuilder::delete::<TestModel>();
+
+        match query_builder.try_build() {
+            Err(OrmError::QueryError(message)) => {
+                assert!(message.contains("DELETE on table 'users' has no WHERE clause"), "unexpected message: {}", message);
+            }
+            other => panic!("Expected QueryError, got {:?}", other.map(|(query, _)| query)),
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "would delete every row")]
+    fn test_delete_build_without_where_panics() {
+        QueryBuilder::delete::<TestModel>().build();
+    }
+
+    #[test]
+    fn test_delete_build_unchecked() {
+        let query_builder = QueryBuilder::delete::<TestModel>();
+        let (query, params) = query_builder.build_unchecked().unwrap();
+
+        assert_eq!(query, "DELETE FROM users");
+        assert!(params.is_empty());
+    }
+
+    #[test]
+    fn test_select_where_eq() {
+        let query_builder = QueryBuilder::select::<TestModel>()
+            .where_eq("name", "Alice".to_string())
+            .where_eq("age", 30);
+
+        let (query, params) = query_builder.build();
+
+        assert_eq!(query, "SELECT * FROM users WHERE name = $1 AND age = $2");
+        assert_eq!(params.len(), 2);
+    }
+
+    #[test]
+    fn test_select_where_ilike_and_text_search() {
+        let query_builder = QueryBuilder::select::<TestModel>()
+            .where_eq("age", 30)
+            .where_ilike("email", "%@Example.com")
+            .where_text_search("name", "ada lovelace");
+
+        let (query, params) = query_builder.build();
+
+        assert_eq!(query, "SELECT * FROM users WHERE age = $1 AND email ILIKE $2 AND to_tsvector(name) @@ plainto_tsquery($3)");
+        assert_eq!(params.len(), 3);
+        assert_eq!(format!("{:?}", params[1]), "\"%@Example.com\"");
+        assert_eq!(format!("{:?}", params[2]), "\"ada lovelace\"");
+    }
+
+    #[test]
+    #[should_panic(expected = "Field 'bio' does not exist in table 'users'")]
+    fn test_select_where_text_search_checks_field() {
+        QueryBuilder::select::<TestModel>().where_text_search("bio", "rust");
+    }
+
+    #[test]
+    fn test_select_where_eq_opt() {
+        let query_builder = QueryBuilder::select::<TestModel>()
+            .where_eq_opt("name", Some("Alice".to_string()))
+            .where_eq_opt("email", None::<String>)
+            .where_eq_opt("age", Some(30));
+
+        let (query, params) = query_builder.build();
+
+        assert_eq!(query, "SELECT * FROM users WHERE name = $1 AND age = $2");
+        assert_eq!(params.len(), 2);
+        assert_eq!(format!("{:?}", params[1]), "30");
+    }
+
+    #[test]
+    fn test_select_where_eq_opt_none_adds_nothing() {
+        let query_builder = QueryBuilder::select::<TestModel>()
+            .where_eq_opt("name", None::<String>)
+            .where_clause("age > $1")
+            .bind_param(18);
+
+        let (query, params) = query_builder.build();
+
+        assert_eq!(query, "SELECT * FROM users WHERE age > $1");
+        assert_eq!(params.len(), 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "Field 'nickname' does not exist in table 'users'")]
+    fn test_select_where_eq_opt_checks_field_when_none() {
+        QueryBuilder::select::<TestModel>().where_eq_opt("nickname", None::<String>);
+    }
+
+    #[test]
+    fn test_select_mixed_manual_and_auto_numbering() {
+        // Raw clauses reserve their numbers even before their values are bound
+        let query_builder = QueryBuilder::select::<TestModel>()
+            .select(&["name"])
+            .where_clause("age > $1")
+            .where_eq("email", "alice@example.com".to_string())
+            .group_by(&["name"])
+            .having("COUNT(id) > $3")
+            .where_eq("name", "Alice".to_string())
+            .bind_param(18)
+            .bind_param(2i64);
+
+        let (query, params) = query_builder.build();
+
+        assert_eq!(query, "SELECT name FROM users WHERE age > $1 AND email = $2 AND name = $4 GROUP BY name HAVING COUNT(id) > $3");
+        assert_eq!(params.len(), 4);
+        assert_eq!(format!("{:?}", params[0]), "18");
+        assert_eq!(format!("{:?}", params[1]), "\"alice@example.com\"");
+        assert_eq!(format!("{:?}", params[2]), "2");
+        assert_eq!(format!("{:?}", params[3]), "\"Alice\"");
+    }
+
+    #[test]
+    fn test_select_unbound_raw_placeholder_is_rejected() {
+        let query_builder = QueryBuilder::select::<TestModel>()
+            .where_clause("age > $1")
+            .where_eq("name", "Alice".to_string());
+
+        match query_builder.try_build() {
+            Err(OrmError::QueryError(message)) => assert!(message.contains("Placeholder $1"), "unexpected message: {}", message),
+            other => panic!("Expected QueryError, got {:?}", other.map(|(query, _)| query)),
+        }
+    }
+
+    #[test]
+    fn test_select_where_in() {
+        let query_builder = QueryBuilder::select::<TestModel>()
+            .where_eq("name", "Alice".to_string())
+            .where_in("id", vec![1, 2, 3]);
+
+        let (query, params) = query_builder.build();
+
+        assert_eq!(query, "SELECT * FROM users WHERE name = $1 AND id IN ($2, $3, $4)");
+        assert_eq!(params.len(), 4);
+    }
+
+    #[test]
+    fn test_where_in_empty_list() {
+        let query_builder = QueryBuilder::select::<TestModel>().where_in("id", Vec::<i32>::new());
+
+        let (query, params) = query_builder.build();
+
+        assert_eq!(query, "SELECT * FROM users WHERE false");
+        assert!(params.is_empty());
+    }
+
+    #[test]
+    fn test_update_and_delete_where_in() {
+        let name = "Bob".to_string();
+        let update = QueryBuilder::update::<TestModel>()
+            .set_values(&[("name", &name)])
+            .where_in("id", vec![4, 5]);
+        let (query, params) = update.build();
+
+        assert_eq!(query, "UPDATE users SET name = $1 WHERE id IN ($2, $3)");
+        assert_eq!(params.len(), 3);
+
+        let delete = QueryBuilder::delete::<TestModel>()
+            .where_clause("age < $1")
+            .bind_param(18)
+            .where_in("id", vec![4, 5]);
+        let (query, params) = delete.build();
+
+        assert_eq!(query, "DELETE FROM users WHERE age < $1 AND id IN ($2, $3)");
+        assert_eq!(params.len(), 3);
+    }
+
+    #[test]
+    fn test_select_filter_or_inside_and() {
+        let query_builder = QueryBuilder::select::<TestModel>()
+            .filter(Condition::And(vec![
+                Condition::Or(vec![Condition::raw("name = $1"), Condition::raw("email = $2")]),
+                Condition::raw("age > $3"),
+            ]))
+            .bind_param("Alice".to_string())
+            .bind_param("alice@example.com".to_string())
+            .bind_param(18);
+
+        let (query, params) = query_builder.build();
+
+        assert_eq!(query, "SELECT * FROM users WHERE (name = $1 OR email = $2) AND age > $3");
+        assert_eq!(params.len(), 3);
+    }
+
+    #[test]
+    fn test_select_filter_nested_groups() {
+        let query_builder = QueryBuilder::select::<TestModel>()
+            .where_clause("id > $1")
+            .bind_param(0)
+            .filter(Condition::Or(vec![
+                Condition::And(vec![Condition::raw("age > $2"), Condition::raw("age < $3")]),
+                Condition::And(vec![Condition::raw("name IS NULL")]),
+                Condition::Or(vec![]),
+            ]))
+            .bind_param(18)
+            .bind_param(65)
+            .where_eq("email", "alice@example.com".to_string());
+
+        let (query, params) = query_builder.build();
+
+        assert_eq!(query, "SELECT * FROM users WHERE id > $1 AND ((age > $2 AND age < $3) OR name IS NULL OR false) AND email = $4");
+        assert_eq!(params.len(), 4);
+    }
+
+    #[test]
+    fn test_update_returning() {
+        let name = "Bob".to_string();
+        let update = QueryBuilder::update::<TestModel>()
+            .set_values(&[("name", &name)])
+            .where_clause("id = $1")
+            .bind_param(1)
+            .returning(&["id", "name"]);
+
+        let (query, params) = update.build();
+
+        assert_eq!(query, "UPDATE users SET name = $1 WHERE id = $2 RETURNING id, name");
+        assert_eq!(params.len(), 2);
+    }
+
+    #[test]
+    fn test_delete_returning() {
+        let delete = QueryBuilder::delete::<TestModel>()
+            .where_clause("id = $1")
+            .bind_param(1)
+            .returning(&["id", "email"]);
+
+        let (query, params) = delete.build();
+
+        assert_eq!(query, "DELETE FROM users WHERE id = $1 RETURNING id, email");
+        assert_eq!(params.len(), 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "Field 'nonexistent' does not exist in table 'users'")]
+    fn test_delete_returning_unknown_field() {
+        QueryBuilder::delete::<TestModel>().returning(&["nonexistent"]);
+    }
+
+    struct QuotedModel;
+
+    impl Model for QuotedModel {
+        fn table_name() -> &'static str {
+            "Order Items"
+        }
+
+        fn columns() -> &'static [&'static str] {
+            &["id", "zip code", "Sku"]
+        }
+
+        fn from_row(_row: &Row) -> Self {
+            QuotedModel
+        }
+    }
+
+    #[test]
+    fn test_identifiers_are_quoted_when_needed() {
+        let (select, _) = QueryBuilder::select::<QuotedModel>()
+            .select(&["id", "Order Items.zip code"])
+            .where_eq("Sku", "A-1".to_string())
+            .where_in("zip code", vec!["10115".to_string()])
+            .order_by("Sku", false)
+            .tiebreak_by("id")
+            .build();
+        assert_eq!(select, "SELECT id, \"Order Items\".\"zip code\" FROM \"Order Items\" WHERE \"Sku\" = $1 AND \"zip code\" IN ($2) ORDER BY \"Sku\" DESC, id ASC");
+
+        let sku = "A-2".to_string();
+        let (update, _) = QueryBuilder::update::<QuotedModel>()
+            .set_values(&[("Sku", &sku)])
+            .where_in("id", vec![1])
+            .returning(&["zip code"])
+            .build();
+        assert_eq!(update, "UPDATE \"Order Items\" SET \"Sku\" = $1 WHERE id IN ($2) RETURNING \"zip code\"");
+
+        let zip_code = "10115".to_string();
+        let (insert, _) = QueryBuilder::insert::<QuotedModel>()
+            .columns(&["zip code", "Sku"])
+            .values(&[&zip_code, &sku])
+            .on_conflict(&["Sku"])
+            .do_update_set(&[("zip code", &zip_code)])
+            .returning(&["id"])
+            .build();
+        assert_eq!(insert, "INSERT INTO \"Order Items\" (\"zip code\", \"Sku\") VALUES ($1, $2) ON CONFLICT (\"Sku\") DO UPDATE SET \"zip code\" = $3 RETURNING id");
+
+        let (delete, _) = QueryBuilder::delete::<QuotedModel>()
+            .where_in("zip code", vec!["10115".to_string()])
+            .returning(&["Sku"])
+            .build();
+        assert_eq!(delete, "DELETE FROM \"Order Items\" WHERE \"zip code\" IN ($1) RETURNING \"Sku\"");
+    }
+
+    #[derive(Debug, PartialEq)]
+    struct FetchUser {
+        id: i32,
+        name: String,
+    }
+
+    impl Model for FetchUser {
+        fn table_name() -> &'static str {
+            "fetch_users"
+        }
+
+        fn columns() -> &'static [&'static str] {
+            &["id", "name"]
+        }
+
+        fn from_row(row: &Row) -> Self {
+            FetchUser { id: row.get("id"), name: row.get("name") }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_fetch_all_and_fetch_one() {
+        dotenv::dotenv().ok();
+        let database_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+        let manager = crate::db::PostgresConnectionManager::new(database_url);
+        let client = manager.connect().await.expect("Failed to connect to database");
+        client.batch_execute(
+            "DROP TABLE IF EXISTS fetch_users;
+             CREATE TABLE fetch_users (id INTEGER PRIMARY KEY, name TEXT NOT NULL);
+             INSERT INTO fetch_users VALUES (1, 'Ada'), (2, 'Grace'), (3, 'Barbara');"
+        ).await.expect("Failed to create test table");
+
+        let all = QueryBuilder::select::<FetchUser>().order_by("id", true).fetch_all(&client).await;
+        let filtered = QueryBuilder::select::<FetchUser>().where_eq("name", "Grace".to_string()).fetch_one(&client).await;
+        let missing = QueryBuilder::select::<FetchUser>().where_eq("id", 42).fetch_one(&client).await;
+        let invalid = QueryBuilder::select::<FetchUser>().where_clause("id = $1").fetch_all(&client).await;
+        let ilike = QueryBuilder::select::<FetchUser>().where_ilike("name", "gRa%").fetch_all(&client).await;
+        let searched = QueryBuilder::select::<FetchUser>().where_text_search("name", "Barbara").fetch_all(&client).await;
+        client.batch_execute("DROP TABLE fetch_users").await.unwrap();
+
+        assert_eq!(all.expect("fetch_all failed"), vec![
+            FetchUser { id: 1, name: "Ada".to_string() },
+            FetchUser { id: 2, name: "Grace".to_string() },
+            FetchUser { id: 3, name: "Barbara".to_string() },
+        ]);
+        assert_eq!(filtered.expect("fetch_one failed"), FetchUser { id: 2, name: "Grace".to_string() });
+        assert!(matches!(missing, Err(OrmError::DatabaseError(_))), "Expected a DatabaseError, got {:?}", missing);
+        assert!(matches!(invalid, Err(OrmError::QueryError(_))), "Expected a QueryError, got {:?}", invalid);
+        assert_eq!(ilike.expect("ILIKE query failed"), vec![FetchUser { id: 2, name: "Grace".to_string() }]);
+        assert_eq!(searched.expect("Text search query failed"), vec![FetchUser { id: 3, name: "Barbara".to_string() }]);
+    }
+
+    #[derive(Debug, PartialEq)]
+    struct FallibleUser {
+        id: i32,
+        nickname: String,
+    }
+
+    impl Model for FallibleUser {
+        fn table_name() -> &'static str {
+            "fallible_users"
+        }
+
+        fn columns() -> &'static [&'static str] {
+            &["id", "nickname"]
+        }
+
+        fn from_row(row: &Row) -> Self {
+            Self::try_from_row(row).unwrap_or_else(|e| panic!("{}", e))
+        }
+
+        fn try_from_row(row: &Row) -> Result<Self, OrmError> {
+            Ok(FallibleUser { id: try_get(row, "id")?, nickname: try_get(row, "nickname")? })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_fetch_reports_unexpected_null() {
+        dotenv::dotenv().ok();
+        let database_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+        let manager = crate::db::PostgresConnectionManager::new(database_url);
+        let client = manager.connect().await.expect("Failed to connect to database");
+        // nickname is mapped as String, as if it were NOT NULL, but one row has NULL
+        client.batch_execute(
+            "DROP TABLE IF EXISTS fallible_users;
+             CREATE TABLE fallible_users (id INTEGER PRIMARY KEY, nickname TEXT);
+             INSERT INTO fallible_users VALUES (1, 'Ada'), (2, NULL);"
+        ).await.expect("Failed to create test table");
+
+        let all = QueryBuilder::select::<FallibleUser>().order_by("id", true).fetch_all(&client).await;
+        let first = QueryBuilder::select::<FallibleUser>().where_eq("id", 1).fetch_one(&client).await;
+        client.batch_execute("DROP TABLE fallible_users").await.unwrap();
+
+        match all {
+            Err(OrmError::ParseError(message)) => assert!(message.contains("column 'nickname'"), "{}", message),
+            other => panic!("Expected a ParseError, got {:?}", other),
+        }
+        assert_eq!(first.expect("fetch_one failed"), FallibleUser { id: 1, nickname: "Ada".to_string() });
+    }
 }
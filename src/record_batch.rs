@@ -0,0 +1,173 @@
+use crate::error::OrmError;
+use arrow::array::{ArrayRef, BinaryArray, BooleanArray, Date32Array, Float32Array, Float64Array, Int16Array, Int32Array, Int64Array, StringArray, TimestampMicrosecondArray};
+use arrow::datatypes::{DataType, Field, Schema, TimeUnit};
+use arrow::record_batch::RecordBatch;
+use std::sync::Arc;
+use tokio_postgres::types::{FromSql, ToSql, Type};
+use tokio_postgres::{Client, Row};
+
+/// Days between the Unix epoch and the Postgres epoch (2000-01-01).
+const PG_EPOCH_DAYS: i32 = 10_957;
+/// Microseconds between the Unix epoch and the Postgres epoch.
+const PG_EPOCH_MICROS: i64 = 946_684_800_000_000;
+
+/// Runs `sql` and returns the rows as a single Arrow `RecordBatch`, for
+/// handing results to DataFusion, Polars or other Arrow consumers.
+///
+/// The schema comes from the prepared statement, so an empty result still
+/// has the right columns. Every field is nullable. Columns whose Postgres
+/// type has no mapping below are rejected with a `QueryError`.
+///
+/// | Postgres                      | Arrow                       |
+/// |-------------------------------|-----------------------------|
+/// | boolean                       | Boolean                     |
+/// | smallint, integer, bigint     | Int16, Int32, Int64         |
+/// | real, double precision        | Float32, Float64            |
+/// | text, varchar, char, name     | Utf8                        |
+/// | bytea                         | Binary                      |
+/// | date                          | Date32                      |
+/// | timestamp                     | Timestamp(Microsecond)      |
+/// | timestamptz                   | Timestamp(Microsecond, UTC) |
+///
+/// Only available with the `arrow` feature.
+pub async fn query_arrow(client: &Client, sql: &str, params: &[&(dyn ToSql + Sync)]) -> Result<RecordBatch, OrmError> {
+    let statement = client.prepare(sql).await?;
+    let fields = statement.columns().iter()
+        .map(|column| arrow_type(column.type_()).map(|data_type| Field::new(column.name(), data_type, true)).ok_or_else(|| OrmError::QueryError(format!(
+            "Column '{}' has type {} which has no Arrow mapping; cast it in the query, e.g. {}::text",
+            column.name(), column.type_(), column.name()
+        ))))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let rows = client.query(&statement, params).await?;
+    let arrays = statement.columns().iter().enumerate()
+        .map(|(idx, column)| column_array(&rows, idx, column.type_()))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    RecordBatch::try_new(Arc::new(Schema::new(fields)), arrays)
+        .map_err(|e| OrmError::QueryError(format!("Failed to build a RecordBatch for '{}': {}", sql, e)))
+}
+
+fn arrow_type(pg_type: &Type) -> Option<DataType> {
+    Some(match *pg_type {
+        Type::BOOL => DataType::Boolean,
+        Type::INT2 => DataType::Int16,
+        Type::INT4 => DataType::Int32,
+        Type::INT8 => DataType::Int64,
+        Type::FLOAT4 => DataType::Float32,
+        Type::FLOAT8 => DataType::Float64,
+        Type::TEXT | Type::VARCHAR | Type::BPCHAR | Type::NAME => DataType::Utf8,
+        Type::BYTEA => DataType::Binary,
+        Type::DATE => DataType::Date32,
+        Type::TIMESTAMP => DataType::Timestamp(TimeUnit::Microsecond, None),
+        Type::TIMESTAMPTZ => DataType::Timestamp(TimeUnit::Microsecond, Some("UTC".into())),
+        _ => return None,
+    })
+}
+
+fn column_array(rows: &[Row], idx: usize, pg_type: &Type) -> Result<ArrayRef, OrmError> {
+    Ok(match *pg_type {
+        Type::BOOL => Arc::new(BooleanArray::from(values::<bool>(rows, idx)?)),
+        Type::INT2 => Arc::new(Int16Array::from(values::<i16>(rows, idx)?)),
+        Type::INT4 => Arc::new(Int32Array::from(values::<i32>(rows, idx)?)),
+        Type::INT8 => Arc::new(Int64Array::from(values::<i64>(rows, idx)?)),
+        Type::FLOAT4 => Arc::new(Float32Array::from(values::<f32>(rows, idx)?)),
+        Type::FLOAT8 => Arc::new(Float64Array::from(values::<f64>(rows, idx)?)),
+        Type::BYTEA => Arc::new(BinaryArray::from(values::<&[u8]>(rows, idx)?)),
+        Type::DATE => Arc::new(Date32Array::from(
+            values::<RawDate>(rows, idx)?.into_iter().map(|d| d.map(|RawDate(days)| days + PG_EPOCH_DAYS)).collect::<Vec<_>>()
+        )),
+        Type::TIMESTAMP | Type::TIMESTAMPTZ => {
+            let micros: Vec<Option<i64>> = values::<RawTimestamp>(rows, idx)?.into_iter()
+                .map(|t| t.map(|RawTimestamp(micros)| micros + PG_EPOCH_MICROS))
+                .collect();
+            let array = TimestampMicrosecondArray::from(micros);
+            match *pg_type {
+                Type::TIMESTAMPTZ => Arc::new(array.with_timezone("UTC")),
+                _ => Arc::new(array),
+            }
+        }
+        _ => Arc::new(StringArray::from(values::<&str>(rows, idx)?)),
+    })
+}
+
+fn values<'a, T: FromSql<'a>>(rows: &'a [Row], idx: usize) -> Result<Vec<Option<T>>, OrmError> {
+    rows.iter().map(|row| row.try_get::<_, Option<T>>(idx).map_err(OrmError::from)).collect()
+}
+
+/// A `date` in its wire format: days since 2000-01-01.
+struct RawDate(i32);
+
+impl<'a> FromSql<'a> for RawDate {
+    fn from_sql(_: &Type, raw: &'a [u8]) -> Result<Self, Box<dyn std::error::Error + Sync + Send>> {
+        Ok(RawDate(i32::from_be_bytes(raw.try_into()?)))
+    }
+
+    fn accepts(ty: &Type) -> bool {
+        *ty == Type::DATE
+    }
+}
+
+/// A `timestamp` or `timestamptz` in its wire format: microseconds since
+/// 2000-01-01 00:00:00 UTC.
+struct RawTimestamp(i64);
+
+impl<'a> FromSql<'a> for RawTimestamp {
+    fn from_sql(_: &Type, raw: &'a [u8]) -> Result<Self, Box<dyn std::error::Error + Sync + Send>> {
+        Ok(RawTimestamp(i64::from_be_bytes(raw.try_into()?)))
+    }
+
+    fn accepts(ty: &Type) -> bool {
+        *ty == Type::TIMESTAMP || *ty == Type::TIMESTAMPTZ
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::PostgresConnectionManager;
+    use arrow::array::Array;
+    use dotenv::dotenv;
+    use std::env;
+
+    #[tokio::test]
+    async fn test_query_arrow() {
+        dotenv().ok();
+        let database_url = env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+        let manager = PostgresConnectionManager::new(database_url);
+        let client = manager.connect().await.expect("Failed to connect to database");
+
+        let batch = query_arrow(
+            &client,
+            "SELECT id, name, score, active, joined_on, seen_at
+             FROM (VALUES
+                 (1, 'Alice'::text, 9.5::float8, true, DATE '1970-01-02', TIMESTAMP '2000-01-01 00:00:01'),
+                 ($1::int4, NULL, NULL, false, NULL, NULL)
+             ) AS t(id, name, score, active, joined_on, seen_at)",
+            &[&2i32],
+        ).await.expect("Failed to run query");
+
+        assert_eq!(batch.num_rows(), 2);
+        let schema = batch.schema();
+        let fields: Vec<(&str, &DataType)> = schema.fields().iter().map(|f| (f.name().as_str(), f.data_type())).collect();
+        assert_eq!(fields, vec![
+            ("id", &DataType::Int32),
+            ("name", &DataType::Utf8),
+            ("score", &DataType::Float64),
+            ("active", &DataType::Boolean),
+            ("joined_on", &DataType::Date32),
+            ("seen_at", &DataType::Timestamp(TimeUnit::Microsecond, None)),
+        ]);
+
+        let ids = batch.column(0).as_any().downcast_ref::<Int32Array>().unwrap();
+        assert_eq!((ids.value(0), ids.value(1)), (1, 2));
+        assert!(batch.column(1).is_null(1));
+        assert_eq!(batch.column(4).as_any().downcast_ref::<Date32Array>().unwrap().value(0), 1);
+        assert_eq!(batch.column(5).as_any().downcast_ref::<TimestampMicrosecondArray>().unwrap().value(0), PG_EPOCH_MICROS + 1_000_000);
+
+        match query_arrow(&client, "SELECT '{}'::jsonb AS doc", &[]).await {
+            Err(OrmError::QueryError(message)) => assert!(message.contains("Column 'doc' has type jsonb"), "{}", message),
+            other => panic!("Expected QueryError, got {:?}", other.map(|batch| batch.num_rows())),
+        }
+    }
+}
@@ -1,4 +1,6 @@
+use crate::query_builder::quote_identifier;
 use std::any::Any;
+use std::collections::{BTreeMap, BTreeSet};
 
 pub enum RelationType {
     OneToOne,
@@ -11,6 +13,7 @@ pub struct Relationship {
     pub related_table: String,
 }
 impl Relationship {
+    #[allow(clippy::new_ret_no_self)]
     pub fn new(relation_type: RelationType, foreign_key: &str, related_table: &str) -> Box<dyn Any> {
         Box::new(Self {
             relation_type,
@@ -24,6 +27,177 @@ pub trait HasRelationships {
     fn relationships() -> Vec<Box<dyn Any>>;
 }
 
+/// A foreign key from `table.column` to `referenced_table.referenced_column`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TableDependency {
+    pub table: String,
+    pub column: String,
+    pub referenced_table: String,
+    pub referenced_column: String,
+}
+
+impl TableDependency {
+    pub fn new(table: &str, column: &str, referenced_table: &str, referenced_column: &str) -> Self {
+        Self {
+            table: table.to_string(),
+            column: column.to_string(),
+            referenced_table: referenced_table.to_string(),
+            referenced_column: referenced_column.to_string(),
+        }
+    }
+}
+
+/// The order in which tables can be created (or filled), plus the foreign
+/// keys that close a cycle and therefore have to be added afterwards as
+/// deferrable constraints.
+#[derive(Debug, PartialEq, Eq)]
+pub struct CreationPlan {
+    pub order: Vec<String>,
+    pub deferred: Vec<TableDependency>,
+}
+
+/// Foreign-key graph between tables, used to order migrations and inserts.
+#[derive(Debug, Default)]
+pub struct DependencyGraph {
+    tables: BTreeSet<String>,
+    dependencies: Vec<TableDependency>,
+}
+
+impl DependencyGraph {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add_table(&mut self, table: &str) {
+        self.tables.insert(table.to_string());
+    }
+
+    pub fn add_dependency(&mut self, dependency: TableDependency) {
+        self.tables.insert(dependency.table.clone());
+        self.tables.insert(dependency.referenced_table.clone());
+        self.dependencies.push(dependency);
+    }
+
+    /// Returns every group of two or more tables that reference each other
+    /// through a chain of foreign keys. Self-references are not reported,
+    /// since a table can always be created with a key to itself.
+    pub fn find_cycles(&self) -> Vec<Vec<String>> {
+        self.strongly_connected_components()
+            .into_iter()
+            .filter(|component| component.len() > 1)
+            .collect()
+    }
+
+    /// Orders tables so that referenced tables come first. Foreign keys that
+    /// form a cycle are left out of the ordering and returned in `deferred`.
+    pub fn creation_plan(&self) -> CreationPlan {
+        let mut component_of: BTreeMap<&str, usize> = BTreeMap::new();
+        for (index, component) in self.strongly_connected_components().iter().enumerate() {
+            for table in component {
+                component_of.insert(self.tables.get(table).unwrap(), index);
+            }
+        }
+
+        let mut deferred = Vec::new();
+        let mut references: BTreeMap<&str, BTreeSet<&str>> = self.tables.iter().map(|t| (t.as_str(), BTreeSet::new())).collect();
+        for dependency in &self.dependencies {
+            if dependency.table == dependency.referenced_table {
+                continue;
+            }
+            if component_of[dependency.table.as_str()] == component_of[dependency.referenced_table.as_str()] {
+                deferred.push(dependency.clone());
+            } else {
+                references.get_mut(dependency.table.as_str()).unwrap().insert(dependency.referenced_table.as_str());
+            }
+        }
+
+        let mut order = Vec::with_capacity(self.tables.len());
+        let mut placed: BTreeSet<&str> = BTreeSet::new();
+        while order.len() < self.tables.len() {
+            let ready: Vec<&str> = references.iter()
+                .filter(|(table, refs)| !placed.contains(*table) && refs.iter().all(|r| placed.contains(r)))
+                .map(|(table, _)| *table)
+                .collect();
+            for table in ready {
+                placed.insert(table);
+                order.push(table.to_string());
+            }
+        }
+
+        CreationPlan { order, deferred }
+    }
+
+    fn strongly_connected_components(&self) -> Vec<Vec<String>> {
+        let mut edges: BTreeMap<&str, BTreeSet<&str>> = self.tables.iter().map(|t| (t.as_str(), BTreeSet::new())).collect();
+        for dependency in &self.dependencies {
+            edges.get_mut(dependency.table.as_str()).unwrap().insert(dependency.referenced_table.as_str());
+        }
+        let mut tarjan = Tarjan { edges: &edges, index: BTreeMap::new(), low_link: BTreeMap::new(), stack: Vec::new(), on_stack: BTreeSet::new(), components: Vec::new() };
+        for table in edges.keys() {
+            if !tarjan.index.contains_key(table) {
+                tarjan.visit(table);
+            }
+        }
+        tarjan.components
+    }
+}
+
+struct Tarjan<'a> {
+    edges: &'a BTreeMap<&'a str, BTreeSet<&'a str>>,
+    index: BTreeMap<&'a str, usize>,
+    low_link: BTreeMap<&'a str, usize>,
+    stack: Vec<&'a str>,
+    on_stack: BTreeSet<&'a str>,
+    components: Vec<Vec<String>>,
+}
+
+impl<'a> Tarjan<'a> {
+    fn visit(&mut self, table: &'a str) {
+        let index = self.index.len();
+        self.index.insert(table, index);
+        self.low_link.insert(table, index);
+        self.stack.push(table);
+        self.on_stack.insert(table);
+
+        for &next in &self.edges[table] {
+            if !self.index.contains_key(next) {
+                self.visit(next);
+                let low = self.low_link[table].min(self.low_link[next]);
+                self.low_link.insert(table, low);
+            } else if self.on_stack.contains(next) {
+                let low = self.low_link[table].min(self.index[next]);
+                self.low_link.insert(table, low);
+            }
+        }
+
+        if self.low_link[table] == self.index[table] {
+            let mut component = Vec::new();
+            while let Some(member) = self.stack.pop() {
+                self.on_stack.remove(member);
+                component.push(member.to_string());
+                if member == table {
+                    break;
+                }
+            }
+            component.sort();
+            self.components.push(component);
+        }
+    }
+}
+
+/// DDL adding `dependency` as a constraint that is only checked at commit,
+/// so rows in mutually-referencing tables can be inserted in one transaction.
+pub fn deferrable_foreign_key_ddl(dependency: &TableDependency) -> String {
+    format!(
+        "ALTER TABLE {} ADD CONSTRAINT {} FOREIGN KEY ({}) REFERENCES {} ({}) DEFERRABLE INITIALLY DEFERRED;",
+        quote_identifier(&dependency.table),
+        quote_identifier(&format!("{}_{}_fkey", dependency.table, dependency.column)),
+        quote_identifier(&dependency.column),
+        quote_identifier(&dependency.referenced_table),
+        quote_identifier(&dependency.referenced_column)
+    )
+}
+
 // Example implementation
 pub struct User;
 
@@ -64,4 +238,48 @@ mod tests {
         assert_eq!(profile_rel.foreign_key, "user_id");
         assert_eq!(profile_rel.related_table, "profile");
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_dependency_graph_orders_referenced_tables_first() {
+        let mut graph = DependencyGraph::new();
+        graph.add_dependency(TableDependency::new("comments", "post_id", "posts", "id"));
+        graph.add_dependency(TableDependency::new("posts", "user_id", "users", "id"));
+        graph.add_dependency(TableDependency::new("users", "manager_id", "users", "id"));
+
+        assert!(graph.find_cycles().is_empty());
+
+        let plan = graph.creation_plan();
+        assert_eq!(plan.order, vec!["users", "posts", "comments"]);
+        assert!(plan.deferred.is_empty());
+    }
+
+    #[test]
+    fn test_dependency_graph_detects_circular_references() {
+        let mut graph = DependencyGraph::new();
+        graph.add_table("audit_log");
+        graph.add_dependency(TableDependency::new("departments", "head_id", "employees", "id"));
+        graph.add_dependency(TableDependency::new("employees", "department_id", "departments", "id"));
+
+        assert_eq!(graph.find_cycles(), vec![vec!["departments".to_string(), "employees".to_string()]]);
+
+        let plan = graph.creation_plan();
+        assert_eq!(plan.order, vec!["audit_log", "departments", "employees"]);
+        assert_eq!(plan.deferred.len(), 2);
+
+        let ddl: Vec<String> = plan.deferred.iter().map(deferrable_foreign_key_ddl).collect();
+        assert_eq!(
+            ddl[0],
+            "ALTER TABLE departments ADD CONSTRAINT departments_head_id_fkey FOREIGN KEY (head_id) REFERENCES employees (id) DEFERRABLE INITIALLY DEFERRED;"
+        );
+        assert!(ddl[1].starts_with("ALTER TABLE employees ADD CONSTRAINT employees_department_id_fkey"));
+    }
+
+    #[test]
+    fn test_deferrable_foreign_key_ddl_quotes_identifiers() {
+        let ddl = deferrable_foreign_key_ddl(&TableDependency::new("Order Items", "order id", "orders", "ID"));
+        assert_eq!(
+            ddl,
+            "ALTER TABLE \"Order Items\" ADD CONSTRAINT \"Order Items_order id_fkey\" FOREIGN KEY (\"order id\") REFERENCES orders (\"ID\") DEFERRABLE INITIALLY DEFERRED;"
+        );
+    }
+}
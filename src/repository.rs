@@ -0,0 +1,124 @@
+use crate::query_builder::Model;
+use std::future::Future;
+
+/// CRUD access to a model `T`.
+///
+/// The error type is chosen by each implementation so repositories can
+/// surface application-specific errors. Repositories backed by this crate
+/// use `OrmError`; associated type defaults are not available on stable
+/// Rust, so it has to be named explicitly (`type Error = OrmError;`).
+pub trait Repository<T: Model> {
+    type Id;
+    type Error: std::error::Error;
+
+    fn create(&self, entity: &T) -> impl Future<Output = Result<T, Self::Error>> + Send;
+    fn get(&self, id: Self::Id) -> impl Future<Output = Result<Option<T>, Self::Error>> + Send;
+    fn update(&self, entity: &T) -> impl Future<Output = Result<T, Self::Error>> + Send;
+    fn delete(&self, id: Self::Id) -> impl Future<Output = Result<bool, Self::Error>> + Send;
+    fn list(&self, limit: Option<i64>, offset: Option<i64>) -> impl Future<Output = Result<Vec<T>, Self::Error>> + Send;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+    use std::fmt;
+    use tokio::sync::Mutex;
+
+    #[derive(Debug, Clone, PartialEq)]
+    struct Note {
+        id: i32,
+        body: String,
+    }
+
+    impl Model for Note {
+        fn table_name() -> &'static str {
+            "notes"
+        }
+
+        fn columns() -> &'static [&'static str] {
+            &["id", "body"]
+        }
+
+        fn from_row(row: &tokio_postgres::Row) -> Self {
+            Note { id: row.get("id"), body: row.get("body") }
+        }
+    }
+
+    #[derive(Debug, PartialEq)]
+    enum NoteError {
+        Duplicate(i32),
+        Missing(i32),
+    }
+
+    impl fmt::Display for NoteError {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            match self {
+                NoteError::Duplicate(id) => write!(f, "Note {} already exists", id),
+                NoteError::Missing(id) => write!(f, "Note {} does not exist", id),
+            }
+        }
+    }
+
+    impl std::error::Error for NoteError {}
+
+    struct InMemoryNotes {
+        notes: Mutex<BTreeMap<i32, Note>>,
+    }
+
+    impl Repository<Note> for InMemoryNotes {
+        type Id = i32;
+        type Error = NoteError;
+
+        async fn create(&self, entity: &Note) -> Result<Note, NoteError> {
+            let mut notes = self.notes.lock().await;
+            if notes.contains_key(&entity.id) {
+                return Err(NoteError::Duplicate(entity.id));
+            }
+            notes.insert(entity.id, entity.clone());
+            Ok(entity.clone())
+        }
+
+        async fn get(&self, id: i32) -> Result<Option<Note>, NoteError> {
+            Ok(self.notes.lock().await.get(&id).cloned())
+        }
+
+        async fn update(&self, entity: &Note) -> Result<Note, NoteError> {
+            match self.notes.lock().await.get_mut(&entity.id) {
+                Some(note) => {
+                    *note = entity.clone();
+                    Ok(entity.clone())
+                }
+                None => Err(NoteError::Missing(entity.id)),
+            }
+        }
+
+        async fn delete(&self, id: i32) -> Result<bool, NoteError> {
+            Ok(self.notes.lock().await.remove(&id).is_some())
+        }
+
+        async fn list(&self, limit: Option<i64>, offset: Option<i64>) -> Result<Vec<Note>, NoteError> {
+            let notes = self.notes.lock().await;
+            let skip = offset.unwrap_or(0) as usize;
+            let take = limit.map_or(usize::MAX, |l| l as usize);
+            Ok(notes.values().skip(skip).take(take).cloned().collect())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_repository_with_custom_error() {
+        let repository = InMemoryNotes { notes: Mutex::new(BTreeMap::new()) };
+        let note = Note { id: 1, body: "first".to_string() };
+
+        assert_eq!(repository.create(&note).await, Ok(note.clone()));
+        assert_eq!(repository.create(&note).await, Err(NoteError::Duplicate(1)));
+        assert_eq!(repository.get(1).await, Ok(Some(note.clone())));
+
+        let missing = Note { id: 2, body: "second".to_string() };
+        assert_eq!(repository.update(&missing).await, Err(NoteError::Missing(2)));
+
+        assert_eq!(repository.list(Some(10), None).await.map(|notes| notes.len()), Ok(1));
+        assert_eq!(repository.delete(1).await, Ok(true));
+        assert_eq!(repository.get(1).await, Ok(None));
+    }
+}
@@ -0,0 +1,524 @@
+use std::collections::BTreeMap;
+use tokio_postgres::Client;
+use crate::db::PostgresConnectionManager;
+use crate::error::OrmError;
+use crate::metadata::{get_columns, get_foreign_keys, get_indexes, get_primary_keys, get_tables, ColumnInfo, ForeignKey, IndexKey};
+use crate::query_builder::quote_identifier;
+use crate::relationships::{deferrable_foreign_key_ddl, DependencyGraph};
+
+/// A table as introspected from the database.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TableSchema {
+    pub name: String,
+    pub columns: Vec<ColumnInfo>,
+    /// Primary key columns in key order; empty when the table has none.
+    pub primary_key: Vec<String>,
+    pub foreign_keys: Vec<ForeignKey>,
+}
+
+/// How likely a schema difference is to break code or data that was written
+/// against the first schema.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Info,
+    Warning,
+    Critical,
+}
+
+/// One difference found by `SchemaDocument::diff`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SchemaChange {
+    TableAdded,
+    TableRemoved,
+    ColumnAdded { column: String },
+    ColumnRemoved { column: String },
+    ColumnRetyped { column: String, from: String, to: String },
+    ForeignKeyAdded(ForeignKey),
+    ForeignKeyRemoved(ForeignKey),
+    /// The foreign key on `to.column` now references another table or
+    /// column, or has other referential actions.
+    ForeignKeyChanged { from: ForeignKey, to: ForeignKey },
+}
+
+impl SchemaChange {
+    pub fn severity(&self) -> Severity {
+        match self {
+            SchemaChange::TableAdded | SchemaChange::ColumnAdded { .. } | SchemaChange::ForeignKeyAdded(_) => Severity::Info,
+            SchemaChange::TableRemoved | SchemaChange::ColumnRemoved { .. } | SchemaChange::ForeignKeyRemoved(_) | SchemaChange::ForeignKeyChanged { .. } => Severity::Warning,
+            SchemaChange::ColumnRetyped { .. } => Severity::Critical,
+        }
+    }
+}
+
+/// A difference in `table`, with the severity of its change.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SchemaChangeEvent {
+    pub table: String,
+    pub change: SchemaChange,
+    pub severity: Severity,
+}
+
+impl SchemaChangeEvent {
+    pub fn new(table: &str, change: SchemaChange) -> Self {
+        let severity = change.severity();
+        Self { table: table.to_string(), change, severity }
+    }
+}
+
+/// What is wrong with the target of a foreign key, as found by
+/// `SchemaDocument::check_foreign_keys`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ForeignKeyProblem {
+    /// The referenced table does not exist in the `public` schema.
+    MissingTable,
+    /// No primary key or unique index covers exactly the referenced column,
+    /// e.g. because the target is a view, so a key may match several rows.
+    NotUnique,
+    /// The referenced table has no rows, so the key can only be NULL.
+    EmptyTable,
+}
+
+impl ForeignKeyProblem {
+    pub fn severity(&self) -> Severity {
+        match self {
+            ForeignKeyProblem::MissingTable | ForeignKeyProblem::NotUnique => Severity::Critical,
+            ForeignKeyProblem::EmptyTable => Severity::Info,
+        }
+    }
+}
+
+/// A foreign key of `table` whose target has a problem.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ForeignKeyIssue {
+    pub table: String,
+    pub foreign_key: ForeignKey,
+    pub problem: ForeignKeyProblem,
+    pub severity: Severity,
+}
+
+impl ForeignKeyIssue {
+    pub fn new(table: &str, foreign_key: &ForeignKey, problem: ForeignKeyProblem) -> Self {
+        let severity = problem.severity();
+        Self { table: table.to_string(), foreign_key: foreign_key.clone(), problem, severity }
+    }
+}
+
+/// What `check_foreign_keys` found out about a referenced table.
+struct ReferencedTable {
+    /// Columns that are on their own the key of a primary key or a
+    /// non-partial unique index.
+    unique_columns: Vec<String>,
+    is_empty: bool,
+}
+
+/// Introspects the databases at `url_a` and `url_b`, e.g. staging and
+/// production, and reports what changes going from the first to the second.
+pub async fn schema_diff(url_a: &str, url_b: &str) -> Result<Vec<SchemaChangeEvent>, OrmError> {
+    let client_a = PostgresConnectionManager::new(url_a.to_string()).connect().await?;
+    let client_b = PostgresConnectionManager::new(url_b.to_string()).connect().await?;
+    let schema_a = SchemaDocument::introspect(&client_a).await?;
+    let schema_b = SchemaDocument::introspect(&client_b).await?;
+    Ok(schema_a.diff(&schema_b))
+}
+
+/// The tables of the `public` schema with their columns and keys, e.g. for
+/// documentation or to seed a test database through `to_ddl`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SchemaDocument {
+    pub tables: Vec<TableSchema>,
+}
+
+impl SchemaDocument {
+    /// Introspects every table `metadata::get_tables` reports.
+    pub async fn introspect(client: &Client) -> Result<Self, OrmError> {
+        let tables = get_tables(client).await?;
+        let tables: Vec<&str> = tables.iter().map(String::as_str).collect();
+        Self::introspect_tables(client, &tables).await
+    }
+
+    /// Introspects only `table_names`, ordered by name.
+    pub async fn introspect_tables(client: &Client, table_names: &[&str]) -> Result<Self, OrmError> {
+        let mut names = table_names.to_vec();
+        names.sort();
+        let mut tables = Vec::with_capacity(names.len());
+        for name in names {
+            tables.push(TableSchema {
+                name: name.to_string(),
+                columns: get_columns(client, name).await?,
+                primary_key: get_primary_keys(client, name).await?,
+                foreign_keys: get_foreign_keys(client, name).await?,
+            });
+        }
+        Ok(SchemaDocument { tables })
+    }
+
+    /// Reports the differences going from `self` to `other`, ordered by
+    /// table name. Columns are matched by name and foreign keys by column.
+    pub fn diff(&self, other: &SchemaDocument) -> Vec<SchemaChangeEvent> {
+        let before: BTreeMap<&str, &TableSchema> = self.tables.iter().map(|table| (table.name.as_str(), table)).collect();
+        let after: BTreeMap<&str, &TableSchema> = other.tables.iter().map(|table| (table.name.as_str(), table)).collect();
+        let mut names: Vec<&str> = before.keys().chain(after.keys()).copied().collect();
+        names.sort();
+        names.dedup();
+
+        let mut events = Vec::new();
+        for name in names {
+            match (before.get(name), after.get(name)) {
+                (Some(_), None) => events.push(SchemaChangeEvent::new(name, SchemaChange::TableRemoved)),
+                (None, Some(_)) => events.push(SchemaChangeEvent::new(name, SchemaChange::TableAdded)),
+                (Some(old), Some(new)) => events.extend(table_changes(old, new).into_iter().map(|change| SchemaChangeEvent::new(name, change))),
+                (None, None) => {}
+            }
+        }
+        events
+    }
+
+    /// Checks what each foreign key in the document references against the
+    /// database, ordered like the tables and their keys. The database
+    /// enforces unique targets for the keys it holds, so invalid targets
+    /// come from documents assembled elsewhere, e.g. parsed with `from_dump`
+    /// or edited by hand; empty referenced tables are reported either way.
+    pub async fn check_foreign_keys(&self, client: &Client) -> Result<Vec<ForeignKeyIssue>, OrmError> {
+        let mut targets: BTreeMap<&str, Option<ReferencedTable>> = BTreeMap::new();
+        for foreign_key in self.tables.iter().flat_map(|table| &table.foreign_keys) {
+            let name = foreign_key.foreign_table.as_str();
+            if !targets.contains_key(name) {
+                targets.insert(name, referenced_table(client, name).await?);
+            }
+        }
+        Ok(foreign_key_issues(self, &targets))
+    }
+
+    /// Emits one `CREATE TABLE` per table, ordered so referenced tables are
+    /// created first. Foreign keys that form a cycle are added afterwards as
+    /// deferrable constraints.
+    ///
+    /// The DDL is lossy: it recreates the tables' columns, keys and generated
+    /// columns, e.g. to seed a test database, not an exact copy. Columns keep
+    /// only the type name the catalog reported, so varchar lengths, numeric
+    /// precision and scale, array and user-defined types are not
+    /// reconstructed, and defaults (including `serial` sequences), check
+    /// constraints and secondary indexes are left out.
+    pub fn to_ddl(&self) -> String {
+        let mut graph = DependencyGraph::new();
+        for table in &self.tables {
+            graph.add_table(&table.name);
+            for foreign_key in &table.foreign_keys {
+                graph.add_dependency(foreign_key.dependency(&table.name));
+            }
+        }
+        let plan = graph.creation_plan();
+
+        let mut statements = Vec::new();
+        for name in &plan.order {
+            // Referenced tables outside the document are expected to exist already
+            let Some(table) = self.tables.iter().find(|table| &table.name == name) else { continue };
+            let mut definitions: Vec<String> = table.columns.iter().map(column_ddl).collect();
+            if !table.primary_key.is_empty() {
+                let key: Vec<String> = table.primary_key.iter().map(|column| quote_identifier(column)).collect();
+                definitions.push(format!("PRIMARY KEY ({})", key.join(", ")));
+            }
+            for foreign_key in table.foreign_keys.iter().filter(|foreign_key| !plan.deferred.contains(&foreign_key.dependency(&table.name))) {
+                definitions.push(format!(
+                    "FOREIGN KEY ({}) REFERENCES {} ({}){}",
+                    quote_identifier(&foreign_key.column),
+                    quote_identifier(&foreign_key.foreign_table),
+                    quote_identifier(&foreign_key.foreign_column),
+                    referential_actions(foreign_key)
+                ));
+            }
+            statements.push(format!("CREATE TABLE {} (\n    {}\n);", quote_identifier(&table.name), definitions.join(",\n    ")));
+        }
+        for dependency in &plan.deferred {
+            let ddl = deferrable_foreign_key_ddl(dependency);
+            let foreign_key = self.tables.iter()
+                .filter(|table| table.name == dependency.table)
+                .flat_map(|table| &table.foreign_keys)
+                .find(|foreign_key| foreign_key.column == dependency.column);
+            match foreign_key {
+                Some(foreign_key) => statements.push(ddl.replace(" DEFERRABLE", &format!("{} DEFERRABLE", referential_actions(foreign_key)))),
+                None => statements.push(ddl),
+            }
+        }
+        statements.join("\n\n") + "\n"
+    }
+}
+
+/// Reads the unique columns and emptiness of `name`, or `None` if there is
+/// no such table or view.
+async fn referenced_table(client: &Client, name: &str) -> Result<Option<ReferencedTable>, OrmError> {
+    let qualified = format!("public.{}", quote_identifier(name));
+    let exists: bool = client.query_one("SELECT to_regclass($1) IS NOT NULL", &[&qualified]).await?.get(0);
+    if !exists {
+        return Ok(None);
+    }
+    // A foreign key can only reference a key that no WHERE predicate limits
+    let unique_columns = get_indexes(client, name).await?.into_iter()
+        .filter(|index| index.is_unique && !index.definition.contains(" WHERE "))
+        .filter_map(|index| match index.keys.as_slice() {
+            [IndexKey::Column(column)] => Some(unquote_identifier(column)),
+            _ => None,
+        })
+        .collect();
+    let is_empty: bool = client.query_one(&format!("SELECT NOT EXISTS (SELECT 1 FROM {})", qualified), &[]).await?.get(0);
+    Ok(Some(ReferencedTable { unique_columns, is_empty }))
+}
+
+fn foreign_key_issues(document: &SchemaDocument, targets: &BTreeMap<&str, Option<ReferencedTable>>) -> Vec<ForeignKeyIssue> {
+    let mut issues = Vec::new();
+    for table in &document.tables {
+        for foreign_key in &table.foreign_keys {
+            let Some(Some(target)) = targets.get(foreign_key.foreign_table.as_str()) else {
+                issues.push(ForeignKeyIssue::new(&table.name, foreign_key, ForeignKeyProblem::MissingTable));
+                continue;
+            };
+            if !target.unique_columns.contains(&foreign_key.foreign_column) {
+                issues.push(ForeignKeyIssue::new(&table.name, foreign_key, ForeignKeyProblem::NotUnique));
+            }
+            if target.is_empty {
+                issues.push(ForeignKeyIssue::new(&table.name, foreign_key, ForeignKeyProblem::EmptyTable));
+            }
+        }
+    }
+    issues
+}
+
+fn table_changes(old: &TableSchema, new: &TableSchema) -> Vec<SchemaChange> {
+    let mut changes = Vec::new();
+    for column in &old.columns {
+        match new.columns.iter().find(|candidate| candidate.name == column.name) {
+            None => changes.push(SchemaChange::ColumnRemoved { column: column.name.clone() }),
+            Some(retyped) if retyped.data_type != column.data_type => changes.push(SchemaChange::ColumnRetyped {
+                column: column.name.clone(),
+                from: column.data_type.clone(),
+                to: retyped.data_type.clone(),
+            }),
+            Some(_) => {}
+        }
+    }
+    for column in new.columns.iter().filter(|column| !old.columns.iter().any(|existing| existing.name == column.name)) {
+        changes.push(SchemaChange::ColumnAdded { column: column.name.clone() });
+    }
+
+    for foreign_key in &old.foreign_keys {
+        match new.foreign_keys.iter().find(|candidate| candidate.column == foreign_key.column) {
+            None => changes.push(SchemaChange::ForeignKeyRemoved(foreign_key.clone())),
+            Some(changed) if changed != foreign_key => changes.push(SchemaChange::ForeignKeyChanged { from: foreign_key.clone(), to: changed.clone() }),
+            Some(_) => {}
+        }
+    }
+    for foreign_key in new.foreign_keys.iter().filter(|foreign_key| !old.foreign_keys.iter().any(|existing| existing.column == foreign_key.column)) {
+        changes.push(SchemaChange::ForeignKeyAdded(foreign_key.clone()));
+    }
+    changes
+}
+
+fn column_ddl(column: &ColumnInfo) -> String {
+    let mut ddl = format!("{} {}", quote_identifier(&column.name), column.data_type);
+    if let Some(expression) = &column.generation_expression {
+        ddl += &format!(" GENERATED ALWAYS AS ({}) STORED", expression);
+    }
+    if !column.is_nullable {
+        ddl += " NOT NULL";
+    }
+    ddl
+}
+
+/// ` ON DELETE ...`/` ON UPDATE ...` for the rules that differ from the default.
+fn referential_actions(foreign_key: &ForeignKey) -> String {
+    let mut actions = String::new();
+    if foreign_key.on_delete != "NO ACTION" {
+        actions += &format!(" ON DELETE {}", foreign_key.on_delete);
+    }
+    if foreign_key.on_update != "NO ACTION" {
+        actions += &format!(" ON UPDATE {}", foreign_key.on_update);
+    }
+    actions
+}
+
+/// Reverses the quoting `pg_get_indexdef` applies to column names.
+fn unquote_identifier(name: &str) -> String {
+    match name.strip_prefix('"').and_then(|name| name.strip_suffix('"')) {
+        Some(quoted) => quoted.replace("\"\"", "\""),
+        None => name.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio;
+    use dotenv::dotenv;
+    use std::env;
+    use crate::db::PostgresConnectionManager;
+
+    #[test]
+    fn test_to_ddl_orders_tables_and_defers_cycles() {
+        let table = |name: &str, foreign_keys: Vec<ForeignKey>| TableSchema {
+            name: name.to_string(),
+            columns: vec![ColumnInfo::new("id", "integer"), ColumnInfo::new("other_id", "integer").nullable()],
+            primary_key: vec!["id".to_string()],
+            foreign_keys,
+        };
+        let document = SchemaDocument {
+            tables: vec![
+                table("employees", vec![ForeignKey::new("other_id", "departments", "id")]),
+                table("departments", vec![ForeignKey::new("other_id", "employees", "id").on_delete("SET NULL")]),
+                table("regions", vec![]),
+                table("offices", vec![ForeignKey::new("other_id", "regions", "id").on_delete("CASCADE")]),
+            ],
+        };
+
+        let ddl = document.to_ddl();
+
+        assert!(ddl.contains("CREATE TABLE offices (\n    id integer NOT NULL,\n    other_id integer,\n    PRIMARY KEY (id),\n    FOREIGN KEY (other_id) REFERENCES regions (id) ON DELETE CASCADE\n);"));
+        assert!(ddl.find("CREATE TABLE regions").unwrap() < ddl.find("CREATE TABLE offices").unwrap());
+        assert!(!ddl.contains("    FOREIGN KEY (other_id) REFERENCES employees"));
+        assert!(ddl.contains("ALTER TABLE departments ADD CONSTRAINT departments_other_id_fkey FOREIGN KEY (other_id) REFERENCES employees (id) ON DELETE SET NULL DEFERRABLE INITIALLY DEFERRED;"));
+        assert!(ddl.contains("ALTER TABLE employees ADD CONSTRAINT employees_other_id_fkey FOREIGN KEY (other_id) REFERENCES departments (id) DEFERRABLE INITIALLY DEFERRED;"));
+    }
+
+    #[test]
+    fn test_diff_reports_changes_with_severity() {
+        let staging = SchemaDocument {
+            tables: vec![
+                TableSchema {
+                    name: "orders".to_string(),
+                    columns: vec![ColumnInfo::new("id", "integer"), ColumnInfo::new("total", "numeric"), ColumnInfo::new("customer_id", "integer"), ColumnInfo::new("notes", "text")],
+                    primary_key: vec!["id".to_string()],
+                    foreign_keys: vec![ForeignKey::new("customer_id", "customers", "id")],
+                },
+                TableSchema { name: "customers".to_string(), columns: vec![ColumnInfo::new("id", "integer")], ..TableSchema::default() },
+                TableSchema { name: "legacy_audit".to_string(), columns: vec![ColumnInfo::new("id", "integer")], ..TableSchema::default() },
+            ],
+        };
+        let production = SchemaDocument {
+            tables: vec![
+                TableSchema { name: "customers".to_string(), columns: vec![ColumnInfo::new("id", "integer")], ..TableSchema::default() },
+                TableSchema {
+                    name: "orders".to_string(),
+                    columns: vec![ColumnInfo::new("id", "integer"), ColumnInfo::new("total", "bigint"), ColumnInfo::new("customer_id", "integer"), ColumnInfo::new("placed_at", "date")],
+                    primary_key: vec!["id".to_string()],
+                    foreign_keys: vec![ForeignKey::new("customer_id", "customers", "id").on_delete("CASCADE")],
+                },
+                TableSchema { name: "invoices".to_string(), columns: vec![ColumnInfo::new("id", "integer")], ..TableSchema::default() },
+            ],
+        };
+
+        let events = staging.diff(&production);
+
+        let expected = vec![
+            SchemaChangeEvent::new("invoices", SchemaChange::TableAdded),
+            SchemaChangeEvent::new("legacy_audit", SchemaChange::TableRemoved),
+            SchemaChangeEvent::new("orders", SchemaChange::ColumnRetyped { column: "total".to_string(), from: "numeric".to_string(), to: "bigint".to_string() }),
+            SchemaChangeEvent::new("orders", SchemaChange::ColumnRemoved { column: "notes".to_string() }),
+            SchemaChangeEvent::new("orders", SchemaChange::ColumnAdded { column: "placed_at".to_string() }),
+            SchemaChangeEvent::new("orders", SchemaChange::ForeignKeyChanged {
+                from: ForeignKey::new("customer_id", "customers", "id"),
+                to: ForeignKey::new("customer_id", "customers", "id").on_delete("CASCADE"),
+            }),
+        ];
+        assert_eq!(events, expected);
+        let severities: Vec<Severity> = events.iter().map(|event| event.severity).collect();
+        assert_eq!(severities, vec![Severity::Info, Severity::Warning, Severity::Critical, Severity::Warning, Severity::Info, Severity::Warning]);
+        assert!(staging.diff(&staging).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_to_ddl_recreates_columns_and_keys() {
+        dotenv().ok();
+        let database_url = env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+        let manager = PostgresConnectionManager::new(database_url);
+        let client = manager.connect().await.expect("Failed to connect to database");
+
+        client.batch_execute(
+            "DROP TABLE IF EXISTS ddl_books, ddl_authors;
+             CREATE TABLE ddl_authors (
+                 id SERIAL PRIMARY KEY,
+                 name VARCHAR(100) NOT NULL,
+                 born DATE
+             );
+             CREATE TABLE ddl_books (
+                 isbn TEXT,
+                 edition INTEGER,
+                 author_id INTEGER NOT NULL REFERENCES ddl_authors (id) ON DELETE CASCADE,
+                 price NUMERIC(8, 2),
+                 price_with_tax NUMERIC GENERATED ALWAYS AS (price * 1.2) STORED,
+                 PRIMARY KEY (isbn, edition)
+             );"
+        ).await.expect("Failed to create test tables");
+
+        let tables = ["ddl_books", "ddl_authors"];
+        let original = SchemaDocument::introspect_tables(&client, &tables).await.expect("Failed to introspect schema");
+        let ddl = original.to_ddl();
+        client.batch_execute("DROP TABLE ddl_books, ddl_authors").await.unwrap();
+        let applied = client.batch_execute(&ddl).await;
+        let recreated = SchemaDocument::introspect_tables(&client, &tables).await;
+        let name_length: Option<i32> = client.query_one(
+            "SELECT character_maximum_length FROM information_schema.columns WHERE table_name = 'ddl_authors' AND column_name = 'name'", &[]
+        ).await.unwrap().get(0);
+        let id_default: Option<String> = client.query_one(
+            "SELECT column_default FROM information_schema.columns WHERE table_name = 'ddl_authors' AND column_name = 'id'", &[]
+        ).await.unwrap().get(0);
+        client.batch_execute("DROP TABLE IF EXISTS ddl_books, ddl_authors").await.unwrap();
+
+        applied.unwrap_or_else(|e| panic!("Failed to apply DDL: {}\n{}", e, ddl));
+        assert!(ddl.find("CREATE TABLE ddl_authors").unwrap() < ddl.find("CREATE TABLE ddl_books").unwrap());
+        assert_eq!(original.tables[1].primary_key, vec!["isbn".to_string(), "edition".to_string()]);
+        assert_eq!(recreated.expect("Failed to introspect recreated schema"), original);
+        // The documented losses: the varchar length and the serial default
+        assert_eq!(name_length, None);
+        assert_eq!(id_default, None);
+    }
+
+    #[tokio::test]
+    async fn test_check_foreign_keys_flags_invalid_and_empty_targets() {
+        dotenv().ok();
+        let database_url = env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+        let manager = PostgresConnectionManager::new(database_url);
+        let client = manager.connect().await.expect("Failed to connect to database");
+        client.batch_execute(
+            "DROP VIEW IF EXISTS fk_check_customer_emails;
+             DROP TABLE IF EXISTS fk_check_customers, fk_check_regions;
+             CREATE TABLE fk_check_customers (id INTEGER PRIMARY KEY, \"Email\" TEXT, code TEXT, UNIQUE (\"Email\"));
+             CREATE UNIQUE INDEX fk_check_customers_code ON fk_check_customers (code) WHERE code IS NOT NULL;
+             CREATE TABLE fk_check_regions (id INTEGER PRIMARY KEY);
+             CREATE VIEW fk_check_customer_emails AS SELECT \"Email\" FROM fk_check_customers;
+             INSERT INTO fk_check_customers VALUES (1, 'a@example.com', 'A');"
+        ).await.unwrap();
+        // A contrived document, as if parsed from a loose schema, with keys Postgres would refuse
+        let keys = vec![
+            ForeignKey::new("customer_id", "fk_check_customers", "id"),
+            ForeignKey::new("customer_email", "fk_check_customers", "Email"),
+            ForeignKey::new("customer_code", "fk_check_customers", "code"),
+            ForeignKey::new("contact_email", "fk_check_customer_emails", "Email"),
+            ForeignKey::new("region_id", "fk_check_regions", "id"),
+            ForeignKey::new("warehouse_id", "fk_check_warehouses", "id"),
+        ];
+        let document = SchemaDocument {
+            tables: vec![TableSchema {
+                name: "fk_check_orders".to_string(),
+                columns: vec![ColumnInfo::new("id", "integer")],
+                primary_key: vec!["id".to_string()],
+                foreign_keys: keys.clone(),
+            }],
+        };
+
+        let issues = document.check_foreign_keys(&client).await;
+        client.batch_execute(
+            "DROP VIEW fk_check_customer_emails;
+             DROP TABLE fk_check_customers, fk_check_regions;"
+        ).await.unwrap();
+
+        let issues = issues.expect("Failed to check foreign keys");
+        assert_eq!(issues, vec![
+            // A partial unique index cannot back a foreign key
+            ForeignKeyIssue::new("fk_check_orders", &keys[2], ForeignKeyProblem::NotUnique),
+            ForeignKeyIssue::new("fk_check_orders", &keys[3], ForeignKeyProblem::NotUnique),
+            ForeignKeyIssue::new("fk_check_orders", &keys[4], ForeignKeyProblem::EmptyTable),
+            ForeignKeyIssue::new("fk_check_orders", &keys[5], ForeignKeyProblem::MissingTable),
+        ]);
+        assert_eq!(issues[1].severity, Severity::Critical);
+        assert_eq!(issues[2].severity, Severity::Info);
+    }
+}
@@ -0,0 +1,125 @@
+use std::future::Future;
+use std::time::Instant;
+
+/// Counter of CRUD calls, labelled with `table` and `operation`.
+pub const OPERATIONS_TOTAL: &str = "rust_orm_gen_operations_total";
+/// Counter of CRUD calls that returned an error, with the same labels.
+pub const OPERATION_ERRORS_TOTAL: &str = "rust_orm_gen_operation_errors_total";
+/// Histogram of CRUD call latency in seconds, with the same labels.
+pub const OPERATION_DURATION_SECONDS: &str = "rust_orm_gen_operation_duration_seconds";
+
+/// Awaits `operation_future` and records the call through the `metrics`
+/// facade, so whichever recorder the application installed (Prometheus,
+/// OpenTelemetry, ...) receives it. Generated CRUD functions wrap their
+/// bodies in this when built with `GeneratorConfig::with_metrics`.
+pub async fn instrument<T, E, F>(table: &'static str, operation: &'static str, operation_future: F) -> Result<T, E>
+where
+    F: Future<Output = Result<T, E>>,
+{
+    let start = Instant::now();
+    let result = operation_future.await;
+    let labels = [("table", table), ("operation", operation)];
+    metrics::counter!(OPERATIONS_TOTAL, &labels).increment(1);
+    metrics::histogram!(OPERATION_DURATION_SECONDS, &labels).record(start.elapsed().as_secs_f64());
+    if result.is_err() {
+        metrics::counter!(OPERATION_ERRORS_TOTAL, &labels).increment(1);
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::PostgresConnectionManager;
+    use crate::error::OrmError;
+    use crate::query_builder::{Model, QueryBuilder};
+    use dotenv::dotenv;
+    use metrics::{Counter, Gauge, Histogram, Key, KeyName, Metadata, Recorder, SharedString, Unit};
+    use std::collections::HashMap;
+    use std::env;
+    use std::sync::atomic::{AtomicU64, Ordering};
+    use std::sync::{Arc, Mutex};
+    use tokio_postgres::Row;
+
+    /// Keeps every counter in memory, keyed by name and labels.
+    #[derive(Default)]
+    struct RecordingRecorder {
+        counters: Mutex<HashMap<String, Arc<AtomicU64>>>,
+    }
+
+    impl RecordingRecorder {
+        fn counter_value(&self, key: &str) -> u64 {
+            self.counters.lock().unwrap().get(key).map_or(0, |counter| counter.load(Ordering::SeqCst))
+        }
+    }
+
+    impl Recorder for RecordingRecorder {
+        fn describe_counter(&self, _key: KeyName, _unit: Option<Unit>, _description: SharedString) {}
+        fn describe_gauge(&self, _key: KeyName, _unit: Option<Unit>, _description: SharedString) {}
+        fn describe_histogram(&self, _key: KeyName, _unit: Option<Unit>, _description: SharedString) {}
+
+        fn register_counter(&self, key: &Key, _metadata: &Metadata<'_>) -> Counter {
+            let labels: Vec<String> = key.labels().map(|label| format!("{}={}", label.key(), label.value())).collect();
+            let name = format!("{}{{{}}}", key.name(), labels.join(","));
+            Counter::from_arc(self.counters.lock().unwrap().entry(name).or_default().clone())
+        }
+
+        fn register_gauge(&self, _key: &Key, _metadata: &Metadata<'_>) -> Gauge {
+            Gauge::noop()
+        }
+
+        fn register_histogram(&self, _key: &Key, _metadata: &Metadata<'_>) -> Histogram {
+            Histogram::noop()
+        }
+    }
+
+    struct TelemetryUsers;
+
+    impl Model for TelemetryUsers {
+        fn table_name() -> &'static str {
+            "telemetry_users"
+        }
+
+        fn columns() -> &'static [&'static str] {
+            &["id", "name"]
+        }
+
+        fn from_row(_row: &Row) -> Self {
+            TelemetryUsers
+        }
+    }
+
+    #[tokio::test]
+    async fn test_instrument_counts_crud_calls() {
+        dotenv().ok();
+        let database_url = env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+        let manager = PostgresConnectionManager::new(database_url);
+        let client = manager.connect().await.expect("Failed to connect to database");
+
+        let recorder: &'static RecordingRecorder = Box::leak(Box::default());
+        metrics::set_global_recorder(recorder).expect("Another recorder is already installed");
+
+        client.batch_execute(
+            "DROP TABLE IF EXISTS telemetry_users;
+             CREATE TABLE telemetry_users (id INTEGER PRIMARY KEY, name TEXT NOT NULL);
+             INSERT INTO telemetry_users VALUES (1, 'Ada');"
+        ).await.expect("Failed to create test table");
+
+        // What a generated get_telemetry_users does when built with_metrics
+        let client = &client;
+        let get = |id: i32| instrument("telemetry_users", "get", async move {
+            QueryBuilder::select::<TelemetryUsers>()
+                .where_eq("id", id)
+                .fetch_one(client)
+                .await
+        });
+        let found = get(1).await;
+        let missing: Result<TelemetryUsers, OrmError> = get(42).await;
+        client.batch_execute("DROP TABLE telemetry_users").await.unwrap();
+
+        assert!(found.is_ok());
+        assert!(missing.is_err());
+        assert_eq!(recorder.counter_value("rust_orm_gen_operations_total{table=telemetry_users,operation=get}"), 2);
+        assert_eq!(recorder.counter_value("rust_orm_gen_operation_errors_total{table=telemetry_users,operation=get}"), 1);
+    }
+}
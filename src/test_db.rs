@@ -0,0 +1,108 @@
+use crate::db::PostgresConnectionManager;
+use crate::error::OrmError;
+use postgresql_embedded::PostgreSQL;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use tokio_postgres::Client;
+
+static NEXT_DATABASE: AtomicUsize = AtomicUsize::new(0);
+
+/// A throwaway Postgres database for tests that need a live server but should
+/// not depend on `DATABASE_URL`.
+///
+/// `TestDb::start` runs an embedded Postgres server (downloading the binaries
+/// on first use) and creates an empty database on it. Call `teardown` to drop
+/// the database; the server itself is stopped and its data directory removed
+/// when the `TestDb` is dropped. Only available with the `test-db` feature.
+pub struct TestDb {
+    server: PostgreSQL,
+    database_name: String,
+    database_url: String,
+}
+
+impl TestDb {
+    pub async fn start() -> Result<Self, OrmError> {
+        let mut server = PostgreSQL::default();
+        server.setup().await.map_err(embedded_error)?;
+        server.start().await.map_err(embedded_error)?;
+
+        let database_name = format!("rust_orm_gen_test_{}_{}", std::process::id(), NEXT_DATABASE.fetch_add(1, Ordering::SeqCst));
+        server.create_database(&database_name).await.map_err(embedded_error)?;
+        let database_url = server.settings().url(&database_name);
+
+        Ok(Self { server, database_name, database_url })
+    }
+
+    pub fn database_url(&self) -> &str {
+        &self.database_url
+    }
+
+    pub fn manager(&self) -> PostgresConnectionManager {
+        PostgresConnectionManager::new(self.database_url.clone())
+    }
+
+    pub async fn connect(&self) -> Result<Client, OrmError> {
+        self.manager().connect().await
+    }
+
+    /// Drops the database and stops the server.
+    pub async fn teardown(self) -> Result<(), OrmError> {
+        self.server.drop_database(&self.database_name).await.map_err(embedded_error)?;
+        self.server.stop().await.map_err(embedded_error)
+    }
+}
+
+fn embedded_error(error: postgresql_embedded::Error) -> OrmError {
+    OrmError::ConnectionError(format!("Embedded Postgres failed: {}", error))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::context::DbContext;
+    use crate::metadata::{get_columns, get_tables};
+    use std::env;
+    use std::fs;
+
+    #[tokio::test]
+    async fn test_metadata_on_test_db() {
+        let test_db = TestDb::start().await.expect("Failed to start test database");
+        let client = test_db.connect().await.expect("Failed to connect to test database");
+
+        client.batch_execute("CREATE TABLE customer (customer_id SERIAL PRIMARY KEY, email TEXT NOT NULL)").await.unwrap();
+        let tables = get_tables(&client).await.expect("Failed to get tables");
+        let columns = get_columns(&client, "customer").await.expect("Failed to get columns");
+
+        assert_eq!(tables, vec!["customer".to_string()]);
+        assert_eq!(columns.iter().map(|c| c.name.as_str()).collect::<Vec<_>>(), vec!["customer_id", "email"]);
+
+        drop(client);
+        test_db.teardown().await.expect("Failed to tear down test database");
+    }
+
+    #[tokio::test]
+    async fn test_reverse_engineer_on_test_db() {
+        let test_db = TestDb::start().await.expect("Failed to start test database");
+        let client = test_db.connect().await.expect("Failed to connect to test database");
+
+        client.batch_execute(
+            "CREATE TABLE customer (id SERIAL PRIMARY KEY, email TEXT NOT NULL UNIQUE);
+             CREATE VIEW customer_list AS SELECT id, email FROM customer;"
+        ).await.unwrap();
+
+        let output_dir = env::temp_dir().join(format!("rust_orm_gen_test_db_{}", std::process::id()));
+        let db_context = DbContext::new(test_db.database_url()).await.unwrap();
+        let result = db_context.reverse_engineer(output_dir.to_str().unwrap(), "Tom Blanchard", "https://github.com/tomblanchard312/rust_orm_gen").await;
+
+        let struct_def = fs::read_to_string(output_dir.join("customer.rs"));
+        let crud_ops = fs::read_to_string(output_dir.join("customer_crud.rs"));
+        let view_ops = fs::read_to_string(output_dir.join("customer_list_crud.rs"));
+        fs::remove_dir_all(&output_dir).ok();
+        drop(client);
+        test_db.teardown().await.expect("Failed to tear down test database");
+
+        assert!(result.is_ok(), "Reverse engineering failed: {:?}", result.err());
+        assert!(struct_def.unwrap().contains("pub struct Customer {"));
+        assert!(crud_ops.unwrap().contains("pub async fn upsert_customer_by_email"));
+        assert!(view_ops.unwrap().contains("pub async fn get_customer_list(client: &Client, id: i32)"));
+    }
+}
@@ -1,22 +1,43 @@
+use crate::db::statement_timeout_sql;
+use crate::error::OrmError;
+use log::warn;
 use tokio_postgres::{Client, Transaction};
 use std::future::Future;
 use std::pin::Pin;
+use std::time::Duration;
 
 pub struct TransactionManager<'a> {
     client: &'a mut Client,
+    statement_timeout: Option<Duration>,
 }
 
 impl<'a> TransactionManager<'a> {
     pub fn new(client: &'a mut Client) -> Self {
-        TransactionManager { client }
+        TransactionManager { client, statement_timeout: None }
+    }
+
+    /// Runs `SET LOCAL statement_timeout` at the start of each transaction,
+    /// so the server cancels any statement in it that runs longer than
+    /// `timeout`. The setting ends with the transaction.
+    pub fn with_statement_timeout(mut self, timeout: Duration) -> Self {
+        self.statement_timeout = Some(timeout);
+        self
+    }
+
+    async fn begin(&mut self) -> Result<Transaction<'_>, tokio_postgres::Error> {
+        let transaction = self.client.transaction().await?;
+        if let Some(timeout) = self.statement_timeout {
+            transaction.batch_execute(&statement_timeout_sql("SET LOCAL", timeout)).await?;
+        }
+        Ok(transaction)
     }
 
     pub async fn run<F, T, E>(&mut self, f: F) -> Result<T, Box<dyn std::error::Error>>
     where
-        F: for<'b> FnOnce(&'b mut Transaction<'b>) -> Pin<Box<dyn Future<Output = Result<T, E>> + Send + 'b>>,
+        F: for<'b, 't> FnOnce(&'b mut Transaction<'t>) -> Pin<Box<dyn Future<Output = Result<T, E>> + Send + 'b>>,
         E: std::error::Error + 'static,
     {
-        let mut transaction = self.client.transaction().await?;
+        let mut transaction = self.begin().await?;
         let result = f(&mut transaction).await;
 
         match result {
@@ -30,4 +51,130 @@ impl<'a> TransactionManager<'a> {
             }
         }
     }
+
+    /// Like `run`, but reports failures as `OrmError` so transactions compose
+    /// with the rest of the crate. The closure may fail with any error that
+    /// converts into `OrmError`, including `tokio_postgres::Error` through `?`;
+    /// the original error stays reachable through `source()`.
+    pub async fn run_orm<F, T, E>(&mut self, f: F) -> Result<T, OrmError>
+    where
+        F: for<'b, 't> FnOnce(&'b mut Transaction<'t>) -> Pin<Box<dyn Future<Output = Result<T, E>> + Send + 'b>>,
+        E: Into<OrmError>,
+    {
+        let mut transaction = self.begin().await?;
+        let result = f(&mut transaction).await;
+
+        match result {
+            Ok(value) => {
+                transaction.commit().await?;
+                Ok(value)
+            },
+            Err(e) => {
+                // Report the closure's error rather than a failed rollback,
+                // e.g. on a connection the closure's error already broke
+                if let Err(rollback_error) = transaction.rollback().await {
+                    warn!("Failed to roll back after an error in run_orm: {}", rollback_error);
+                }
+                Err(e.into())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio;
+    use dotenv::dotenv;
+    use std::env;
+    use std::error::Error;
+    use tokio_postgres::error::SqlState;
+    use crate::db::PostgresConnectionManager;
+
+    #[tokio::test]
+    async fn test_run_orm_surfaces_closure_errors() {
+        dotenv().ok();
+        let database_url = env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+        let manager = PostgresConnectionManager::new(database_url);
+        let mut client = manager.connect().await.expect("Failed to connect to database");
+        client.batch_execute(
+            "DROP TABLE IF EXISTS transaction_ledger;
+             CREATE TABLE transaction_ledger (id INTEGER PRIMARY KEY, amount INTEGER NOT NULL);"
+        ).await.expect("Failed to create test table");
+
+        let mut transactions = TransactionManager::new(&mut client);
+        let rejected: Result<(), OrmError> = transactions.run_orm(|tx| Box::pin(async move {
+            tx.execute("INSERT INTO transaction_ledger VALUES (1, 100)", &[]).await?;
+            Err(OrmError::QueryError("Insufficient funds".to_string()))
+        })).await;
+        let failed: Result<u64, OrmError> = transactions.run_orm(|tx| Box::pin(async move {
+            tx.execute("INSERT INTO transaction_ledger VALUES (2, 50)", &[]).await?;
+            tx.execute("INSERT INTO transaction_ledger VALUES (2, 75)", &[]).await.map_err(OrmError::from)
+        })).await;
+        let committed = transactions.run_orm(|tx| Box::pin(async move {
+            tx.execute("INSERT INTO transaction_ledger VALUES (3, 25)", &[]).await
+        })).await;
+
+        let ids: Vec<i32> = client.query("SELECT id FROM transaction_ledger", &[]).await.unwrap()
+            .iter().map(|row| row.get(0)).collect();
+        client.batch_execute("DROP TABLE transaction_ledger").await.unwrap();
+
+        match rejected {
+            Err(OrmError::QueryError(message)) => assert_eq!(message, "Insufficient funds"),
+            other => panic!("Expected QueryError, got {:?}", other),
+        }
+        match failed {
+            Err(error @ OrmError::DatabaseError(_)) => {
+                let source = error.source().and_then(|e| e.downcast_ref::<tokio_postgres::Error>()).expect("Missing source error");
+                assert_eq!(source.code(), Some(&SqlState::UNIQUE_VIOLATION));
+            }
+            other => panic!("Expected DatabaseError, got {:?}", other),
+        }
+        assert_eq!(committed.expect("Transaction should commit"), 1);
+        assert_eq!(ids, vec![3]);
+    }
+
+    #[tokio::test]
+    async fn test_run_orm_reports_closure_error_when_rollback_fails() {
+        dotenv().ok();
+        let database_url = env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+        let manager = PostgresConnectionManager::new(database_url);
+        let mut client = manager.connect().await.expect("Failed to connect to database");
+
+        // Ending its own backend fails the query and leaves nothing to roll back on
+        let mut transactions = TransactionManager::new(&mut client);
+        let result: Result<u64, OrmError> = transactions.run_orm(|tx| Box::pin(async move {
+            tx.execute("SELECT pg_terminate_backend(pg_backend_pid())", &[]).await
+        })).await;
+
+        match result {
+            Err(OrmError::DatabaseError(e)) => assert_eq!(e.code(), Some(&SqlState::ADMIN_SHUTDOWN), "{}", e),
+            other => panic!("Expected the query's error, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_statement_timeout_cancels_slow_queries() {
+        dotenv().ok();
+        let database_url = env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+        let manager = PostgresConnectionManager::new(database_url);
+        let mut client = manager.connect().await.expect("Failed to connect to database");
+
+        let mut transactions = TransactionManager::new(&mut client).with_statement_timeout(Duration::from_millis(100));
+        let slow = transactions.run_orm(|tx| Box::pin(async move {
+            tx.execute("SELECT pg_sleep(5)", &[]).await
+        })).await;
+        let fast = transactions.run_orm(|tx| Box::pin(async move {
+            tx.execute("SELECT pg_sleep(0.01)", &[]).await
+        })).await;
+        // SET LOCAL ends with the transaction
+        let session_timeout: String = client.query_one("SHOW statement_timeout", &[]).await.unwrap().get(0);
+
+        match slow {
+            Err(OrmError::DatabaseError(e)) => assert_eq!(e.code(), Some(&SqlState::QUERY_CANCELED)),
+            other => panic!("Expected the server to cancel the query, got {:?}", other),
+        }
+        assert!(fast.is_ok(), "Fast query failed: {:?}", fast.err());
+        assert_eq!(session_timeout, "0");
+    }
 }
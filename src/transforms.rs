@@ -0,0 +1,126 @@
+use std::collections::HashMap;
+use std::sync::{Arc, OnceLock, RwLock};
+
+/// Converts a text column's value on its way to and from the database, e.g.
+/// to encrypt PII at rest. `from_db` must undo `to_db`.
+///
+/// Only text columns, i.e. `String` and `Option<String>` fields, are
+/// transformed; a transform registered for a column of any other type is
+/// never called by the generated code. The values of a generated `*Filter`
+/// are run through `to_db` as well, so filtering on a column only matches
+/// when its transform is deterministic.
+#[allow(clippy::wrong_self_convention)]
+pub trait ColumnTransform: Send + Sync {
+    fn to_db(&self, value: &str) -> String;
+    fn from_db(&self, value: &str) -> String;
+}
+
+/// Leaves values unchanged; used for every column without a registered transform.
+pub struct NoTransform;
+
+impl ColumnTransform for NoTransform {
+    fn to_db(&self, value: &str) -> String {
+        value.to_string()
+    }
+
+    fn from_db(&self, value: &str) -> String {
+        value.to_string()
+    }
+}
+
+type Registry = RwLock<HashMap<(String, String), Arc<dyn ColumnTransform>>>;
+
+fn registry() -> &'static Registry {
+    static REGISTRY: OnceLock<Registry> = OnceLock::new();
+    REGISTRY.get_or_init(Default::default)
+}
+
+/// Registers `transform` for `table.column`, replacing any earlier one. The
+/// generated CRUD functions apply it to that column on every read and write,
+/// provided the column is text.
+pub fn register_transform<C: ColumnTransform + 'static>(table: &str, column: &str, transform: C) {
+    registry().write().unwrap().insert((table.to_string(), column.to_string()), Arc::new(transform));
+}
+
+/// Removes the transform for `table.column`, if any.
+pub fn unregister_transform(table: &str, column: &str) {
+    registry().write().unwrap().remove(&(table.to_string(), column.to_string()));
+}
+
+/// The transform for `table.column`, or `NoTransform` if none is registered.
+pub fn transform_for(table: &str, column: &str) -> Arc<dyn ColumnTransform> {
+    registry().read().unwrap()
+        .get(&(table.to_string(), column.to_string()))
+        .cloned()
+        .unwrap_or_else(|| Arc::new(NoTransform))
+}
+
+pub fn to_db(table: &str, column: &str, value: &str) -> String {
+    transform_for(table, column).to_db(value)
+}
+
+pub fn from_db(table: &str, column: &str, value: &str) -> String {
+    transform_for(table, column).from_db(value)
+}
+
+/// A text field value `to_db_value` can transform: `String`, or
+/// `Option<String>` for a nullable column, where `None` stays `None`.
+pub trait TextValue {
+    fn transform_to_db(&self, table: &str, column: &str) -> Self;
+}
+
+impl TextValue for String {
+    fn transform_to_db(&self, table: &str, column: &str) -> Self {
+        to_db(table, column, self)
+    }
+}
+
+impl TextValue for Option<String> {
+    fn transform_to_db(&self, table: &str, column: &str) -> Self {
+        self.as_deref().map(|value| to_db(table, column, value))
+    }
+}
+
+/// `to_db` for a text field of a generated struct, nullable or not.
+pub fn to_db_value<V: TextValue>(table: &str, column: &str, value: &V) -> V {
+    value.transform_to_db(table, column)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Reverses the string, standing in for real encryption.
+    struct Reverse;
+
+    impl ColumnTransform for Reverse {
+        fn to_db(&self, value: &str) -> String {
+            value.chars().rev().collect()
+        }
+
+        fn from_db(&self, value: &str) -> String {
+            value.chars().rev().collect()
+        }
+    }
+
+    #[test]
+    fn test_unregistered_column_is_unchanged() {
+        assert_eq!(to_db("transform_defaults", "email", "alice@example.com"), "alice@example.com");
+        assert_eq!(from_db("transform_defaults", "email", "alice@example.com"), "alice@example.com");
+        assert_eq!(to_db_value("transform_defaults", "email", &None::<String>), None);
+    }
+
+    #[test]
+    fn test_registered_transform_round_trip() {
+        register_transform("transform_registry", "ssn", Reverse);
+        let stored = to_db("transform_registry", "ssn", "123-45-6789");
+        let read = from_db("transform_registry", "ssn", &stored);
+        let nullable = to_db_value("transform_registry", "ssn", &Some("123-45-6789".to_string()));
+        unregister_transform("transform_registry", "ssn");
+
+        assert_eq!(stored, "9876-54-321");
+        assert_eq!(read, "123-45-6789");
+        assert_eq!(nullable.as_deref(), Some("9876-54-321"));
+        assert_eq!(to_db("transform_registry", "ssn", "123-45-6789"), "123-45-6789");
+    }
+}
@@ -0,0 +1,167 @@
+use bytes::{BufMut, BytesMut};
+use chrono::{FixedOffset, NaiveTime, Timelike};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+use tokio_postgres::types::{accepts, to_sql_checked, FromSql, IsNull, ToSql, Type};
+
+/// A Postgres `time with time zone` value: a time of day together with the
+/// UTC offset it was recorded in.
+///
+/// chrono has no equivalent type and `chrono::NaiveTime` would silently drop
+/// the offset, so generated structs use this for `timetz` columns.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TimeTz {
+    pub time: NaiveTime,
+    pub offset: FixedOffset,
+}
+
+impl TimeTz {
+    pub fn new(time: NaiveTime, offset: FixedOffset) -> Self {
+        Self { time, offset }
+    }
+}
+
+impl fmt::Display for TimeTz {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}{}", self.time, self.offset)
+    }
+}
+
+impl FromStr for TimeTz {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let split = s.rfind(['+', '-']).ok_or_else(|| format!("Missing UTC offset in '{}'", s))?;
+        let time = NaiveTime::from_str(&s[..split]).map_err(|e| format!("Invalid time in '{}': {}", s, e))?;
+        let offset = FixedOffset::from_str(&s[split..]).map_err(|e| format!("Invalid UTC offset in '{}': {}", s, e))?;
+        Ok(Self { time, offset })
+    }
+}
+
+impl Serialize for TimeTz {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for TimeTz {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = String::deserialize(deserializer)?;
+        value.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+// On the wire a timetz is the microseconds since midnight followed by the
+// zone offset in seconds *west* of UTC.
+impl<'a> FromSql<'a> for TimeTz {
+    fn from_sql(_: &Type, raw: &'a [u8]) -> Result<Self, Box<dyn Error + Sync + Send>> {
+        if raw.len() != 12 {
+            return Err("invalid message length: timetz size mismatch".into());
+        }
+        let micros = i64::from_be_bytes(raw[..8].try_into()?);
+        let seconds_west = i32::from_be_bytes(raw[8..].try_into()?);
+
+        let time = NaiveTime::from_num_seconds_from_midnight_opt((micros / 1_000_000) as u32, (micros % 1_000_000) as u32 * 1_000)
+            .ok_or("timetz value out of range")?;
+        let offset = FixedOffset::west_opt(seconds_west).ok_or("timetz offset out of range")?;
+        Ok(Self { time, offset })
+    }
+
+    accepts!(TIMETZ);
+}
+
+impl ToSql for TimeTz {
+    fn to_sql(&self, _: &Type, out: &mut BytesMut) -> Result<IsNull, Box<dyn Error + Sync + Send>> {
+        let micros = i64::from(self.time.num_seconds_from_midnight()) * 1_000_000 + i64::from(self.time.nanosecond() / 1_000);
+        out.put_i64(micros);
+        out.put_i32(-self.offset.local_minus_utc());
+        Ok(IsNull::No)
+    }
+
+    accepts!(TIMETZ);
+    to_sql_checked!();
+}
+
+/// A Postgres `money` value, stored as the integer number of minor units
+/// (cents for the usual two-digit `lc_monetary` locales).
+///
+/// This is exactly what the server sends on the wire, so values round-trip
+/// without the precision loss of a float or the locale-dependent formatting
+/// of the text representation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Default, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct PgMoney(pub i64);
+
+impl<'a> FromSql<'a> for PgMoney {
+    fn from_sql(_: &Type, raw: &'a [u8]) -> Result<Self, Box<dyn Error + Sync + Send>> {
+        if raw.len() != 8 {
+            return Err("invalid message length: money size mismatch".into());
+        }
+        Ok(PgMoney(i64::from_be_bytes(raw.try_into()?)))
+    }
+
+    accepts!(MONEY);
+}
+
+impl ToSql for PgMoney {
+    fn to_sql(&self, _: &Type, out: &mut BytesMut) -> Result<IsNull, Box<dyn Error + Sync + Send>> {
+        out.put_i64(self.0);
+        Ok(IsNull::No)
+    }
+
+    accepts!(MONEY);
+    to_sql_checked!();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio;
+    use dotenv::dotenv;
+    use std::env;
+    use crate::db::PostgresConnectionManager;
+
+    #[test]
+    fn test_timetz_string_round_trip() {
+        let value: TimeTz = "10:30:15+02:00".parse().unwrap();
+        assert_eq!(value.time, NaiveTime::from_hms_opt(10, 30, 15).unwrap());
+        assert_eq!(value.offset, FixedOffset::east_opt(2 * 3600).unwrap());
+        assert_eq!(value.to_string(), "10:30:15+02:00");
+    }
+
+    #[tokio::test]
+    async fn test_timetz_database_round_trip() {
+        dotenv().ok();
+        let database_url = env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+        let manager = PostgresConnectionManager::new(database_url);
+        let client = manager.connect().await.expect("Failed to connect to database");
+
+        let row = client.query_one("SELECT '08:15:30.25-05:00'::timetz", &[]).await.unwrap();
+        let value: TimeTz = row.get(0);
+        assert_eq!(value.time, NaiveTime::from_hms_milli_opt(8, 15, 30, 250).unwrap());
+        assert_eq!(value.offset, FixedOffset::west_opt(5 * 3600).unwrap());
+
+        let row = client.query_one("SELECT $1::timetz::text", &[&value]).await.unwrap();
+        let text: String = row.get(0);
+        assert_eq!(text, "08:15:30.25-05");
+    }
+
+    #[tokio::test]
+    async fn test_money_database_round_trip() {
+        dotenv().ok();
+        let database_url = env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+        let manager = PostgresConnectionManager::new(database_url);
+        let client = manager.connect().await.expect("Failed to connect to database");
+
+        let row = client.query_one("SELECT '12.34'::numeric::money", &[]).await.unwrap();
+        assert_eq!(row.get::<_, PgMoney>(0), PgMoney(1234));
+
+        // 2^53 + 1 minor units cannot be represented by an f64
+        let amount = PgMoney(9_007_199_254_740_993);
+        let row = client.query_one("SELECT $1::money, $1::money::numeric::text", &[&amount]).await.unwrap();
+        assert_eq!(row.get::<_, PgMoney>(0), amount);
+        assert_eq!(row.get::<_, String>(1), "90071992547409.93");
+    }
+}
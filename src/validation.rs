@@ -20,11 +20,17 @@ impl ValidationResult {
     pub fn add_error(&mut self, field: &str, message: &str) {
         self.errors
             .entry(field.to_string())
-            .or_insert_with(Vec::new)
+            .or_default()
             .push(message.to_string());
     }
 
     pub fn is_valid(&self) -> bool {
         self.errors.is_empty()
     }
-}
\ No newline at end of file
+}
+
+impl Default for ValidationResult {
+    fn default() -> Self {
+        Self::new()
+    }
+}